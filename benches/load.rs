@@ -0,0 +1,50 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::io::{Cursor, Read, Write};
+
+/// Returns the test document with 4096 tile refs on each of its layers, so that parsing
+/// `docData.json` dominates loading it.
+fn large_document() -> Vec<u8> {
+    let file = std::fs::File::open("resources/test_v0.4.8.pyxel").unwrap();
+    let mut src = zip::ZipArchive::new(file).unwrap();
+    let mut dst = zip::ZipWriter::new(Cursor::new(Vec::new()));
+
+    for i in 0..src.len() {
+        let mut entry = src.by_index(i).unwrap();
+        let name = entry.name().to_owned();
+        let mut buf = Vec::new();
+        entry.read_to_end(&mut buf).unwrap();
+
+        if name == "docData.json" {
+            let mut json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+            for layer in json["canvas"]["layers"]
+                .as_object_mut()
+                .unwrap()
+                .values_mut()
+            {
+                for cell in 0..4096 {
+                    layer["tileRefs"][cell.to_string()] =
+                        serde_json::json!({ "index": cell % 4, "rot": cell % 4, "flipX": false });
+                }
+            }
+            buf = serde_json::to_vec(&json).unwrap();
+        }
+
+        dst.start_file(name, zip::write::FileOptions::default())
+            .unwrap();
+        dst.write_all(&buf).unwrap();
+    }
+
+    dst.finish().unwrap().into_inner()
+}
+
+fn load(c: &mut Criterion) {
+    let buf = large_document();
+
+    // Run with and without `--features simd-json` to compare the JSON parsers.
+    c.bench_function("load 45k tile refs", |b| {
+        b.iter(|| black_box(pyxel::load_from_memory(&buf).unwrap()))
+    });
+}
+
+criterion_group!(benches, load);
+criterion_main!(benches);
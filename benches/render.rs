@@ -0,0 +1,15 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn flatten(c: &mut Criterion) {
+    let mut doc = pyxel::open("resources/test_v0.4.8.pyxel").unwrap();
+    // 64 × 64 tiles of 32 × 16 pixels, a 2048 × 1024 canvas.
+    doc.resize_canvas(64, 64, pyxel::Anchor::TopLeft);
+    let canvas = doc.canvas();
+
+    c.bench_function("flatten 2048x1024", |b| {
+        b.iter(|| black_box(canvas.flatten()))
+    });
+}
+
+criterion_group!(benches, flatten);
+criterion_main!(benches);
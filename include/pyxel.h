@@ -0,0 +1,152 @@
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#ifndef PYXEL_H
+#define PYXEL_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// A loaded Pyxel document, freed with pyxel_free.
+typedef struct PyxelDocument PyxelDocument;
+
+// Opens the document at the NUL terminated `path`, returning null on failure.
+//
+// # Safety
+//
+// `path` must point to a NUL terminated string.
+PyxelDocument *pyxel_open(const char *path);
+
+// Loads a document from the `len` bytes at `data`, returning null on failure.
+//
+// # Safety
+//
+// `data` must point to at least `len` readable bytes.
+PyxelDocument *pyxel_load_from_memory(const uint8_t *data, size_t len);
+
+// Frees a document returned by `pyxel_open` or `pyxel_load_from_memory`.
+//
+// # Safety
+//
+// `doc` must be null or a document that hasn't been freed yet.
+void pyxel_free(PyxelDocument *doc);
+
+// Copies the message of the last error on this thread, returning zero if there hasn't been one.
+//
+// # Safety
+//
+// `buf` must be null or point to at least `len` writable bytes.
+size_t pyxel_last_error(uint8_t *buf, size_t len);
+
+// Returns the width of the canvas in pixels.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+int32_t pyxel_canvas_width(const PyxelDocument *doc);
+
+// Returns the height of the canvas in pixels.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+int32_t pyxel_canvas_height(const PyxelDocument *doc);
+
+// Returns the width of the tiles in pixels.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+uint16_t pyxel_tile_width(const PyxelDocument *doc);
+
+// Returns the height of the tiles in pixels.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+uint16_t pyxel_tile_height(const PyxelDocument *doc);
+
+// Returns the number of layers, top-most first.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+size_t pyxel_layer_count(const PyxelDocument *doc);
+
+// Copies the NUL terminated name of `layer`, returning zero if it doesn't exist.
+//
+// # Safety
+//
+// `doc` must be null or a live document, and `buf` must be null or point to at least `len`
+// writable bytes.
+size_t pyxel_layer_name(const PyxelDocument *doc, size_t layer, uint8_t *buf, size_t len);
+
+// Returns the alpha of `layer`, or zero if it doesn't exist.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+uint8_t pyxel_layer_alpha(const PyxelDocument *doc, size_t layer);
+
+// Returns whether `layer` is shown in the editor, taking hidden, muted and soloed layers into
+// account.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+bool pyxel_layer_visible(const PyxelDocument *doc, size_t layer);
+
+// Copies the pixels of `layer` as rows of 8-bit RGBA, returning zero if it doesn't exist.
+//
+// # Safety
+//
+// `doc` must be null or a live document, and `buf` must be null or point to at least `len`
+// writable bytes.
+size_t pyxel_layer_pixels(const PyxelDocument *doc, size_t layer, uint8_t *buf, size_t len);
+
+// Returns the number of tiles in the tileset.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+size_t pyxel_tile_count(const PyxelDocument *doc);
+
+// Copies the pixels of `tile` as rows of 8-bit RGBA, returning zero if it doesn't exist.
+//
+// # Safety
+//
+// `doc` must be null or a live document, and `buf` must be null or point to at least `len`
+// writable bytes.
+size_t pyxel_tile_pixels(const PyxelDocument *doc, size_t tile, uint8_t *buf, size_t len);
+
+// Returns the number of animations.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+size_t pyxel_animation_count(const PyxelDocument *doc);
+
+// Returns the canvas cell of the first frame of `animation`, or zero if it doesn't exist.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+size_t pyxel_animation_base_tile(const PyxelDocument *doc, size_t animation);
+
+// Returns the number of frames of `animation`, or zero if it doesn't exist.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+size_t pyxel_animation_length(const PyxelDocument *doc, size_t animation);
+
+// Returns the duration of `frame` of `animation` in milliseconds, or zero if it doesn't exist.
+//
+// # Safety
+//
+// `doc` must be null or a live document.
+uint64_t pyxel_animation_frame_duration_ms(const PyxelDocument *doc,
+                                           size_t animation,
+                                           size_t frame);
+
+#endif  /* PYXEL_H */
@@ -0,0 +1,234 @@
+use crate::pyxel::Tileset;
+
+use image::{GenericImageView, RgbaImage};
+
+/// The position of a tile within an [`Atlas`](struct.Atlas.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasRect {
+    /// The left edge of the tile in pixels.
+    pub x: u32,
+    /// The top edge of the tile in pixels.
+    pub y: u32,
+    /// The width of the tile in pixels.
+    pub width: u32,
+    /// The height of the tile in pixels.
+    pub height: u32,
+    /// The normalized texture coordinates of the top left corner of the tile.
+    pub uv_min: (f32, f32),
+    /// The normalized texture coordinates of the bottom right corner of the tile.
+    pub uv_max: (f32, f32),
+}
+
+/// A tileset stitched into a single image by an [`AtlasBuilder`](struct.AtlasBuilder.html).
+#[derive(Clone, Debug)]
+pub struct Atlas {
+    /// The atlas image.
+    pub image: RgbaImage,
+    /// The position of each tile within the image, in tileset order.
+    pub tiles: Vec<AtlasRect>,
+}
+
+/// Stitches the tiles of a tileset into a single [`Atlas`](struct.Atlas.html) image suitable for
+/// uploading to a GPU.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let doc = pyxel::open("resources/doc.pyxel")?;
+/// let atlas = pyxel::AtlasBuilder::new()
+///     .padding(2)
+///     .extrude(1)
+///     .power_of_two(true)
+///     .build(doc.tileset());
+///
+/// let rect = atlas.tiles[0];
+/// assert_eq!((3, 3), (rect.x, rect.y));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AtlasBuilder {
+    columns: Option<usize>,
+    padding: u32,
+    extrude: u32,
+    power_of_two: bool,
+}
+
+impl AtlasBuilder {
+    /// Creates a builder laying tiles out `tiles_wide` across without any padding or extrusion.
+    pub fn new() -> AtlasBuilder {
+        AtlasBuilder::default()
+    }
+
+    /// Sets the number of tiles in each row of the atlas. Defaults to the tileset's `tiles_wide`.
+    pub fn columns(mut self, columns: usize) -> AtlasBuilder {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Sets the number of transparent pixels between tiles and around the edge of the atlas.
+    pub fn padding(mut self, padding: u32) -> AtlasBuilder {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the number of times the edge pixels of each tile are repeated outwards, which stops
+    /// neighbouring tiles bleeding into each other when sampled with linear filtering.
+    pub fn extrude(mut self, extrude: u32) -> AtlasBuilder {
+        self.extrude = extrude;
+        self
+    }
+
+    /// Sets whether the width and height of the atlas are rounded up to powers of two.
+    pub fn power_of_two(mut self, power_of_two: bool) -> AtlasBuilder {
+        self.power_of_two = power_of_two;
+        self
+    }
+
+    /// Builds an atlas from the tiles of `tileset`.
+    pub fn build(&self, tileset: &Tileset) -> Atlas {
+        let count = tileset.images.len();
+        let columns = self
+            .columns
+            .unwrap_or_else(|| usize::from(tileset.tiles_wide))
+            .max(1);
+        let rows = count.div_ceil(columns);
+
+        let (tile_width, tile_height) = (
+            u32::from(tileset.tile_width),
+            u32::from(tileset.tile_height),
+        );
+        let (cell_width, cell_height) = (
+            tile_width + 2 * self.extrude + self.padding,
+            tile_height + 2 * self.extrude + self.padding,
+        );
+
+        let mut width = self.padding + columns as u32 * cell_width;
+        let mut height = self.padding + rows as u32 * cell_height;
+        if self.power_of_two {
+            width = width.next_power_of_two();
+            height = height.next_power_of_two();
+        }
+
+        let mut image = RgbaImage::new(width, height);
+        let mut tiles = Vec::with_capacity(count);
+
+        for (i, tile) in tileset.images.iter().enumerate() {
+            let (col, row) = ((i % columns) as u32, (i / columns) as u32);
+            let x = self.padding + col * cell_width + self.extrude;
+            let y = self.padding + row * cell_height + self.extrude;
+
+            let (w, h) = (tile.width().min(tile_width), tile.height().min(tile_height));
+            if w > 0 && h > 0 {
+                let e = self.extrude as i64;
+                for dy in -e..i64::from(h) + e {
+                    for dx in -e..i64::from(w) + e {
+                        let sx = dx.max(0).min(i64::from(w) - 1) as u32;
+                        let sy = dy.max(0).min(i64::from(h) - 1) as u32;
+                        image.put_pixel(
+                            (i64::from(x) + dx) as u32,
+                            (i64::from(y) + dy) as u32,
+                            tile.get_pixel(sx, sy),
+                        );
+                    }
+                }
+            }
+
+            tiles.push(AtlasRect {
+                x,
+                y,
+                width: tile_width,
+                height: tile_height,
+                uv_min: (x as f32 / width as f32, y as f32 / height as f32),
+                uv_max: (
+                    (x + tile_width) as f32 / width as f32,
+                    (y + tile_height) as f32 / height as f32,
+                ),
+            });
+        }
+
+        Atlas { image, tiles }
+    }
+}
+
+impl Tileset {
+    /// Stitches the tiles of this tileset into a single image, laid out `tiles_wide` across as in
+    /// the PyxelEdit UI.
+    ///
+    /// Use an [`AtlasBuilder`](struct.AtlasBuilder.html) for padding, extrusion and the positions
+    /// of the tiles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let atlas = doc.tileset().atlas();
+    /// assert_eq!(8 * 16, atlas.width());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn atlas(&self) -> RgbaImage {
+        AtlasBuilder::new().build(self).image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn atlas_is_laid_out_tiles_wide_across() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let tileset = doc.tileset();
+        let atlas = tileset.atlas();
+
+        assert_eq!((8 * 32, 16), atlas.dimensions());
+
+        let tile = tileset.images()[3].to_rgba();
+        let stitched = atlas.view(3 * 32, 0, 32, 16).to_image();
+        assert_eq!(tile.into_raw(), stitched.into_raw());
+    }
+
+    #[test]
+    fn builder_pads_and_extrudes_tiles() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let tileset = doc.tileset();
+        let atlas = AtlasBuilder::new()
+            .columns(2)
+            .padding(2)
+            .extrude(1)
+            .power_of_two(true)
+            .build(tileset);
+
+        // Two columns of 2 + 1 + 32 + 1 pixels, plus the padding on the right, is 74 wide.
+        assert_eq!((128, 64), atlas.image.dimensions());
+        assert_eq!(4, atlas.tiles.len());
+
+        let rect = atlas.tiles[3];
+        assert_eq!(
+            (2 + 36 + 1, 2 + 20 + 1, 32, 16),
+            (rect.x, rect.y, rect.width, rect.height)
+        );
+        assert_eq!((39. / 128., 23. / 64.), rect.uv_min);
+        assert_eq!((71. / 128., 39. / 64.), rect.uv_max);
+
+        let tile = tileset.images()[3].to_rgba();
+        let stitched = atlas.image.view(rect.x, rect.y, 32, 16).to_image();
+        assert_eq!(tile.get_pixel(0, 0), stitched.get_pixel(0, 0));
+        assert_eq!(
+            *tile.get_pixel(0, 0),
+            *atlas.image.get_pixel(rect.x - 1, rect.y - 1)
+        );
+        assert_eq!(
+            *tile.get_pixel(31, 15),
+            *atlas.image.get_pixel(rect.x + 32, rect.y + 16)
+        );
+        assert_eq!(0, atlas.image.get_pixel(rect.x - 2, rect.y)[3]);
+    }
+}
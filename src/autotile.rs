@@ -0,0 +1,207 @@
+use crate::{
+    collision::{CollisionGrid, CollisionRule},
+    error::PyxelError,
+    pyxel::{Pyxel, TileRef},
+};
+
+use std::collections::HashMap;
+
+/// Which neighbors of a cell make up its auto-tiling bitmask.
+///
+/// Bits are assigned clockwise starting from the north.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Neighborhood {
+    /// The four edge neighbors, giving a 4-bit Wang mask: north 1, east 2, south 4, west 8.
+    Edges,
+    /// All eight neighbors, giving an 8-bit blob mask: north 1, north-east 2, east 4,
+    /// south-east 8, south 16, south-west 32, west 64, north-west 128.
+    ///
+    /// A corner is only set when both of its adjacent edges are, which leaves the 47 masks blob
+    /// tilesets provide tiles for.
+    EdgesAndCorners,
+}
+
+const DIRECTIONS: [(isize, isize); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+impl CollisionGrid {
+    /// Returns the auto-tiling bitmask of the cell at `col`, `row`, with a bit set for each solid
+    /// neighbor, or `None` if the cell isn't solid. Cells outside of the grid count as empty.
+    pub fn bitmask(&self, col: usize, row: usize, neighborhood: Neighborhood) -> Option<u8> {
+        if !self.is_solid(col, row) {
+            return None;
+        }
+
+        let solid = |(dx, dy): (isize, isize)| match (
+            col.checked_add_signed(dx),
+            row.checked_add_signed(dy),
+        ) {
+            (Some(col), Some(row)) => self.is_solid(col, row),
+            _ => false,
+        };
+
+        let mut mask = 0;
+        for (bit, &direction) in DIRECTIONS.iter().enumerate() {
+            let corner = bit % 2 == 1;
+            let set = match neighborhood {
+                Neighborhood::Edges if corner => continue,
+                Neighborhood::EdgesAndCorners if corner => {
+                    solid(direction)
+                        && solid(DIRECTIONS[bit - 1])
+                        && solid(DIRECTIONS[(bit + 1) % 8])
+                }
+                _ => solid(direction),
+            };
+
+            if set {
+                mask |= match neighborhood {
+                    Neighborhood::Edges => 1 << (bit / 2),
+                    Neighborhood::EdgesAndCorners => 1 << bit,
+                };
+            }
+        }
+
+        Some(mask)
+    }
+
+    /// Returns the auto-tiling bitmask of every cell of this grid row by row. See
+    /// [`bitmask`](#method.bitmask).
+    pub fn bitmasks(&self, neighborhood: Neighborhood) -> Vec<Option<u8>> {
+        (0..self.height())
+            .flat_map(|row| (0..self.width()).map(move |col| (col, row)))
+            .map(|(col, row)| self.bitmask(col, row, neighborhood))
+            .collect()
+    }
+}
+
+impl Pyxel {
+    /// Auto-tiles the first layer named `layer_name`, replacing each of its `terrain` tiles with
+    /// the tile `table` gives for the cell's bitmask, unrotated and unflipped. Terrain cells with
+    /// a bitmask missing from `table` are left unchanged.
+    ///
+    /// Returns the number of cells that were replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyxel::Neighborhood;
+    /// use std::collections::HashMap;
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    ///
+    /// // Use tile 0 for isolated cells.
+    /// let table: HashMap<u8, usize> = vec![(0, 0)].into_iter().collect();
+    /// doc.autotile("Layer 0", &[0], Neighborhood::Edges, &table)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn autotile(
+        &mut self,
+        layer_name: &str,
+        terrain: &[usize],
+        neighborhood: Neighborhood,
+        table: &HashMap<u8, usize>,
+    ) -> Result<usize, PyxelError> {
+        let grid = self.collision_grid(layer_name, &CollisionRule::Tiles(terrain.to_vec()))?;
+
+        let layer = self
+            .canvas
+            .layers
+            .iter_mut()
+            .find(|layer| layer.name == layer_name)
+            .ok_or_else(|| PyxelError::LayerNotFound(layer_name.to_owned()))?;
+
+        let mut replaced = 0;
+        for (cell, mask) in grid.bitmasks(neighborhood).into_iter().enumerate() {
+            if let Some(&tile) = mask.and_then(|mask| table.get(&mask)) {
+                layer.tile_refs.insert(cell, TileRef::new(tile, 0., false));
+                replaced += 1;
+            }
+        }
+
+        Ok(replaced)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    // A 3x3 block of terrain in the top-left corner of the canvas.
+    fn document() -> Pyxel {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let tile_refs = &mut doc.canvas.layers[0].tile_refs;
+        tile_refs.clear();
+        for row in 0..3 {
+            for col in 0..3 {
+                tile_refs.insert(row * 8 + col, TileRef::new(1, 0., false));
+            }
+        }
+        doc
+    }
+
+    #[test]
+    fn bitmasks_count_solid_neighbors() {
+        let doc = document();
+        let grid = doc
+            .collision_grid("Layer 10", &CollisionRule::Tiles(vec![1]))
+            .unwrap();
+
+        assert_eq!(Some(2 | 4), grid.bitmask(0, 0, Neighborhood::Edges));
+        assert_eq!(Some(15), grid.bitmask(1, 1, Neighborhood::Edges));
+        assert_eq!(Some(1 | 8), grid.bitmask(2, 2, Neighborhood::Edges));
+        assert_eq!(None, grid.bitmask(3, 0, Neighborhood::Edges));
+
+        assert_eq!(
+            Some(4 | 8 | 16),
+            grid.bitmask(0, 0, Neighborhood::EdgesAndCorners)
+        );
+        assert_eq!(Some(255), grid.bitmask(1, 1, Neighborhood::EdgesAndCorners));
+
+        let masks = grid.bitmasks(Neighborhood::Edges);
+        assert_eq!(64, masks.len());
+        assert_eq!(9, masks.iter().filter(|mask| mask.is_some()).count());
+    }
+
+    #[test]
+    fn corners_require_both_edges() {
+        let mut doc = document();
+        // Leave only the corner cells and the center, which no longer touch by an edge.
+        for &cell in &[1, 8, 10, 17] {
+            doc.canvas.layers[0].tile_refs.remove(&cell);
+        }
+
+        let grid = doc
+            .collision_grid("Layer 10", &CollisionRule::AnyTile)
+            .unwrap();
+        assert_eq!(Some(0), grid.bitmask(1, 1, Neighborhood::EdgesAndCorners));
+    }
+
+    #[test]
+    fn autotile_remaps_terrain_tiles() {
+        let mut doc = document();
+        let table: HashMap<u8, usize> = vec![(15, 3), (2 | 4, 2)].into_iter().collect();
+
+        assert_eq!(
+            2,
+            doc.autotile("Layer 10", &[1], Neighborhood::Edges, &table)
+                .unwrap()
+        );
+        let tile_refs = &doc.canvas.layers[0].tile_refs;
+        assert_eq!(2, tile_refs[&0].index);
+        assert_eq!(3, tile_refs[&9].index);
+        assert_eq!(1, tile_refs[&1].index);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::PyxelError;
+#[cfg(not(feature = "images"))]
+use crate::pyxel::{Layer, Tileset};
+
+/// An image with 8 bits per channel in red, green, blue, alpha order, decoded by an
+/// [`ImageBackend`](trait.ImageBackend.html). Rows are tightly packed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rgba8 {
+    /// The width of the image in pixels.
+    pub width: u32,
+    /// The height of the image in pixels.
+    pub height: u32,
+    /// The pixels of the image, `width * height * 4` bytes.
+    pub pixels: Vec<u8>,
+}
+
+/// Decodes and encodes the PNG images within Pyxel documents.
+///
+/// Without the `images` feature, layer and tile images are kept as PNG data and this crate
+/// doesn't depend on `image` at all. Implement this for the image library an application
+/// already uses to decode them through
+/// [`Layer::decode_with`](struct.Layer.html#method.decode_with) and friends, rather than pulling
+/// in a second version of `image`. With the `images` feature,
+/// [`ImageCrateBackend`](struct.ImageCrateBackend.html) decodes them with `image`, e.g. for the
+/// PNG data handed over by a [`DocReader`](struct.DocReader.html).
+///
+/// Backends report their own errors with
+/// [`PyxelError::Backend`](enum.PyxelError.html#variant.Backend).
+pub trait ImageBackend {
+    /// Decodes the PNG image `png`.
+    fn decode_rgba8(&self, png: &[u8]) -> Result<Rgba8, PyxelError>;
+
+    /// Encodes `image` as PNG.
+    fn encode_rgba8(&self, image: &Rgba8) -> Result<Vec<u8>, PyxelError>;
+}
+
+/// An [`ImageBackend`](trait.ImageBackend.html) using the version of `image` this crate depends
+/// on.
+#[cfg(feature = "images")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImageCrateBackend;
+
+#[cfg(feature = "images")]
+impl ImageBackend for ImageCrateBackend {
+    fn decode_rgba8(&self, png: &[u8]) -> Result<Rgba8, PyxelError> {
+        let image = image::load_from_memory_with_format(png, image::ImageFormat::PNG)?.to_rgba();
+        let (width, height) = image.dimensions();
+        Ok(Rgba8 {
+            width,
+            height,
+            pixels: image.into_raw(),
+        })
+    }
+
+    fn encode_rgba8(&self, image: &Rgba8) -> Result<Vec<u8>, PyxelError> {
+        let buffer = image::RgbaImage::from_raw(image.width, image.height, image.pixels.clone())
+            .ok_or(PyxelError::InvalidImage)?;
+        crate::png::encode_png(&buffer)
+    }
+}
+
+#[cfg(not(feature = "images"))]
+impl Layer {
+    /// Decodes the image for this layer with `backend`.
+    pub fn decode_with<B: ImageBackend + ?Sized>(&self, backend: &B) -> Result<Rgba8, PyxelError> {
+        backend.decode_rgba8(&self.image_data)
+    }
+
+    /// Replaces the image for this layer with `image`, encoded with `backend`. See
+    /// [`set_image_data`](#method.set_image_data).
+    pub fn encode_with<B: ImageBackend + ?Sized>(
+        &mut self,
+        backend: &B,
+        image: &Rgba8,
+    ) -> Result<(), PyxelError> {
+        self.set_image_data(backend.encode_rgba8(image)?)
+    }
+}
+
+#[cfg(not(feature = "images"))]
+impl Tileset {
+    /// Decodes the image for the tile at `index` with `backend`.
+    pub fn decode_tile_with<B: ImageBackend + ?Sized>(
+        &self,
+        index: usize,
+        backend: &B,
+    ) -> Result<Rgba8, PyxelError> {
+        let png = self
+            .image_data
+            .get(index)
+            .ok_or(PyxelError::TileOutOfBounds(index))?;
+        backend.decode_rgba8(png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes only the dimensions of images, and encodes the blank images of `png`.
+    #[cfg(not(feature = "images"))]
+    struct HeaderBackend;
+
+    #[cfg(not(feature = "images"))]
+    impl ImageBackend for HeaderBackend {
+        fn decode_rgba8(&self, png: &[u8]) -> Result<Rgba8, PyxelError> {
+            let (width, height) = crate::png::png_dimensions(png)?;
+            Ok(Rgba8 {
+                width,
+                height,
+                pixels: vec![0; (width * height * 4) as usize],
+            })
+        }
+
+        fn encode_rgba8(&self, image: &Rgba8) -> Result<Vec<u8>, PyxelError> {
+            if image.pixels.iter().any(|&b| b != 0) {
+                return Err(PyxelError::Backend(
+                    "only blank images are supported".into(),
+                ));
+            }
+            Ok(crate::png::blank_png(image.width, image.height))
+        }
+    }
+
+    #[cfg(not(feature = "images"))]
+    #[test]
+    fn layers_and_tiles_decode_with_backend() {
+        let mut doc = crate::open("resources/test_v0.4.8.pyxel").unwrap();
+
+        let tile = doc.tileset().decode_tile_with(0, &HeaderBackend).unwrap();
+        assert_eq!((32, 16), (tile.width, tile.height));
+        assert!(matches!(
+            doc.tileset().decode_tile_with(4, &HeaderBackend),
+            Err(PyxelError::TileOutOfBounds(4))
+        ));
+
+        let layer = &mut doc.canvas_mut().layers_mut()[0];
+        let mut image = layer.decode_with(&HeaderBackend).unwrap();
+        assert_eq!((256, 128), (image.width, image.height));
+        layer.encode_with(&HeaderBackend, &image).unwrap();
+        assert_eq!(crate::png::blank_png(256, 128), layer.image_data());
+
+        image.pixels[0] = 1;
+        assert!(matches!(
+            layer.encode_with(&HeaderBackend, &image),
+            Err(PyxelError::Backend(_))
+        ));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn image_crate_backend_round_trips() {
+        let image = Rgba8 {
+            width: 2,
+            height: 1,
+            pixels: vec![1, 2, 3, 4, 5, 6, 7, 8],
+        };
+        let png = ImageCrateBackend.encode_rgba8(&image).unwrap();
+        assert_eq!(image, ImageCrateBackend.decode_rgba8(&png).unwrap());
+
+        let short = Rgba8 {
+            pixels: vec![0; 4],
+            ..image
+        };
+        assert!(matches!(
+            ImageCrateBackend.encode_rgba8(&short),
+            Err(PyxelError::InvalidImage)
+        ));
+    }
+}
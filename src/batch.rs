@@ -0,0 +1,195 @@
+use crate::{
+    doc_data::LoadOptions,
+    error::PyxelError,
+    load::{load_with_options, read_doc_data, Budget},
+    pyxel::Pyxel,
+};
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::BufReader,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// The documents loaded by [`open_dir`](fn.open_dir.html), keyed by their path relative to the
+/// directory. Documents that failed to load hold the error instead.
+pub type DirDocuments = BTreeMap<PathBuf, Result<Pyxel, PyxelError>>;
+
+/// Options for [`open_dir_with_options`](fn.open_dir_with_options.html) and
+/// [`open_dir_parallel`](fn.open_dir_parallel.html).
+#[derive(Clone, Debug, Default)]
+pub struct DirOptions {
+    /// The options each document is loaded with.
+    pub load: LoadOptions,
+    /// Only read the metadata of each document, along with its user metadata and extra entries,
+    /// leaving its layers and tiles without images. This is much faster when only the structure
+    /// of the documents is needed.
+    pub metadata_only: bool,
+}
+
+/// Returns the paths of the `.pyxel` files within `root` and its subdirectories, relative to
+/// `root`. Symbolic links to directories aren't followed.
+fn find_documents(root: &Path) -> Result<Vec<PathBuf>, PyxelError> {
+    fn visit(root: &Path, dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), PyxelError> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                visit(root, &path, paths)?;
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("pyxel"))
+            {
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                paths.push(relative.to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    let mut paths = Vec::new();
+    visit(root, root, &mut paths)?;
+    Ok(paths)
+}
+
+fn open_document(path: &Path, options: &DirOptions) -> Result<Pyxel, PyxelError> {
+    let file = BufReader::new(File::open(path)?);
+    if !options.metadata_only {
+        return load_with_options(file, &options.load);
+    }
+
+    let mut archive = zip::ZipArchive::new(file)?;
+    read_doc_data(&mut archive, &Budget::new(&options.load), &options.load)
+}
+
+/// Opens every `.pyxel` file within the directory at `path` and its subdirectories.
+///
+/// A document that fails to load doesn't stop the others from being loaded, its error is
+/// returned in its place. An error is only returned if the directory can't be read.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// for (path, doc) in pyxel::open_dir("assets")? {
+///     match doc {
+///         Ok(doc) => println!("{}: {} layers", path.display(), doc.canvas().layers().len()),
+///         Err(e) => eprintln!("{}: {}", path.display(), e),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn open_dir<P: AsRef<Path>>(path: P) -> Result<DirDocuments, PyxelError> {
+    open_dir_with_options(path, &DirOptions::default())
+}
+
+/// Opens every `.pyxel` file within the directory at `path` and its subdirectories using the
+/// options specified, see [`open_dir`](fn.open_dir.html).
+pub fn open_dir_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &DirOptions,
+) -> Result<DirDocuments, PyxelError> {
+    let root = path.as_ref();
+    Ok(find_documents(root)?
+        .into_iter()
+        .map(|relative| {
+            let doc = open_document(&root.join(&relative), options);
+            (relative, doc)
+        })
+        .collect())
+}
+
+/// Opens every `.pyxel` file within the directory at `path` and its subdirectories as
+/// [`open_dir_with_options`](fn.open_dir_with_options.html) does, loading several documents at
+/// once on multiple threads.
+///
+/// Each document is loaded on a single thread, so this suits directories of many small
+/// documents. Use [`open_parallel`](fn.open_parallel.html) for a single large one.
+pub fn open_dir_parallel<P: AsRef<Path>>(
+    path: P,
+    options: &DirOptions,
+) -> Result<DirDocuments, PyxelError> {
+    let root = path.as_ref();
+    let paths = find_documents(root)?;
+
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(paths.len());
+    if threads <= 1 {
+        return open_dir_with_options(root, options);
+    }
+
+    let next = AtomicUsize::new(0);
+    let docs = Mutex::new(BTreeMap::new());
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                while let Some(relative) = paths.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let doc = open_document(&root.join(relative), options);
+                    docs.lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(relative.clone(), doc);
+                }
+            });
+        }
+    });
+
+    Ok(docs.into_inner().unwrap_or_else(|e| e.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn documents_are_found_recursively() {
+        let dir = std::env::temp_dir().join(format!("pyxel-batch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("chars/hero")).unwrap();
+        fs::copy(TEST_FILE, dir.join("test.pyxel")).unwrap();
+        fs::copy("resources/doc.pyxel", dir.join("chars/hero/Doc.PYXEL")).unwrap();
+        fs::write(dir.join("chars/broken.pyxel"), b"not a zip").unwrap();
+        fs::write(dir.join("chars/notes.txt"), b"shade the hero").unwrap();
+
+        let docs = open_dir(&dir).unwrap();
+        let paths: Vec<&Path> = docs.keys().map(PathBuf::as_path).collect();
+        assert_eq!(
+            vec![
+                Path::new("chars/broken.pyxel"),
+                Path::new("chars/hero/Doc.PYXEL"),
+                Path::new("test.pyxel")
+            ],
+            paths
+        );
+        assert!(matches!(
+            docs[Path::new("chars/broken.pyxel")],
+            Err(PyxelError::Zip(_))
+        ));
+        let expected = crate::open(TEST_FILE).unwrap();
+        let test = docs[Path::new("test.pyxel")].as_ref().unwrap();
+        assert!(expected.eq_with_pixels(test));
+
+        let options = DirOptions {
+            metadata_only: true,
+            ..DirOptions::default()
+        };
+        let docs = open_dir_parallel(&dir, &options).unwrap();
+        assert_eq!(3, docs.len());
+        let test = docs[Path::new("test.pyxel")].as_ref().unwrap();
+        assert_eq!(&expected, test);
+        assert!(test.tileset().tile_count() == 0);
+
+        assert!(open_dir(dir.join("missing")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,284 @@
+use crate::{
+    error::PyxelError,
+    export::flip_flags,
+    pyxel::{Canvas, Layer, Pyxel, Tileset},
+};
+
+use bevy::{
+    asset::{Assets, Handle, RenderAssetUsages},
+    camera::visibility::Visibility,
+    ecs::{entity::Entity, system::Commands},
+    image::{Image, ImageSampler},
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    transform::components::Transform,
+};
+use bevy_ecs_tilemap::prelude::*;
+
+/// A tile of a [`BevyTilemap`](struct.BevyTilemap.html).
+#[derive(Clone, Copy, Debug)]
+pub struct BevyTile {
+    /// The position of the tile, with the bottom row of the canvas at `y` 0 as bevy_ecs_tilemap
+    /// expects.
+    pub position: TilePos,
+    /// The index of the tile in the atlas, which is its index in the tileset.
+    pub texture_index: TileTextureIndex,
+    /// The flips standing in for the rotation and flip of the tile ref.
+    pub flip: TileFlip,
+}
+
+/// The tile refs of a layer converted to the components bevy_ecs_tilemap needs, created by
+/// [`Layer::to_bevy_tilemap`](struct.Layer.html#method.to_bevy_tilemap).
+#[derive(Clone, Debug)]
+pub struct BevyTilemap {
+    /// The size of the tilemap in tiles, which is the size of the canvas.
+    pub size: TilemapSize,
+    /// The size of each tile in pixels.
+    pub tile_size: TilemapTileSize,
+    /// The tiles of the tilemap. Cells without a tile ref have no tile.
+    pub tiles: Vec<BevyTile>,
+    /// The [`atlas`](struct.Tileset.html#method.atlas) of the tileset, to use as a
+    /// `TilemapTexture::Single`.
+    pub atlas: Image,
+}
+
+impl BevyTilemap {
+    /// Adds the atlas of this tilemap to `images` and spawns the tilemap, returning the tilemap
+    /// entity. Each tile is spawned as its own entity, as bevy_ecs_tilemap expects.
+    pub fn spawn(self, commands: &mut Commands, images: &mut Assets<Image>) -> Entity {
+        let texture = images.add(self.atlas);
+        spawn_tilemap(
+            commands,
+            self.size,
+            self.tile_size,
+            &self.tiles,
+            texture,
+            Transform::default(),
+        )
+    }
+}
+
+/// Spawns a tilemap of `tiles` drawn from `texture`, returning the tilemap entity.
+fn spawn_tilemap(
+    commands: &mut Commands,
+    size: TilemapSize,
+    tile_size: TilemapTileSize,
+    tiles: &[BevyTile],
+    texture: Handle<Image>,
+    transform: Transform,
+) -> Entity {
+    let tilemap = commands.spawn_empty().id();
+
+    let mut storage = TileStorage::empty(size);
+    for tile in tiles {
+        let entity = commands
+            .spawn(TileBundle {
+                position: tile.position,
+                texture_index: tile.texture_index,
+                tilemap_id: TilemapId(tilemap),
+                flip: tile.flip,
+                ..TileBundle::default()
+            })
+            .id();
+        storage.set(&tile.position, entity);
+    }
+
+    commands.entity(tilemap).insert(TilemapBundle {
+        grid_size: tile_size.into(),
+        size,
+        storage,
+        texture: TilemapTexture::Single(texture),
+        tile_size,
+        transform,
+        ..TilemapBundle::default()
+    });
+
+    tilemap
+}
+
+/// Returns the size of the canvas in tiles and the size of its tiles.
+fn tilemap_size(canvas: &Canvas) -> (TilemapSize, TilemapTileSize) {
+    (
+        TilemapSize::new(canvas.tiles_wide() as u32, canvas.tiles_high() as u32),
+        TilemapTileSize::new(f32::from(canvas.tile_width), f32::from(canvas.tile_height)),
+    )
+}
+
+/// Converts the tile refs of `layer` into tiles, checking each refers to a tile of `tileset`.
+fn bevy_tiles(
+    layer: &Layer,
+    canvas: &Canvas,
+    tileset: &Tileset,
+) -> Result<Vec<BevyTile>, PyxelError> {
+    let (size, _) = tilemap_size(canvas);
+    let tiles_wide = size.x.max(1) as usize;
+
+    layer
+        .tile_refs
+        .iter()
+        .filter(|&(&cell, _)| cell < size.count())
+        .map(|(&cell, tile_ref)| {
+            if tile_ref.index >= tileset.images.len() {
+                return Err(PyxelError::TileOutOfBounds(tile_ref.index));
+            }
+
+            let (x, y) = (cell % tiles_wide, cell / tiles_wide);
+            let (horizontal, vertical, diagonal) = flip_flags(tile_ref);
+            Ok(BevyTile {
+                position: TilePos::new(x as u32, size.y - 1 - y as u32),
+                texture_index: TileTextureIndex(tile_ref.index as u32),
+                flip: TileFlip {
+                    x: horizontal,
+                    y: vertical,
+                    d: diagonal,
+                },
+            })
+        })
+        .collect()
+}
+
+impl Layer {
+    /// Converts the tile refs of this layer to a [`BevyTilemap`](struct.BevyTilemap.html), with
+    /// the atlas of `tileset` as its texture. Tile refs outside of the canvas are left out.
+    ///
+    /// The pixels of the layer aren't included, only its tile refs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    ///
+    /// fn spawn_map(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    ///     let doc = pyxel::open("resources/doc.pyxel").unwrap();
+    ///     let layer = &doc.canvas().layers()[0];
+    ///     let tilemap = layer.to_bevy_tilemap(doc.canvas(), doc.tileset()).unwrap();
+    ///     tilemap.spawn(&mut commands, &mut images);
+    /// }
+    /// ```
+    pub fn to_bevy_tilemap(
+        &self,
+        canvas: &Canvas,
+        tileset: &Tileset,
+    ) -> Result<BevyTilemap, PyxelError> {
+        let (size, tile_size) = tilemap_size(canvas);
+        Ok(BevyTilemap {
+            size,
+            tile_size,
+            tiles: bevy_tiles(self, canvas, tileset)?,
+            atlas: tileset.to_bevy_image(),
+        })
+    }
+}
+
+impl Tileset {
+    /// Returns the [`atlas`](#method.atlas) of this tileset as an sRGB Bevy `Image`, sampled with
+    /// nearest-neighbor filtering so pixel art stays crisp.
+    pub fn to_bevy_image(&self) -> Image {
+        let atlas = self.atlas();
+        let size = Extent3d {
+            width: atlas.width(),
+            height: atlas.height(),
+            depth_or_array_layers: 1,
+        };
+
+        let mut image = Image::new(
+            size,
+            TextureDimension::D2,
+            atlas.into_raw(),
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.sampler = ImageSampler::nearest();
+        image
+    }
+}
+
+impl Pyxel {
+    /// Spawns a tilemap for each layer of the canvas, returning the tilemap entities in layer
+    /// order. The atlas of the tileset is added to `images` once and shared by every tilemap.
+    ///
+    /// The bottom layer is spawned at a z of 0, with each layer above it one higher. Layers that
+    /// aren't visible, as by
+    /// [`Canvas::effective_visibility`](struct.Canvas.html#method.effective_visibility), are
+    /// spawned hidden.
+    pub fn spawn_bevy_tilemaps(
+        &self,
+        commands: &mut Commands,
+        images: &mut Assets<Image>,
+    ) -> Result<Vec<Entity>, PyxelError> {
+        let (size, tile_size) = tilemap_size(&self.canvas);
+        let layers = self
+            .canvas
+            .layers
+            .iter()
+            .map(|layer| bevy_tiles(layer, &self.canvas, &self.tileset))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let texture = images.add(self.tileset.to_bevy_image());
+        let visible = self.canvas.effective_visibility();
+        let count = layers.len();
+
+        Ok(layers
+            .iter()
+            .enumerate()
+            .map(|(index, tiles)| {
+                let z = (count - 1 - index) as f32;
+                let tilemap = spawn_tilemap(
+                    commands,
+                    size,
+                    tile_size,
+                    tiles,
+                    texture.clone(),
+                    Transform::from_xyz(0., 0., z),
+                );
+                if !visible[index] {
+                    commands.entity(tilemap).insert(Visibility::Hidden);
+                }
+                tilemap
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use bevy::ecs::{system::RunSystemOnce, world::World};
+    use std::fs::File;
+
+    #[test]
+    fn tile_refs_become_bevy_tiles() {
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let (canvas, tileset) = (doc.canvas(), doc.tileset());
+        let tilemap = canvas.layers[1].to_bevy_tilemap(canvas, tileset).unwrap();
+
+        assert_eq!((8, 8), (tilemap.size.x, tilemap.size.y));
+        assert_eq!((32., 16.), (tilemap.tile_size.x, tilemap.tile_size.y));
+        assert_eq!(8, tilemap.tiles.len());
+        for (tile, (&cell, tile_ref)) in tilemap.tiles.iter().zip(&canvas.layers[1].tile_refs) {
+            assert_eq!(TilePos::new(cell as u32 % 8, 0), tile.position);
+            assert_eq!(tile_ref.index as u32, tile.texture_index.0);
+            let (x, y, d) = flip_flags(tile_ref);
+            assert_eq!((x, y, d), (tile.flip.x, tile.flip.y, tile.flip.d));
+        }
+        let atlas = tileset.atlas();
+        assert_eq!(atlas.width(), tilemap.atlas.width());
+        assert_eq!(Some(&atlas.into_raw()), tilemap.atlas.data.as_ref());
+
+        let mut world = World::new();
+        world.init_resource::<Assets<Image>>();
+        let tilemaps = world
+            .run_system_once(
+                move |mut commands: Commands,
+                      mut images: bevy::ecs::system::ResMut<Assets<Image>>| {
+                    doc.spawn_bevy_tilemaps(&mut commands, &mut images).unwrap()
+                },
+            )
+            .unwrap();
+        assert_eq!(11, tilemaps.len());
+        let storage = world.get::<TileStorage>(tilemaps[1]).unwrap();
+        assert_eq!(8, storage.iter().flatten().count());
+        assert_eq!(Some(&Visibility::Hidden), world.get(tilemaps[3]));
+        assert_eq!(1, world.resource::<Assets<Image>>().len());
+    }
+}
@@ -0,0 +1,186 @@
+//! Command line tool for inspecting, validating and exporting PyxelEdit documents.
+
+use pyxel::{FrameOptions, Pyxel, PyxelError, TmxOptions};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+const USAGE: &str = "usage: pyxel <command> <file> [<dir>]
+
+commands:
+    info <file>                     print a summary of the document
+    validate <file>                 check the document for inconsistencies
+    export-frames <file> <dir>      write each animation frame as a PNG
+    export-tmx <file> <dir>         write a Tiled map and its tile images";
+
+enum Command {
+    Info(PathBuf),
+    Validate(PathBuf),
+    ExportFrames(PathBuf, PathBuf),
+    ExportTmx(PathBuf, PathBuf),
+}
+
+fn parse_args(args: &[String]) -> Option<Command> {
+    match args {
+        [command, file] if command == "info" => Some(Command::Info(file.into())),
+        [command, file] if command == "validate" => Some(Command::Validate(file.into())),
+        [command, file, dir] if command == "export-frames" => {
+            Some(Command::ExportFrames(file.into(), dir.into()))
+        }
+        [command, file, dir] if command == "export-tmx" => {
+            Some(Command::ExportTmx(file.into(), dir.into()))
+        }
+        _ => None,
+    }
+}
+
+/// Replaces anything that isn't safe to use in a file name.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn info(doc: &Pyxel) {
+    let canvas = doc.canvas();
+    let tileset = doc.tileset();
+
+    println!("name:     {}", doc.name());
+    println!("version:  {}", doc.version());
+    println!(
+        "canvas:   {}x{} px, {}x{} tiles of {}x{} px",
+        canvas.width(),
+        canvas.height(),
+        canvas.tiles_wide(),
+        canvas.tiles_high(),
+        canvas.tile_width(),
+        canvas.tile_height()
+    );
+    println!(
+        "tileset:  {} tiles of {}x{} px",
+        tileset.images().len(),
+        tileset.tile_width(),
+        tileset.tile_height()
+    );
+    println!(
+        "palette:  {} colors",
+        doc.palette().colors().iter().flatten().count()
+    );
+
+    println!("layers:");
+    for (i, layer) in canvas.layers().iter().enumerate() {
+        println!(
+            "    {:>3} {:<24} {:?}, alpha {}{}",
+            i,
+            layer.name(),
+            layer.blend_mode(),
+            layer.alpha(),
+            if layer.hidden() { ", hidden" } else { "" }
+        );
+    }
+
+    println!("animations:");
+    for animation in doc.animations() {
+        let durations: Vec<String> = animation
+            .frame_durations()
+            .iter()
+            .map(|d| d.as_millis().to_string())
+            .collect();
+        println!(
+            "    {:<28} {} frames from tile {} ({} ms)",
+            animation.name(),
+            animation.length(),
+            animation.base_tile(),
+            durations.join(", ")
+        );
+    }
+}
+
+fn validate(doc: &Pyxel) -> bool {
+    let issues = doc.validate();
+    for issue in &issues {
+        println!("{}", issue);
+    }
+
+    issues.is_empty()
+}
+
+fn export_frames(doc: &Pyxel, dir: &Path) -> Result<(), PyxelError> {
+    fs::create_dir_all(dir)?;
+
+    let mut result = Ok(());
+    doc.for_each_frame_pixels(&FrameOptions::default(), |meta, pixels| {
+        if result.is_err() {
+            return;
+        }
+
+        let path = dir.join(format!(
+            "{}_{}.png",
+            file_name(meta.animation_name),
+            meta.frame
+        ));
+        result = image::save_buffer(
+            &path,
+            pixels,
+            meta.width,
+            meta.height,
+            image::ColorType::RGBA(8),
+        );
+    })?;
+
+    Ok(result?)
+}
+
+fn export_tmx(doc: &Pyxel, dir: &Path) -> Result<(), PyxelError> {
+    fs::create_dir_all(dir)?;
+
+    for (i, image) in doc.tileset().images().iter().enumerate() {
+        image.save(dir.join(format!("tile{}.png", i)))?;
+    }
+
+    let tmx = doc.to_tmx(&TmxOptions::default());
+    fs::write(dir.join(format!("{}.tmx", file_name(doc.name()))), tmx)?;
+    Ok(())
+}
+
+fn run(command: Command) -> Result<bool, PyxelError> {
+    match command {
+        Command::Info(file) => {
+            info(&pyxel::open(file)?);
+        }
+        Command::Validate(file) => return Ok(validate(&pyxel::open(file)?)),
+        Command::ExportFrames(file, dir) => export_frames(&pyxel::open(file)?, &dir)?,
+        Command::ExportTmx(file, dir) => export_tmx(&pyxel::open(file)?, &dir)?,
+    }
+
+    Ok(true)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let command = match parse_args(&args) {
+        Some(command) => command,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    match run(command) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
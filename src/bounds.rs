@@ -0,0 +1,96 @@
+use crate::pyxel::Layer;
+
+use image::{GenericImageView, Rgba};
+
+/// A rectangle of pixels.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Rect {
+    /// The column of the left edge of the rectangle.
+    pub x: u32,
+    /// The row of the top edge of the rectangle.
+    pub y: u32,
+    /// The width of the rectangle in pixels.
+    pub width: u32,
+    /// The height of the rectangle in pixels.
+    pub height: u32,
+}
+
+impl Layer {
+    /// Returns the smallest rectangle containing every non-transparent pixel of this layer, or
+    /// `None` if the layer is fully transparent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// if let Some(bounds) = doc.canvas().layers()[0].content_bounds() {
+    ///     println!("{}x{} at ({}, {})", bounds.width, bounds.height, bounds.x, bounds.y);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn content_bounds(&self) -> Option<Rect> {
+        opaque_bounds(self.image.as_ref())
+    }
+}
+
+/// Returns the smallest rectangle containing every non-transparent pixel of `image`.
+pub(crate) fn opaque_bounds<I: GenericImageView<Pixel = Rgba<u8>>>(image: &I) -> Option<Rect> {
+    let (mut min_x, mut min_y) = (u32::MAX, u32::MAX);
+    let (mut max_x, mut max_y) = (0, 0);
+
+    for (x, y, pixel) in image.pixels() {
+        if pixel.0[3] != 0 {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+
+    if min_x > max_x {
+        return None;
+    }
+
+    Some(Rect {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x + 1,
+        height: max_y - min_y + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    fn layer(image: RgbaImage) -> Layer {
+        let mut layer = crate::open("resources/doc.pyxel").unwrap().canvas.layers[0].clone();
+        *layer.image_mut() = DynamicImage::ImageRgba8(image);
+        layer
+    }
+
+    #[test]
+    fn empty_layer_has_no_bounds() {
+        assert_eq!(None, layer(RgbaImage::new(8, 8)).content_bounds());
+    }
+
+    #[test]
+    fn bounds_are_tight() {
+        let mut image = RgbaImage::new(8, 8);
+        image.put_pixel(2, 5, Rgba([255, 0, 0, 1]));
+        image.put_pixel(6, 3, Rgba([0, 0, 0, 255]));
+
+        assert_eq!(
+            Some(Rect {
+                x: 2,
+                y: 3,
+                width: 5,
+                height: 3
+            }),
+            layer(image).content_bounds()
+        );
+    }
+}
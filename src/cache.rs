@@ -0,0 +1,235 @@
+use crate::{doc_data::LoadOptions, error::PyxelError, load::load_with_options, pyxel::Pyxel};
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// Statistics about the loads made by a [`PyxelCache`](struct.PyxelCache.html).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    /// The number of requests answered with a document that was already loaded.
+    pub hits: u64,
+    /// The number of requests that had to load the document.
+    pub misses: u64,
+    /// The number of loads that failed.
+    pub failures: u64,
+    /// The time spent loading documents, including failed loads.
+    pub load_time: Duration,
+}
+
+/// The document loaded from one path. Its mutex is held whilst loading, so that concurrent
+/// requests for the same path wait for that load instead of starting their own.
+type Slot = Arc<Mutex<Option<Arc<Pyxel>>>>;
+
+#[derive(Debug, Default)]
+struct Inner {
+    options: LoadOptions,
+    slots: Mutex<HashMap<PathBuf, Slot>>,
+    stats: Mutex<CacheStats>,
+}
+
+/// Locks `mutex`, ignoring poisoning. Nothing is left half updated by a panic whilst one of the
+/// cache's locks is held, since the loaded document is only stored once it's complete.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Inner {
+    fn slot(&self, path: &Path) -> Slot {
+        lock(&self.slots)
+            .entry(path.to_path_buf())
+            .or_default()
+            .clone()
+    }
+
+    fn load(&self, path: &Path) -> Result<Pyxel, PyxelError> {
+        let start = Instant::now();
+        let result = File::open(path)
+            .map_err(PyxelError::from)
+            .and_then(|file| load_with_options(file, &self.options));
+
+        let mut stats = lock(&self.stats);
+        stats.misses += 1;
+        stats.load_time += start.elapsed();
+        if result.is_err() {
+            stats.failures += 1;
+        }
+
+        result
+    }
+}
+
+/// A cache of loaded Pyxel documents, keyed by path, which can be shared between threads.
+///
+/// Each document is loaded at most once until it's [invalidated](#method.invalidate), even when
+/// several threads ask for it at the same time. Paths are used as given, so the same file
+/// reached by two different paths is loaded twice. Clones of a cache share its documents and
+/// statistics.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let cache = pyxel::PyxelCache::new();
+/// let a = cache.get("resources/doc.pyxel")?;
+/// let b = cache.get("resources/doc.pyxel")?;
+/// assert!(std::sync::Arc::ptr_eq(&a, &b));
+/// assert_eq!(1, cache.stats().hits);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PyxelCache {
+    inner: Arc<Inner>,
+}
+
+impl PyxelCache {
+    /// Creates an empty cache that loads documents with the default options.
+    pub fn new() -> PyxelCache {
+        PyxelCache::default()
+    }
+
+    /// Creates an empty cache that loads documents with `options`.
+    pub fn with_options(options: LoadOptions) -> PyxelCache {
+        PyxelCache {
+            inner: Arc::new(Inner {
+                options,
+                ..Inner::default()
+            }),
+        }
+    }
+
+    /// Returns the document at `path`, loading it if it isn't already cached.
+    ///
+    /// If another thread is loading the same document, this waits for it to finish. Failed loads
+    /// aren't cached, so the next request tries again.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Result<Arc<Pyxel>, PyxelError> {
+        let path = path.as_ref();
+        let slot = self.inner.slot(path);
+        let mut doc = lock(&slot);
+
+        if let Some(ref doc) = *doc {
+            lock(&self.inner.stats).hits += 1;
+            return Ok(doc.clone());
+        }
+
+        let loaded = Arc::new(self.inner.load(path)?);
+        *doc = Some(loaded.clone());
+        Ok(loaded)
+    }
+
+    /// Returns the document at `path` if it's cached, without loading it.
+    pub fn get_cached<P: AsRef<Path>>(&self, path: P) -> Option<Arc<Pyxel>> {
+        let slot = lock(&self.inner.slots).get(path.as_ref())?.clone();
+        let doc = lock(&slot).clone();
+        doc
+    }
+
+    /// Removes the document at `path` from the cache, so that it's loaded again the next time
+    /// it's requested. Returns `true` if it was cached.
+    ///
+    /// Documents already handed out are unaffected.
+    pub fn invalidate<P: AsRef<Path>>(&self, path: P) -> bool {
+        match lock(&self.inner.slots).remove(path.as_ref()) {
+            Some(slot) => lock(&slot).is_some(),
+            None => false,
+        }
+    }
+
+    /// Removes every document from the cache.
+    pub fn clear(&self) {
+        lock(&self.inner.slots).clear();
+    }
+
+    /// Returns the number of documents in the cache.
+    pub fn len(&self) -> usize {
+        let slots: Vec<Slot> = lock(&self.inner.slots).values().cloned().collect();
+        slots.iter().filter(|slot| lock(slot).is_some()).count()
+    }
+
+    /// Returns `true` if the cache holds no documents.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns statistics about the requests made to this cache so far.
+    pub fn stats(&self) -> CacheStats {
+        *lock(&self.inner.stats)
+    }
+
+    /// Watches the document at `path`, replacing it in the cache whenever it's saved. A save
+    /// that fails to load invalidates the cached document instead.
+    ///
+    /// The document is watched until the returned watcher is dropped, see
+    /// [`watch`](fn.watch.html).
+    #[cfg(feature = "watch")]
+    pub fn watch<P: AsRef<Path>>(&self, path: P) -> Result<crate::watch::Watcher, PyxelError> {
+        let path = path.as_ref().to_path_buf();
+        let inner = self.inner.clone();
+
+        crate::watch::watch(path.clone(), move |result| match result {
+            Ok(doc) => {
+                *lock(&inner.slot(&path)) = Some(Arc::new(doc));
+            }
+            Err(_) => {
+                lock(&inner.slots).remove(&path);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn concurrent_requests_load_once() {
+        let cache = PyxelCache::new();
+
+        let docs: Vec<Arc<Pyxel>> = (0..8)
+            .map(|_| {
+                let cache = cache.clone();
+                thread::spawn(move || cache.get(TEST_FILE).unwrap())
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        assert!(docs.iter().all(|doc| Arc::ptr_eq(doc, &docs[0])));
+        let stats = cache.stats();
+        assert_eq!((7, 1, 0), (stats.hits, stats.misses, stats.failures));
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn invalidated_documents_are_reloaded() {
+        let cache = PyxelCache::new();
+        let first = cache.get(TEST_FILE).unwrap();
+
+        assert!(cache.invalidate(TEST_FILE));
+        assert!(!cache.invalidate(TEST_FILE));
+        assert!(cache.get_cached(TEST_FILE).is_none());
+
+        let second = cache.get(TEST_FILE).unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_eq!(first, second);
+        assert_eq!(2, cache.stats().misses);
+
+        assert!(cache.get("resources/missing.pyxel").is_err());
+        assert!(cache.get("resources/missing.pyxel").is_err());
+        let stats = cache.stats();
+        assert_eq!((4, 2), (stats.misses, stats.failures));
+        assert_eq!(1, cache.len());
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
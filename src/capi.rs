@@ -0,0 +1,340 @@
+//! The C ABI exposed by the `capi` feature, see `include/pyxel.h`.
+//!
+//! Every function taking a document accepts a null pointer, returning zero. Functions copying data
+//! out return the number of bytes required, and only copy when `buf` is at least that large, so
+//! callers can pass a null buffer first to size their allocation.
+
+use crate::{error::PyxelError, pyxel::Pyxel};
+
+use std::{cell::RefCell, ffi::CStr, os::raw::c_char, ptr, slice};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn into_handle(result: Result<Pyxel, PyxelError>) -> *mut Pyxel {
+    match result {
+        Ok(doc) => Box::into_raw(Box::new(doc)),
+        Err(e) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some(e.to_string()));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Copies `data` to `buf` if it holds at least `len` bytes, returning the number required.
+unsafe fn copy_out(data: &[u8], buf: *mut u8, len: usize) -> usize {
+    if !buf.is_null() && len >= data.len() {
+        ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len());
+    }
+    data.len()
+}
+
+/// Copies `s` to `buf` as a NUL terminated string if it holds at least `len` bytes, returning
+/// the number required.
+unsafe fn copy_str(s: &str, buf: *mut u8, len: usize) -> usize {
+    let mut data = Vec::with_capacity(s.len() + 1);
+    data.extend_from_slice(s.as_bytes());
+    data.push(0);
+    copy_out(&data, buf, len)
+}
+
+/// Opens the document at the NUL terminated `path`, returning null on failure.
+///
+/// # Safety
+///
+/// `path` must point to a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_open(path: *const c_char) -> *mut Pyxel {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(_) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some("path is not UTF-8".to_owned()));
+            return ptr::null_mut();
+        }
+    };
+    into_handle(crate::open(path))
+}
+
+/// Loads a document from the `len` bytes at `data`, returning null on failure.
+///
+/// # Safety
+///
+/// `data` must point to at least `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_load_from_memory(data: *const u8, len: usize) -> *mut Pyxel {
+    if data.is_null() {
+        return ptr::null_mut();
+    }
+    into_handle(crate::load_from_memory(slice::from_raw_parts(data, len)))
+}
+
+/// Frees a document returned by `pyxel_open` or `pyxel_load_from_memory`.
+///
+/// # Safety
+///
+/// `doc` must be null or a document that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_free(doc: *mut Pyxel) {
+    if !doc.is_null() {
+        drop(Box::from_raw(doc));
+    }
+}
+
+/// Copies the message of the last error on this thread, returning zero if there hasn't been one.
+///
+/// # Safety
+///
+/// `buf` must be null or point to at least `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_last_error(buf: *mut u8, len: usize) -> usize {
+    LAST_ERROR.with(|last| match *last.borrow() {
+        Some(ref message) => copy_str(message, buf, len),
+        None => 0,
+    })
+}
+
+/// Returns the width of the canvas in pixels.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_canvas_width(doc: *const Pyxel) -> i32 {
+    doc.as_ref().map_or(0, |doc| doc.canvas.width)
+}
+
+/// Returns the height of the canvas in pixels.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_canvas_height(doc: *const Pyxel) -> i32 {
+    doc.as_ref().map_or(0, |doc| doc.canvas.height)
+}
+
+/// Returns the width of the tiles in pixels.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_tile_width(doc: *const Pyxel) -> u16 {
+    doc.as_ref().map_or(0, |doc| doc.canvas.tile_width)
+}
+
+/// Returns the height of the tiles in pixels.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_tile_height(doc: *const Pyxel) -> u16 {
+    doc.as_ref().map_or(0, |doc| doc.canvas.tile_height)
+}
+
+/// Returns the number of layers, top-most first.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_layer_count(doc: *const Pyxel) -> usize {
+    doc.as_ref().map_or(0, |doc| doc.canvas.layers.len())
+}
+
+/// Copies the NUL terminated name of `layer`, returning zero if it doesn't exist.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document, and `buf` must be null or point to at least `len`
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_layer_name(
+    doc: *const Pyxel,
+    layer: usize,
+    buf: *mut u8,
+    len: usize,
+) -> usize {
+    match doc.as_ref().and_then(|doc| doc.canvas.layers.get(layer)) {
+        Some(layer) => copy_str(&layer.name, buf, len),
+        None => 0,
+    }
+}
+
+/// Returns the alpha of `layer`, or zero if it doesn't exist.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_layer_alpha(doc: *const Pyxel, layer: usize) -> u8 {
+    doc.as_ref()
+        .and_then(|doc| doc.canvas.layers.get(layer))
+        .map_or(0, |layer| layer.alpha)
+}
+
+/// Returns whether `layer` is shown in the editor, taking hidden, muted and soloed layers into
+/// account.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_layer_visible(doc: *const Pyxel, layer: usize) -> bool {
+    doc.as_ref()
+        .and_then(|doc| doc.canvas.effective_visibility().get(layer).copied())
+        .unwrap_or(false)
+}
+
+/// Copies the pixels of `layer` as rows of 8-bit RGBA, returning zero if it doesn't exist.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document, and `buf` must be null or point to at least `len`
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_layer_pixels(
+    doc: *const Pyxel,
+    layer: usize,
+    buf: *mut u8,
+    len: usize,
+) -> usize {
+    match doc.as_ref().and_then(|doc| doc.canvas.layers.get(layer)) {
+        Some(layer) => copy_out(&layer.image.to_rgba(), buf, len),
+        None => 0,
+    }
+}
+
+/// Returns the number of tiles in the tileset.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_tile_count(doc: *const Pyxel) -> usize {
+    doc.as_ref().map_or(0, |doc| doc.tileset.images.len())
+}
+
+/// Copies the pixels of `tile` as rows of 8-bit RGBA, returning zero if it doesn't exist.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document, and `buf` must be null or point to at least `len`
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_tile_pixels(
+    doc: *const Pyxel,
+    tile: usize,
+    buf: *mut u8,
+    len: usize,
+) -> usize {
+    match doc.as_ref().and_then(|doc| doc.tileset.images.get(tile)) {
+        Some(image) => copy_out(&image.to_rgba(), buf, len),
+        None => 0,
+    }
+}
+
+/// Returns the number of animations.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_animation_count(doc: *const Pyxel) -> usize {
+    doc.as_ref().map_or(0, |doc| doc.animations.len())
+}
+
+/// Returns the canvas cell of the first frame of `animation`, or zero if it doesn't exist.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_animation_base_tile(doc: *const Pyxel, animation: usize) -> usize {
+    doc.as_ref()
+        .and_then(|doc| doc.animations.get(animation))
+        .map_or(0, |animation| animation.base_tile)
+}
+
+/// Returns the number of frames of `animation`, or zero if it doesn't exist.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_animation_length(doc: *const Pyxel, animation: usize) -> usize {
+    doc.as_ref()
+        .and_then(|doc| doc.animations.get(animation))
+        .map_or(0, |animation| animation.length)
+}
+
+/// Returns the duration of `frame` of `animation` in milliseconds, or zero if it doesn't exist.
+///
+/// # Safety
+///
+/// `doc` must be null or a live document.
+#[no_mangle]
+pub unsafe extern "C" fn pyxel_animation_frame_duration_ms(
+    doc: *const Pyxel,
+    animation: usize,
+    frame: usize,
+) -> u64 {
+    doc.as_ref()
+        .and_then(|doc| doc.animations.get(animation))
+        .and_then(|animation| animation.frame_durations().get(frame).copied())
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn read_document_through_c_abi() {
+        let path = CString::new("resources/test_v0.4.8.pyxel").unwrap();
+
+        unsafe {
+            let doc = pyxel_open(path.as_ptr());
+            assert!(!doc.is_null());
+
+            assert_eq!(256, pyxel_canvas_width(doc));
+            assert_eq!(11, pyxel_layer_count(doc));
+
+            let len = pyxel_layer_name(doc, 0, ptr::null_mut(), 0);
+            let mut name = vec![0; len];
+            assert_eq!(len, pyxel_layer_name(doc, 0, name.as_mut_ptr(), len));
+            assert_eq!(b"Layer 10\0", name.as_slice());
+
+            assert_eq!(
+                256 * 128 * 4,
+                pyxel_layer_pixels(doc, 0, ptr::null_mut(), 0)
+            );
+            assert_eq!(0, pyxel_layer_pixels(doc, 11, ptr::null_mut(), 0));
+            assert_eq!(300, pyxel_animation_frame_duration_ms(doc, 0, 1));
+
+            pyxel_free(doc);
+        }
+    }
+
+    #[test]
+    fn report_errors() {
+        let path = CString::new("resources/missing.pyxel").unwrap();
+
+        unsafe {
+            assert!(pyxel_open(path.as_ptr()).is_null());
+
+            let len = pyxel_last_error(ptr::null_mut(), 0);
+            assert!(len > 1);
+            let mut message = vec![0xff; len];
+            pyxel_last_error(message.as_mut_ptr(), len);
+            assert_eq!(Some(&0), message.last());
+        }
+    }
+}
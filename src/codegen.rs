@@ -0,0 +1,227 @@
+use crate::{
+    error::PyxelError,
+    frames::FrameOptions,
+    pixels::{convert, PixelFormat},
+    pyxel::Pyxel,
+    text::write_str,
+};
+
+use image::{ImageBuffer, RgbaImage};
+
+/// Options for [`Pyxel::to_rust_source`](struct.Pyxel.html#method.to_rust_source).
+#[derive(Clone, Debug)]
+pub struct CodegenOptions {
+    /// The format the pixels are emitted in.
+    pub format: PixelFormat,
+    /// The indices of the tiles to emit, in order. All tiles are emitted when `None`.
+    pub tiles: Option<Vec<usize>>,
+    /// The indices of the animations whose frames are emitted, in order. All animations are
+    /// emitted when `None`.
+    pub animations: Option<Vec<usize>>,
+}
+
+impl Default for CodegenOptions {
+    fn default() -> Self {
+        CodegenOptions {
+            format: PixelFormat::Rgba8888,
+            tiles: None,
+            animations: None,
+        }
+    }
+}
+
+fn write_bytes(rs: &mut String, bytes: &[u8]) {
+    rs.push_str("&[\n");
+    for chunk in bytes.chunks(16) {
+        rs.push_str("        ");
+        let line: Vec<String> = chunk.iter().map(|b| format!("0x{:02x},", b)).collect();
+        rs.push_str(&line.join(" "));
+        rs.push('\n');
+    }
+    rs.push_str("    ]");
+}
+
+impl Pyxel {
+    /// Renders the selected tiles and animation frames of this document and emits Rust source
+    /// holding them as `const` byte arrays, so they can be compiled into a binary from a build
+    /// script and used without `std`.
+    ///
+    /// The source defines `TILE_WIDTH` and `TILE_HEIGHT`, `TILES` and `FRAMES` holding the pixels
+    /// of each tile and frame in `options.format`, and three arrays with an entry per animation:
+    /// `ANIMATION_NAMES`, `ANIMATION_FRAMES` holding indices into `FRAMES`, and
+    /// `ANIMATION_DURATIONS_MS`. Wrap the `include!` in a module to embed several documents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let source = doc.to_rust_source(&pyxel::CodegenOptions::default())?;
+    ///
+    /// // In a build script, write this to a file in `OUT_DIR` and `include!` it.
+    /// assert!(source.contains("pub const TILES: [&[u8]; 4]"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_rust_source(&self, options: &CodegenOptions) -> Result<String, PyxelError> {
+        let tiles: Vec<usize> = match options.tiles {
+            Some(ref tiles) => tiles.clone(),
+            None => (0..self.tileset.tile_count()).collect(),
+        };
+        let tile_pixels = tiles
+            .iter()
+            .map(|&i| {
+                self.tileset
+                    .images
+                    .get(i)
+                    .map(|image| convert(&image.to_rgba(), options.format))
+                    .ok_or(PyxelError::TileOutOfBounds(i))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let frame_options = FrameOptions {
+            animations: options.animations.clone(),
+            ..FrameOptions::default()
+        };
+        let mut frames = Vec::new();
+        let mut animations: Vec<(usize, Vec<usize>, Vec<u128>)> = Vec::new();
+        self.for_each_frame_pixels(&frame_options, |meta, pixels| {
+            if meta.frame == 0 {
+                animations.push((meta.animation, Vec::new(), Vec::new()));
+            }
+            let animation = animations.last_mut().expect("frame 0 starts an animation");
+            animation.1.push(frames.len());
+            animation.2.push(meta.duration.as_millis());
+
+            let image: RgbaImage = ImageBuffer::from_raw(meta.width, meta.height, pixels.to_vec())
+                .expect("frame pixels match their dimensions");
+            frames.push(convert(&image, options.format));
+        })?;
+
+        let mut rs = String::new();
+
+        write_str!(
+            rs,
+            "// Generated by pyxel {} from {:?}.\n",
+            env!("CARGO_PKG_VERSION"),
+            self.name
+        );
+        write_str!(rs, "// Pixel format: {:?}.\n\n", options.format);
+        write_str!(
+            rs,
+            "pub const TILE_WIDTH: u32 = {};\n",
+            self.canvas.tile_width
+        );
+        write_str!(
+            rs,
+            "pub const TILE_HEIGHT: u32 = {};\n\n",
+            self.canvas.tile_height
+        );
+
+        write_str!(rs, "pub const TILES: [&[u8]; {}] = [\n", tile_pixels.len());
+        for (i, pixels) in tiles.iter().zip(&tile_pixels) {
+            write_str!(rs, "    // Tile {}.\n    ", i);
+            write_bytes(&mut rs, pixels);
+            write_str!(rs, ",\n");
+        }
+        write_str!(rs, "];\n\n");
+
+        write_str!(rs, "pub const FRAMES: [&[u8]; {}] = [\n", frames.len());
+        for pixels in &frames {
+            write_str!(rs, "    ");
+            write_bytes(&mut rs, pixels);
+            write_str!(rs, ",\n");
+        }
+        write_str!(rs, "];\n\n");
+
+        let list = |values: &[String]| values.join(", ");
+        write_str!(
+            rs,
+            "pub const ANIMATION_NAMES: [&str; {}] = [{}];\n",
+            animations.len(),
+            list(
+                &animations
+                    .iter()
+                    .map(|a| format!("{:?}", self.animations[a.0].name))
+                    .collect::<Vec<_>>()
+            )
+        );
+        write_str!(
+            rs,
+            "pub const ANIMATION_FRAMES: [&[usize]; {}] = [{}];\n",
+            animations.len(),
+            list(
+                &animations
+                    .iter()
+                    .map(|a| format!("&{:?}", a.1))
+                    .collect::<Vec<_>>()
+            )
+        );
+        write_str!(
+            rs,
+            "pub const ANIMATION_DURATIONS_MS: [&[u32]; {}] = [{}];\n",
+            animations.len(),
+            list(
+                &animations
+                    .iter()
+                    .map(|a| format!("&{:?}", a.2))
+                    .collect::<Vec<_>>()
+            )
+        );
+
+        Ok(rs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load::load, pixels::ByteOrder};
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn emits_selected_tiles_and_animations() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = CodegenOptions {
+            format: PixelFormat::Rgb565(ByteOrder::BigEndian),
+            tiles: Some(vec![3, 1]),
+            animations: Some(vec![2, 1]),
+        };
+        let rs = doc.to_rust_source(&options).unwrap();
+
+        assert!(rs.contains("pub const TILE_WIDTH: u32 = 32;\n"));
+        assert!(rs.contains("pub const TILES: [&[u8]; 2] = [\n    // Tile 3.\n"));
+        assert!(rs.contains("pub const FRAMES: [&[u8]; 4] = [\n"));
+        assert!(rs.contains(&format!(
+            "pub const ANIMATION_NAMES: [&str; 2] = [{:?}, {:?}];\n",
+            doc.animations[2].name, doc.animations[1].name
+        )));
+        assert!(rs.contains("pub const ANIMATION_FRAMES: [&[usize]; 2] = [&[0, 1], &[2, 3]];\n"));
+        assert!(rs.contains(
+            "pub const ANIMATION_DURATIONS_MS: [&[u32]; 2] = [&[1000, 1000], &[100, 100]];\n"
+        ));
+
+        // Each 32x16 tile is 1024 bytes of RGB565, 16 to a line.
+        let tile = &rs[rs.find("// Tile 1.").unwrap()..];
+        let lines = tile
+            .lines()
+            .skip(2)
+            .take_while(|l| l.starts_with("        0x"));
+        assert_eq!(32 * 16 * 2 / 16, lines.count());
+    }
+
+    #[test]
+    fn unknown_tile_is_rejected() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = CodegenOptions {
+            tiles: Some(vec![4]),
+            ..CodegenOptions::default()
+        };
+        match doc.to_rust_source(&options) {
+            Err(PyxelError::TileOutOfBounds(4)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
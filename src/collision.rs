@@ -0,0 +1,190 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Canvas, Layer, Pyxel},
+};
+
+/// How [`Pyxel::collision_grid`](struct.Pyxel.html#method.collision_grid) decides whether a
+/// canvas cell is solid.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CollisionRule {
+    /// A cell is solid if the layer places any tile in it.
+    AnyTile,
+    /// A cell is solid if the layer places one of these tiles in it.
+    Tiles(Vec<usize>),
+    /// A cell is solid if any of the layer's pixels within it aren't fully transparent.
+    #[cfg(feature = "images")]
+    Opaque,
+}
+
+/// A grid of the solid and empty cells of a canvas, produced by
+/// [`Pyxel::collision_grid`](struct.Pyxel.html#method.collision_grid).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollisionGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+}
+
+impl CollisionGrid {
+    /// Returns the number of columns of this grid.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows of this grid.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns `true` if the cell at `col`, `row` is solid. Cells outside of the grid are empty.
+    pub fn is_solid(&self, col: usize, row: usize) -> bool {
+        col < self.width && row < self.height && self.cells[row * self.width + col]
+    }
+
+    /// Returns the cells of this grid row by row.
+    pub fn cells(&self) -> &[bool] {
+        &self.cells
+    }
+
+    /// Returns an iterator over the rows of this grid.
+    pub fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        // `chunks` panics on a zero chunk size, and an empty grid has no rows either way.
+        self.cells.chunks(self.width.max(1))
+    }
+}
+
+impl Pyxel {
+    /// Converts the first layer named `layer_name` into a grid with a cell for each canvas tile,
+    /// marking the cells that `rule` considers solid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyxel::CollisionRule;
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let grid = doc.collision_grid("Layer 0", &CollisionRule::AnyTile)?;
+    /// for row in grid.rows() {
+    ///     let line: String = row.iter().map(|&solid| if solid { '#' } else { '.' }).collect();
+    ///     println!("{}", line);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn collision_grid(
+        &self,
+        layer_name: &str,
+        rule: &CollisionRule,
+    ) -> Result<CollisionGrid, PyxelError> {
+        let canvas = &self.canvas;
+        let layer = canvas
+            .layers
+            .iter()
+            .find(|layer| layer.name == layer_name)
+            .ok_or_else(|| PyxelError::LayerNotFound(layer_name.to_owned()))?;
+
+        let (width, height) = (canvas.tiles_wide(), canvas.tiles_high());
+        let mut cells = Vec::with_capacity(width * height);
+        for row in 0..height {
+            for col in 0..width {
+                cells.push(is_solid(canvas, layer, col, row, rule));
+            }
+        }
+
+        Ok(CollisionGrid {
+            width,
+            height,
+            cells,
+        })
+    }
+}
+
+fn is_solid(canvas: &Canvas, layer: &Layer, col: usize, row: usize, rule: &CollisionRule) -> bool {
+    let tile_ref = layer.tile_refs.get(&(row * canvas.tiles_wide() + col));
+
+    match *rule {
+        CollisionRule::AnyTile => tile_ref.is_some(),
+        CollisionRule::Tiles(ref tiles) => tile_ref.is_some_and(|r| tiles.contains(&r.index)),
+        #[cfg(feature = "images")]
+        CollisionRule::Opaque => {
+            use image::GenericImageView;
+
+            let (tile_width, tile_height) =
+                (u32::from(canvas.tile_width), u32::from(canvas.tile_height));
+            let (x, y) = (col as u32 * tile_width, row as u32 * tile_height);
+            let image = layer.image.as_ref();
+            if x + tile_width > image.width() || y + tile_height > image.height() {
+                return false;
+            }
+
+            image
+                .view(x, y, tile_width, tile_height)
+                .pixels()
+                .any(|(_, _, pixel)| pixel.0[3] != 0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    fn load_test_file() -> Pyxel {
+        load(File::open(TEST_FILE).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn collision_grid_follows_tile_refs() {
+        let doc = load_test_file();
+        let layer = &doc.canvas.layers[1];
+
+        let grid = doc
+            .collision_grid(&layer.name, &CollisionRule::AnyTile)
+            .unwrap();
+        assert_eq!((8, 8), (grid.width(), grid.height()));
+        assert_eq!(8, grid.cells().iter().filter(|&&solid| solid).count());
+        for &cell in layer.tile_refs.keys() {
+            assert!(grid.is_solid(cell % 8, cell / 8));
+        }
+
+        let tile = layer.tile_refs.values().next().unwrap().index;
+        let grid = doc
+            .collision_grid(&layer.name, &CollisionRule::Tiles(vec![tile]))
+            .unwrap();
+        let expected = layer.tile_refs.values().filter(|r| r.index == tile).count();
+        assert_eq!(
+            expected,
+            grid.cells().iter().filter(|&&solid| solid).count()
+        );
+    }
+
+    #[test]
+    fn collision_grid_requires_layer() {
+        let doc = load_test_file();
+        match doc.collision_grid("Collision", &CollisionRule::AnyTile) {
+            Err(PyxelError::LayerNotFound(ref name)) if name == "Collision" => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn opaque_cells_are_solid() {
+        let mut doc = load_test_file();
+        *doc.canvas.layers[0].image_mut() = image::DynamicImage::new_rgba8(256, 128);
+        doc.canvas.layers[0]
+            .image_mut()
+            .as_mut_rgba8()
+            .unwrap()
+            .put_pixel(40, 20, image::Rgba([0, 0, 0, 1]));
+
+        let name = doc.canvas.layers[0].name.clone();
+        let grid = doc.collision_grid(&name, &CollisionRule::Opaque).unwrap();
+        assert_eq!(1, grid.cells().iter().filter(|&&solid| solid).count());
+        assert!(grid.is_solid(1, 1));
+    }
+}
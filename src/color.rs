@@ -0,0 +1,251 @@
+use crate::pyxel::Color;
+
+/// Returns `value`, between 0 and 1, as a color component.
+fn component(value: f64) -> u8 {
+    (value.clamp(0., 1.) * 255.).round() as u8
+}
+
+/// Returns the hue in degrees, the smallest and the largest of the components of `color`, each
+/// between 0 and 1.
+fn hue_min_max(color: Color) -> (f64, f64, f64) {
+    let (r, g, b) = (
+        f64::from(color.r) / 255.,
+        f64::from(color.g) / 255.,
+        f64::from(color.b) / 255.,
+    );
+    let (min, max) = (r.min(g).min(b), r.max(g).max(b));
+    let delta = max - min;
+
+    let hue = if delta == 0. {
+        0.
+    } else if max == r {
+        60. * ((g - b) / delta).rem_euclid(6.)
+    } else if max == g {
+        60. * ((b - r) / delta + 2.)
+    } else {
+        60. * ((r - g) / delta + 4.)
+    };
+
+    (hue, min, max)
+}
+
+/// Returns the color with the given hue in degrees, chroma and smallest component, each between
+/// 0 and 1.
+fn from_hue_chroma(hue: f64, chroma: f64, min: f64, a: u8) -> Color {
+    let hue = hue.rem_euclid(360.) / 60.;
+    let x = chroma * (1. - (hue.rem_euclid(2.) - 1.).abs());
+
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+
+    Color {
+        r: component(r + min),
+        g: component(g + min),
+        b: component(b + min),
+        a,
+    }
+}
+
+/// Converts an sRGB component to linear light.
+fn linearize(component: u8) -> f64 {
+    let c = f64::from(component) / 255.;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl Color {
+    /// Returns the hue in degrees, from 0 up to 360, and the saturation and value of this color,
+    /// between 0 and 1. The alpha of the color is left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyxel::Color;
+    ///
+    /// let orange: Color = "ffff8000".parse().unwrap();
+    /// let (h, s, v) = orange.to_hsv();
+    /// assert_eq!((30, 1.0, 1.0), (h.round() as u32, s, v));
+    /// ```
+    pub fn to_hsv(self) -> (f64, f64, f64) {
+        let (hue, min, max) = hue_min_max(self);
+        let saturation = if max == 0. { 0. } else { (max - min) / max };
+        (hue, saturation, max)
+    }
+
+    /// Returns the color with the given hue in degrees and saturation and value between 0 and 1,
+    /// and the alpha `a`. The hue wraps around and the saturation and value are clamped.
+    pub fn from_hsv(hue: f64, saturation: f64, value: f64, a: u8) -> Color {
+        let (saturation, value) = (saturation.clamp(0., 1.), value.clamp(0., 1.));
+        let chroma = value * saturation;
+        from_hue_chroma(hue, chroma, value - chroma, a)
+    }
+
+    /// Returns the hue in degrees, from 0 up to 360, and the saturation and lightness of this
+    /// color, between 0 and 1. The alpha of the color is left out.
+    pub fn to_hsl(self) -> (f64, f64, f64) {
+        let (hue, min, max) = hue_min_max(self);
+        let lightness = (max + min) / 2.;
+        let saturation = if max == min {
+            0.
+        } else {
+            (max - min) / (1. - (2. * lightness - 1.).abs())
+        };
+        (hue, saturation, lightness)
+    }
+
+    /// Returns the color with the given hue in degrees and saturation and lightness between 0
+    /// and 1, and the alpha `a`. The hue wraps around and the saturation and lightness are
+    /// clamped.
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64, a: u8) -> Color {
+        let (saturation, lightness) = (saturation.clamp(0., 1.), lightness.clamp(0., 1.));
+        let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+        from_hue_chroma(hue, chroma, lightness - chroma / 2., a)
+    }
+
+    /// Returns this color with its HSL lightness raised by `percent` percentage points, keeping
+    /// its hue, saturation and alpha. Lightening by 100 always gives white.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyxel::Color;
+    ///
+    /// let red: Color = "ffff0000".parse().unwrap();
+    /// assert_eq!("ffff6666", red.lighten(20.).to_string());
+    /// assert_eq!("ff990000", red.darken(20.).to_string());
+    /// ```
+    pub fn lighten(self, percent: f64) -> Color {
+        let (hue, saturation, lightness) = self.to_hsl();
+        Color::from_hsl(hue, saturation, lightness + percent / 100., self.a)
+    }
+
+    /// Returns this color with its HSL lightness lowered by `percent` percentage points, keeping
+    /// its hue, saturation and alpha. Darkening by 100 always gives black.
+    pub fn darken(self, percent: f64) -> Color {
+        self.lighten(-percent)
+    }
+
+    /// Returns the color `t` of the way from this color to `other`, where `t` is clamped between 0
+    /// and 1.
+    ///
+    /// The components are weighted by their alpha, so mixing with a transparent color only
+    /// changes the alpha rather than darkening the result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyxel::Color;
+    ///
+    /// let red: Color = "ffff0000".parse().unwrap();
+    /// let clear: Color = "00000000".parse().unwrap();
+    /// assert_eq!("80ff0000", red.mix(clear, 0.5).to_string());
+    /// ```
+    pub fn mix(self, other: Color, t: f64) -> Color {
+        let t = t.clamp(0., 1.);
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+
+        let (alpha, other_alpha) = (f64::from(self.a) / 255., f64::from(other.a) / 255.);
+        let a = lerp(alpha, other_alpha);
+        if a == 0. {
+            return Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 0,
+            };
+        }
+
+        let mix = |c: u8, other: u8| {
+            let premultiplied = lerp(
+                f64::from(c) / 255. * alpha,
+                f64::from(other) / 255. * other_alpha,
+            );
+            component(premultiplied / a)
+        };
+
+        Color {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b),
+            a: component(a),
+        }
+    }
+
+    /// Returns the relative luminance of this color, between 0 for black and 1 for white, as
+    /// used for contrast ratios. The alpha of the color is left out.
+    pub fn luminance(self) -> f64 {
+        0.2126 * linearize(self.r) + 0.7152 * linearize(self.g) + 0.0722 * linearize(self.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(s: &str) -> Color {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn hsv_and_hsl_round_trip() {
+        for c in &[
+            "ffbe3535", "fff99b97", "ff915f33", "80d17f30", "fff7ee59", "ff59cd36",
+        ] {
+            let c = color(c);
+
+            let (h, s, v) = c.to_hsv();
+            assert_eq!(c, Color::from_hsv(h, s, v, c.a));
+
+            let (h, s, l) = c.to_hsl();
+            assert_eq!(c, Color::from_hsl(h, s, l, c.a));
+        }
+
+        let (h, s, l) = color("ff3366cc").to_hsl();
+        assert_eq!(
+            (220, 60, 50),
+            (
+                h.round() as u32,
+                (s * 100.).round() as u32,
+                (l * 100.).round() as u32
+            )
+        );
+        assert_eq!(color("ff808080"), Color::from_hsv(400., 0., 0.5, 255));
+        assert_eq!(color("ffff0000"), Color::from_hsv(360., 2., 1., 255));
+    }
+
+    #[test]
+    fn lighten_and_darken_keep_alpha() {
+        let c = color("80336699");
+        assert_eq!(color("80ffffff"), c.lighten(100.));
+        assert_eq!(color("80000000"), c.darken(100.));
+        assert_eq!(c, c.lighten(0.));
+    }
+
+    #[test]
+    fn mix_weighs_by_alpha() {
+        let (red, blue) = (color("ffff0000"), color("ff0000ff"));
+        assert_eq!(color("ff800080"), red.mix(blue, 0.5));
+        assert_eq!(red, red.mix(blue, -1.));
+        assert_eq!(blue, red.mix(blue, 2.));
+        assert_eq!(
+            color("00000000"),
+            color("00ff0000").mix(color("0000ff00"), 0.5)
+        );
+    }
+
+    #[test]
+    fn luminance_spans_black_to_white() {
+        assert_eq!(0., color("ff000000").luminance());
+        assert!((color("ffffffff").luminance() - 1.).abs() < 1e-9);
+        assert!(color("ff00ff00").luminance() > color("ffff0000").luminance());
+    }
+}
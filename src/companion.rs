@@ -0,0 +1,380 @@
+use crate::pyxel::{Canvas, Layer};
+#[cfg(feature = "images")]
+use crate::{
+    bounds::{opaque_bounds, Rect},
+    pyxel::Pyxel,
+    render::crop_layer,
+};
+
+#[cfg(feature = "images")]
+use image::{imageops, GenericImageView, RgbaImage};
+
+/// A layer together with the companion layers named after it by convention, such as the normal
+/// map `Hero_normal` and emissive mask `Hero_emissive` of the layer `Hero`. Created by
+/// [`Canvas::layer_groups_by_suffix`](struct.Canvas.html#method.layer_groups_by_suffix).
+#[derive(Clone, Debug)]
+pub struct LayerGroup<'a> {
+    /// The layer the companions are named after.
+    pub base: &'a Layer,
+    /// The companion layers with the suffix each was found by, in the order the suffixes were
+    /// given. Suffixes without a companion layer are left out.
+    pub companions: Vec<(&'a str, &'a Layer)>,
+}
+
+impl<'a> LayerGroup<'a> {
+    /// Returns the companion layer found by `suffix`, if there is one.
+    pub fn companion(&self, suffix: &str) -> Option<&'a Layer> {
+        self.companions
+            .iter()
+            .find(|(s, _)| *s == suffix)
+            .map(|&(_, layer)| layer)
+    }
+
+    /// Returns the base layer followed by its companions.
+    pub fn layers(&self) -> impl Iterator<Item = &'a Layer> + '_ {
+        std::iter::once(self.base).chain(self.companions.iter().map(|&(_, layer)| layer))
+    }
+}
+
+impl Canvas {
+    /// Pairs each layer with the layers named after it with one of `suffixes` appended, in layer
+    /// order.
+    ///
+    /// A layer is a companion when its name is the name of another layer followed by one of the
+    /// suffixes, and every other layer is the base of a group, so a `Hero_normal` layer without a
+    /// `Hero` layer is a group of its own. Empty suffixes are ignored. When several layers share
+    /// a name the first is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// for group in doc.canvas().layer_groups_by_suffix(&["_normal", "_emissive"]) {
+    ///     if let Some(normal) = group.companion("_normal") {
+    ///         println!("{} is lit by {}", group.base.name(), normal.name());
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn layer_groups_by_suffix<'a>(&'a self, suffixes: &[&'a str]) -> Vec<LayerGroup<'a>> {
+        let find = |name: &str| self.layers.iter().find(|layer| layer.name == name);
+        let is_companion = |layer: &Layer| {
+            suffixes.iter().any(|suffix| {
+                !suffix.is_empty()
+                    && layer
+                        .name
+                        .strip_suffix(suffix)
+                        .and_then(find)
+                        .is_some_and(|base| !std::ptr::eq(base, layer))
+            })
+        };
+
+        self.layers
+            .iter()
+            .filter(|layer| !is_companion(layer))
+            .map(|base| LayerGroup {
+                base,
+                companions: suffixes
+                    .iter()
+                    .filter(|suffix| !suffix.is_empty())
+                    .filter_map(|&suffix| {
+                        find(&format!("{}{}", base.name, suffix)).map(|layer| (suffix, layer))
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// The images of a [`LayerGroup`](struct.LayerGroup.html) cropped to the same rectangle, as
+/// returned by [`LayerGroup::trimmed`](struct.LayerGroup.html#method.trimmed).
+#[cfg(feature = "images")]
+#[derive(Clone, Debug)]
+pub struct TrimmedLayerGroup {
+    /// The area of the layers the images were cropped to.
+    pub bounds: Rect,
+    /// The cropped image of the base layer.
+    pub base: RgbaImage,
+    /// The cropped image of each companion layer with its suffix, in the order of the group.
+    pub companions: Vec<(String, RgbaImage)>,
+}
+
+/// A frame of the sheets packed by
+/// [`Pyxel::pack_layer_group_frames`](struct.Pyxel.html#method.pack_layer_group_frames).
+#[cfg(feature = "images")]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct LayerGroupFrame {
+    /// The index of the animation in the document.
+    pub animation: usize,
+    /// The index of the frame within the animation.
+    pub frame: usize,
+    /// The area of every sheet holding the frame's pixels.
+    pub rect: Rect,
+    /// The area of the untrimmed frame the pixels were taken from.
+    pub trimmed: Rect,
+}
+
+/// The animation frames of a [`LayerGroup`](struct.LayerGroup.html) packed into a sheet per
+/// layer with the same layout, as returned by
+/// [`Pyxel::pack_layer_group_frames`](struct.Pyxel.html#method.pack_layer_group_frames).
+#[cfg(feature = "images")]
+#[derive(Clone, Debug)]
+pub struct LayerGroupSheets {
+    /// The sheet of the base layer.
+    pub base: RgbaImage,
+    /// The sheet of each companion layer with its suffix, in the order of the group.
+    pub companions: Vec<(String, RgbaImage)>,
+    /// Where each frame is in the sheets, in animation order.
+    pub frames: Vec<LayerGroupFrame>,
+}
+
+/// Returns the smallest rectangle containing every non-transparent pixel of any of `images`, or
+/// `None` if they're all fully transparent.
+#[cfg(feature = "images")]
+fn union_bounds<'a>(images: impl IntoIterator<Item = &'a RgbaImage>) -> Option<Rect> {
+    images.into_iter().filter_map(opaque_bounds).reduce(|a, b| {
+        let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+        Rect {
+            x,
+            y,
+            width: (a.x + a.width).max(b.x + b.width) - x,
+            height: (a.y + a.height).max(b.y + b.height) - y,
+        }
+    })
+}
+
+#[cfg(feature = "images")]
+impl<'a> LayerGroup<'a> {
+    /// Crops the base layer and every companion to the smallest rectangle containing the
+    /// non-transparent pixels of any of them, so the images stay aligned, or returns `None` if
+    /// they're all fully transparent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// for group in doc.canvas().layer_groups_by_suffix(&["_normal"]) {
+    ///     if let Some(trimmed) = group.trimmed() {
+    ///         assert_eq!(trimmed.bounds.width, trimmed.base.width());
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn trimmed(&self) -> Option<TrimmedLayerGroup> {
+        let images: Vec<RgbaImage> = self.layers().map(|layer| layer.image.to_rgba()).collect();
+        let bounds = union_bounds(&images)?;
+
+        let mut cropped = images.into_iter().map(|mut image| {
+            imageops::crop(&mut image, bounds.x, bounds.y, bounds.width, bounds.height).to_image()
+        });
+        Some(TrimmedLayerGroup {
+            bounds,
+            base: cropped.next().expect("the base layer has an image"),
+            companions: self
+                .companions
+                .iter()
+                .zip(cropped)
+                .map(|(&(suffix, _), image)| (suffix.to_owned(), image))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(feature = "images")]
+impl Pyxel {
+    /// Packs the frames of this document's animations, as shown by each layer of `group`, into a
+    /// sheet per layer, a row of frames per animation with `padding` transparent pixels between
+    /// and around them.
+    ///
+    /// The sheets share one layout. When `trim` is set each frame is trimmed to the smallest
+    /// rectangle containing the non-transparent pixels of any of the layers, so a normal map or
+    /// emissive mask stays aligned with the base layer however little of it is drawn. Only the
+    /// layers of the group are rendered, without their blend modes or alpha.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let groups = doc.canvas().layer_groups_by_suffix(&["_normal"]);
+    /// let sheets = doc.pack_layer_group_frames(&groups[0], true, 1);
+    /// for (_, sheet) in &sheets.companions {
+    ///     assert_eq!(sheets.base.dimensions(), sheet.dimensions());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pack_layer_group_frames(
+        &self,
+        group: &LayerGroup<'_>,
+        trim: bool,
+        padding: u32,
+    ) -> LayerGroupSheets {
+        let canvas = &self.canvas;
+        let (tile_width, tile_height) =
+            (u32::from(canvas.tile_width), u32::from(canvas.tile_height));
+        let columns = self.animations.iter().map(|a| a.length).max().unwrap_or(0) as u32;
+        let rows = self.animations.len() as u32;
+        let sheet = || {
+            RgbaImage::new(
+                padding + columns * (tile_width + padding),
+                padding + rows * (tile_height + padding),
+            )
+        };
+
+        let mut base = sheet();
+        let mut companions: Vec<(String, RgbaImage)> = group
+            .companions
+            .iter()
+            .map(|&(suffix, _)| (suffix.to_owned(), sheet()))
+            .collect();
+        let mut frames = Vec::new();
+
+        for (index, animation) in self.animations.iter().enumerate() {
+            for frame in 0..animation.length {
+                let (x, y) = match canvas.frame_origin(animation, frame) {
+                    Some(origin) => origin,
+                    None => continue,
+                };
+                let images: Vec<RgbaImage> = group
+                    .layers()
+                    .map(|layer| crop_layer(layer, x, y, tile_width, tile_height))
+                    .collect();
+
+                let full = Rect {
+                    x: 0,
+                    y: 0,
+                    width: tile_width,
+                    height: tile_height,
+                };
+                let trimmed = if trim {
+                    union_bounds(&images).unwrap_or(Rect {
+                        width: 0,
+                        height: 0,
+                        ..full
+                    })
+                } else {
+                    full
+                };
+
+                let rect = Rect {
+                    x: padding + frame as u32 * (tile_width + padding),
+                    y: padding + index as u32 * (tile_height + padding),
+                    width: trimmed.width,
+                    height: trimmed.height,
+                };
+                let sheets =
+                    std::iter::once(&mut base).chain(companions.iter_mut().map(|(_, s)| s));
+                for (sheet, image) in sheets.zip(&images) {
+                    let view = image.view(trimmed.x, trimmed.y, trimmed.width, trimmed.height);
+                    imageops::replace(sheet, &view.to_image(), rect.x, rect.y);
+                }
+
+                frames.push(LayerGroupFrame {
+                    animation: index,
+                    frame,
+                    rect,
+                    trimmed,
+                });
+            }
+        }
+
+        LayerGroupSheets {
+            base,
+            companions,
+            frames,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::load::load;
+    use std::fs::File;
+
+    fn doc_with_names(names: &[(usize, &str)]) -> crate::Pyxel {
+        let mut doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        for &(index, name) in names {
+            doc.canvas.layers[index].name = name.to_owned();
+        }
+        doc
+    }
+
+    #[test]
+    fn companions_are_grouped_with_their_base() {
+        let doc = doc_with_names(&[
+            (0, "Hero_emissive"),
+            (2, "Hero"),
+            (4, "Hero_normal"),
+            (5, "Ghost_normal"),
+        ]);
+        let groups = doc
+            .canvas
+            .layer_groups_by_suffix(&["_normal", "", "_emissive"]);
+
+        assert_eq!(9, groups.len());
+        let hero = groups.iter().find(|g| g.base.name == "Hero").unwrap();
+        let suffixes: Vec<&str> = hero.companions.iter().map(|&(s, _)| s).collect();
+        assert_eq!(vec!["_normal", "_emissive"], suffixes);
+        assert_eq!("Hero_normal", hero.companion("_normal").unwrap().name);
+        assert_eq!(3, hero.layers().count());
+        assert!(groups.iter().any(|g| g.base.name == "Ghost_normal"));
+        assert!(!groups.iter().any(|g| g.base.name.starts_with("Hero_")));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn companions_stay_aligned_when_trimmed_and_packed() {
+        use image::{DynamicImage, Rgba, RgbaImage};
+        use std::sync::Arc;
+
+        let mut doc = doc_with_names(&[(0, "Hero"), (1, "Hero_normal")]);
+        let (width, height) = (doc.canvas.width as u32, doc.canvas.height as u32);
+        let mut base = RgbaImage::new(width, height);
+        base.put_pixel(2, 3, Rgba([255; 4]));
+        let mut normal = RgbaImage::new(width, height);
+        normal.put_pixel(5, 1, Rgba([128, 128, 255, 255]));
+        doc.canvas.layers[0].image = Arc::new(DynamicImage::ImageRgba8(base));
+        doc.canvas.layers[1].image = Arc::new(DynamicImage::ImageRgba8(normal));
+
+        let groups = doc.canvas.layer_groups_by_suffix(&["_normal"]);
+        let trimmed = groups[0].trimmed().unwrap();
+        assert_eq!(
+            (2, 1, 4, 3),
+            (
+                trimmed.bounds.x,
+                trimmed.bounds.y,
+                trimmed.bounds.width,
+                trimmed.bounds.height
+            )
+        );
+        assert_eq!(Rgba([255; 4]), *trimmed.base.get_pixel(0, 2));
+        assert_eq!("_normal", trimmed.companions[0].0);
+        assert_eq!(255, trimmed.companions[0].1.get_pixel(3, 0)[2]);
+
+        let sheets = doc.pack_layer_group_frames(&groups[0], true, 1);
+        assert_eq!(8, sheets.frames.len());
+        let first = sheets.frames[0];
+        assert_eq!(
+            (1, 1, 4, 3),
+            (
+                first.rect.x,
+                first.rect.y,
+                first.rect.width,
+                first.rect.height
+            )
+        );
+        assert_eq!(Rgba([255; 4]), *sheets.base.get_pixel(1, 3));
+        assert_eq!(255, sheets.companions[0].1.get_pixel(4, 1)[2]);
+        assert_eq!(
+            sheets.base.dimensions(),
+            sheets.companions[0].1.dimensions()
+        );
+        // Frames without any pixels are trimmed to nothing.
+        assert_eq!(0, sheets.frames[1].rect.width);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::pyxel::{Color, Pyxel};
+
+use image::GenericImageView;
+use std::collections::{HashMap, HashSet};
+
+/// A pixel of a layer.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PixelPosition {
+    /// The index of the layer.
+    pub layer: usize,
+    /// The column of the pixel.
+    pub x: u32,
+    /// The row of the pixel.
+    pub y: u32,
+}
+
+/// A color used by layer pixels that isn't in the palette, found by
+/// [`Pyxel::check_palette_conformance`](struct.Pyxel.html#method.check_palette_conformance).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OffPaletteColor {
+    /// The color of the pixels.
+    pub color: Color,
+    /// The pixels of this color, ordered by layer and then by row.
+    pub pixels: Vec<PixelPosition>,
+}
+
+impl OffPaletteColor {
+    /// Returns the number of pixels of this color.
+    pub fn count(&self) -> usize {
+        self.pixels.len()
+    }
+}
+
+impl Pyxel {
+    /// Returns the colors used by the pixels of any layer that aren't in the palette, in the order
+    /// they're first found. The document conforms to its palette when this is empty.
+    ///
+    /// Colors must match a palette color exactly, alpha included. Fully transparent pixels are
+    /// ignored, as are the alpha and visibility of the layers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// for off_palette in doc.check_palette_conformance() {
+    ///     println!("{} is used by {} pixels", off_palette.color, off_palette.count());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_palette_conformance(&self) -> Vec<OffPaletteColor> {
+        let palette: HashSet<Color> = self.palette.colors.iter().flatten().copied().collect();
+
+        let mut found: Vec<OffPaletteColor> = Vec::new();
+        let mut indices: HashMap<Color, usize> = HashMap::new();
+
+        for (layer, contents) in self.canvas.layers.iter().enumerate() {
+            for (x, y, pixel) in contents.image.pixels() {
+                let color = Color::from(pixel);
+                if color.a == 0 || palette.contains(&color) {
+                    continue;
+                }
+
+                let index = *indices.entry(color).or_insert_with(|| {
+                    found.push(OffPaletteColor {
+                        color,
+                        pixels: Vec::new(),
+                    });
+                    found.len() - 1
+                });
+                found[index].pixels.push(PixelPosition { layer, x, y });
+            }
+        }
+
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use image::{DynamicImage, Rgba};
+    use std::fs::File;
+
+    #[test]
+    fn reports_colors_missing_from_the_palette() {
+        let mut doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let in_palette = doc.palette.colors[0].unwrap();
+        for layer in &mut doc.canvas.layers {
+            layer.set_image(DynamicImage::new_rgba8(256, 128)).unwrap();
+        }
+        assert!(doc.check_palette_conformance().is_empty());
+
+        let pixel = |doc: &mut Pyxel, layer: usize, x, y, color| {
+            doc.canvas.layers[layer]
+                .image_mut()
+                .as_mut_rgba8()
+                .unwrap()
+                .put_pixel(x, y, color);
+        };
+        let off = Rgba([1, 2, 3, 255]);
+        pixel(&mut doc, 0, 5, 6, off);
+        pixel(&mut doc, 0, 0, 0, Rgba::from(in_palette));
+        pixel(&mut doc, 2, 7, 8, off);
+        pixel(&mut doc, 2, 9, 9, Rgba([1, 2, 3, 128]));
+
+        let found = doc.check_palette_conformance();
+        assert_eq!(2, found.len());
+        assert_eq!(Color::from(off), found[0].color);
+        assert_eq!(
+            vec![
+                PixelPosition {
+                    layer: 0,
+                    x: 5,
+                    y: 6
+                },
+                PixelPosition {
+                    layer: 2,
+                    x: 7,
+                    y: 8
+                },
+            ],
+            found[0].pixels
+        );
+        assert_eq!(1, found[1].count());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Color, Layer, Tileset},
+};
+
+use image::{Rgba, RgbaImage};
+use std::convert::TryFrom;
+
+impl From<Color> for Rgba<u8> {
+    fn from(color: Color) -> Rgba<u8> {
+        Rgba([color.r, color.g, color.b, color.a])
+    }
+}
+
+impl From<Rgba<u8>> for Color {
+    fn from(pixel: Rgba<u8>) -> Color {
+        let [r, g, b, a] = pixel.0;
+        Color { r, g, b, a }
+    }
+}
+
+/// Converts the image for the layer to 8 bit RGBA, as
+/// [`Layer::image`](struct.Layer.html#method.image) followed by `to_rgba` would.
+impl TryFrom<&Layer> for RgbaImage {
+    type Error = PyxelError;
+
+    fn try_from(layer: &Layer) -> Result<RgbaImage, PyxelError> {
+        Ok(match layer.image.as_rgba8() {
+            Some(image) => image.clone(),
+            None => layer.image.to_rgba(),
+        })
+    }
+}
+
+/// Converts the image for each tile of the tileset to 8 bit RGBA, in tile order.
+impl TryFrom<&Tileset> for Vec<RgbaImage> {
+    type Error = PyxelError;
+
+    fn try_from(tileset: &Tileset) -> Result<Vec<RgbaImage>, PyxelError> {
+        Ok(tileset.images.iter().map(|image| image.to_rgba()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    #[test]
+    fn documents_convert_to_image_types() {
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+
+        let layer = &doc.canvas().layers()[0];
+        let image = RgbaImage::try_from(layer).unwrap();
+        assert_eq!(layer.image().to_rgba().into_raw(), image.into_raw());
+
+        let tiles = Vec::<RgbaImage>::try_from(doc.tileset()).unwrap();
+        assert_eq!(4, tiles.len());
+        assert!(tiles.iter().all(|tile| tile.dimensions() == (32, 16)));
+
+        let color = Color {
+            r: 1,
+            g: 2,
+            b: 3,
+            a: 4,
+        };
+        assert_eq!(Rgba([1, 2, 3, 4]), Rgba::from(color));
+        assert_eq!(color, Color::from(Rgba([1, 2, 3, 4])));
+    }
+}
@@ -0,0 +1,286 @@
+use crate::pyxel::{Pyxel, TileRef, Tileset};
+
+use image::{imageops, RgbaImage};
+use std::collections::HashMap;
+
+/// A tile found by [`Tileset::find_duplicates`](struct.Tileset.html#method.find_duplicates) to
+/// have the same pixels as an earlier tile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DuplicateTile {
+    /// The index of the duplicate tile.
+    pub tile: usize,
+    /// The index of the earlier tile it duplicates.
+    pub canonical: usize,
+    /// The clockwise rotation in degrees that, after `flip_x`, turns the canonical tile into the
+    /// duplicate.
+    pub rot: f64,
+    /// Whether the canonical tile is flipped horizontally before being rotated.
+    pub flip_x: bool,
+}
+
+/// A tile transform as a number of clockwise quarter turns applied after an optional horizontal
+/// flip.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Transform {
+    turns: u8,
+    flip_x: bool,
+}
+
+impl Transform {
+    fn of(tile_ref: &TileRef) -> Transform {
+        Transform {
+            turns: (tile_ref.rot / 90.).round().rem_euclid(4.) as u8,
+            flip_x: tile_ref.flip_x,
+        }
+    }
+
+    /// Returns the transform applying `self` and then `then`.
+    fn then(self, then: Transform) -> Transform {
+        // Flipping reverses the direction of any rotation applied before it.
+        let turns = if then.flip_x {
+            then.turns + 4 - self.turns
+        } else {
+            then.turns + self.turns
+        };
+
+        Transform {
+            turns: turns % 4,
+            flip_x: self.flip_x != then.flip_x,
+        }
+    }
+
+    fn apply(self, image: &RgbaImage) -> RgbaImage {
+        let mut image = if self.flip_x {
+            imageops::flip_horizontal(image)
+        } else {
+            image.clone()
+        };
+
+        for _ in 0..self.turns {
+            image = imageops::rotate90(&image);
+        }
+        image
+    }
+}
+
+type Key = (u32, u32, Vec<u8>);
+
+fn key(image: &RgbaImage) -> Key {
+    (image.width(), image.height(), image.to_vec())
+}
+
+impl Tileset {
+    /// Finds the tiles with the same pixels as an earlier tile, in tile order.
+    ///
+    /// When `transforms` is `true`, a tile is also a duplicate if it matches a rotated or flipped
+    /// earlier tile. Quarter turns are only considered for square tiles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// for duplicate in doc.tileset().find_duplicates(true) {
+    ///     println!("tile {} duplicates tile {}", duplicate.tile, duplicate.canonical);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_duplicates(&self, transforms: bool) -> Vec<DuplicateTile> {
+        let mut seen: HashMap<Key, (usize, Transform)> = HashMap::new();
+        let mut duplicates = Vec::new();
+
+        for (i, image) in self.images.iter().enumerate() {
+            let image = image.to_rgba();
+
+            if let Some(&(canonical, transform)) = seen.get(&key(&image)) {
+                duplicates.push(DuplicateTile {
+                    tile: i,
+                    canonical,
+                    rot: f64::from(transform.turns) * 90.,
+                    flip_x: transform.flip_x,
+                });
+                continue;
+            }
+
+            let square = image.width() == image.height();
+            for &flip_x in &[false, true] {
+                for turns in 0..4 {
+                    let transform = Transform { turns, flip_x };
+                    let identity = turns == 0 && !flip_x;
+                    if !identity && (!transforms || (turns % 2 == 1 && !square)) {
+                        continue;
+                    }
+
+                    seen.entry(key(&transform.apply(&image)))
+                        .or_insert((i, transform));
+                }
+            }
+        }
+
+        duplicates
+    }
+}
+
+impl Pyxel {
+    /// Removes the tiles with the same pixels as an earlier tile from the tileset, rewriting the
+    /// tile refs of every layer to point at the remaining tiles, and returns the duplicates that
+    /// were removed.
+    ///
+    /// When `transforms` is `true`, tiles matching a rotated or flipped earlier tile are removed
+    /// too, with the rotation and flip folded into the rewritten tile refs. The pixels of the
+    /// layers are left unchanged.
+    pub fn dedupe(&mut self, transforms: bool) -> Vec<DuplicateTile> {
+        let duplicates = self.tileset.find_duplicates(transforms);
+        if duplicates.is_empty() {
+            return duplicates;
+        }
+
+        let count = self.tileset.images.len();
+        let mut duplicate_of = vec![None; count];
+        for duplicate in &duplicates {
+            duplicate_of[duplicate.tile] = Some(duplicate);
+        }
+
+        // Canonical tiles keep their order, so each one just moves down past the removed tiles.
+        let mut remap: Vec<(usize, Transform)> = Vec::with_capacity(count);
+        let mut next = 0;
+        for duplicate in &duplicate_of {
+            match duplicate {
+                Some(duplicate) => {
+                    let transform = Transform {
+                        turns: (duplicate.rot / 90.) as u8,
+                        flip_x: duplicate.flip_x,
+                    };
+                    remap.push((remap[duplicate.canonical].0, transform));
+                }
+                None => {
+                    remap.push((
+                        next,
+                        Transform {
+                            turns: 0,
+                            flip_x: false,
+                        },
+                    ));
+                    next += 1;
+                }
+            }
+        }
+
+        for layer in &mut self.canvas.layers {
+            for tile_ref in layer.tile_refs.values_mut() {
+                if let Some(&(index, transform)) = remap.get(tile_ref.index) {
+                    let combined = transform.then(Transform::of(tile_ref));
+                    tile_ref.index = index;
+                    tile_ref.rot = f64::from(combined.turns) * 90.;
+                    tile_ref.flip_x = combined.flip_x;
+                }
+            }
+        }
+
+        let mut i = 0;
+        self.tileset.images.retain(|_| {
+            let keep = duplicate_of[i].is_none();
+            i += 1;
+            keep
+        });
+        self.tileset.num_tiles = self.tileset.images.len();
+
+        duplicates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{import::document_from_tiles, pyxel::Pyxel};
+    use image::Rgba;
+
+    fn tile(pixels: [[u8; 4]; 4]) -> RgbaImage {
+        let mut image = RgbaImage::new(2, 2);
+        for (i, pixel) in pixels.iter().enumerate() {
+            image.put_pixel(i as u32 % 2, i as u32 / 2, Rgba(*pixel));
+        }
+        image
+    }
+
+    const R: [u8; 4] = [255, 0, 0, 255];
+    const G: [u8; 4] = [0, 255, 0, 255];
+    const B: [u8; 4] = [0, 0, 255, 255];
+
+    fn document() -> Pyxel {
+        let tiles = vec![
+            tile([R, G, B, B]),
+            tile([R, G, B, B]),
+            // The first tile rotated a quarter turn clockwise.
+            tile([B, R, B, G]),
+            // The first tile flipped horizontally.
+            tile([G, R, B, B]),
+            tile([G, G, G, G]),
+        ];
+        document_from_tiles("dedupe".to_owned(), 2, 2, 5, tiles, Vec::new())
+    }
+
+    #[test]
+    fn finds_exact_duplicates() {
+        let doc = document();
+        assert_eq!(
+            vec![DuplicateTile {
+                tile: 1,
+                canonical: 0,
+                rot: 0.,
+                flip_x: false
+            }],
+            doc.tileset().find_duplicates(false)
+        );
+    }
+
+    #[test]
+    fn finds_transformed_duplicates() {
+        let doc = document();
+        let duplicates = doc.tileset().find_duplicates(true);
+
+        assert_eq!(3, duplicates.len());
+        assert_eq!((2, 0, 90., false), {
+            let d = duplicates[1];
+            (d.tile, d.canonical, d.rot, d.flip_x)
+        });
+        assert_eq!((3, 0, 0., true), {
+            let d = duplicates[2];
+            (d.tile, d.canonical, d.rot, d.flip_x)
+        });
+    }
+
+    #[test]
+    fn dedupe_rewrites_tile_refs() {
+        let mut doc = document();
+        doc.canvas.layers[0]
+            .tile_refs
+            .insert(2, TileRef::new(2, 90., false));
+        doc.canvas.layers[0]
+            .tile_refs
+            .insert(3, TileRef::new(3, 0., true));
+
+        let rendered: Vec<RgbaImage> = doc.canvas.layers[0]
+            .tile_refs
+            .values()
+            .map(|r| Transform::of(r).apply(&doc.tileset.images[r.index].to_rgba()))
+            .collect();
+
+        assert_eq!(3, doc.dedupe(true).len());
+        assert_eq!(2, doc.tileset.num_tiles);
+        assert_eq!(2, doc.tileset.images.len());
+
+        let tile_refs = &doc.canvas.layers[0].tile_refs;
+        assert_eq!(TileRef::new(0, 0., false), tile_refs[&1]);
+        assert_eq!(TileRef::new(0, 180., false), tile_refs[&2]);
+        assert_eq!(TileRef::new(0, 0., false), tile_refs[&3]);
+        assert_eq!(TileRef::new(1, 0., false), tile_refs[&4]);
+
+        // Every cell still shows the same pixels.
+        for (r, expected) in tile_refs.values().zip(rendered) {
+            let image = Transform::of(r).apply(&doc.tileset.images[r.index].to_rgba());
+            assert_eq!(expected.into_raw(), image.into_raw());
+        }
+    }
+}
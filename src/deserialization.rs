@@ -75,13 +75,32 @@ where
         where
             M: MapAccess<'de>,
         {
-            let mut vec = Vec::with_capacity(access.size_hint().unwrap_or(0));
+            let mut entries = Vec::with_capacity(access.size_hint().unwrap_or(0));
+            while let Some(entry) = access.next_entry::<usize, T>()? {
+                entries.push(entry);
+            }
 
-            while let Some((key, value)) = access.next_entry::<usize, T>()? {
-                vec.insert(key, value);
+            // The keys must be exactly 0..n in any order, so each entry has its own slot.
+            let mut slots: Vec<Option<T>> = Vec::with_capacity(entries.len());
+            slots.resize_with(entries.len(), || None);
+
+            let len = entries.len();
+            for (key, value) in entries {
+                match slots.get_mut(key) {
+                    Some(slot @ None) => *slot = Some(value),
+                    Some(Some(_)) => {
+                        return Err(M::Error::custom(format_args!("duplicate key {}", key)));
+                    }
+                    None => {
+                        return Err(M::Error::custom(format_args!(
+                            "key {} out of range for a map of {} entries",
+                            key, len
+                        )));
+                    }
+                }
             }
 
-            Ok(vec)
+            Ok(slots.into_iter().map(Option::unwrap).collect())
         }
     }
 
@@ -119,3 +138,30 @@ where
     let visitor = MultipliersVisitor;
     deserializer.deserialize_seq(visitor)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "deserialize_map_as_vec")] Vec<String>);
+
+    fn parse(json: &str) -> Result<Vec<String>, serde_json::Error> {
+        serde_json::from_str::<Wrapper>(json).map(|w| w.0)
+    }
+
+    #[test]
+    fn map_keys_may_arrive_out_of_order() {
+        let vec = parse(r#"{"2": "c", "0": "a", "1": "b"}"#).unwrap();
+        assert_eq!(vec!["a", "b", "c"], vec);
+    }
+
+    #[test]
+    fn map_with_gaps_or_duplicates_is_rejected() {
+        let err = parse(r#"{"0": "a", "2": "c"}"#).unwrap_err();
+        assert!(err.to_string().contains("key 2 out of range"), "{}", err);
+
+        let err = parse(r#"{"0": "a", "0": "b"}"#).unwrap_err();
+        assert!(err.to_string().contains("duplicate key 0"), "{}", err);
+    }
+}
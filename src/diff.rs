@@ -0,0 +1,335 @@
+use crate::pyxel::{Color, Layer, Pyxel, TileRef};
+
+/// A layer or animation whose name differs between two documents.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Renamed {
+    /// The index in the old document.
+    pub old_index: usize,
+    /// The index in the new document.
+    pub new_index: usize,
+    /// The name in the old document.
+    pub old_name: String,
+    /// The name in the new document.
+    pub new_name: String,
+}
+
+/// A palette slot that differs between two documents.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PaletteChange {
+    /// The index of the palette slot.
+    pub index: usize,
+    /// The color in the old document, or `None` if the slot was empty or missing.
+    pub old: Option<Color>,
+    /// The color in the new document, or `None` if the slot is empty or missing.
+    pub new: Option<Color>,
+}
+
+/// A canvas cell of a layer whose tile ref differs between two documents.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileRefChange {
+    /// The canvas cell.
+    pub cell: usize,
+    /// The tile ref in the old document, or `None` if the cell was empty.
+    pub old: Option<TileRef>,
+    /// The tile ref in the new document, or `None` if the cell is empty.
+    pub new: Option<TileRef>,
+}
+
+/// The changes to a layer present in both documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerDiff {
+    /// The index of the layer in the old document.
+    pub old_index: usize,
+    /// The index of the layer in the new document.
+    pub new_index: usize,
+    /// Whether the alpha, blend mode or visibility flags of the layer changed.
+    pub properties_changed: bool,
+    /// The cells whose tile refs changed.
+    pub tile_refs: Vec<TileRefChange>,
+    /// Whether the pixels of the layer's image changed.
+    pub pixels_changed: bool,
+}
+
+/// The differences between two documents, produced by [`diff`](fn.diff.html).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct DocumentDiff {
+    /// The indices in the new document of layers that were added.
+    pub layers_added: Vec<usize>,
+    /// The indices in the old document of layers that were removed.
+    pub layers_removed: Vec<usize>,
+    /// The layers that were renamed.
+    pub layers_renamed: Vec<Renamed>,
+    /// The changes to layers present in both documents. Layers without changes are left out.
+    pub layers_changed: Vec<LayerDiff>,
+    /// The indices in the new document of animations that were added.
+    pub animations_added: Vec<usize>,
+    /// The indices in the old document of animations that were removed.
+    pub animations_removed: Vec<usize>,
+    /// The animations that were renamed.
+    pub animations_renamed: Vec<Renamed>,
+    /// The indices in the new document of animations present in both documents whose frames or
+    /// timing changed.
+    pub animations_changed: Vec<usize>,
+    /// The palette slots that changed.
+    pub palette: Vec<PaletteChange>,
+}
+
+impl DocumentDiff {
+    /// Returns `true` if no differences were found.
+    pub fn is_empty(&self) -> bool {
+        *self == DocumentDiff::default()
+    }
+}
+
+/// Pairs up the items of two lists by name, then pairs any that are left at the same index, which
+/// are taken to be renamed. Returns the pairs and the unpaired indices of each list.
+pub(crate) fn match_by_name(
+    old: &[&str],
+    new: &[&str],
+) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+    let mut old_matched = vec![None; old.len()];
+    let mut new_matched = vec![false; new.len()];
+
+    for (i, name) in old.iter().enumerate() {
+        if let Some(j) = (0..new.len()).find(|&j| !new_matched[j] && new[j] == *name) {
+            old_matched[i] = Some(j);
+            new_matched[j] = true;
+        }
+    }
+
+    for i in 0..old.len().min(new.len()) {
+        if old_matched[i].is_none() && !new_matched[i] {
+            old_matched[i] = Some(i);
+            new_matched[i] = true;
+        }
+    }
+
+    let pairs = old_matched
+        .iter()
+        .enumerate()
+        .filter_map(|(i, j)| j.map(|j| (i, j)))
+        .collect();
+    let removed = (0..old.len())
+        .filter(|&i| old_matched[i].is_none())
+        .collect();
+    let added = (0..new.len()).filter(|&j| !new_matched[j]).collect();
+
+    (pairs, removed, added)
+}
+
+fn diff_layer(old_index: usize, old: &Layer, new_index: usize, new: &Layer) -> LayerDiff {
+    let properties_changed = old.alpha != new.alpha
+        || old.blend_mode != new.blend_mode
+        || old.hidden != new.hidden
+        || old.muted != new.muted
+        || old.soloed != new.soloed;
+
+    let mut cells: Vec<usize> = old
+        .tile_refs
+        .keys()
+        .chain(new.tile_refs.keys())
+        .copied()
+        .collect();
+    cells.sort_unstable();
+    cells.dedup();
+
+    let tile_refs = cells
+        .into_iter()
+        .filter_map(|cell| {
+            let (old, new) = (old.tile_refs.get(&cell), new.tile_refs.get(&cell));
+            if old == new {
+                None
+            } else {
+                Some(TileRefChange {
+                    cell,
+                    old: old.copied(),
+                    new: new.copied(),
+                })
+            }
+        })
+        .collect();
+
+    LayerDiff {
+        old_index,
+        new_index,
+        properties_changed,
+        tile_refs,
+        pixels_changed: !old.pixels_eq(new),
+    }
+}
+
+/// Compares two versions of a document, reporting the layers and animations that were added,
+/// removed or renamed, and the changes to the palette, tile refs and layer pixels.
+///
+/// Layers and animations are matched up by name first, then by index, so a layer renamed
+/// without being moved is reported as renamed rather than removed and added.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let old = pyxel::open("resources/doc.pyxel")?;
+/// let new = pyxel::open("resources/doc.pyxel")?;
+/// assert!(pyxel::diff(&old, &new).is_empty());
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff(old: &Pyxel, new: &Pyxel) -> DocumentDiff {
+    let mut diff = DocumentDiff::default();
+
+    let (old_layers, new_layers) = (&old.canvas.layers, &new.canvas.layers);
+    let (pairs, removed, added) = match_by_name(
+        &old_layers
+            .iter()
+            .map(|l| l.name.as_str())
+            .collect::<Vec<_>>(),
+        &new_layers
+            .iter()
+            .map(|l| l.name.as_str())
+            .collect::<Vec<_>>(),
+    );
+    diff.layers_removed = removed;
+    diff.layers_added = added;
+
+    for (i, j) in pairs {
+        let (old_layer, new_layer) = (&old_layers[i], &new_layers[j]);
+
+        if old_layer.name != new_layer.name {
+            diff.layers_renamed.push(Renamed {
+                old_index: i,
+                new_index: j,
+                old_name: old_layer.name.clone(),
+                new_name: new_layer.name.clone(),
+            });
+        }
+
+        let layer_diff = diff_layer(i, old_layer, j, new_layer);
+        if layer_diff.properties_changed
+            || !layer_diff.tile_refs.is_empty()
+            || layer_diff.pixels_changed
+        {
+            diff.layers_changed.push(layer_diff);
+        }
+    }
+
+    let (old_animations, new_animations) = (&old.animations, &new.animations);
+    let (pairs, removed, added) = match_by_name(
+        &old_animations
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<_>>(),
+        &new_animations
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<_>>(),
+    );
+    diff.animations_removed = removed;
+    diff.animations_added = added;
+
+    for (i, j) in pairs {
+        let (old_animation, new_animation) = (&old_animations[i], &new_animations[j]);
+
+        if old_animation.name != new_animation.name {
+            diff.animations_renamed.push(Renamed {
+                old_index: i,
+                new_index: j,
+                old_name: old_animation.name.clone(),
+                new_name: new_animation.name.clone(),
+            });
+        }
+
+        if old_animation.base_tile != new_animation.base_tile
+            || old_animation.length != new_animation.length
+            || old_animation.frame_durations() != new_animation.frame_durations()
+        {
+            diff.animations_changed.push(j);
+        }
+    }
+
+    let (old_colors, new_colors) = (&old.palette.colors, &new.palette.colors);
+    for index in 0..old_colors.len().max(new_colors.len()) {
+        let old = old_colors.get(index).copied().flatten();
+        let new = new_colors.get(index).copied().flatten();
+        if old != new {
+            diff.palette.push(PaletteChange { index, old, new });
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    fn load_test_file() -> Pyxel {
+        load(File::open(TEST_FILE).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn identical_documents_have_no_differences() {
+        assert!(diff(&load_test_file(), &load_test_file()).is_empty());
+    }
+
+    #[test]
+    fn reports_layer_and_animation_changes() {
+        let old = load_test_file();
+        let mut new = load_test_file();
+
+        new.canvas.layers[1].name = "Clouds".to_owned();
+        new.canvas.layers[0]
+            .tile_refs
+            .insert(5, TileRef::new(2, 0., false));
+        new.canvas.layers.remove(2);
+        new.canvas.layers[3].alpha = 128;
+        let mut added = new.canvas.layers[9].clone();
+        added.name = "Layer 11".to_owned();
+        new.canvas.layers.push(added);
+
+        new.animations.swap(0, 1);
+        new.animations[0].frame_duration_multipliers = vec![2., 2.];
+        new.palette.colors[3] = None;
+
+        let diff = diff(&old, &new);
+
+        assert_eq!(vec![10], diff.layers_added);
+        assert_eq!(vec![2], diff.layers_removed);
+        assert_eq!(
+            vec![Renamed {
+                old_index: 1,
+                new_index: 1,
+                old_name: "Layer 9".to_owned(),
+                new_name: "Clouds".to_owned(),
+            }],
+            diff.layers_renamed
+        );
+
+        assert_eq!(2, diff.layers_changed.len());
+        let changed = &diff.layers_changed[0];
+        assert_eq!((0, 0), (changed.old_index, changed.new_index));
+        assert_eq!(
+            vec![TileRefChange {
+                cell: 5,
+                old: None,
+                new: Some(TileRef::new(2, 0., false)),
+            }],
+            changed.tile_refs
+        );
+        assert!(!changed.properties_changed && !changed.pixels_changed);
+        let changed = &diff.layers_changed[1];
+        assert_eq!((4, 3), (changed.old_index, changed.new_index));
+        assert!(changed.properties_changed);
+
+        assert!(diff.animations_added.is_empty() && diff.animations_renamed.is_empty());
+        assert_eq!(vec![0], diff.animations_changed);
+
+        assert_eq!(1, diff.palette.len());
+        assert_eq!(3, diff.palette[0].index);
+        assert_eq!(None, diff.palette[0].new);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Color, Palette},
+};
+
+use image::{Rgba, RgbaImage};
+
+/// How colors are dithered when an image is quantized to a palette.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Dither {
+    /// Map each pixel to the nearest palette color on its own. Gradients become bands of flat
+    /// color.
+    #[default]
+    None,
+    /// Offset each pixel by a 4 × 4 Bayer matrix before mapping it, giving a regular
+    /// crosshatch pattern that suits pixel art and animates without flickering.
+    Ordered,
+    /// Spread the difference between each pixel and its palette color onto the pixels to its
+    /// right and below, giving the closest overall tone at the cost of a noisier pattern.
+    FloydSteinberg,
+}
+
+const BAYER: [[f32; 4]; 4] = [
+    [0., 8., 2., 10.],
+    [12., 4., 14., 6.],
+    [3., 11., 1., 9.],
+    [15., 7., 13., 5.],
+];
+
+/// The range, in channel values, that ordered dithering offsets pixels by.
+const ORDERED_SPREAD: f32 = 64.;
+
+/// Returns the index of the color of `colors` nearest `rgba`, ignoring fully transparent colors.
+fn nearest(colors: &[Color], rgba: [f32; 4]) -> Option<usize> {
+    let distance = |c: &Color| {
+        [c.r, c.g, c.b, c.a]
+            .iter()
+            .zip(&rgba)
+            .map(|(&a, &b)| (f32::from(a) - b).powi(2))
+            .sum::<f32>()
+    };
+
+    colors
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.a != 0)
+        .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+        .map(|(index, _)| index)
+}
+
+/// Maps each pixel of `image` to the index of a color of `colors`, in row order, dithering as
+/// specified. Fully transparent pixels map to the first fully transparent color.
+pub(crate) fn quantize(
+    image: &RgbaImage,
+    colors: &[Color],
+    dither: Dither,
+) -> Result<Vec<usize>, PyxelError> {
+    let (width, height) = image.dimensions();
+    let transparent = colors.iter().position(|c| c.a == 0);
+
+    // The error carried to each pixel of the current and next rows by Floyd-Steinberg dithering.
+    let row_len = width as usize + 2;
+    let mut errors = vec![[0f32; 3]; row_len * 2];
+
+    let mut indices = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        let (current, next) = errors.split_at_mut(row_len);
+        for x in 0..width as usize {
+            let [r, g, b, a] = image.get_pixel(x as u32, y).0;
+            if a == 0 {
+                if let Some(index) = transparent {
+                    indices.push(index);
+                    continue;
+                }
+            }
+
+            let mut rgba = [f32::from(r), f32::from(g), f32::from(b), f32::from(a)];
+            match dither {
+                Dither::None => {}
+                Dither::Ordered => {
+                    let offset = (BAYER[y as usize % 4][x % 4] + 0.5) / 16. - 0.5;
+                    for c in &mut rgba[..3] {
+                        *c += offset * ORDERED_SPREAD;
+                    }
+                }
+                Dither::FloydSteinberg => {
+                    for (c, error) in rgba.iter_mut().zip(&current[x + 1]) {
+                        *c += error;
+                    }
+                }
+            }
+
+            let index =
+                nearest(colors, rgba).ok_or(PyxelError::ColorNotInPalette { x: x as u32, y })?;
+            indices.push(index);
+
+            if dither == Dither::FloydSteinberg {
+                let color = colors[index];
+                let chosen = [color.r, color.g, color.b];
+                for channel in 0..3 {
+                    let error = rgba[channel].clamp(0., 255.) - f32::from(chosen[channel]);
+                    current[x + 2][channel] += error * 7. / 16.;
+                    next[x][channel] += error * 3. / 16.;
+                    next[x + 1][channel] += error * 5. / 16.;
+                    next[x + 2][channel] += error / 16.;
+                }
+            }
+        }
+
+        errors.copy_within(row_len.., 0);
+        for error in &mut errors[row_len..] {
+            *error = [0.; 3];
+        }
+    }
+
+    Ok(indices)
+}
+
+impl Palette {
+    /// Returns `image` with every pixel replaced by the nearest color of this palette, dithered
+    /// as specified.
+    ///
+    /// Fully transparent pixels are left transparent if the palette has a fully transparent
+    /// color. Otherwise, like every other pixel, they're mapped to the nearest color that isn't
+    /// fully transparent, so the palette must have at least one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let image = doc.canvas().flatten();
+    /// let remapped = doc.palette().remap_image(&image, pyxel::Dither::FloydSteinberg)?;
+    /// assert_eq!(image.dimensions(), remapped.dimensions());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remap_image(&self, image: &RgbaImage, dither: Dither) -> Result<RgbaImage, PyxelError> {
+        let colors: Vec<Color> = self.colors.iter().flatten().copied().collect();
+        let indices = quantize(image, &colors, dither)?;
+
+        let mut out = RgbaImage::new(image.width(), image.height());
+        for (pixel, &index) in out.pixels_mut().zip(&indices) {
+            *pixel = Rgba::from(colors[index]);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> RgbaImage {
+        RgbaImage::from_fn(64, 4, |x, _| {
+            let v = (x * 4) as u8;
+            Rgba([v, v, v, 255])
+        })
+    }
+
+    fn grays() -> Palette {
+        let gray = |v| Color {
+            r: v,
+            g: v,
+            b: v,
+            a: 255,
+        };
+        Palette::from_colors(vec![gray(0), gray(255)], 8)
+    }
+
+    fn mean(image: &RgbaImage) -> f64 {
+        let sum: f64 = image.pixels().map(|p| f64::from(p[0])).sum();
+        sum / f64::from(image.width() * image.height())
+    }
+
+    #[test]
+    fn undithered_pixels_take_the_nearest_color() {
+        let image = grays().remap_image(&gradient(), Dither::None).unwrap();
+        assert_eq!(Rgba([0, 0, 0, 255]), *image.get_pixel(31, 0));
+        assert_eq!(Rgba([255, 255, 255, 255]), *image.get_pixel(32, 0));
+    }
+
+    #[test]
+    fn dithering_keeps_the_overall_tone() {
+        let image = gradient();
+        for &dither in &[Dither::Ordered, Dither::FloydSteinberg] {
+            let remapped = grays().remap_image(&image, dither).unwrap();
+            assert!(remapped.pixels().all(|p| p[0] == 0 || p[0] == 255));
+            assert!((mean(&image) - mean(&remapped)).abs() < 8.);
+
+            // Dark pixels near the middle of the gradient are sometimes made white.
+            assert!((24..32).any(|x| remapped.get_pixel(x, 0)[0] == 255));
+        }
+    }
+
+    #[test]
+    fn transparent_pixels_use_the_transparent_color() {
+        let mut palette = grays();
+        let mut image = gradient();
+        image.put_pixel(0, 0, Rgba([9, 9, 9, 0]));
+        assert_eq!(
+            Rgba([0, 0, 0, 255]),
+            *palette
+                .remap_image(&image, Dither::None)
+                .unwrap()
+                .get_pixel(0, 0)
+        );
+
+        palette.colors.push(Some(Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0,
+        }));
+        let remapped = palette.remap_image(&image, Dither::FloydSteinberg).unwrap();
+        assert_eq!(0, remapped.get_pixel(0, 0)[3]);
+
+        let empty = Palette::from_colors(Vec::new(), 8);
+        assert!(matches!(
+            empty.remap_image(&image, Dither::None),
+            Err(PyxelError::ColorNotInPalette { x: 0, y: 0 })
+        ));
+    }
+}
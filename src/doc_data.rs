@@ -0,0 +1,461 @@
+use crate::{error::PyxelError, pyxel::Pyxel};
+
+use semver::Version;
+use serde::{
+    de::{DeserializeOwned, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::{
+    fmt, io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// A resource limit enforced whilst loading a document.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Limit {
+    /// The total number of bytes decompressed from the archive.
+    DecompressedBytes,
+    /// The number of layers in the canvas.
+    Layers,
+    /// The number of tiles in the tileset.
+    Tiles,
+    /// The width in pixels of the canvas or any image within the document.
+    CanvasWidth,
+    /// The height in pixels of the canvas or any image within the document.
+    CanvasHeight,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Limit::DecompressedBytes => "decompressed size",
+            Limit::Layers => "number of layers",
+            Limit::Tiles => "number of tiles",
+            Limit::CanvasWidth => "width",
+            Limit::CanvasHeight => "height",
+        })
+    }
+}
+
+/// Resource limits checked whilst loading a document.
+///
+/// Every limit is unset by default. Set them when loading documents from untrusted sources so
+/// that corrupt or malicious archives fail with
+/// [`PyxelError::LimitExceeded`](enum.PyxelError.html#variant.LimitExceeded) instead of
+/// exhausting memory.
+///
+/// The counts and canvas dimensions are checked by a first pass over `docData.json` that skips
+/// everything else, so a document declaring millions of layers is rejected before any of them
+/// are deserialized.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Limits {
+    /// The maximum total number of bytes decompressed from the archive.
+    pub max_decompressed_bytes: Option<u64>,
+    /// The maximum number of layers in the canvas.
+    pub max_layers: Option<usize>,
+    /// The maximum number of tiles in the tileset.
+    pub max_tiles: Option<usize>,
+    /// The maximum width in pixels of the canvas. Images wider than this are also rejected
+    /// before being decoded.
+    pub max_canvas_width: Option<u32>,
+    /// The maximum height in pixels of the canvas. Images taller than this are also rejected
+    /// before being decoded.
+    pub max_canvas_height: Option<u32>,
+}
+
+pub(crate) fn check_limit(limit: Limit, max: Option<u64>, value: u64) -> Result<(), PyxelError> {
+    match max {
+        Some(max) if value > max => Err(PyxelError::LimitExceeded { limit, max }),
+        _ => Ok(()),
+    }
+}
+
+/// Counts the entries of a map without keeping any of them.
+struct EntryCount(usize);
+
+impl<'de> Deserialize<'de> for EntryCount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CountVisitor;
+
+        impl<'de> Visitor<'de> for CountVisitor {
+            type Value = EntryCount;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<EntryCount, M::Error> {
+                let mut count = 0;
+                while access.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {
+                    count += 1;
+                }
+                Ok(EntryCount(count))
+            }
+        }
+
+        deserializer.deserialize_map(CountVisitor)
+    }
+}
+
+/// The parts of `docData.json` the limits apply to.
+#[derive(Deserialize)]
+struct DocCounts {
+    canvas: CanvasCounts,
+    tileset: TilesetCounts,
+}
+
+#[derive(Deserialize)]
+struct CanvasCounts {
+    layers: EntryCount,
+    #[serde(rename = "numLayers")]
+    num_layers: usize,
+    width: i32,
+    height: i32,
+}
+
+#[derive(Deserialize)]
+struct TilesetCounts {
+    #[serde(rename = "numTiles")]
+    num_tiles: usize,
+}
+
+impl Limits {
+    /// Checks the counts and dimensions declared by `doc_data` before the document is
+    /// deserialized.
+    fn check_doc_data(&self, doc_data: &mut DocData) -> Result<(), PyxelError> {
+        if self.max_layers.is_none()
+            && self.max_tiles.is_none()
+            && self.max_canvas_width.is_none()
+            && self.max_canvas_height.is_none()
+        {
+            return Ok(());
+        }
+
+        let DocCounts { canvas, tileset } = doc_data.deserialize()?;
+
+        let layers = canvas.num_layers.max(canvas.layers.0);
+        check_limit(
+            Limit::Layers,
+            self.max_layers.map(|m| m as u64),
+            layers as u64,
+        )?;
+        check_limit(
+            Limit::Tiles,
+            self.max_tiles.map(|m| m as u64),
+            tileset.num_tiles as u64,
+        )?;
+
+        self.check_dimensions(canvas.width.max(0) as u64, canvas.height.max(0) as u64)
+    }
+
+    pub(crate) fn check_dimensions(&self, width: u64, height: u64) -> Result<(), PyxelError> {
+        check_limit(
+            Limit::CanvasWidth,
+            self.max_canvas_width.map(u64::from),
+            width,
+        )?;
+        check_limit(
+            Limit::CanvasHeight,
+            self.max_canvas_height.map(u64::from),
+            height,
+        )
+    }
+}
+
+/// A flag used to cancel loading a document from another thread.
+///
+/// Clones of a token share the same flag, so keep a clone and pass another in the
+/// [`LoadOptions`](struct.LoadOptions.html), then call [`cancel`](#method.cancel) to stop the load.
+/// Loading fails with [`PyxelError::Cancelled`](enum.PyxelError.html#variant.Cancelled) as soon as
+/// it next reads from the archive, including part way through decoding an image.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let token = pyxel::CancelToken::new();
+/// let options = pyxel::LoadOptions {
+///     cancel: Some(token.clone()),
+///     ..pyxel::LoadOptions::default()
+/// };
+///
+/// // E.g. when the user navigates away whilst a worker thread is loading the document.
+/// token.cancel();
+///
+/// let result = pyxel::load_with_options(File::open("resources/doc.pyxel")?, &options);
+/// assert!(matches!(result, Err(pyxel::PyxelError::Cancelled)));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a token that hasn't been cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Cancels every load using this token or a clone of it.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if [`cancel`](#method.cancel) has been called on this token or a clone of
+    /// it.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Options controlling how a document is loaded.
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    /// The resource limits to enforce.
+    pub limits: Limits,
+    /// A token that cancels the load when cancelled. Loads can't be cancelled when this is `None`.
+    pub cancel: Option<CancelToken>,
+    /// Reject documents created with a version of PyxelEdit other than the
+    /// [`SUPPORTED_VERSIONS`](constant.SUPPORTED_VERSIONS.html), rather than only those from a
+    /// different major version.
+    pub strict_version: bool,
+    /// Load documents that are missing the images of some of their layers or tiles, using
+    /// transparent images in their place, rather than failing. Each substitution is recorded in
+    /// [`Pyxel::load_warnings`](struct.Pyxel.html#method.load_warnings). The pixels of each
+    /// substitute count towards the limit on decompressed bytes.
+    pub lenient: bool,
+    /// Leave layers that aren't shown in the editor, as by
+    /// [`Canvas::effective_visibility`](struct.Canvas.html#method.effective_visibility), without
+    /// an image rather than decoding them. Their metadata and tile refs are still loaded.
+    ///
+    /// This only applies to the loaders that return a [`Pyxel`](struct.Pyxel.html), a
+    /// [`DocReader`](struct.DocReader.html) reads every image.
+    pub skip_invisible_layers: bool,
+    /// Keep the text of `docData.json` as it was read, see
+    /// [`Pyxel::raw_doc_data`](struct.Pyxel.html#method.raw_doc_data).
+    pub keep_raw_doc_data: bool,
+    /// Read the entries of the archive that aren't part of the document, see
+    /// [`Pyxel::extra_entries`](struct.Pyxel.html#method.extra_entries). They're charged to the
+    /// limit on decompressed bytes like every other entry.
+    pub keep_extra_entries: bool,
+}
+
+/// A problem found whilst loading a document that didn't stop it from being loaded, see
+/// [`LoadOptions::lenient`](struct.LoadOptions.html#structfield.lenient).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LoadWarning {
+    /// The image entry with this name was missing from the archive, so a transparent image the
+    /// size of the layer or tile was used in its place.
+    MissingImage(String),
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadWarning::MissingImage(ref name) => {
+                write!(f, "{} is missing, using a transparent image", name)
+            }
+        }
+    }
+}
+
+impl Pyxel {
+    /// Returns the problems found whilst loading this document with
+    /// [`LoadOptions::lenient`](struct.LoadOptions.html#structfield.lenient) set. This is always
+    /// empty for documents loaded otherwise.
+    pub fn load_warnings(&self) -> &[LoadWarning] {
+        &self.load_warnings
+    }
+}
+
+pub(crate) const DOC_DATA: &str = "docData.json";
+
+#[derive(Deserialize)]
+struct DocVersion {
+    version: Version,
+}
+
+/// The versions of PyxelEdit whose documents this crate is tested against.
+///
+/// Documents from any version with the same major version are loaded by default, set
+/// [`LoadOptions::strict_version`](struct.LoadOptions.html#structfield.strict_version) to only
+/// load documents from these versions.
+pub const SUPPORTED_VERSIONS: &[&str] = &["0.4.8"];
+
+/// Returns `true` if `version` is one of the
+/// [`SUPPORTED_VERSIONS`](constant.SUPPORTED_VERSIONS.html).
+///
+/// # Examples
+///
+/// ```
+/// use semver::Version;
+///
+/// assert!(pyxel::is_supported(&Version::new(0, 4, 8)));
+/// assert!(!pyxel::is_supported(&Version::new(0, 4, 7)));
+/// ```
+pub fn is_supported(version: &Version) -> bool {
+    let version = version.to_string();
+    SUPPORTED_VERSIONS.iter().any(|v| *v == version)
+}
+
+/// Returns `true` if documents created with `version` of PyxelEdit can be loaded.
+fn is_supported_version(version: &Version, strict: bool) -> bool {
+    if strict {
+        is_supported(version)
+    } else {
+        // Every release of PyxelEdit so far has been 0.x, and its format has only grown fields
+        // between them, so only a new major version is assumed to be unreadable.
+        version.major == 0
+    }
+}
+
+/// The `docData.json` entry, which is deserialized in several passes: its version, the counts
+/// the limits apply to, then the whole document.
+#[cfg(not(feature = "simd-json"))]
+struct DocData<'a>(&'a [u8]);
+
+#[cfg(not(feature = "simd-json"))]
+impl<'a> DocData<'a> {
+    fn parse(buf: &'a [u8]) -> Result<DocData<'a>, PyxelError> {
+        Ok(DocData(buf))
+    }
+
+    fn deserialize<T: DeserializeOwned>(&mut self) -> Result<T, PyxelError> {
+        serde_json::from_slice(self.0).map_err(|e| PyxelError::from(e).in_entry(DOC_DATA))
+    }
+}
+
+/// The `docData.json` entry, which is deserialized in several passes: its version, the counts
+/// the limits apply to, then the whole document.
+///
+/// simd-json parses the entry into a tape once, and each pass deserializes from the start of the
+/// tape rather than parsing the entry again.
+#[cfg(feature = "simd-json")]
+struct DocData<'a>(simd_json::Deserializer<'a>);
+
+#[cfg(feature = "simd-json")]
+impl<'a> DocData<'a> {
+    fn parse(buf: &'a mut [u8]) -> Result<DocData<'a>, PyxelError> {
+        simd_json::Deserializer::from_slice(buf)
+            .map(DocData)
+            .map_err(|e| PyxelError::from(e).in_entry(DOC_DATA))
+    }
+
+    fn deserialize<T: DeserializeOwned>(&mut self) -> Result<T, PyxelError> {
+        self.0.restart();
+        T::deserialize(&mut self.0).map_err(|e| PyxelError::from(e).in_entry(DOC_DATA))
+    }
+}
+
+pub(crate) fn parse_doc_data(buf: &[u8], options: &LoadOptions) -> Result<Pyxel, PyxelError> {
+    // simd-json parses in place, so needs a copy of the entry it can modify.
+    #[cfg(feature = "simd-json")]
+    let mut copy = buf.to_vec();
+    #[cfg(feature = "simd-json")]
+    let mut doc_data = DocData::parse(&mut copy)?;
+    #[cfg(not(feature = "simd-json"))]
+    let mut doc_data = DocData::parse(buf)?;
+
+    let DocVersion { version } = doc_data.deserialize()?;
+
+    if !is_supported_version(&version, options.strict_version) {
+        return Err(PyxelError::UnsupportedVersion(version));
+    }
+
+    options.limits.check_doc_data(&mut doc_data)?;
+
+    let mut pyxel: Pyxel = doc_data.deserialize()?;
+    if options.keep_raw_doc_data {
+        // Parsing succeeded, so this only fails for JSON parsers that accept invalid UTF-8.
+        let text = std::str::from_utf8(buf)
+            .map_err(|e| PyxelError::from(io::Error::new(io::ErrorKind::InvalidData, e)))
+            .map_err(|e| e.in_entry(DOC_DATA))?;
+        pyxel.raw_doc_data = Some(text.into());
+    }
+    Ok(pyxel)
+}
+
+/// Parses a Pyxel document from the contents of its `docData.json` entry, for documents that
+/// have already been extracted from their archive.
+///
+/// The document is returned without any of its images, user metadata or extra entries. The
+/// version of the document and the limits of `options` that apply to `docData.json` are checked,
+/// as when loading the whole archive.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let json = br#"{
+///     "version": "0.4.8",
+///     "name": "blank",
+///     "canvas": {
+///         "width": 16, "height": 16, "tileWidth": 8, "tileHeight": 8,
+///         "numLayers": 0, "layers": {}
+///     },
+///     "tileset": {
+///         "tileWidth": 8, "tileHeight": 8, "tilesWide": 1, "numTiles": 0, "fixedWidth": false
+///     },
+///     "palette": { "width": 1, "height": 1, "numColors": 0, "colors": {} },
+///     "animations": {}
+/// }"#;
+/// let doc = pyxel::from_doc_data(json, &pyxel::LoadOptions::default())?;
+/// assert_eq!("blank", doc.name());
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_doc_data(buf: &[u8], options: &LoadOptions) -> Result<Pyxel, PyxelError> {
+    parse_doc_data(buf, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::{fs::File, io::Read};
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn extracted_doc_data_parses_without_the_archive() {
+        let mut archive = zip::ZipArchive::new(File::open(TEST_FILE).unwrap()).unwrap();
+        let mut buf = Vec::new();
+        archive
+            .by_name(DOC_DATA)
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+
+        let expected = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let doc = from_doc_data(&buf, &LoadOptions::default()).unwrap();
+        assert_eq!(expected.name(), doc.name());
+        assert_eq!(
+            expected.canvas().layers().len(),
+            doc.canvas().layers().len()
+        );
+        for (expected, layer) in expected.canvas().layers().iter().zip(doc.canvas().layers()) {
+            assert_eq!(expected.tile_refs(), layer.tile_refs());
+        }
+
+        let options = LoadOptions {
+            limits: Limits {
+                max_layers: Some(10),
+                ..Limits::default()
+            },
+            ..LoadOptions::default()
+        };
+        match from_doc_data(&buf, &options) {
+            Err(PyxelError::LimitExceeded {
+                limit: Limit::Layers,
+                max: 10,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
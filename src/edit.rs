@@ -0,0 +1,163 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Pyxel, TileRef},
+    stamp::check_region,
+};
+
+impl Pyxel {
+    /// Places `tile_ref` in the cell at `cell` (column, row) of `layer`, returning the tile ref
+    /// it replaced.
+    ///
+    /// The cell must lie within the canvas and the tile ref must refer to a tile of the tileset.
+    /// Rotations are wrapped into `0..360` degrees as PyxelEdit stores them. Only the tile ref is
+    /// changed, the layer's pixels are left as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// doc.set_tile_ref(0, (3, 2), pyxel::TileRef::new(0, -90., true))?;
+    /// assert_eq!(270., doc.canvas().layers()[0].tile_refs()[&(2 * 8 + 3)].rot());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_tile_ref(
+        &mut self,
+        layer: usize,
+        cell: (usize, usize),
+        tile_ref: TileRef,
+    ) -> Result<Option<TileRef>, PyxelError> {
+        let tile_ref = self.check_tile_ref(tile_ref)?;
+        let index = self.cell(layer, cell)?;
+        Ok(self.canvas.layers[layer].tile_refs.insert(index, tile_ref))
+    }
+
+    /// Removes the tile ref from the cell at `cell` (column, row) of `layer`, returning it if
+    /// the cell had one.
+    pub fn clear_tile_ref(
+        &mut self,
+        layer: usize,
+        cell: (usize, usize),
+    ) -> Result<Option<TileRef>, PyxelError> {
+        let index = self.cell(layer, cell)?;
+        Ok(self.canvas.layers[layer].tile_refs.remove(&index))
+    }
+
+    /// Fills the `width` × `height` cell region starting at `origin` (column, row) of `layer`
+    /// with `tile_ref`, or clears it if `tile_ref` is `None`.
+    ///
+    /// Everything is checked as for [`set_tile_ref`](#method.set_tile_ref) before any cell is
+    /// changed, so nothing is modified if the region doesn't fit within the canvas.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// doc.fill_rect(0, (0, 6), 8, 2, Some(pyxel::TileRef::new(0, 0., false)))?;
+    /// assert_eq!(16, doc.canvas().layers()[0].tile_refs().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fill_rect(
+        &mut self,
+        layer: usize,
+        origin: (usize, usize),
+        width: usize,
+        height: usize,
+        tile_ref: Option<TileRef>,
+    ) -> Result<(), PyxelError> {
+        let tile_ref = tile_ref.map(|t| self.check_tile_ref(t)).transpose()?;
+        self.cell(layer, origin)?;
+        check_region(&self.canvas, origin, width, height)?;
+
+        let tiles_wide = self.canvas.tiles_wide();
+        let tile_refs = &mut self.canvas.layers[layer].tile_refs;
+        for row in origin.1..origin.1 + height {
+            for col in origin.0..origin.0 + width {
+                let index = row * tiles_wide + col;
+                match tile_ref {
+                    Some(tile_ref) => tile_refs.insert(index, tile_ref),
+                    None => tile_refs.remove(&index),
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the index of the cell at `cell` after checking it and `layer` exist.
+    fn cell(&self, layer: usize, (col, row): (usize, usize)) -> Result<usize, PyxelError> {
+        if layer >= self.canvas.layers.len() {
+            return Err(PyxelError::LayerOutOfBounds(layer));
+        }
+
+        self.canvas
+            .cell_index(col, row)
+            .ok_or(PyxelError::CellOutOfBounds { col, row })
+    }
+
+    fn check_tile_ref(&self, mut tile_ref: TileRef) -> Result<TileRef, PyxelError> {
+        if tile_ref.index >= self.tileset.tile_count() {
+            return Err(PyxelError::TileOutOfBounds(tile_ref.index));
+        }
+
+        tile_ref.rot = tile_ref.rot.rem_euclid(360.);
+        Ok(tile_ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn edits_are_bounds_checked() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let tile_ref = TileRef::new(3, 450., false);
+
+        assert_eq!(None, doc.set_tile_ref(2, (1, 1), tile_ref).unwrap());
+        assert_eq!(
+            Some(TileRef::new(3, 90., false)),
+            doc.clear_tile_ref(2, (1, 1)).unwrap()
+        );
+        assert_eq!(None, doc.clear_tile_ref(2, (1, 1)).unwrap());
+
+        let original = doc.clone();
+        match doc.set_tile_ref(2, (0, 0), TileRef::new(4, 0., false)) {
+            Err(PyxelError::TileOutOfBounds(4)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match doc.set_tile_ref(11, (0, 0), tile_ref) {
+            Err(PyxelError::LayerOutOfBounds(11)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match doc.fill_rect(2, (6, 6), 3, 1, Some(tile_ref)) {
+            Err(PyxelError::CellOutOfBounds { col: 8, row: 6 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(original, doc);
+    }
+
+    #[test]
+    fn fill_rect_sets_and_clears_cells() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let tile_ref = TileRef::new(1, 180., true);
+
+        doc.fill_rect(1, (2, 6), 3, 2, Some(tile_ref)).unwrap();
+        let tile_refs = &doc.canvas.layers[1].tile_refs;
+        for &index in &[50, 51, 52, 58, 59, 60] {
+            assert_eq!(tile_ref, tile_refs[&index]);
+        }
+        assert_eq!(TileRef::new(0, 0., false), tile_refs[&56]);
+
+        doc.fill_rect(1, (0, 7), 8, 1, None).unwrap();
+        assert_eq!(3, doc.canvas.layers[1].tile_refs.len());
+        assert!(doc.validate().is_empty());
+    }
+}
@@ -1,52 +1,243 @@
+use crate::doc_data::Limit;
+
 use std::{error::Error, fmt};
 
 /// An enumeration of Pyxel errors.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum PyxelError {
     /// An error occured during an IO operation.
     Io(std::io::Error),
 
     /// An error occured during a zip operation.
+    #[cfg(feature = "archive")]
     Zip(zip::result::ZipError),
 
     /// An error occured during deserialization.
     Serde(serde_json::error::Error),
 
+    /// An error occured during deserialization with simd-json.
+    #[cfg(feature = "simd-json")]
+    SimdJson(simd_json::Error),
+
     /// An error occured whilst loading an image.
     #[cfg(feature = "images")]
     Image(image::ImageError),
+
+    /// An error occured whilst watching a document for changes.
+    #[cfg(feature = "watch")]
+    Watch(notify::Error),
+
+    /// An error occured whilst converting a document to `tiled` types.
+    #[cfg(feature = "tiled")]
+    Tiled(tiled::Error),
+
+    /// An error returned by an [`ImageBackend`](trait.ImageBackend.html).
+    Backend(Box<dyn Error + Send + Sync>),
+
+    /// An error occured whilst reading a specific entry of the Pyxel archive.
+    InEntry {
+        /// The name of the archive entry, e.g. `docData.json` or `layer7.png`.
+        name: String,
+        /// The error that occured whilst reading the entry.
+        source: Box<PyxelError>,
+    },
+
+    /// The document was created with a version of PyxelEdit that isn't supported.
+    UnsupportedVersion(semver::Version),
+
+    /// A resource limit was exceeded whilst loading a document.
+    LimitExceeded {
+        /// The limit that was exceeded.
+        limit: Limit,
+        /// The maximum allowed by the limit.
+        max: u64,
+    },
+
+    /// Loading was cancelled through the [`CancelToken`](struct.CancelToken.html) of its
+    /// options.
+    Cancelled,
+
+    /// A tile size was zero or larger than the image being sliced into tiles.
+    InvalidTileSize {
+        /// The width of the tiles in pixels.
+        width: u32,
+        /// The height of the tiles in pixels.
+        height: u32,
+    },
+
+    /// An image within the document was not a valid PNG.
+    InvalidImage,
+
+    /// A palette slot was empty and the export's missing color policy was
+    /// [`MissingColorPolicy::Error`](enum.MissingColorPolicy.html#variant.Error).
+    MissingColor(usize),
+
+    /// A pixel's color was not part of the palette being exported to.
+    ColorNotInPalette {
+        /// The column of the pixel.
+        x: u32,
+        /// The row of the pixel.
+        y: u32,
+    },
+
+    /// A palette had more colors than the export format supports.
+    TooManyColors(usize),
+
+    /// The tiles of another document didn't have the same size as the tiles of this document.
+    TileSizeMismatch {
+        /// The width of the other document's tiles in pixels.
+        width: u16,
+        /// The height of the other document's tiles in pixels.
+        height: u16,
+    },
+
+    /// An image didn't have the dimensions of the canvas it was being set on.
+    ImageSizeMismatch {
+        /// The width of the image in pixels.
+        width: u32,
+        /// The height of the image in pixels.
+        height: u32,
+    },
+
+    /// A palette file was not valid for its format.
+    InvalidPalette,
+
+    /// No layer of the canvas had this name.
+    LayerNotFound(String),
+
+    /// A layer index did not refer to a layer of the canvas.
+    LayerOutOfBounds(usize),
+
+    /// An animation index did not refer to an animation of the document.
+    AnimationOutOfBounds(usize),
+
+    /// A tile index did not refer to a tile of the tileset.
+    TileOutOfBounds(usize),
+
+    /// A cell position was outside of the canvas tile grid.
+    CellOutOfBounds {
+        /// The column of the cell.
+        col: usize,
+        /// The row of the cell.
+        row: usize,
+    },
+
+    /// An icon size was zero or larger than the 256 pixels ICO files allow.
+    InvalidIconSize(u32),
+}
+
+impl PyxelError {
+    pub(crate) fn in_entry(self, name: &str) -> PyxelError {
+        // Cancellation isn't a problem with the entry being read when it happens.
+        if let PyxelError::Cancelled = self {
+            return self;
+        }
+
+        PyxelError::InEntry {
+            name: name.to_owned(),
+            source: Box::new(self),
+        }
+    }
 }
 
 impl fmt::Display for PyxelError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             PyxelError::Io(ref e) => e.fmt(f),
+            #[cfg(feature = "archive")]
             PyxelError::Zip(ref e) => e.fmt(f),
             PyxelError::Serde(ref e) => e.fmt(f),
+            #[cfg(feature = "simd-json")]
+            PyxelError::SimdJson(ref e) => e.fmt(f),
             #[cfg(feature = "images")]
             PyxelError::Image(ref e) => e.fmt(f),
+            #[cfg(feature = "watch")]
+            PyxelError::Watch(ref e) => e.fmt(f),
+            #[cfg(feature = "tiled")]
+            PyxelError::Tiled(ref e) => e.fmt(f),
+            PyxelError::Backend(ref e) => e.fmt(f),
+            PyxelError::InEntry {
+                ref name,
+                ref source,
+            } => write!(f, "{}: {}", name, source),
+            PyxelError::UnsupportedVersion(ref version) => {
+                write!(f, "unsupported PyxelEdit version {}", version)
+            }
+            PyxelError::LimitExceeded { limit, max } => {
+                write!(f, "{} exceeds the limit of {}", limit, max)
+            }
+            PyxelError::Cancelled => write!(f, "loading was cancelled"),
+            PyxelError::InvalidTileSize { width, height } => {
+                write!(f, "invalid tile size {}x{}", width, height)
+            }
+            PyxelError::InvalidImage => write!(f, "invalid PNG image"),
+            PyxelError::MissingColor(index) => write!(f, "palette slot {} is empty", index),
+            PyxelError::ColorNotInPalette { x, y } => {
+                write!(f, "pixel ({}, {}) is not in the palette", x, y)
+            }
+            PyxelError::TooManyColors(count) => {
+                write!(f, "{} colors is more than the format supports", count)
+            }
+            PyxelError::TileSizeMismatch { width, height } => {
+                write!(f, "tile size {}x{} does not match", width, height)
+            }
+            PyxelError::ImageSizeMismatch { width, height } => {
+                write!(
+                    f,
+                    "image size {}x{} does not match the canvas",
+                    width, height
+                )
+            }
+            PyxelError::InvalidPalette => write!(f, "invalid palette file"),
+            PyxelError::LayerNotFound(ref name) => write!(f, "no layer is named {:?}", name),
+            PyxelError::LayerOutOfBounds(index) => write!(f, "layer {} does not exist", index),
+            PyxelError::AnimationOutOfBounds(index) => {
+                write!(f, "animation {} does not exist", index)
+            }
+            PyxelError::TileOutOfBounds(index) => write!(f, "tile {} does not exist", index),
+            PyxelError::CellOutOfBounds { col, row } => {
+                write!(f, "cell ({}, {}) is outside of the canvas", col, row)
+            }
+            PyxelError::InvalidIconSize(size) => write!(f, "invalid icon size {}", size),
         }
     }
 }
 
 impl Error for PyxelError {
-    fn description(&self) -> &str {
-        match *self {
-            PyxelError::Io(ref e) => e.description(),
-            PyxelError::Zip(ref e) => e.description(),
-            PyxelError::Serde(ref e) => e.description(),
-            #[cfg(feature = "images")]
-            PyxelError::Image(ref e) => e.description(),
-        }
-    }
-
-    fn cause(&self) -> Option<&Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match *self {
             PyxelError::Io(ref e) => Some(e),
+            #[cfg(feature = "archive")]
             PyxelError::Zip(ref e) => Some(e),
             PyxelError::Serde(ref e) => Some(e),
+            #[cfg(feature = "simd-json")]
+            PyxelError::SimdJson(ref e) => Some(e),
             #[cfg(feature = "images")]
             PyxelError::Image(ref e) => Some(e),
+            #[cfg(feature = "watch")]
+            PyxelError::Watch(ref e) => Some(e),
+            #[cfg(feature = "tiled")]
+            PyxelError::Tiled(ref e) => Some(e),
+            PyxelError::Backend(ref e) => Some(e.as_ref()),
+            PyxelError::InEntry { ref source, .. } => Some(source.as_ref()),
+            PyxelError::UnsupportedVersion(_)
+            | PyxelError::LimitExceeded { .. }
+            | PyxelError::Cancelled
+            | PyxelError::InvalidTileSize { .. }
+            | PyxelError::InvalidImage
+            | PyxelError::MissingColor(_)
+            | PyxelError::ColorNotInPalette { .. }
+            | PyxelError::TooManyColors(_)
+            | PyxelError::TileSizeMismatch { .. }
+            | PyxelError::ImageSizeMismatch { .. }
+            | PyxelError::InvalidPalette
+            | PyxelError::LayerNotFound(_)
+            | PyxelError::LayerOutOfBounds(_)
+            | PyxelError::AnimationOutOfBounds(_)
+            | PyxelError::TileOutOfBounds(_)
+            | PyxelError::CellOutOfBounds { .. }
+            | PyxelError::InvalidIconSize(_) => None,
         }
     }
 }
@@ -57,6 +248,7 @@ impl std::convert::From<std::io::Error> for PyxelError {
     }
 }
 
+#[cfg(feature = "archive")]
 impl std::convert::From<zip::result::ZipError> for PyxelError {
     fn from(err: zip::result::ZipError) -> PyxelError {
         PyxelError::Zip(err)
@@ -69,9 +261,30 @@ impl std::convert::From<serde_json::error::Error> for PyxelError {
     }
 }
 
+#[cfg(feature = "simd-json")]
+impl std::convert::From<simd_json::Error> for PyxelError {
+    fn from(err: simd_json::Error) -> PyxelError {
+        PyxelError::SimdJson(err)
+    }
+}
+
 #[cfg(feature = "images")]
 impl std::convert::From<image::ImageError> for PyxelError {
     fn from(err: image::ImageError) -> PyxelError {
         PyxelError::Image(err)
     }
 }
+
+#[cfg(feature = "watch")]
+impl std::convert::From<notify::Error> for PyxelError {
+    fn from(err: notify::Error) -> PyxelError {
+        PyxelError::Watch(err)
+    }
+}
+
+#[cfg(feature = "tiled")]
+impl std::convert::From<tiled::Error> for PyxelError {
+    fn from(err: tiled::Error) -> PyxelError {
+        PyxelError::Tiled(err)
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{
+    error::PyxelError, png::encode_png, pyxel::Pyxel, render::scale_image, text::write_str,
+};
+
+use image::RgbaImage;
+use std::collections::HashSet;
+
+/// Where [`Pyxel::export_bmfont`](struct.Pyxel.html#method.export_bmfont) takes glyphs from.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum GlyphSource {
+    /// The tiles of the tileset, in tile order.
+    #[default]
+    Tileset,
+    /// The cells of the flattened canvas, row by row.
+    Canvas,
+}
+
+/// Options for [`Pyxel::export_bmfont`](struct.Pyxel.html#method.export_bmfont).
+#[derive(Clone, Debug)]
+pub struct BmFontOptions {
+    /// The name of the font.
+    pub face: String,
+    /// The file name the descriptor refers to the glyph image by.
+    pub image: String,
+    /// Where the glyphs are taken from.
+    pub source: GlyphSource,
+    /// The index of the tile or cell holding the first glyph.
+    pub first_glyph: usize,
+    /// Give every glyph the full width of a tile rather than trimming the transparent columns
+    /// either side of it.
+    pub monospace: bool,
+    /// The number of pixels between trimmed glyphs.
+    pub letter_spacing: u32,
+    /// How far glyphs without any opaque pixels, such as spaces, advance when trimmed. Defaults
+    /// to half the width of a tile.
+    pub space_width: Option<u32>,
+    /// The distance from the top of a line to the baseline. Defaults to the height of a tile.
+    pub base: Option<u32>,
+    /// The factor the glyph image and the glyphs' sizes and positions are scaled up by with
+    /// nearest-neighbor filtering. Letter spacing, space width and base aren't scaled.
+    pub scale: u32,
+}
+
+impl Default for BmFontOptions {
+    fn default() -> Self {
+        BmFontOptions {
+            face: "pyxel".to_owned(),
+            image: "font.png".to_owned(),
+            source: GlyphSource::Tileset,
+            first_glyph: 0,
+            monospace: false,
+            letter_spacing: 1,
+            space_width: None,
+            base: None,
+            scale: 1,
+        }
+    }
+}
+
+/// The files produced by [`Pyxel::export_bmfont`](struct.Pyxel.html#method.export_bmfont).
+#[derive(Clone, Debug)]
+pub struct BmFont {
+    /// The glyph image, encoded as PNG.
+    pub image: Vec<u8>,
+    /// The text `.fnt` descriptor giving the position and metrics of each glyph.
+    pub descriptor: String,
+}
+
+/// Returns the first and last columns of the `width` × `height` region of `image` at `x`, `y`
+/// holding a pixel that isn't fully transparent.
+fn opaque_columns(
+    image: &RgbaImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Option<(u32, u32)> {
+    let opaque = |col: &u32| (y..y + height).any(|row| image.get_pixel(x + col, row)[3] != 0);
+    let first = (0..width).find(opaque)?;
+    let last = (0..width).rev().find(opaque)?;
+    Some((first, last))
+}
+
+impl Pyxel {
+    /// Exports the tiles of the tileset or the cells of the canvas as the glyphs of an
+    /// [AngelCode BMFont](https://www.angelcode.com/products/bmfont/), with the characters of
+    /// `glyphs` drawn by consecutive tiles or cells starting at
+    /// [`first_glyph`](struct.BmFontOptions.html#structfield.first_glyph).
+    ///
+    /// The image is the [atlas](struct.Tileset.html#method.atlas) of the tileset or the flattened
+    /// canvas. Glyphs are trimmed to their opaque columns unless
+    /// [`monospace`](struct.BmFontOptions.html#structfield.monospace) is set, but always keep the
+    /// full height of a tile so they share a baseline. Characters repeated in `glyphs` keep their
+    /// first glyph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let font = doc.export_bmfont("ABCD", &pyxel::BmFontOptions::default())?;
+    /// assert!(font.descriptor.contains("chars count=4"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_bmfont(
+        &self,
+        glyphs: &str,
+        options: &BmFontOptions,
+    ) -> Result<BmFont, PyxelError> {
+        let (image, tile_width, tile_height, columns, count) = match options.source {
+            GlyphSource::Tileset => {
+                let tileset = &self.tileset;
+                (
+                    tileset.atlas(),
+                    u32::from(tileset.tile_width),
+                    u32::from(tileset.tile_height),
+                    usize::from(tileset.tiles_wide).max(1),
+                    tileset.tile_count(),
+                )
+            }
+            GlyphSource::Canvas => {
+                let canvas = &self.canvas;
+                (
+                    canvas.flatten(),
+                    u32::from(canvas.tile_width),
+                    u32::from(canvas.tile_height),
+                    canvas.tiles_wide().max(1),
+                    canvas.tiles_wide() * canvas.tiles_high(),
+                )
+            }
+        };
+        let scale = options.scale.max(1);
+        let (image, tile_width, tile_height) = (
+            scale_image(image, scale),
+            tile_width * scale,
+            tile_height * scale,
+        );
+
+        let mut chars = Vec::new();
+        let mut seen = HashSet::new();
+        for (i, c) in glyphs.chars().enumerate() {
+            let index = options.first_glyph + i;
+            if index >= count {
+                return Err(match options.source {
+                    GlyphSource::Tileset => PyxelError::TileOutOfBounds(index),
+                    GlyphSource::Canvas => PyxelError::CellOutOfBounds {
+                        col: index % columns,
+                        row: index / columns,
+                    },
+                });
+            }
+            if !seen.insert(c) {
+                continue;
+            }
+
+            let x = (index % columns) as u32 * tile_width;
+            let y = (index / columns) as u32 * tile_height;
+            let (offset, width, advance) = if options.monospace {
+                (0, tile_width, tile_width)
+            } else {
+                match opaque_columns(&image, x, y, tile_width, tile_height) {
+                    Some((first, last)) => {
+                        let width = last - first + 1;
+                        (first, width, width + options.letter_spacing)
+                    }
+                    None => (0, 0, options.space_width.unwrap_or(tile_width / 2)),
+                }
+            };
+            chars.push((c, x + offset, y, width, advance));
+        }
+
+        let mut descriptor = String::new();
+
+        write_str!(
+            descriptor,
+            "info face=\"{}\" size={} bold=0 italic=0 charset=\"\" unicode=1 stretchH=100 smooth=0 aa=0 padding=0,0,0,0 spacing=0,0\n",
+            options.face.replace('"', "'"),
+            tile_height
+        );
+        write_str!(
+            descriptor,
+            "common lineHeight={} base={} scaleW={} scaleH={} pages=1 packed=0\n",
+            tile_height,
+            options.base.unwrap_or(tile_height),
+            image.width(),
+            image.height()
+        );
+        write_str!(
+            descriptor,
+            "page id=0 file=\"{}\"\n",
+            options.image.replace('"', "'")
+        );
+        write_str!(descriptor, "chars count={}\n", chars.len());
+        for (c, x, y, width, advance) in chars {
+            write_str!(
+                descriptor,
+                "char id={} x={} y={} width={} height={} xoffset=0 yoffset=0 xadvance={} page=0 chnl=15\n",
+                u32::from(c),
+                x,
+                y,
+                width,
+                tile_height,
+                advance
+            );
+        }
+
+        Ok(BmFont {
+            image: encode_png(&image)?,
+            descriptor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    fn char_line(font: &BmFont, c: char) -> &str {
+        let prefix = format!("char id={} ", u32::from(c));
+        font.descriptor
+            .lines()
+            .find(|line| line.starts_with(&prefix))
+            .unwrap()
+    }
+
+    #[test]
+    fn glyphs_follow_the_glyph_order() {
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let options = BmFontOptions {
+            monospace: true,
+            ..BmFontOptions::default()
+        };
+
+        let font = doc.export_bmfont("abca", &options).unwrap();
+        assert!(font.descriptor.contains("chars count=3\n"));
+        assert!(font
+            .descriptor
+            .contains("common lineHeight=16 base=16 scaleW=256 scaleH=16 pages=1"));
+        assert_eq!(
+            "char id=99 x=64 y=0 width=32 height=16 xoffset=0 yoffset=0 xadvance=32 page=0 chnl=15",
+            char_line(&font, 'c')
+        );
+        let image = image::load_from_memory(&font.image).unwrap();
+        assert_eq!((256, 16), image::GenericImageView::dimensions(&image));
+
+        match doc.export_bmfont("abcde", &options) {
+            Err(PyxelError::TileOutOfBounds(4)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn glyphs_are_scaled() {
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let options = BmFontOptions {
+            monospace: true,
+            scale: 2,
+            ..BmFontOptions::default()
+        };
+
+        let font = doc.export_bmfont("abc", &options).unwrap();
+        assert!(font
+            .descriptor
+            .contains("common lineHeight=32 base=32 scaleW=512 scaleH=32 pages=1"));
+        assert_eq!(
+            "char id=99 x=128 y=0 width=64 height=32 xoffset=0 yoffset=0 xadvance=64 page=0 chnl=15",
+            char_line(&font, 'c')
+        );
+        let image = image::load_from_memory(&font.image).unwrap();
+        assert_eq!((512, 32), image::GenericImageView::dimensions(&image));
+    }
+
+    #[test]
+    fn trimmed_glyphs_fit_their_pixels() {
+        let mut doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let mut tiles = vec![RgbaImage::new(8, 4), RgbaImage::new(8, 4)];
+        tiles[0].put_pixel(2, 1, image::Rgba([255; 4]));
+        tiles[0].put_pixel(4, 3, image::Rgba([255; 4]));
+        doc.tileset =
+            crate::import::document_from_tiles("font".to_owned(), 8, 4, 2, tiles, Vec::new())
+                .tileset;
+
+        let options = BmFontOptions {
+            space_width: Some(3),
+            ..BmFontOptions::default()
+        };
+        let font = doc.export_bmfont("! ", &options).unwrap();
+        assert_eq!(
+            "char id=33 x=2 y=0 width=3 height=4 xoffset=0 yoffset=0 xadvance=4 page=0 chnl=15",
+            char_line(&font, '!')
+        );
+        assert_eq!(
+            "char id=32 x=8 y=0 width=0 height=4 xoffset=0 yoffset=0 xadvance=3 page=0 chnl=15",
+            char_line(&font, ' ')
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    error::PyxelError,
+    png::encode_png,
+    pyxel::{Animation, Pyxel},
+    render::scale_image,
+};
+
+use image::{imageops, RgbaImage};
+use serde_json::json;
+
+/// Options for
+/// [`Pyxel::export_gamemaker_sprites`](struct.Pyxel.html#method.export_gamemaker_sprites).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GameMakerOptions {
+    /// The factor frames are scaled up by with nearest-neighbor filtering.
+    pub scale: u32,
+}
+
+impl Default for GameMakerOptions {
+    fn default() -> Self {
+        GameMakerOptions { scale: 1 }
+    }
+}
+
+/// A sprite strip produced by
+/// [`Pyxel::export_gamemaker_sprites`](struct.Pyxel.html#method.export_gamemaker_sprites).
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameMakerSprite {
+    /// The file name of the strip, following GameMaker's `name_stripN.png` convention so that
+    /// "Import Strip Image" slices it automatically.
+    pub file_name: String,
+    /// The frames of the animation side by side, encoded as PNG.
+    pub strip: Vec<u8>,
+    /// The playback speed in frames per second, taken from the animation's base frame duration.
+    pub fps: f64,
+    /// The length of each frame in frames at `fps`, taken from the animation's frame duration
+    /// multipliers.
+    pub frame_lengths: Vec<f64>,
+    /// The playback speed and frame lengths as JSON, for tools that fill in the sprite's
+    /// `.yy` file.
+    pub timing: String,
+}
+
+/// Returns `name` with every character GameMaker doesn't allow in resource names replaced by an
+/// underscore.
+fn resource_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    // Resource names can't start with a digit.
+    match name.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", name),
+        _ => name,
+    }
+}
+
+fn frame_lengths(animation: &Animation) -> Vec<f64> {
+    (0..animation.length)
+        .map(|i| *animation.frame_duration_multipliers.get(i).unwrap_or(&1.))
+        .collect()
+}
+
+impl Pyxel {
+    /// Renders each animation of this document as a horizontal
+    /// [GameMaker](https://gamemaker.io) sprite strip, with its frame timings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// for sprite in doc.export_gamemaker_sprites(&pyxel::GameMakerOptions::default())? {
+    ///     println!("{} plays at {} fps", sprite.file_name, sprite.fps);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_gamemaker_sprites(
+        &self,
+        options: &GameMakerOptions,
+    ) -> Result<Vec<GameMakerSprite>, PyxelError> {
+        let scale = options.scale.max(1);
+        let (tile_width, tile_height) = (
+            u32::from(self.canvas.tile_width) * scale,
+            u32::from(self.canvas.tile_height) * scale,
+        );
+
+        let mut sprites = Vec::with_capacity(self.animations.len());
+        for animation in &self.animations {
+            let mut strip = RgbaImage::new(tile_width * animation.length as u32, tile_height);
+            for frame in 0..animation.length {
+                let image = self.canvas.render_frame(animation, frame).ok_or_else(|| {
+                    let tile = animation.base_tile + frame;
+                    let tiles_wide = self.canvas.tiles_wide().max(1);
+                    PyxelError::CellOutOfBounds {
+                        col: tile % tiles_wide,
+                        row: tile / tiles_wide,
+                    }
+                })?;
+                imageops::replace(
+                    &mut strip,
+                    &scale_image(image, scale),
+                    frame as u32 * tile_width,
+                    0,
+                );
+            }
+
+            let millis = animation.frame_duration.as_secs_f64() * 1000.;
+            let fps = if millis > 0. { 1000. / millis } else { 0. };
+            let frame_lengths = frame_lengths(animation);
+            let name = resource_name(&animation.name);
+
+            let timing = json!({
+                "name": name,
+                "playbackSpeed": fps,
+                // Frames per second rather than frames per game frame.
+                "playbackSpeedType": 0,
+                "frames": frame_lengths
+                    .iter()
+                    .map(|length| json!({ "length": length }))
+                    .collect::<Vec<_>>(),
+            });
+
+            sprites.push(GameMakerSprite {
+                file_name: format!("{}_strip{}.png", name, animation.length),
+                strip: encode_png(&strip)?,
+                fps,
+                frame_lengths,
+                timing: timing.to_string(),
+            });
+        }
+
+        Ok(sprites)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use image::GenericImageView;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn strips_hold_every_frame() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let sprites = doc
+            .export_gamemaker_sprites(&GameMakerOptions::default())
+            .unwrap();
+        assert_eq!(3, sprites.len());
+
+        let sprite = &sprites[0];
+        assert_eq!("Animation_1_strip4.png", sprite.file_name);
+        assert_eq!(1000. / 150., sprite.fps);
+        assert_eq!(vec![1., 2., 3., 4.], sprite.frame_lengths);
+
+        let strip = image::load_from_memory(&sprite.strip).unwrap().to_rgba();
+        assert_eq!((4 * 32, 16), strip.dimensions());
+        let frame = doc.canvas.render_frame(&doc.animations[0], 2).unwrap();
+        assert_eq!(
+            frame.into_raw(),
+            strip.view(2 * 32, 0, 32, 16).to_image().into_raw()
+        );
+
+        let timing: serde_json::Value = serde_json::from_str(&sprites[2].timing).unwrap();
+        assert_eq!(1., timing["playbackSpeed"]);
+        assert_eq!(2, timing["frames"].as_array().unwrap().len());
+    }
+
+    #[test]
+    fn strips_are_scaled() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let sprites = doc
+            .export_gamemaker_sprites(&GameMakerOptions { scale: 3 })
+            .unwrap();
+
+        let strip = image::load_from_memory(&sprites[0].strip)
+            .unwrap()
+            .to_rgba();
+        assert_eq!((4 * 32 * 3, 16 * 3), strip.dimensions());
+        let frame = doc.canvas.render_frame(&doc.animations[0], 1).unwrap();
+        for (x, y, pixel) in strip.view(32 * 3, 0, 32 * 3, 16 * 3).pixels() {
+            assert_eq!(*frame.get_pixel(x / 3, y / 3), pixel);
+        }
+    }
+
+    #[test]
+    fn resource_names_are_sanitised() {
+        assert_eq!("Walk_Left", resource_name("Walk Left"));
+        assert_eq!("_2nd", resource_name("2nd"));
+    }
+}
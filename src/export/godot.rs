@@ -0,0 +1,284 @@
+use crate::{
+    atlas::AtlasBuilder,
+    error::PyxelError,
+    export::tmx::flip_flags,
+    png::encode_png,
+    pyxel::{Pyxel, TileRef},
+    render::scale_image,
+    text::write_str,
+};
+
+use std::collections::BTreeSet;
+
+/// Options for [`Pyxel::export_godot_tileset`](struct.Pyxel.html#method.export_godot_tileset)
+/// and [`Pyxel::to_godot_scene`](struct.Pyxel.html#method.to_godot_scene).
+#[derive(Clone, Debug)]
+pub struct GodotOptions {
+    /// The resource path the tileset refers to the atlas texture by.
+    pub texture_path: String,
+    /// The resource path the scene refers to the tileset by.
+    pub tileset_path: String,
+    /// The factor the atlas texture is scaled up by with nearest-neighbor filtering. The tile
+    /// sizes of the tileset are scaled to match, and scenes are unaffected.
+    pub scale: u32,
+}
+
+impl Default for GodotOptions {
+    fn default() -> Self {
+        GodotOptions {
+            texture_path: "res://tileset.png".to_owned(),
+            tileset_path: "res://tileset.tres".to_owned(),
+            scale: 1,
+        }
+    }
+}
+
+/// The files produced by
+/// [`Pyxel::export_godot_tileset`](struct.Pyxel.html#method.export_godot_tileset).
+#[derive(Clone, Debug)]
+pub struct GodotTileset {
+    /// The atlas texture, encoded as PNG.
+    pub texture: Vec<u8>,
+    /// The `TileSet` resource, in Godot's text resource format.
+    pub tileset: String,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Returns the ID of the alternative tile standing in for the rotation and flip of `tile_ref`.
+/// The untransformed tile is alternative 0.
+fn alternative(tile_ref: &TileRef) -> u16 {
+    let quarter_turns = (tile_ref.rot / 90.).round().rem_euclid(4.) as u16;
+    quarter_turns + if tile_ref.flip_x { 4 } else { 0 }
+}
+
+impl Pyxel {
+    /// Returns the atlas coordinates of `tile`, with tiles laid out `tiles_wide` across.
+    fn godot_atlas_coords(&self, tile: usize) -> (usize, usize) {
+        let columns = usize::from(self.tileset.tiles_wide).max(1);
+        (tile % columns, tile / columns)
+    }
+
+    /// Converts the tileset of this document to a [Godot 4](https://godotengine.org) `TileSet`
+    /// resource with a single atlas source, along with the atlas texture it refers to.
+    ///
+    /// Each rotation and flip used by the canvas becomes an alternative tile, so scenes produced
+    /// by [`to_godot_scene`](#method.to_godot_scene) can refer to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let exported = doc.export_godot_tileset(&pyxel::GodotOptions::default())?;
+    /// assert!(exported.tileset.starts_with("[gd_resource type=\"TileSet\""));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_godot_tileset(&self, options: &GodotOptions) -> Result<GodotTileset, PyxelError> {
+        let tileset = &self.tileset;
+        let atlas = AtlasBuilder::new().build(tileset);
+        let scale = options.scale.max(1);
+        let (tile_width, tile_height) = (
+            u32::from(tileset.tile_width) * scale,
+            u32::from(tileset.tile_height) * scale,
+        );
+
+        let mut alternatives: Vec<BTreeSet<u16>> = vec![BTreeSet::new(); tileset.tile_count()];
+        for layer in &self.canvas.layers {
+            for tile_ref in layer.tile_refs.values() {
+                if let Some(used) = alternatives.get_mut(tile_ref.index) {
+                    used.insert(alternative(tile_ref));
+                }
+            }
+        }
+
+        let mut tres = String::new();
+
+        write_str!(
+            tres,
+            "[gd_resource type=\"TileSet\" load_steps=3 format=3]\n\n"
+        );
+        write_str!(
+            tres,
+            "[ext_resource type=\"Texture2D\" path=\"{}\" id=\"1\"]\n\n",
+            escape(&options.texture_path)
+        );
+        write_str!(
+            tres,
+            "[sub_resource type=\"TileSetAtlasSource\" id=\"TileSetAtlasSource_1\"]\n"
+        );
+        write_str!(tres, "texture = ExtResource(\"1\")\n");
+        write_str!(
+            tres,
+            "texture_region_size = Vector2i({}, {})\n",
+            tile_width,
+            tile_height
+        );
+
+        for (tile, used) in alternatives.iter().enumerate() {
+            let (x, y) = self.godot_atlas_coords(tile);
+            write_str!(tres, "{}:{}/0 = 0\n", x, y);
+
+            for &id in used.iter().filter(|&&id| id != 0) {
+                let tile_ref = TileRef::new(tile, f64::from(id % 4) * 90., id >= 4);
+                let (flip_h, flip_v, transpose) = flip_flags(&tile_ref);
+
+                write_str!(tres, "{}:{}/{} = {}\n", x, y, id, id);
+                write_str!(tres, "{}:{}/{}/flip_h = {}\n", x, y, id, flip_h);
+                write_str!(tres, "{}:{}/{}/flip_v = {}\n", x, y, id, flip_v);
+                write_str!(tres, "{}:{}/{}/transpose = {}\n", x, y, id, transpose);
+            }
+        }
+
+        write_str!(tres, "\n[resource]\n");
+        write_str!(
+            tres,
+            "tile_size = Vector2i({}, {})\n",
+            tile_width,
+            tile_height
+        );
+        write_str!(tres, "sources/0 = SubResource(\"TileSetAtlasSource_1\")\n");
+
+        Ok(GodotTileset {
+            texture: encode_png(&scale_image(atlas.image, scale))?,
+            tileset: tres,
+        })
+    }
+
+    /// Converts the canvas of this document to a Godot 4 scene holding a `TileMap` node, with a
+    /// layer for each canvas layer, referring to the tileset produced by
+    /// [`export_godot_tileset`](#method.export_godot_tileset).
+    ///
+    /// Layers are ordered bottom-most first as Godot expects, and hidden layers are disabled.
+    /// Blend modes can't be represented and are dropped.
+    pub fn to_godot_scene(&self, options: &GodotOptions) -> String {
+        let canvas = &self.canvas;
+        let (columns, cells) = (
+            canvas.tiles_wide(),
+            canvas.tiles_wide() * canvas.tiles_high(),
+        );
+
+        let mut tscn = String::new();
+
+        write_str!(tscn, "[gd_scene load_steps=2 format=3]\n\n");
+        write_str!(
+            tscn,
+            "[ext_resource type=\"TileSet\" path=\"{}\" id=\"1\"]\n\n",
+            escape(&options.tileset_path)
+        );
+        write_str!(
+            tscn,
+            "[node name=\"{}\" type=\"TileMap\"]\n",
+            escape(&self.name)
+        );
+        write_str!(tscn, "tile_set = ExtResource(\"1\")\n");
+        write_str!(tscn, "format = 2\n");
+
+        // Layers are stored top-most first, but Godot draws the first layer at the bottom.
+        let visibility = canvas.effective_visibility();
+        for (id, (layer, visible)) in canvas.layers.iter().zip(visibility).rev().enumerate() {
+            write_str!(tscn, "layer_{}/name = \"{}\"\n", id, escape(&layer.name));
+            write_str!(tscn, "layer_{}/enabled = {}\n", id, visible);
+            write_str!(
+                tscn,
+                "layer_{}/modulate = Color(1, 1, 1, {:.3})\n",
+                id,
+                f32::from(layer.alpha) / 255.
+            );
+            write_str!(tscn, "layer_{}/z_index = {}\n", id, id);
+
+            // Each cell is three integers: its coordinates, the source ID and atlas column, and
+            // the atlas row and alternative tile, with each pair packed into 16-bit halves.
+            let data = layer
+                .tile_refs
+                .iter()
+                .filter(|&(&cell, _)| cell < cells)
+                .flat_map(|(&cell, tile_ref)| {
+                    let (col, row) = (cell % columns, cell / columns);
+                    let (x, y) = self.godot_atlas_coords(tile_ref.index);
+                    vec![
+                        (col as u32 | (row as u32) << 16) as i32,
+                        ((x as u32) << 16) as i32,
+                        (y as u32 | u32::from(alternative(tile_ref)) << 16) as i32,
+                    ]
+                })
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            write_str!(
+                tscn,
+                "layer_{}/tile_data = PackedInt32Array({})\n",
+                id,
+                data
+            );
+        }
+
+        tscn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn tileset_has_alternatives_for_used_transforms() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        doc.canvas.layers[0]
+            .tile_refs
+            .insert(63, TileRef::new(2, 90., false));
+        doc.canvas.layers[0]
+            .tile_refs
+            .insert(62, TileRef::new(3, 0., true));
+
+        let exported = doc.export_godot_tileset(&GodotOptions::default()).unwrap();
+        let tres = &exported.tileset;
+
+        assert!(tres.contains("path=\"res://tileset.png\""));
+        assert!(tres.contains("texture_region_size = Vector2i(32, 16)"));
+        for tile in 0..4 {
+            assert!(tres.contains(&format!("{}:0/0 = 0\n", tile)));
+        }
+        assert!(tres.contains(
+            "2:0/1 = 1\n2:0/1/flip_h = true\n2:0/1/flip_v = false\n2:0/1/transpose = true\n"
+        ));
+        assert!(
+            tres.contains("3:0/4/flip_h = true\n3:0/4/flip_v = false\n3:0/4/transpose = false\n")
+        );
+
+        let texture = image::load_from_memory(&exported.texture).unwrap();
+        assert_eq!(
+            doc.tileset.atlas().dimensions(),
+            texture.to_rgba().dimensions()
+        );
+    }
+
+    #[test]
+    fn scene_packs_cells_of_each_layer() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let layer = &mut doc.canvas.layers[0];
+        layer.tile_refs.clear();
+        layer.tile_refs.insert(8 + 3, TileRef::new(2, 180., false));
+
+        let tscn = doc.to_godot_scene(&GodotOptions::default());
+        assert!(tscn.contains("[node name=\"test_v0.4.8\" type=\"TileMap\"]"));
+        assert_eq!(11, tscn.matches("/tile_data = ").count());
+
+        // The top-most layer comes last.
+        let expected = format!(
+            "layer_10/tile_data = PackedInt32Array({}, {}, {})\n",
+            3 | 1 << 16,
+            2 << 16,
+            2 << 16
+        );
+        assert!(tscn.contains(&expected), "{}", tscn);
+        assert!(tscn.contains("layer_7/name = \"Layer 7\"\nlayer_7/enabled = false\n"));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{error::PyxelError, png::encode_png, pyxel::Pyxel, render::scale_image};
+
+use image::{imageops, RgbaImage};
+
+/// The image [`Pyxel::export_ico`](struct.Pyxel.html#method.export_ico) makes an icon of.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum IconSource {
+    /// A frame of an animation, rendered from the canvas.
+    Frame {
+        /// The index of the animation in the document.
+        animation: usize,
+        /// The index of the frame within the animation.
+        frame: usize,
+    },
+    /// A tile of the tileset.
+    Tile(usize),
+    /// A cell of the canvas, rendered with every visible layer.
+    Cell {
+        /// The column of the cell.
+        col: usize,
+        /// The row of the cell.
+        row: usize,
+    },
+}
+
+/// Options for [`Pyxel::export_ico`](struct.Pyxel.html#method.export_ico).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IcoOptions {
+    /// The width and height in pixels of each image of the icon, from 1 to 256.
+    pub sizes: Vec<u32>,
+    /// The factor the source is scaled up by with nearest-neighbor filtering before it's fit to
+    /// each size.
+    pub scale: u32,
+}
+
+impl Default for IcoOptions {
+    fn default() -> Self {
+        IcoOptions {
+            sizes: vec![16, 32, 48, 64],
+            scale: 1,
+        }
+    }
+}
+
+/// Scales `image` up by the largest whole factor that fits a `size` × `size` square, or down to
+/// fit it if it's larger, and centers it in a transparent square of that size.
+fn fit_icon(image: &RgbaImage, size: u32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let largest = width.max(height).max(1);
+    let scaled = if largest <= size {
+        scale_image(image.clone(), size / largest)
+    } else {
+        let fit = |length: u32| (length * size / largest).max(1);
+        imageops::resize(
+            image,
+            fit(width),
+            fit(height),
+            imageops::FilterType::Nearest,
+        )
+    };
+
+    let mut icon = RgbaImage::new(size, size);
+    imageops::replace(
+        &mut icon,
+        &scaled,
+        (size - scaled.width()) / 2,
+        (size - scaled.height()) / 2,
+    );
+    icon
+}
+
+impl Pyxel {
+    /// Renders `source` at each of the sizes of `options` and writes them into a single `.ico`
+    /// file, such as a favicon.
+    ///
+    /// Each image is scaled up by the largest whole factor that fits, so pixels stay square and
+    /// crisp, and centered on a transparent background when the source isn't square or doesn't
+    /// divide the size. Sources larger than a size are scaled down to fit it. The images are
+    /// stored as PNG, smallest first, which every version of Windows since Vista reads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let source = pyxel::IconSource::Frame {
+    ///     animation: 0,
+    ///     frame: 0,
+    /// };
+    /// let ico = doc.export_ico(source, &pyxel::IcoOptions::default())?;
+    /// // One image for each of the default 16, 32, 48 and 64 pixel sizes.
+    /// assert_eq!(&[0, 0, 1, 0, 4, 0], &ico[..6]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_ico(
+        &self,
+        source: IconSource,
+        options: &IcoOptions,
+    ) -> Result<Vec<u8>, PyxelError> {
+        let image = match source {
+            IconSource::Frame { animation, frame } => {
+                let anim = self
+                    .animations
+                    .get(animation)
+                    .ok_or(PyxelError::AnimationOutOfBounds(animation))?;
+                self.canvas.render_frame(anim, frame).ok_or_else(|| {
+                    let tile = anim.base_tile + frame;
+                    let tiles_wide = self.canvas.tiles_wide().max(1);
+                    PyxelError::CellOutOfBounds {
+                        col: tile % tiles_wide,
+                        row: tile / tiles_wide,
+                    }
+                })?
+            }
+            IconSource::Tile(index) => self
+                .tileset
+                .images
+                .get(index)
+                .ok_or(PyxelError::TileOutOfBounds(index))?
+                .to_rgba(),
+            IconSource::Cell { col, row } => self
+                .canvas
+                .render_tile(col, row)
+                .ok_or(PyxelError::CellOutOfBounds { col, row })?,
+        };
+        let image = scale_image(image, options.scale);
+
+        let mut sizes = options.sizes.clone();
+        sizes.sort_unstable();
+        sizes.dedup();
+        if let Some(&size) = sizes.iter().find(|&&size| size == 0 || size > 256) {
+            return Err(PyxelError::InvalidIconSize(size));
+        }
+
+        let pngs = sizes
+            .iter()
+            .map(|&size| encode_png(&fit_icon(&image, size)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut ico = Vec::new();
+        ico.extend_from_slice(&0u16.to_le_bytes());
+        ico.extend_from_slice(&1u16.to_le_bytes());
+        ico.extend_from_slice(&(sizes.len() as u16).to_le_bytes());
+
+        let mut offset = 6 + 16 * sizes.len() as u32;
+        for (&size, png) in sizes.iter().zip(&pngs) {
+            // Sizes are stored in a byte, with 0 standing for 256.
+            let size = (size % 256) as u8;
+            ico.extend_from_slice(&[size, size, 0, 0]);
+            ico.extend_from_slice(&1u16.to_le_bytes());
+            ico.extend_from_slice(&32u16.to_le_bytes());
+            ico.extend_from_slice(&(png.len() as u32).to_le_bytes());
+            ico.extend_from_slice(&offset.to_le_bytes());
+            offset += png.len() as u32;
+        }
+        for png in &pngs {
+            ico.extend_from_slice(png);
+        }
+
+        Ok(ico)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::{convert::TryInto, fs::File};
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn icons_hold_an_image_per_size() {
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let options = IcoOptions {
+            sizes: vec![64, 16, 256, 16],
+            ..IcoOptions::default()
+        };
+        let ico = doc.export_ico(IconSource::Tile(1), &options).unwrap();
+
+        assert_eq!(&[0, 0, 1, 0, 3, 0], &ico[..6]);
+        let tile = doc.tileset.images[1].to_rgba();
+        for (entry, &size) in [16, 64, 256].iter().enumerate() {
+            let at = 6 + 16 * entry;
+            assert_eq!((size % 256) as u8, ico[at]);
+            let (len, offset) = (
+                u32_at(&ico, at + 8) as usize,
+                u32_at(&ico, at + 12) as usize,
+            );
+            let image = image::load_from_memory(&ico[offset..offset + len])
+                .unwrap()
+                .to_rgba();
+            assert_eq!((size, size), image.dimensions());
+
+            // The 32 × 16 tile is scaled by a whole factor and centered vertically, or scaled
+            // down to fit the smallest icon.
+            if size < 32 {
+                assert_eq!(0, image.get_pixel(0, 3)[3]);
+                continue;
+            }
+            let scale = size / 32;
+            let top = (size - 16 * scale) / 2;
+            assert_eq!(
+                tile.get_pixel(3, 5),
+                image.get_pixel(3 * scale, top + 5 * scale)
+            );
+            assert_eq!(0, image.get_pixel(0, top - 1)[3]);
+        }
+        assert_eq!(ico.len(), {
+            let at = 6 + 16 * 2;
+            (u32_at(&ico, at + 8) + u32_at(&ico, at + 12)) as usize
+        });
+    }
+
+    #[test]
+    fn invalid_sources_and_sizes_are_errors() {
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let options = IcoOptions::default();
+        match doc.export_ico(IconSource::Tile(4), &options) {
+            Err(PyxelError::TileOutOfBounds(4)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let source = IconSource::Frame {
+            animation: 3,
+            frame: 0,
+        };
+        match doc.export_ico(source, &options) {
+            Err(PyxelError::AnimationOutOfBounds(3)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let options = IcoOptions {
+            sizes: vec![16, 512],
+            ..IcoOptions::default()
+        };
+        match doc.export_ico(IconSource::Cell { col: 0, row: 0 }, &options) {
+            Err(PyxelError::InvalidIconSize(512)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
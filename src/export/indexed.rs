@@ -0,0 +1,213 @@
+use crate::{
+    dither::{quantize, Dither},
+    error::PyxelError,
+    export::{ExportReport, MissingColorPolicy, TRANSPARENT},
+    pyxel::{Color, Pyxel},
+};
+
+use image::RgbaImage;
+use std::{collections::HashMap, io};
+
+/// Options for [`Pyxel::export_indexed_png`](struct.Pyxel.html#method.export_indexed_png).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IndexedPngOptions {
+    /// How empty palette slots are handled.
+    pub missing_colors: MissingColorPolicy,
+    /// The factor the canvas is scaled up by with nearest-neighbor filtering. Values of 0 and 1
+    /// leave it at its original size.
+    pub scale: u32,
+    /// Map colors outside the palette to the nearest palette color, dithered as specified,
+    /// rather than failing the export. Every pixel must match a palette color exactly when this
+    /// is `None`.
+    pub quantize: Option<Dither>,
+}
+
+// Fully transparent pixels all map to the same palette entry, whatever their color channels.
+fn key(rgba: [u8; 4]) -> [u8; 4] {
+    if rgba[3] == 0 {
+        [0; 4]
+    } else {
+        rgba
+    }
+}
+
+/// Maps each pixel of `image` to the index of the color of `colors` it matches exactly.
+fn exact_indices(image: &RgbaImage, colors: &[Color]) -> Result<Vec<u8>, PyxelError> {
+    let mut lookup = HashMap::new();
+    for (i, c) in colors.iter().enumerate() {
+        lookup.entry(key([c.r, c.g, c.b, c.a])).or_insert(i as u8);
+    }
+
+    let mut indices = Vec::with_capacity((image.width() * image.height()) as usize);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        match lookup.get(&key(pixel.0)) {
+            Some(&index) => indices.push(index),
+            None => return Err(PyxelError::ColorNotInPalette { x, y }),
+        }
+    }
+    Ok(indices)
+}
+
+impl Pyxel {
+    /// Exports the flattened canvas as an 8-bit indexed PNG using this document's palette.
+    ///
+    /// Every pixel must match a palette color exactly, unless
+    /// [`quantize`](struct.IndexedPngOptions.html#structfield.quantize) is set. Fully transparent
+    /// pixels use the first transparent palette entry, or one appended to the end of the palette
+    /// if there isn't one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// match doc.export_indexed_png(&pyxel::IndexedPngOptions::default()) {
+    ///     Ok((png, report)) => println!("wrote {} colors", report.colors_written),
+    ///     Err(pyxel::PyxelError::ColorNotInPalette { x, y }) => {
+    ///         println!("pixel ({}, {}) is not in the palette", x, y)
+    ///     }
+    ///     Err(e) => return Err(e),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_indexed_png(
+        &self,
+        options: &IndexedPngOptions,
+    ) -> Result<(Vec<u8>, ExportReport), PyxelError> {
+        let image = self.canvas.flatten_scaled(options.scale);
+        let (mut colors, mut report) = self.palette.export_colors(options.missing_colors)?;
+
+        if image.pixels().any(|p| p[3] == 0) && !colors.iter().any(|c| c.a == 0) {
+            colors.push(TRANSPARENT);
+            report.colors_written += 1;
+        }
+
+        if colors.len() > 256 {
+            return Err(PyxelError::TooManyColors(colors.len()));
+        }
+
+        let indices = match options.quantize {
+            Some(dither) => quantize(&image, &colors, dither)?
+                .into_iter()
+                .map(|index| index as u8)
+                .collect(),
+            None => exact_indices(&image, &colors)?,
+        };
+
+        let plte: Vec<u8> = colors.iter().flat_map(|c| vec![c.r, c.g, c.b]).collect();
+        let mut trns: Vec<u8> = colors.iter().map(|c| c.a).collect();
+        while trns.last() == Some(&255) {
+            trns.pop();
+        }
+
+        let mut buf = Vec::new();
+        {
+            let mut encoder = ::png::Encoder::new(&mut buf, image.width(), image.height());
+            encoder.set_color(::png::ColorType::Indexed);
+            encoder.set_depth(::png::BitDepth::Eight);
+
+            let mut writer = encoder.write_header().map_err(io::Error::from)?;
+            writer
+                .write_chunk(*b"PLTE", &plte)
+                .map_err(io::Error::from)?;
+            if !trns.is_empty() {
+                writer
+                    .write_chunk(*b"tRNS", &trns)
+                    .map_err(io::Error::from)?;
+            }
+            writer.write_image_data(&indices).map_err(io::Error::from)?;
+        }
+
+        Ok((buf, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::document_from_tiles;
+    use image::{GenericImageView, Rgba, RgbaImage};
+
+    fn color(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+
+    fn document(pixels: &[[u8; 4]]) -> Pyxel {
+        let mut tile = RgbaImage::new(pixels.len() as u32, 1);
+        for (x, pixel) in pixels.iter().enumerate() {
+            tile.put_pixel(x as u32, 0, Rgba(*pixel));
+        }
+
+        let mut doc = document_from_tiles(
+            "indexed".to_owned(),
+            pixels.len() as u16,
+            1,
+            1,
+            vec![tile],
+            Vec::new(),
+        );
+        doc.palette.colors = vec![Some(color(255, 0, 0)), None, Some(color(0, 0, 255))];
+        doc
+    }
+
+    #[test]
+    fn writes_palette_indices() {
+        let doc = document(&[[0, 0, 255, 255], [255, 0, 0, 255], [9, 9, 9, 0]]);
+        let (png, report) = doc
+            .export_indexed_png(&IndexedPngOptions::default())
+            .unwrap();
+
+        // The empty slot is transparent, so it's used for the transparent pixel.
+        assert_eq!(MissingColorPolicy::Transparent, report.missing_color_policy);
+        assert_eq!(vec![1], report.missing_colors);
+        assert_eq!(3, report.colors_written);
+
+        let mut decoder = ::png::Decoder::new(&png[..]);
+        decoder.set_transformations(::png::Transformations::IDENTITY);
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut data = vec![0; info.buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        assert_eq!(vec![2, 0, 1], data);
+
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!(Rgba([0, 0, 255, 255]), image.get_pixel(0, 0));
+        assert_eq!(0, image.get_pixel(2, 0)[3]);
+    }
+
+    #[test]
+    fn appends_transparent_entry() {
+        let doc = document(&[[0, 0, 0, 0]]);
+        let options = IndexedPngOptions {
+            missing_colors: MissingColorPolicy::Skip,
+            ..Default::default()
+        };
+
+        let (_, report) = doc.export_indexed_png(&options).unwrap();
+        assert_eq!(3, report.colors_written);
+    }
+
+    #[test]
+    fn rejects_colors_outside_palette() {
+        let doc = document(&[[255, 0, 0, 255], [0, 255, 0, 255]]);
+
+        match doc.export_indexed_png(&IndexedPngOptions::default()) {
+            Err(PyxelError::ColorNotInPalette { x: 1, y: 0 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn quantizes_colors_outside_palette() {
+        let doc = document(&[[250, 10, 0, 255], [0, 255, 0, 255], [0, 0, 0, 0]]);
+        let options = IndexedPngOptions {
+            quantize: Some(Dither::None),
+            ..Default::default()
+        };
+
+        let (png, _) = doc.export_indexed_png(&options).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!(Rgba([255, 0, 0, 255]), image.get_pixel(0, 0));
+        assert_eq!(0, image.get_pixel(2, 0)[3]);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{atlas::Atlas, pyxel::Canvas, render::scale_image};
+
+use image::{Rgba, RgbaImage};
+
+const IDENTIFIER: [u8; 12] = [
+    0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+];
+
+const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+
+const HEADER_LENGTH: usize = 80;
+const LEVEL_INDEX_ENTRY_LENGTH: usize = 24;
+const DFD_LENGTH: usize = 92;
+
+/// Options for [`Canvas::to_ktx2`](struct.Canvas.html#method.to_ktx2) and
+/// [`Atlas::to_ktx2`](struct.Atlas.html#method.to_ktx2).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Ktx2Options {
+    /// Whether the pixels are marked as sRGB encoded, so GPUs convert them to linear when they're
+    /// sampled. PyxelEdit colors are sRGB.
+    pub srgb: bool,
+    /// Whether a full chain of mip levels is generated, each averaging 2x2 blocks of the level
+    /// above it.
+    pub mipmaps: bool,
+    /// The factor the image is scaled up by with nearest-neighbor filtering, before any mip
+    /// levels are generated.
+    pub scale: u32,
+}
+
+impl Default for Ktx2Options {
+    fn default() -> Self {
+        Ktx2Options {
+            srgb: true,
+            mipmaps: false,
+            scale: 1,
+        }
+    }
+}
+
+/// Halves the size of `image`, averaging each 2x2 block of pixels. Odd rows and columns are
+/// averaged with themselves.
+fn downsample(image: &RgbaImage) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    RgbaImage::from_fn((width / 2).max(1), (height / 2).max(1), |x, y| {
+        let mut sum = [0u32; 4];
+        for &(dx, dy) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let pixel = image.get_pixel((x * 2 + dx).min(width - 1), (y * 2 + dy).min(height - 1));
+            for (total, &channel) in sum.iter_mut().zip(&pixel.0) {
+                *total += u32::from(channel);
+            }
+        }
+
+        let mut pixel = [0; 4];
+        for (channel, total) in pixel.iter_mut().zip(&sum) {
+            *channel = ((total + 2) / 4) as u8;
+        }
+        Rgba(pixel)
+    })
+}
+
+/// Returns the basic data format descriptor for 8-bit RGBA pixels.
+fn data_format_descriptor(srgb: bool) -> Vec<u8> {
+    let mut dfd = Vec::with_capacity(DFD_LENGTH);
+    let mut word = |value: u32| dfd.extend_from_slice(&value.to_le_bytes());
+
+    word(DFD_LENGTH as u32);
+    // Khronos basic descriptor block, version 2, with four samples.
+    word(0);
+    word(2 | ((DFD_LENGTH as u32 - 4) << 16));
+    // RGBSDA color model, BT.709 primaries, straight alpha.
+    let transfer = if srgb { 2 } else { 1 };
+    word(1 | (1 << 8) | (transfer << 16));
+    // A 1x1 texel block of 4 bytes in a single plane.
+    word(0);
+    word(4);
+    word(0);
+
+    for (i, &channel) in [0u32, 1, 2, 15].iter().enumerate() {
+        // Alpha is always linear, even when the color channels are sRGB encoded.
+        let linear = if channel == 15 && srgb { 0x10 } else { 0 };
+        word((i as u32 * 8) | (7 << 16) | ((channel | linear) << 24));
+        word(0);
+        word(0);
+        word(255);
+    }
+
+    dfd
+}
+
+/// Wraps `image` in a KTX2 container holding uncompressed RGBA8 pixels.
+pub(crate) fn encode_ktx2(image: &RgbaImage, options: &Ktx2Options) -> Vec<u8> {
+    let image = &scale_image(image.clone(), options.scale);
+    let mut levels = vec![image.clone()];
+    if options.mipmaps && image.width() > 0 && image.height() > 0 {
+        let (mut width, mut height) = image.dimensions();
+        while width > 1 || height > 1 {
+            let next = downsample(&levels[levels.len() - 1]);
+            width = next.width();
+            height = next.height();
+            levels.push(next);
+        }
+    }
+
+    let dfd_offset = HEADER_LENGTH + levels.len() * LEVEL_INDEX_ENTRY_LENGTH;
+    let data_offset = dfd_offset + DFD_LENGTH;
+
+    // The smallest level is stored first. Every level is a multiple of 4 bytes long, so they all
+    // stay aligned to a texel.
+    let mut offsets = vec![0; levels.len()];
+    let mut offset = data_offset;
+    for (i, level) in levels.iter().enumerate().rev() {
+        offsets[i] = offset;
+        offset += level.len();
+    }
+
+    let mut ktx = Vec::with_capacity(offset);
+    let u32_le = |ktx: &mut Vec<u8>, value: u32| ktx.extend_from_slice(&value.to_le_bytes());
+    let u64_le = |ktx: &mut Vec<u8>, value: u64| ktx.extend_from_slice(&value.to_le_bytes());
+
+    ktx.extend_from_slice(&IDENTIFIER);
+    let format = if options.srgb {
+        VK_FORMAT_R8G8B8A8_SRGB
+    } else {
+        VK_FORMAT_R8G8B8A8_UNORM
+    };
+    for &value in &[
+        format,
+        1,
+        image.width(),
+        image.height(),
+        0,
+        0,
+        1,
+        levels.len() as u32,
+        0,
+    ] {
+        u32_le(&mut ktx, value);
+    }
+
+    // The data format descriptor, with no key/value data or supercompression global data.
+    u32_le(&mut ktx, dfd_offset as u32);
+    u32_le(&mut ktx, DFD_LENGTH as u32);
+    u32_le(&mut ktx, 0);
+    u32_le(&mut ktx, 0);
+    u64_le(&mut ktx, 0);
+    u64_le(&mut ktx, 0);
+
+    for (level, &offset) in levels.iter().zip(&offsets) {
+        u64_le(&mut ktx, offset as u64);
+        u64_le(&mut ktx, level.len() as u64);
+        u64_le(&mut ktx, level.len() as u64);
+    }
+
+    ktx.extend_from_slice(&data_format_descriptor(options.srgb));
+    for level in levels.iter().rev() {
+        ktx.extend_from_slice(level);
+    }
+
+    ktx
+}
+
+impl Canvas {
+    /// Flattens this canvas and wraps it in a [KTX2](https://www.khronos.org/ktx/) container,
+    /// ready to upload as a Vulkan or wgpu texture.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let ktx = doc.canvas().to_ktx2(&pyxel::Ktx2Options::default());
+    /// assert_eq!(b"\xabKTX 20\xbb", &ktx[..8]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_ktx2(&self, options: &Ktx2Options) -> Vec<u8> {
+        encode_ktx2(&self.flatten(), options)
+    }
+}
+
+impl Atlas {
+    /// Wraps the atlas image in a [KTX2](https://www.khronos.org/ktx/) container, ready to
+    /// upload as a Vulkan or wgpu texture.
+    pub fn to_ktx2(&self, options: &Ktx2Options) -> Vec<u8> {
+        encode_ktx2(&self.image, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(ktx: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([
+            ktx[offset],
+            ktx[offset + 1],
+            ktx[offset + 2],
+            ktx[offset + 3],
+        ])
+    }
+
+    fn u64_at(ktx: &[u8], offset: usize) -> u64 {
+        u64::from(u32_at(ktx, offset)) | u64::from(u32_at(ktx, offset + 4)) << 32
+    }
+
+    #[test]
+    fn writes_single_level_container() {
+        let image = RgbaImage::from_pixel(3, 2, Rgba([1, 2, 3, 4]));
+        let ktx = encode_ktx2(&image, &Ktx2Options::default());
+
+        assert_eq!(IDENTIFIER, ktx[..12]);
+        assert_eq!(VK_FORMAT_R8G8B8A8_SRGB, u32_at(&ktx, 12));
+        assert_eq!((3, 2), (u32_at(&ktx, 20), u32_at(&ktx, 24)));
+        assert_eq!(1, u32_at(&ktx, 40));
+
+        let dfd_offset = u32_at(&ktx, 48) as usize;
+        assert_eq!(HEADER_LENGTH + LEVEL_INDEX_ENTRY_LENGTH, dfd_offset);
+        assert_eq!(DFD_LENGTH as u32, u32_at(&ktx, dfd_offset));
+        // sRGB transfer function.
+        assert_eq!(2, ktx[dfd_offset + 14]);
+
+        let level_offset = u64_at(&ktx, HEADER_LENGTH) as usize;
+        assert_eq!(24, u64_at(&ktx, HEADER_LENGTH + 8));
+        assert_eq!(&image.into_raw()[..], &ktx[level_offset..]);
+    }
+
+    #[test]
+    fn stores_mip_chain_smallest_first() {
+        let mut image = RgbaImage::new(4, 2);
+        image.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        let options = Ktx2Options {
+            srgb: false,
+            mipmaps: true,
+            scale: 1,
+        };
+        let ktx = encode_ktx2(&image, &options);
+
+        assert_eq!(VK_FORMAT_R8G8B8A8_UNORM, u32_at(&ktx, 12));
+        assert_eq!(3, u32_at(&ktx, 40));
+
+        let level = |i: usize| {
+            let entry = HEADER_LENGTH + i * LEVEL_INDEX_ENTRY_LENGTH;
+            let offset = u64_at(&ktx, entry) as usize;
+            let length = u64_at(&ktx, entry + 8) as usize;
+            &ktx[offset..offset + length]
+        };
+        assert_eq!(32, level(0).len());
+        assert_eq!(&[64, 64, 64, 64, 0, 0, 0, 0][..], level(1));
+        assert_eq!(&[32, 32, 32, 32][..], level(2));
+
+        // The smallest level comes straight after the data format descriptor.
+        let dfd_end = u32_at(&ktx, 48) as usize + DFD_LENGTH;
+        assert_eq!(
+            dfd_end,
+            u64_at(&ktx, HEADER_LENGTH + 2 * LEVEL_INDEX_ENTRY_LENGTH) as usize
+        );
+        assert_eq!(ktx.len(), u64_at(&ktx, HEADER_LENGTH) as usize + 32);
+    }
+}
@@ -0,0 +1,179 @@
+#[cfg(feature = "images")]
+mod bmfont;
+#[cfg(feature = "images")]
+mod gamemaker;
+#[cfg(feature = "images")]
+mod godot;
+#[cfg(feature = "images")]
+mod ico;
+#[cfg(feature = "images")]
+mod indexed;
+#[cfg(feature = "images")]
+mod ktx2;
+mod palette;
+#[cfg(feature = "images")]
+mod phaser;
+#[cfg(feature = "images")]
+mod sheet;
+#[cfg(feature = "images")]
+mod strip;
+#[cfg(feature = "images")]
+mod texture_packer;
+mod timing;
+mod tmx;
+#[cfg(feature = "images")]
+mod unity;
+
+#[cfg(feature = "images")]
+pub use self::bmfont::{BmFont, BmFontOptions, GlyphSource};
+#[cfg(feature = "images")]
+pub use self::gamemaker::{GameMakerOptions, GameMakerSprite};
+#[cfg(feature = "images")]
+pub use self::godot::{GodotOptions, GodotTileset};
+#[cfg(feature = "images")]
+pub use self::ico::{IcoOptions, IconSource};
+#[cfg(feature = "images")]
+pub use self::indexed::IndexedPngOptions;
+#[cfg(feature = "images")]
+pub use self::ktx2::Ktx2Options;
+#[cfg(feature = "images")]
+pub use self::phaser::{PhaserAtlas, PhaserAtlasOptions};
+#[cfg(feature = "images")]
+pub use self::strip::{StripDirection, StripOptions};
+#[cfg(feature = "images")]
+pub use self::texture_packer::{TexturePackerLayout, TexturePackerOptions};
+#[cfg(feature = "bevy")]
+pub(crate) use self::tmx::flip_flags;
+pub use self::tmx::TmxOptions;
+#[cfg(feature = "images")]
+pub use self::unity::UnityOptions;
+
+use crate::{
+    error::PyxelError,
+    pyxel::{Color, Palette},
+};
+
+/// How empty palette slots are handled when exporting indexed formats.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MissingColorPolicy {
+    /// Leave empty slots out of the exported palette, shifting the indices of later colors down.
+    Skip,
+    /// Export empty slots as fully transparent black so palette indices match PyxelEdit.
+    #[default]
+    Transparent,
+    /// Fail the export with [`PyxelError::MissingColor`](enum.PyxelError.html#variant.MissingColor).
+    Error,
+}
+
+/// Describes how a document's palette was written by an exporter.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ExportReport {
+    /// The policy used for empty palette slots.
+    pub missing_color_policy: MissingColorPolicy,
+    /// The indices of the palette slots that were empty.
+    pub missing_colors: Vec<usize>,
+    /// The number of colors written to the exported palette.
+    pub colors_written: usize,
+}
+
+const TRANSPARENT: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 0,
+};
+
+impl Palette {
+    /// Returns the colors to export for this palette after applying `policy` to its empty slots,
+    /// along with a report of the slots that were empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let (colors, report) = doc
+    ///     .palette()
+    ///     .export_colors(pyxel::MissingColorPolicy::Skip)?;
+    /// assert_eq!(colors.len(), report.colors_written);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_colors(
+        &self,
+        policy: MissingColorPolicy,
+    ) -> Result<(Vec<Color>, ExportReport), PyxelError> {
+        let mut colors = Vec::with_capacity(self.colors.len());
+        let mut missing_colors = Vec::new();
+
+        for (i, color) in self.colors.iter().enumerate() {
+            match (color, policy) {
+                (Some(color), _) => colors.push(*color),
+                (None, MissingColorPolicy::Skip) => missing_colors.push(i),
+                (None, MissingColorPolicy::Transparent) => {
+                    missing_colors.push(i);
+                    colors.push(TRANSPARENT);
+                }
+                (None, MissingColorPolicy::Error) => return Err(PyxelError::MissingColor(i)),
+            }
+        }
+
+        let report = ExportReport {
+            missing_color_policy: policy,
+            missing_colors,
+            colors_written: colors.len(),
+        };
+        Ok((colors, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> Palette {
+        let red = Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+
+        Palette {
+            colors: vec![Some(red), None, Some(red), None],
+            height: 1,
+            num_colors: 4,
+            width: 4,
+        }
+    }
+
+    #[test]
+    fn skip_missing_colors() {
+        let (colors, report) = palette().export_colors(MissingColorPolicy::Skip).unwrap();
+
+        assert_eq!(2, colors.len());
+        assert_eq!(MissingColorPolicy::Skip, report.missing_color_policy);
+        assert_eq!(vec![1, 3], report.missing_colors);
+        assert_eq!(2, report.colors_written);
+    }
+
+    #[test]
+    fn fill_missing_colors_with_transparent() {
+        let (colors, report) = palette()
+            .export_colors(MissingColorPolicy::Transparent)
+            .unwrap();
+
+        assert_eq!(4, colors.len());
+        assert_eq!(TRANSPARENT, colors[1]);
+        assert_eq!(vec![1, 3], report.missing_colors);
+    }
+
+    #[test]
+    fn error_on_missing_colors() {
+        match palette().export_colors(MissingColorPolicy::Error) {
+            Err(PyxelError::MissingColor(1)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use super::{ExportReport, MissingColorPolicy};
+use crate::{error::PyxelError, pyxel::Palette};
+
+use std::io::Write;
+
+impl Palette {
+    /// Writes this palette as a GIMP palette (`.gpl`), applying `policy` to its empty slots.
+    ///
+    /// GIMP palettes have no alpha channel, so the alpha of each color is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let mut gpl = Vec::new();
+    /// doc.palette()
+    ///     .export_gpl(&mut gpl, pyxel::MissingColorPolicy::Skip)?;
+    /// assert!(gpl.starts_with(b"GIMP Palette\n"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_gpl<W: Write>(
+        &self,
+        mut writer: W,
+        policy: MissingColorPolicy,
+    ) -> Result<ExportReport, PyxelError> {
+        let (colors, report) = self.export_colors(policy)?;
+
+        write!(writer, "GIMP Palette\nColumns: {}\n#\n", self.width)?;
+        for (i, color) in colors.iter().enumerate() {
+            writeln!(
+                writer,
+                "{:3} {:3} {:3}\tIndex {}",
+                color.r, color.g, color.b, i
+            )?;
+        }
+
+        Ok(report)
+    }
+
+    /// Writes this palette as an Adobe Swatch Exchange file (`.ase`), applying `policy` to its
+    /// empty slots.
+    ///
+    /// Each color is written as a global RGB swatch named after its hex code. Swatches have no
+    /// alpha channel, so the alpha of each color is dropped.
+    pub fn export_ase<W: Write>(
+        &self,
+        mut writer: W,
+        policy: MissingColorPolicy,
+    ) -> Result<ExportReport, PyxelError> {
+        let (colors, report) = self.export_colors(policy)?;
+
+        writer.write_all(b"ASEF")?;
+        writer.write_all(&1u16.to_be_bytes())?;
+        writer.write_all(&0u16.to_be_bytes())?;
+        writer.write_all(&(colors.len() as u32).to_be_bytes())?;
+
+        for color in &colors {
+            let name: Vec<u16> = format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+                .encode_utf16()
+                .chain(Some(0))
+                .collect();
+
+            let mut block = Vec::new();
+            block.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            for unit in &name {
+                block.extend_from_slice(&unit.to_be_bytes());
+            }
+            block.extend_from_slice(b"RGB ");
+            for &channel in &[color.r, color.g, color.b] {
+                block.extend_from_slice(&(f32::from(channel) / 255.).to_be_bytes());
+            }
+            // The swatch is a global color.
+            block.extend_from_slice(&0u16.to_be_bytes());
+
+            writer.write_all(&1u16.to_be_bytes())?;
+            writer.write_all(&(block.len() as u32).to_be_bytes())?;
+            writer.write_all(&block)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Writes this palette as a plain list of `rrggbb` hex codes (`.hex`), one per line,
+    /// applying `policy` to its empty slots.
+    ///
+    /// The format has no alpha channel, so the alpha of each color is dropped.
+    pub fn export_hex<W: Write>(
+        &self,
+        mut writer: W,
+        policy: MissingColorPolicy,
+    ) -> Result<ExportReport, PyxelError> {
+        let (colors, report) = self.export_colors(policy)?;
+
+        for color in &colors {
+            writeln!(writer, "{:02x}{:02x}{:02x}", color.r, color.g, color.b)?;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pyxel::{Color, Palette};
+    use crate::MissingColorPolicy;
+
+    fn palette() -> Palette {
+        Palette {
+            colors: vec![
+                Some(Color {
+                    r: 255,
+                    g: 0,
+                    b: 128,
+                    a: 255,
+                }),
+                None,
+                Some(Color {
+                    r: 1,
+                    g: 2,
+                    b: 3,
+                    a: 4,
+                }),
+            ],
+            height: 1,
+            num_colors: 3,
+            width: 3,
+        }
+    }
+
+    #[test]
+    fn export_gpl() {
+        let mut out = Vec::new();
+        let report = palette()
+            .export_gpl(&mut out, MissingColorPolicy::Skip)
+            .unwrap();
+
+        assert_eq!(2, report.colors_written);
+        assert_eq!(
+            "GIMP Palette\nColumns: 3\n#\n255   0 128\tIndex 0\n  1   2   3\tIndex 1\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn export_ase() {
+        let mut out = Vec::new();
+        palette()
+            .export_ase(&mut out, MissingColorPolicy::Transparent)
+            .unwrap();
+
+        assert_eq!(b"ASEF\0\x01\0\0\0\0\0\x03", &out[..12]);
+        // Each block is the name length, eight UTF-16 units, the color model, three channels and
+        // the color type.
+        let block_len = 2 + 8 * 2 + 4 + 3 * 4 + 2;
+        assert_eq!(12 + 3 * (6 + block_len), out.len());
+        assert_eq!(
+            &[0, 1, 0, 0, 0, block_len as u8, 0, 8, 0, b'#'],
+            &out[12..22]
+        );
+        assert_eq!(b"RGB ", &out[12 + 6 + 18..12 + 6 + 22]);
+        assert_eq!(1f32.to_be_bytes(), out[12 + 6 + 22..12 + 6 + 26]);
+    }
+
+    #[test]
+    fn export_hex() {
+        let mut out = Vec::new();
+        palette()
+            .export_hex(&mut out, MissingColorPolicy::Transparent)
+            .unwrap();
+
+        assert_eq!("ff0080\n000000\n010203\n", String::from_utf8(out).unwrap());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    error::PyxelError,
+    export::sheet::{frame_json, pack_frames},
+    frames::Pivot,
+    png::encode_png,
+    pyxel::Pyxel,
+};
+
+use serde_json::{json, Value};
+
+/// Options for [`Pyxel::export_phaser_atlas`](struct.Pyxel.html#method.export_phaser_atlas).
+#[derive(Clone, Debug)]
+pub struct PhaserAtlasOptions {
+    /// The file name the atlas refers to the packed image by.
+    pub image: String,
+    /// The key the atlas is loaded under, which the animations refer to their frames by.
+    pub texture_key: String,
+    /// Whether the transparent borders of each frame are trimmed off.
+    pub trim: bool,
+    /// The point the frames are anchored at.
+    pub pivot: Pivot,
+    /// The number of transparent pixels between frames and around the edge of the image.
+    pub padding: u32,
+    /// The factor frames are scaled up by with nearest-neighbor filtering. Padding isn't scaled.
+    pub scale: u32,
+}
+
+impl Default for PhaserAtlasOptions {
+    fn default() -> Self {
+        PhaserAtlasOptions {
+            image: "atlas.png".to_owned(),
+            texture_key: "atlas".to_owned(),
+            trim: false,
+            pivot: Pivot::default(),
+            padding: 0,
+            scale: 1,
+        }
+    }
+}
+
+/// The files produced by [`Pyxel::export_phaser_atlas`](struct.Pyxel.html#method.export_phaser_atlas).
+#[derive(Clone, Debug)]
+pub struct PhaserAtlas {
+    /// The packed frames, encoded as PNG.
+    pub image: Vec<u8>,
+    /// The multiatlas JSON describing where each frame is in the image.
+    pub atlas: String,
+    /// The animations JSON, for loading with `this.anims.fromJSON`.
+    pub animations: String,
+}
+
+impl Pyxel {
+    /// Packs the frames of this document's animations into a single image and describes them with
+    /// a [Phaser 3](https://phaser.io) multiatlas JSON, along with the animations JSON giving
+    /// each frame's duration.
+    ///
+    /// Frames are named after their animation and their index within it, e.g. `Walk/0`.
+    /// Per-frame durations are exact from Phaser 3.60 onwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let atlas = doc.export_phaser_atlas(&pyxel::PhaserAtlasOptions::default())?;
+    /// assert!(atlas.atlas.contains("\"textures\""));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_phaser_atlas(
+        &self,
+        options: &PhaserAtlasOptions,
+    ) -> Result<PhaserAtlas, PyxelError> {
+        let (sheet, frames) = pack_frames(
+            self,
+            options.trim,
+            options.pivot,
+            options.padding,
+            options.scale,
+        )?;
+
+        let atlas = json!({
+            "textures": [{
+                "image": options.image,
+                "format": "RGBA8888",
+                "size": { "w": sheet.width(), "h": sheet.height() },
+                "scale": 1,
+                "frames": frames
+                    .iter()
+                    .map(|frame| {
+                        let mut json = frame_json(frame);
+                        json["filename"] = json!(frame.name);
+                        json
+                    })
+                    .collect::<Vec<_>>(),
+            }],
+            "meta": {
+                "app": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        });
+
+        let anims: Vec<Value> = self
+            .animations
+            .iter()
+            .enumerate()
+            .map(|(i, animation)| {
+                let frames: Vec<Value> = frames
+                    .iter()
+                    .filter(|frame| frame.animation == i)
+                    .map(|frame| {
+                        json!({
+                            "key": options.texture_key,
+                            "frame": frame.name,
+                            "duration": frame.duration.as_millis() as u64,
+                        })
+                    })
+                    .collect();
+
+                json!({
+                    "key": animation.name,
+                    "type": "frame",
+                    "frames": frames,
+                    "repeat": -1,
+                })
+            })
+            .collect();
+
+        Ok(PhaserAtlas {
+            image: encode_png(&sheet)?,
+            atlas: atlas.to_string(),
+            animations: json!({ "anims": anims }).to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn atlas_describes_every_frame() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = PhaserAtlasOptions {
+            padding: 1,
+            ..PhaserAtlasOptions::default()
+        };
+        let exported = doc.export_phaser_atlas(&options).unwrap();
+
+        let sheet = image::load_from_memory(&exported.image).unwrap().to_rgba();
+        assert_eq!((1 + 4 * 33, 1 + 3 * 17), sheet.dimensions());
+
+        let atlas: Value = serde_json::from_str(&exported.atlas).unwrap();
+        let texture = &atlas["textures"][0];
+        assert_eq!("atlas.png", texture["image"]);
+        let frames = texture["frames"].as_array().unwrap();
+        assert_eq!(8, frames.len());
+
+        let frame = &frames[5];
+        assert_eq!(
+            format!("{}/1", doc.animations[1].name),
+            frame["filename"].as_str().unwrap()
+        );
+        assert_eq!(
+            json!({ "x": 34, "y": 18, "w": 32, "h": 16 }),
+            frame["frame"]
+        );
+        assert_eq!(json!({ "w": 32, "h": 16 }), frame["sourceSize"]);
+        assert_eq!(false, frame["trimmed"]);
+
+        let animations: Value = serde_json::from_str(&exported.animations).unwrap();
+        let durations: Vec<u64> = animations["anims"][0]["frames"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|frame| frame["duration"].as_u64().unwrap())
+            .collect();
+        assert_eq!(vec![150, 300, 450, 600], durations);
+        assert_eq!("atlas", animations["anims"][2]["frames"][0]["key"]);
+    }
+}
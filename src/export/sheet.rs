@@ -0,0 +1,118 @@
+use crate::{
+    bounds::Rect,
+    error::PyxelError,
+    frames::{FrameOptions, Pivot},
+    pyxel::Pyxel,
+};
+
+use image::{imageops, ImageBuffer, RgbaImage};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// A frame packed into a sprite sheet by [`pack_frames`](fn.pack_frames.html).
+#[derive(Clone, Debug)]
+pub(crate) struct SheetFrame {
+    /// The name the frame is exported under.
+    pub name: String,
+    /// The index of the animation in the document.
+    pub animation: usize,
+    /// How long the frame is displayed for.
+    pub duration: Duration,
+    /// The area of the sheet holding the frame's pixels.
+    pub frame: Rect,
+    /// The area of the untrimmed frame the pixels were taken from.
+    pub trimmed: Rect,
+    /// The width and height of the untrimmed frame.
+    pub source_size: (u32, u32),
+    /// The pivot as fractions of the untrimmed frame's width and height.
+    pub pivot: (f32, f32),
+}
+
+/// Returns the name a frame is exported under.
+pub(crate) fn frame_name(animation_name: &str, frame: usize) -> String {
+    format!("{}/{}", animation_name, frame)
+}
+
+/// Renders the frames of every animation of `doc`, scaled up `scale` times, and packs them into a
+/// single sheet, a row of frames per animation, with `padding` transparent pixels between and
+/// around them.
+pub(crate) fn pack_frames(
+    doc: &Pyxel,
+    trim: bool,
+    pivot: Pivot,
+    padding: u32,
+    scale: u32,
+) -> Result<(RgbaImage, Vec<SheetFrame>), PyxelError> {
+    let options = FrameOptions {
+        trim,
+        pivot,
+        scale,
+        ..FrameOptions::default()
+    };
+
+    let scale = scale.max(1);
+    let (tile_width, tile_height) = (
+        u32::from(doc.canvas.tile_width) * scale,
+        u32::from(doc.canvas.tile_height) * scale,
+    );
+    let columns = doc.animations.iter().map(|a| a.length).max().unwrap_or(0) as u32;
+    let rows = doc.animations.len() as u32;
+    let mut sheet = RgbaImage::new(
+        padding + columns * (tile_width + padding),
+        padding + rows * (tile_height + padding),
+    );
+    let mut frames = Vec::new();
+
+    doc.for_each_frame_pixels(&options, |meta, pixels| {
+        let x = padding + meta.frame as u32 * (tile_width + padding);
+        let y = padding + meta.animation as u32 * (tile_height + padding);
+
+        let image: RgbaImage = ImageBuffer::from_raw(meta.width, meta.height, pixels.to_vec())
+            .expect("frame pixels match their dimensions");
+        imageops::replace(&mut sheet, &image, x, y);
+
+        frames.push(SheetFrame {
+            name: frame_name(meta.animation_name, meta.frame),
+            animation: meta.animation,
+            duration: meta.duration,
+            frame: Rect {
+                x,
+                y,
+                width: meta.width,
+                height: meta.height,
+            },
+            trimmed: meta.trimmed,
+            source_size: (meta.source_width, meta.source_height),
+            pivot: (
+                meta.pivot.0 / meta.source_width.max(1) as f32,
+                meta.pivot.1 / meta.source_height.max(1) as f32,
+            ),
+        });
+    })?;
+
+    Ok((sheet, frames))
+}
+
+/// Describes `frame` in the layout shared by TexturePacker and the engines reading its JSON.
+pub(crate) fn frame_json(frame: &SheetFrame) -> Value {
+    let (source_width, source_height) = frame.source_size;
+    let trimmed = frame.trimmed;
+    json!({
+        "rotated": false,
+        "trimmed": (trimmed.width, trimmed.height) != frame.source_size,
+        "sourceSize": { "w": source_width, "h": source_height },
+        "spriteSourceSize": {
+            "x": trimmed.x,
+            "y": trimmed.y,
+            "w": trimmed.width,
+            "h": trimmed.height,
+        },
+        "frame": {
+            "x": frame.frame.x,
+            "y": frame.frame.y,
+            "w": frame.frame.width,
+            "h": frame.frame.height,
+        },
+        "pivot": { "x": frame.pivot.0, "y": frame.pivot.1 },
+    })
+}
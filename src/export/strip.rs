@@ -0,0 +1,155 @@
+use crate::{
+    error::PyxelError,
+    png::encode_png,
+    pyxel::{Animation, Canvas},
+    render::scale_image,
+};
+
+use image::{imageops, RgbaImage};
+use std::io::Write;
+
+/// The direction the frames of a strip run in.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum StripDirection {
+    /// A single row of frames, from left to right.
+    #[default]
+    Horizontal,
+    /// A single column of frames, from top to bottom.
+    Vertical,
+}
+
+/// Options for [`Animation::export_strip`](struct.Animation.html#method.export_strip).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StripOptions {
+    /// The direction the frames run in.
+    pub direction: StripDirection,
+    /// The number of transparent pixels between frames. There's no spacing around the edge of
+    /// the strip.
+    pub spacing: u32,
+    /// The factor frames are scaled up by with nearest-neighbor filtering. Spacing isn't scaled.
+    pub scale: u32,
+}
+
+impl Default for StripOptions {
+    fn default() -> Self {
+        StripOptions {
+            direction: StripDirection::Horizontal,
+            spacing: 0,
+            scale: 1,
+        }
+    }
+}
+
+impl Animation {
+    /// Renders the frames of this animation from `canvas` into a single strip, in frame order.
+    ///
+    /// Every frame of the animation must lie within the canvas.
+    pub fn render_strip(
+        &self,
+        canvas: &Canvas,
+        options: &StripOptions,
+    ) -> Result<RgbaImage, PyxelError> {
+        let scale = options.scale.max(1);
+        let (width, height) = (
+            u32::from(canvas.tile_width) * scale,
+            u32::from(canvas.tile_height) * scale,
+        );
+        let frames = self.length as u32;
+        let length =
+            |size: u32| (frames * (size + options.spacing)).saturating_sub(options.spacing);
+
+        let (mut strip, step) = match options.direction {
+            StripDirection::Horizontal => (
+                RgbaImage::new(length(width), height),
+                (width + options.spacing, 0),
+            ),
+            StripDirection::Vertical => (
+                RgbaImage::new(width, length(height)),
+                (0, height + options.spacing),
+            ),
+        };
+
+        for frame in 0..self.length {
+            let image = canvas.render_frame(self, frame).ok_or_else(|| {
+                let tile = self.base_tile + frame;
+                let tiles_wide = canvas.tiles_wide().max(1);
+                PyxelError::CellOutOfBounds {
+                    col: tile % tiles_wide,
+                    row: tile / tiles_wide,
+                }
+            })?;
+
+            let frame = frame as u32;
+            imageops::replace(
+                &mut strip,
+                &scale_image(image, scale),
+                frame * step.0,
+                frame * step.1,
+            );
+        }
+
+        Ok(strip)
+    }
+
+    /// Writes the frames of this animation from `canvas` to `w` as a PNG strip, see
+    /// [`render_strip`](#method.render_strip).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let options = pyxel::StripOptions::default();
+    /// let mut png = Vec::new();
+    /// doc.animations()[0].export_strip(doc.canvas(), &options, &mut png)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_strip<W: Write>(
+        &self,
+        canvas: &Canvas,
+        options: &StripOptions,
+        mut w: W,
+    ) -> Result<(), PyxelError> {
+        w.write_all(&encode_png(&self.render_strip(canvas, options)?)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    #[test]
+    fn strips_lay_out_frames_in_order() {
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let (canvas, animation) = (doc.canvas(), &doc.animations()[0]);
+
+        let options = StripOptions {
+            spacing: 2,
+            ..StripOptions::default()
+        };
+        let strip = animation.render_strip(canvas, &options).unwrap();
+        assert_eq!((4 * 32 + 3 * 2, 16), strip.dimensions());
+        let frame = canvas.render_frame(animation, 2).unwrap();
+        let copied = image::GenericImageView::view(&strip, 68, 0, 32, 16).to_image();
+        assert_eq!(frame.into_raw(), copied.into_raw());
+
+        let options = StripOptions {
+            direction: StripDirection::Vertical,
+            scale: 2,
+            ..options
+        };
+        let strip = animation.render_strip(canvas, &options).unwrap();
+        assert_eq!((64, 4 * 32 + 3 * 2), strip.dimensions());
+
+        let mut outside = animation.clone();
+        outside.base_tile = 62;
+        assert!(matches!(
+            outside.render_strip(canvas, &options),
+            Err(PyxelError::CellOutOfBounds { col: 0, row: 8 })
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    error::PyxelError,
+    export::sheet::{frame_json, pack_frames},
+    frames::Pivot,
+    png::encode_png,
+    pyxel::Pyxel,
+};
+
+use serde_json::{json, Map, Value};
+
+/// How the frames are listed in TexturePacker JSON.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TexturePackerLayout {
+    /// An object keyed by frame name, TexturePacker's "JSON (Hash)" format.
+    #[default]
+    Hash,
+    /// An array of frames in animation order, each with a `filename`, TexturePacker's
+    /// "JSON (Array)" format.
+    Array,
+}
+
+/// Options for
+/// [`Pyxel::export_texture_packer`](struct.Pyxel.html#method.export_texture_packer).
+#[derive(Clone, Debug)]
+pub struct TexturePackerOptions {
+    /// The file name the JSON refers to the packed image by.
+    pub image: String,
+    /// How the frames are listed.
+    pub layout: TexturePackerLayout,
+    /// Whether the transparent borders of each frame are trimmed off.
+    pub trim: bool,
+    /// The point the frames are anchored at.
+    pub pivot: Pivot,
+    /// The number of transparent pixels between frames and around the edge of the image.
+    pub padding: u32,
+    /// The factor frames are scaled up by with nearest-neighbor filtering. Padding isn't scaled.
+    pub scale: u32,
+}
+
+impl Default for TexturePackerOptions {
+    fn default() -> Self {
+        TexturePackerOptions {
+            image: "sheet.png".to_owned(),
+            layout: TexturePackerLayout::default(),
+            trim: false,
+            pivot: Pivot::default(),
+            padding: 0,
+            scale: 1,
+        }
+    }
+}
+
+impl Pyxel {
+    /// Packs the frames of this document's animations into a single image, returned as PNG, and
+    /// describes them with [TexturePacker](https://www.codeandweb.com/texturepacker) JSON.
+    ///
+    /// Frames are named after their animation and their index within it, e.g. `Walk/0`, and
+    /// carry their duration in milliseconds as Aseprite's JSON does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let (png, json) = doc.export_texture_packer(&pyxel::TexturePackerOptions::default())?;
+    /// assert!(json.contains("\"frames\""));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_texture_packer(
+        &self,
+        options: &TexturePackerOptions,
+    ) -> Result<(Vec<u8>, String), PyxelError> {
+        let (sheet, frames) = pack_frames(
+            self,
+            options.trim,
+            options.pivot,
+            options.padding,
+            options.scale,
+        )?;
+
+        let described = frames.iter().map(|frame| {
+            let mut json = frame_json(frame);
+            json["duration"] = json!(frame.duration.as_millis() as u64);
+            (frame, json)
+        });
+        let frames = match options.layout {
+            TexturePackerLayout::Hash => Value::Object(
+                described
+                    .map(|(frame, json)| (frame.name.clone(), json))
+                    .collect::<Map<_, _>>(),
+            ),
+            TexturePackerLayout::Array => Value::Array(
+                described
+                    .map(|(frame, mut json)| {
+                        json["filename"] = json!(frame.name);
+                        json
+                    })
+                    .collect(),
+            ),
+        };
+
+        let json = json!({
+            "frames": frames,
+            "meta": {
+                "app": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "image": options.image,
+                "format": "RGBA8888",
+                "size": { "w": sheet.width(), "h": sheet.height() },
+                "scale": options.scale.max(1).to_string(),
+            },
+        });
+
+        Ok((encode_png(&sheet)?, json.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn hash_is_keyed_by_frame_name() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let (_, json) = doc
+            .export_texture_packer(&TexturePackerOptions::default())
+            .unwrap();
+
+        let json: Value = serde_json::from_str(&json).unwrap();
+        let frames = json["frames"].as_object().unwrap();
+        assert_eq!(8, frames.len());
+
+        let frame = &frames[&format!("{}/3", doc.animations[0].name)];
+        assert_eq!(json!({ "x": 96, "y": 0, "w": 32, "h": 16 }), frame["frame"]);
+        assert_eq!(600, frame["duration"]);
+        assert_eq!(json!({ "x": 0.5, "y": 0.5 }), frame["pivot"]);
+        assert_eq!("sheet.png", json["meta"]["image"]);
+    }
+
+    #[test]
+    fn array_lists_trimmed_frames_in_order() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = TexturePackerOptions {
+            layout: TexturePackerLayout::Array,
+            trim: true,
+            pivot: Pivot::BottomCenter,
+            ..TexturePackerOptions::default()
+        };
+        let (png, json) = doc.export_texture_packer(&options).unwrap();
+
+        let json: Value = serde_json::from_str(&json).unwrap();
+        let frames = json["frames"].as_array().unwrap();
+        let names: Vec<&str> = frames
+            .iter()
+            .map(|frame| frame["filename"].as_str().unwrap())
+            .collect();
+        assert_eq!(format!("{}/0", doc.animations[2].name), names[6]);
+
+        let sheet = image::load_from_memory(&png).unwrap().to_rgba();
+        for frame in frames {
+            let (w, h) = (frame["frame"]["w"].as_u64(), frame["frame"]["h"].as_u64());
+            assert_eq!(w, frame["spriteSourceSize"]["w"].as_u64());
+            assert_eq!(h, frame["spriteSourceSize"]["h"].as_u64());
+            assert!(frame["frame"]["x"].as_u64().unwrap() + w.unwrap() <= u64::from(sheet.width()));
+        }
+    }
+}
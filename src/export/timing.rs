@@ -0,0 +1,107 @@
+use crate::pyxel::{Animation, Pyxel};
+
+use serde_json::{json, Value};
+
+/// The version of the timing JSON schema, bumped whenever it changes incompatibly.
+const TIMING_VERSION: u32 = 1;
+
+fn timing_value(animation: &Animation) -> Value {
+    let durations = animation.frame_durations();
+    let frames: Vec<Value> = durations
+        .iter()
+        .enumerate()
+        .map(|(frame, duration)| {
+            json!({
+                "frame": frame,
+                "tile": animation.base_tile + frame,
+                "duration": duration.as_millis() as u64,
+            })
+        })
+        .collect();
+
+    json!({
+        "name": animation.name,
+        "frames": frames,
+        "duration": durations.iter().map(|d| d.as_millis() as u64).sum::<u64>(),
+        "loop": true,
+    })
+}
+
+impl Animation {
+    /// Describes the timing of this animation as JSON, in the schema documented on
+    /// [`Pyxel::export_animation_timing`](struct.Pyxel.html#method.export_animation_timing).
+    /// The result is a single animation object.
+    pub fn to_timing_json(&self) -> String {
+        timing_value(self).to_string()
+    }
+}
+
+impl Pyxel {
+    /// Describes the timing of this document's animations as JSON, for engines that don't read
+    /// Pyxel documents themselves.
+    ///
+    /// The JSON is an object with a `version` of `1` and an `animations` array holding an object
+    /// for each animation, in document order, with:
+    ///
+    /// - `name`: the name of the animation.
+    /// - `frames`: an array of the animation's frames in order, each with its index within the
+    ///   animation (`frame`), the linear index of the canvas cell it shows (`tile`, see
+    ///   [`Canvas::cell_index`](struct.Canvas.html#method.cell_index)) and its `duration` in
+    ///   milliseconds, after applying the frame's duration multiplier.
+    /// - `duration`: the length of one pass through the frames in milliseconds.
+    /// - `loop`: whether the animation repeats, which is always `true` as PyxelEdit plays every
+    ///   animation in a loop.
+    ///
+    /// Frames are numbered as they are by the renderers, e.g. `Canvas::render_frame` and
+    /// `Pyxel::for_each_frame_pixels` with the `images` feature, so the two can be used together.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let timing: serde_json::Value = serde_json::from_str(&doc.export_animation_timing())?;
+    /// assert_eq!(300, timing["animations"][0]["frames"][1]["duration"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_animation_timing(&self) -> String {
+        let animations: Vec<Value> = self.animations.iter().map(timing_value).collect();
+        json!({ "version": TIMING_VERSION, "animations": animations }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn describes_frames_and_durations() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let timing: Value = serde_json::from_str(&doc.export_animation_timing()).unwrap();
+
+        assert_eq!(1, timing["version"]);
+        let animations = timing["animations"].as_array().unwrap();
+        assert_eq!(3, animations.len());
+
+        let first = &animations[0];
+        assert_eq!("Animation 1", first["name"]);
+        assert_eq!(1500, first["duration"]);
+        assert_eq!(true, first["loop"]);
+        assert_eq!(
+            json!({ "frame": 3, "tile": 3, "duration": 600 }),
+            first["frames"][3]
+        );
+
+        let second: Value = serde_json::from_str(&doc.animations[1].to_timing_json()).unwrap();
+        assert_eq!(&animations[1], &second);
+        assert_eq!(
+            json!([4, 5]),
+            json!([second["frames"][0]["tile"], second["frames"][1]["tile"]])
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{
+    pyxel::{Pyxel, TileRef},
+    text::write_str,
+};
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+
+/// Options for [`Pyxel::to_tmx`](struct.Pyxel.html#method.to_tmx).
+#[derive(Clone, Debug)]
+pub struct TmxOptions {
+    /// The path each tile image is referenced by, relative to the map, with `{}` replaced by the
+    /// index of the tile.
+    pub tile_images: String,
+    /// The factor the tile and image sizes in the map are scaled up by, for tile images exported
+    /// at that scale.
+    pub scale: u32,
+}
+
+impl Default for TmxOptions {
+    fn default() -> Self {
+        TmxOptions {
+            tile_images: "tile{}.png".to_owned(),
+            scale: 1,
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns whether `tile_ref` is drawn flipped horizontally, flipped vertically and flipped
+/// diagonally, the diagonal flip being applied first. Rotations are clockwise and applied after
+/// the horizontal flip.
+pub(crate) fn flip_flags(tile_ref: &TileRef) -> (bool, bool, bool) {
+    let quarter_turns = (tile_ref.rot / 90.).round().rem_euclid(4.) as u8;
+    match (quarter_turns, tile_ref.flip_x) {
+        (0, false) => (false, false, false),
+        (1, false) => (true, false, true),
+        (2, false) => (true, true, false),
+        (3, false) => (false, true, true),
+        (0, true) => (true, false, false),
+        (1, true) => (true, true, true),
+        (2, true) => (false, true, false),
+        _ => (false, false, true),
+    }
+}
+
+/// Returns the global tile ID for `tile_ref`, with Tiled's flip flags standing in for the
+/// rotation and flip.
+fn gid(tile_ref: &TileRef) -> u32 {
+    let (horizontal, vertical, diagonal) = flip_flags(tile_ref);
+    let mut flags = 0;
+    if horizontal {
+        flags |= FLIPPED_HORIZONTALLY;
+    }
+    if vertical {
+        flags |= FLIPPED_VERTICALLY;
+    }
+    if diagonal {
+        flags |= FLIPPED_DIAGONALLY;
+    }
+
+    (tile_ref.index as u32 + 1) | flags
+}
+
+impl Pyxel {
+    /// Converts this document to a [Tiled](https://www.mapeditor.org) TMX map.
+    ///
+    /// Each layer becomes a tile layer, ordered bottom-most first as Tiled expects, and the
+    /// tileset becomes an image collection tileset referring to the tile images by path. Blend
+    /// modes can't be represented and are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let tmx = doc.to_tmx(&pyxel::TmxOptions::default());
+    /// assert!(tmx.starts_with("<?xml"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_tmx(&self, options: &TmxOptions) -> String {
+        let canvas = &self.canvas;
+        let tileset = &self.tileset;
+        let (columns, rows) = (canvas.tiles_wide(), canvas.tiles_high());
+        let scale = options.scale.max(1);
+
+        let mut tmx = String::new();
+
+        write_str!(tmx, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        write_str!(
+            tmx,
+            "<map version=\"1.2\" orientation=\"orthogonal\" renderorder=\"right-down\" width=\"{}\" height=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" infinite=\"0\" nextlayerid=\"{}\" nextobjectid=\"1\">\n",
+            columns,
+            rows,
+            u32::from(canvas.tile_width) * scale,
+            u32::from(canvas.tile_height) * scale,
+            canvas.layers.len() + 1
+        );
+
+        write_str!(
+            tmx,
+            " <tileset firstgid=\"1\" name=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" tilecount=\"{}\" columns=\"0\">\n",
+            escape(&self.name),
+            u32::from(tileset.tile_width) * scale,
+            u32::from(tileset.tile_height) * scale,
+            tileset.num_tiles
+        );
+        write_str!(
+            tmx,
+            "  <grid orientation=\"orthogonal\" width=\"1\" height=\"1\"/>\n"
+        );
+        for i in 0..tileset.num_tiles {
+            let (width, height) = tileset.tile_image_size(i).unwrap_or((
+                u32::from(tileset.tile_width),
+                u32::from(tileset.tile_height),
+            ));
+            write_str!(
+                tmx,
+                "  <tile id=\"{}\">\n   <image width=\"{}\" height=\"{}\" source=\"{}\"/>\n  </tile>\n",
+                i,
+                width * scale,
+                height * scale,
+                escape(&options.tile_images.replace("{}", &i.to_string()))
+            );
+        }
+        write_str!(tmx, " </tileset>\n");
+
+        // Layers are stored top-most first, but Tiled draws the first layer at the bottom.
+        let visibility = canvas.effective_visibility();
+        for (id, (layer, visible)) in canvas.layers.iter().zip(visibility).rev().enumerate() {
+            write_str!(
+                tmx,
+                " <layer id=\"{}\" name=\"{}\" width=\"{}\" height=\"{}\"",
+                id + 1,
+                escape(&layer.name),
+                columns,
+                rows
+            );
+            if !visible {
+                write_str!(tmx, " visible=\"0\"");
+            }
+            if layer.alpha != 255 {
+                write_str!(tmx, " opacity=\"{:.3}\"", f32::from(layer.alpha) / 255.);
+            }
+            write_str!(tmx, ">\n  <data encoding=\"csv\">\n");
+
+            for row in 0..rows {
+                let cells = (0..columns)
+                    .map(|col| {
+                        layer
+                            .tile_refs
+                            .get(&(row * columns + col))
+                            .map_or(0, gid)
+                            .to_string()
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let separator = if row + 1 < rows { "," } else { "" };
+                write_str!(tmx, "{}{}\n", cells, separator);
+            }
+
+            write_str!(tmx, "  </data>\n </layer>\n");
+        }
+
+        write_str!(tmx, "</map>\n");
+        tmx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn gids_encode_rotation_and_flip() {
+        assert_eq!(1, gid(&TileRef::new(0, 0., false)));
+        assert_eq!(
+            3 | FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY,
+            gid(&TileRef::new(2, 90., false))
+        );
+        assert_eq!(
+            1 | FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY,
+            gid(&TileRef::new(0, 180., false))
+        );
+        assert_eq!(1 | FLIPPED_HORIZONTALLY, gid(&TileRef::new(0, 0., true)));
+        assert_eq!(1 | FLIPPED_DIAGONALLY, gid(&TileRef::new(0, 270., true)));
+    }
+
+    #[test]
+    fn converts_layers_and_tileset() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = TmxOptions {
+            tile_images: "tiles/{}.png".to_owned(),
+            ..TmxOptions::default()
+        };
+        let tmx = doc.to_tmx(&options);
+
+        assert!(tmx.contains("width=\"8\" height=\"8\" tilewidth=\"32\" tileheight=\"16\""));
+        assert!(tmx.contains("tilecount=\"4\""));
+        assert!(tmx.contains("source=\"tiles/3.png\""));
+        assert_eq!(11, tmx.matches("<layer ").count());
+
+        // The bottom-most layer comes first.
+        let first = tmx.find("name=\"Layer 0\"").unwrap();
+        let last = tmx.find("name=\"Layer 10\"").unwrap();
+        assert!(first < last);
+        assert!(tmx.contains("name=\"Layer 7\" width=\"8\" height=\"8\" visible=\"0\""));
+    }
+
+    #[test]
+    fn tile_sizes_are_scaled() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = TmxOptions {
+            scale: 2,
+            ..TmxOptions::default()
+        };
+        let tmx = doc.to_tmx(&options);
+
+        assert!(tmx.contains("width=\"8\" height=\"8\" tilewidth=\"64\" tileheight=\"32\""));
+        assert!(tmx.contains("tilewidth=\"64\" tileheight=\"32\" tilecount=\"4\""));
+        assert!(tmx.contains("<image width=\"64\" height=\"32\" source=\"tile0.png\"/>"));
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    error::PyxelError,
+    export::sheet::{pack_frames, SheetFrame},
+    frames::Pivot,
+    png::encode_png,
+    pyxel::Pyxel,
+};
+
+use serde_json::{json, Value};
+
+/// Options for [`Pyxel::export_unity_sheet`](struct.Pyxel.html#method.export_unity_sheet).
+#[derive(Clone, Debug)]
+pub struct UnityOptions {
+    /// The file name the metadata refers to the packed image by.
+    pub image: String,
+    /// Whether the transparent borders of each frame are trimmed off.
+    pub trim: bool,
+    /// The point the sprites are anchored at.
+    pub pivot: Pivot,
+    /// The number of transparent pixels between frames and around the edge of the image.
+    pub padding: u32,
+    /// The factor frames are scaled up by with nearest-neighbor filtering. Padding isn't scaled.
+    pub scale: u32,
+    /// The number of pixels per world unit the sprites should be imported with.
+    pub pixels_per_unit: f32,
+}
+
+impl Default for UnityOptions {
+    fn default() -> Self {
+        UnityOptions {
+            image: "sheet.png".to_owned(),
+            trim: false,
+            pivot: Pivot::default(),
+            padding: 0,
+            scale: 1,
+            pixels_per_unit: 100.,
+        }
+    }
+}
+
+/// Returns the pivot of `frame` as fractions of its sprite rect, measured from the bottom left as
+/// Unity does.
+fn sprite_pivot(frame: &SheetFrame) -> (f32, f32) {
+    let r = frame.trimmed;
+    if r.width == 0 || r.height == 0 {
+        return (0.5, 0.5);
+    }
+
+    let (x, y) = (
+        frame.pivot.0 * frame.source_size.0 as f32,
+        frame.pivot.1 * frame.source_size.1 as f32,
+    );
+    (
+        (x - r.x as f32) / r.width as f32,
+        1. - (y - r.y as f32) / r.height as f32,
+    )
+}
+
+impl Pyxel {
+    /// Packs the frames of this document's animations into a single image, returned as PNG, and
+    /// describes them with JSON a Unity editor script can turn into sliced sprites and
+    /// animation clips.
+    ///
+    /// The JSON holds the image's `texture`, `width`, `height` and `pixelsPerUnit`, a `sprites`
+    /// array giving each sprite's `name`, `rect` and normalized `pivot`, and a `clips` array
+    /// giving each animation's `name` and `frames`, each frame being the index of its sprite and
+    /// its duration in seconds. Rects and pivots are measured from the bottom left, as Unity's
+    /// `SpriteMetaData` expects.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let (png, json) = doc.export_unity_sheet(&pyxel::UnityOptions::default())?;
+    /// assert!(json.contains("\"clips\""));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_unity_sheet(
+        &self,
+        options: &UnityOptions,
+    ) -> Result<(Vec<u8>, String), PyxelError> {
+        let (sheet, frames) = pack_frames(
+            self,
+            options.trim,
+            options.pivot,
+            options.padding,
+            options.scale,
+        )?;
+
+        let sprites: Vec<Value> = frames
+            .iter()
+            .map(|frame| {
+                let r = frame.frame;
+                let (pivot_x, pivot_y) = sprite_pivot(frame);
+                json!({
+                    "name": frame.name,
+                    "rect": {
+                        "x": r.x,
+                        "y": sheet.height() - r.y - r.height,
+                        "width": r.width,
+                        "height": r.height,
+                    },
+                    "pivot": { "x": pivot_x, "y": pivot_y },
+                })
+            })
+            .collect();
+
+        let clips: Vec<Value> = self
+            .animations
+            .iter()
+            .enumerate()
+            .map(|(i, animation)| {
+                let clip_frames: Vec<Value> = frames
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, frame)| frame.animation == i)
+                    .map(|(sprite, frame)| {
+                        json!({
+                            "sprite": sprite,
+                            "duration": frame.duration.as_secs_f64(),
+                        })
+                    })
+                    .collect();
+
+                json!({
+                    "name": animation.name,
+                    "frames": clip_frames,
+                })
+            })
+            .collect();
+
+        let json = json!({
+            "texture": options.image,
+            "width": sheet.width(),
+            "height": sheet.height(),
+            "pixelsPerUnit": options.pixels_per_unit,
+            "sprites": sprites,
+            "clips": clips,
+        });
+
+        Ok((encode_png(&sheet)?, json.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bounds::Rect, load::load};
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn describes_sprites_and_clips() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = UnityOptions {
+            pivot: Pivot::BottomCenter,
+            ..UnityOptions::default()
+        };
+        let (_, json) = doc.export_unity_sheet(&options).unwrap();
+        let json: Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            (128, 48),
+            (
+                json["width"].as_u64().unwrap(),
+                json["height"].as_u64().unwrap()
+            )
+        );
+        let sprites = json["sprites"].as_array().unwrap();
+        assert_eq!(8, sprites.len());
+
+        // The first animation is the top row of the sheet, which Unity counts from the bottom.
+        assert_eq!(
+            json!({ "x": 32, "y": 32, "width": 32, "height": 16 }),
+            sprites[1]["rect"]
+        );
+        assert_eq!(json!({ "x": 0.5, "y": 0.0 }), sprites[1]["pivot"]);
+
+        let clip = &json["clips"][1];
+        assert_eq!(doc.animations[1].name, clip["name"].as_str().unwrap());
+        assert_eq!(
+            json!([{ "sprite": 4, "duration": 0.1 }, { "sprite": 5, "duration": 0.1 }]),
+            clip["frames"]
+        );
+    }
+
+    #[test]
+    fn trimmed_pivots_stay_in_place() {
+        let frame = SheetFrame {
+            name: "a/0".to_owned(),
+            animation: 0,
+            duration: Default::default(),
+            frame: Rect {
+                x: 0,
+                y: 0,
+                width: 16,
+                height: 8,
+            },
+            trimmed: Rect {
+                x: 8,
+                y: 4,
+                width: 16,
+                height: 8,
+            },
+            source_size: (32, 16),
+            pivot: (0.5, 1.),
+        };
+        assert_eq!((0.5, -0.5), sprite_pivot(&frame));
+    }
+}
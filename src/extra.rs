@@ -0,0 +1,215 @@
+use crate::pyxel::Pyxel;
+#[cfg(feature = "archive")]
+use crate::{
+    doc_data::DOC_DATA,
+    error::PyxelError,
+    load::{find_entry, layer_entry, tile_entry, Budget},
+    metadata::USER_METADATA,
+    source::ArchiveSource,
+};
+
+#[cfg(feature = "archive")]
+use std::{borrow::Cow, collections::HashSet};
+
+/// An entry of a Pyxel document's archive that isn't part of the document itself, such as data
+/// stored by another version of PyxelEdit or by other tools.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtraEntry {
+    pub(crate) name: String,
+    pub(crate) data: Vec<u8>,
+}
+
+impl ExtraEntry {
+    /// Returns the name of this entry within the archive.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the decompressed contents of this entry. Slices implement `Read`, so this can be
+    /// passed to anything expecting a reader.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Reads every file in `source` other than the document data, the layer and tile images of
+/// `doc` and its user metadata, in the order they're stored.
+#[cfg(feature = "archive")]
+pub(crate) fn read_extra_entries<S: ArchiveSource>(
+    source: &mut S,
+    budget: &Budget,
+    doc: &Pyxel,
+) -> Result<Vec<ExtraEntry>, PyxelError> {
+    // Match the entries that were actually read, which may not have exactly these names.
+    let known: HashSet<String> = (0..doc.canvas.layers.len())
+        .map(layer_entry)
+        .chain((0..doc.tileset.num_tiles).map(tile_entry))
+        .chain(vec![DOC_DATA.to_owned(), USER_METADATA.to_owned()])
+        .filter_map(|name| find_entry(source, &name).map(Cow::into_owned))
+        .collect();
+
+    let names: Vec<String> = source
+        .entries()
+        .into_iter()
+        .filter(|name| !name.ends_with('/') && !known.contains(name))
+        .collect();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let data = budget
+                .read_entry(source, &name)
+                .map_err(|e| e.in_entry(&name))?;
+            Ok(ExtraEntry { name, data })
+        })
+        .collect()
+}
+
+impl Pyxel {
+    /// Returns the entries of this document's archive that aren't part of the document, in the
+    /// order they're stored. The document data, the layer and tile images and the
+    /// [user metadata](#method.user_metadata) aren't included.
+    ///
+    /// This is empty unless the document was loaded with
+    /// [`LoadOptions::keep_extra_entries`](struct.LoadOptions.html#structfield.keep_extra_entries)
+    /// set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let options = pyxel::LoadOptions {
+    ///     keep_extra_entries: true,
+    ///     ..pyxel::LoadOptions::default()
+    /// };
+    /// let doc = pyxel::load_with_options(File::open("resources/doc.pyxel")?, &options)?;
+    /// for entry in doc.extra_entries() {
+    ///     println!("{}: {} bytes", entry.name(), entry.data().len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extra_entries(&self) -> &[ExtraEntry] {
+        &self.extra_entries
+    }
+
+    /// Returns the extra entry named `name`, if the archive has one. See
+    /// [`extra_entries`](#method.extra_entries).
+    pub fn extra_entry(&self, name: &str) -> Option<&ExtraEntry> {
+        self.extra_entries.iter().find(|entry| entry.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        doc_data::{Limit, Limits, LoadOptions},
+        error::PyxelError,
+        load::{load, load_with_options},
+    };
+    use std::{
+        fs,
+        io::{Cursor, Read, Write},
+    };
+    use zip::write::FileOptions;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn unknown_entries_are_kept() {
+        let buf = fs::read(TEST_FILE).unwrap();
+        assert!(load(Cursor::new(&buf[..]))
+            .unwrap()
+            .extra_entries()
+            .is_empty());
+
+        let mut out = Cursor::new(Vec::new());
+        {
+            let mut archive = zip::ZipArchive::new(Cursor::new(&buf[..])).unwrap();
+            let mut writer = zip::ZipWriter::new(&mut out);
+            for i in 0..archive.len() {
+                writer
+                    .raw_copy_file(archive.by_index_raw(i).unwrap())
+                    .unwrap();
+            }
+            writer
+                .start_file("thumbnail.png", FileOptions::default())
+                .unwrap();
+            writer.write_all(b"not really a png").unwrap();
+            writer
+                .add_directory("notes/", FileOptions::default())
+                .unwrap();
+            writer
+                .start_file("notes/todo.txt", FileOptions::default())
+                .unwrap();
+            writer.write_all(b"shade the hero").unwrap();
+            // Beyond the layers of the document, so not one of its images.
+            writer
+                .start_file("layer11.png", FileOptions::default())
+                .unwrap();
+            writer.finish().unwrap();
+        }
+
+        assert!(load(Cursor::new(out.get_ref()))
+            .unwrap()
+            .extra_entries()
+            .is_empty());
+
+        let options = LoadOptions {
+            keep_extra_entries: true,
+            ..LoadOptions::default()
+        };
+        let doc = load_with_options(Cursor::new(out.get_ref()), &options).unwrap();
+        let names: Vec<&str> = doc.extra_entries().iter().map(|e| e.name()).collect();
+        assert_eq!(
+            vec!["thumbnail.png", "notes/todo.txt", "layer11.png"],
+            names
+        );
+
+        let mut todo = String::new();
+        doc.extra_entry("notes/todo.txt")
+            .unwrap()
+            .data()
+            .read_to_string(&mut todo)
+            .unwrap();
+        assert_eq!("shade the hero", todo);
+        assert!(doc.extra_entry("docData.json").is_none());
+
+        // The extra entries are charged to the limit on decompressed bytes.
+        let extra_bytes = (b"not really a png".len() + b"shade the hero".len()) as u64;
+        let document_bytes = {
+            let mut archive = zip::ZipArchive::new(Cursor::new(&buf[..])).unwrap();
+            (0..archive.len())
+                .map(|i| archive.by_index(i).unwrap().size())
+                .sum::<u64>()
+        };
+        let limited = |max| LoadOptions {
+            limits: Limits {
+                max_decompressed_bytes: Some(max),
+                ..Limits::default()
+            },
+            ..options.clone()
+        };
+        assert!(load_with_options(
+            Cursor::new(out.get_ref()),
+            &limited(document_bytes + extra_bytes)
+        )
+        .is_ok());
+        match load_with_options(
+            Cursor::new(out.get_ref()),
+            &limited(document_bytes + extra_bytes - 1),
+        ) {
+            // The error names whichever entry was being read when the budget ran out.
+            Err(PyxelError::InEntry { source, .. })
+                if matches!(
+                    *source,
+                    PyxelError::LimitExceeded {
+                        limit: Limit::DecompressedBytes,
+                        ..
+                    }
+                ) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
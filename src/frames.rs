@@ -0,0 +1,324 @@
+use crate::{
+    bounds::{opaque_bounds, Rect},
+    error::PyxelError,
+    pyxel::Pyxel,
+    render::scale_pixels,
+};
+
+use image::{GenericImageView, RgbaImage};
+use std::time::Duration;
+
+/// Options controlling which frames are streamed by
+/// [`Pyxel::for_each_frame_pixels`](struct.Pyxel.html#method.for_each_frame_pixels).
+#[derive(Clone, Debug, Default)]
+pub struct FrameOptions {
+    /// The indices of the animations to render, in order. All animations are rendered when
+    /// `None`.
+    pub animations: Option<Vec<usize>>,
+    /// Whether the transparent borders of each frame are trimmed off before it is passed to the
+    /// callback. A fully transparent frame is trimmed to nothing.
+    pub trim: bool,
+    /// The point the frames are anchored at.
+    pub pivot: Pivot,
+    /// The factor frames are scaled up by with nearest-neighbor filtering, after trimming. The
+    /// sizes and positions in [`FrameMeta`](struct.FrameMeta.html) are scaled to match. Values of
+    /// 0 and 1 leave frames at their original size.
+    pub scale: u32,
+}
+
+/// The point a frame is anchored at, reported in
+/// [`FrameMeta::pivot`](struct.FrameMeta.html#structfield.pivot).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Pivot {
+    /// The center of the frame.
+    #[default]
+    Center,
+    /// The middle of the bottom edge of the frame, as suits characters standing on the ground.
+    BottomCenter,
+    /// A point given as fractions of the frame's width and height, measured from its top left.
+    Custom(f32, f32),
+}
+
+impl Pivot {
+    fn fractions(self) -> (f32, f32) {
+        match self {
+            Pivot::Center => (0.5, 0.5),
+            Pivot::BottomCenter => (0.5, 1.),
+            Pivot::Custom(x, y) => (x, y),
+        }
+    }
+}
+
+/// Describes a frame passed to the callback of
+/// [`Pyxel::for_each_frame_pixels`](struct.Pyxel.html#method.for_each_frame_pixels).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameMeta<'a> {
+    /// The index of the animation in the document.
+    pub animation: usize,
+    /// The name of the animation.
+    pub animation_name: &'a str,
+    /// The index of the frame within the animation.
+    pub frame: usize,
+    /// How long the frame is displayed for.
+    pub duration: Duration,
+    /// The width of the pixels passed to the callback.
+    pub width: u32,
+    /// The height of the pixels passed to the callback.
+    pub height: u32,
+    /// The width of the untrimmed frame in pixels.
+    pub source_width: u32,
+    /// The height of the untrimmed frame in pixels.
+    pub source_height: u32,
+    /// The area of the untrimmed frame the pixels were taken from. This covers the whole frame
+    /// unless [`FrameOptions::trim`](struct.FrameOptions.html#structfield.trim) is set.
+    pub trimmed: Rect,
+    /// The position of the pivot in pixels, measured from the top left of the untrimmed frame.
+    pub pivot: (f32, f32),
+}
+
+impl Pyxel {
+    /// Renders the frames of this document's animations one at a time, passing each to `f` as
+    /// tightly packed RGBA8 pixels.
+    ///
+    /// A single buffer is reused for every frame, so long animations can be fed into an encoder
+    /// without holding all of their frames in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let mut frames = 0;
+    /// doc.for_each_frame_pixels(&pyxel::FrameOptions::default(), |meta, pixels| {
+    ///     assert_eq!((meta.width * meta.height * 4) as usize, pixels.len());
+    ///     frames += 1;
+    /// })?;
+    /// assert_eq!(8, frames);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn for_each_frame_pixels<F>(
+        &self,
+        options: &FrameOptions,
+        mut f: F,
+    ) -> Result<(), PyxelError>
+    where
+        F: FnMut(FrameMeta, &[u8]),
+    {
+        let indices: Vec<usize> = match options.animations {
+            Some(ref indices) => indices.clone(),
+            None => (0..self.animations.len()).collect(),
+        };
+
+        // Check everything up front so the callback never sees a partial stream.
+        for &index in &indices {
+            let animation = self
+                .animations
+                .get(index)
+                .ok_or(PyxelError::AnimationOutOfBounds(index))?;
+
+            for frame in 0..animation.length {
+                if self.canvas.frame_origin(animation, frame).is_none() {
+                    let tile = animation.base_tile + frame;
+                    let tiles_wide = self.canvas.tiles_wide().max(1);
+                    return Err(PyxelError::CellOutOfBounds {
+                        col: tile % tiles_wide,
+                        row: tile / tiles_wide,
+                    });
+                }
+            }
+        }
+
+        let width = u32::from(self.canvas.tile_width);
+        let height = u32::from(self.canvas.tile_height);
+        let mut buf = RgbaImage::new(width, height);
+        let mut trimmed_buf = Vec::new();
+        let mut scaled_buf = Vec::new();
+
+        let scale = options.scale.max(1);
+        let (pivot_x, pivot_y) = options.pivot.fractions();
+        let pivot = (
+            pivot_x * (width * scale) as f32,
+            pivot_y * (height * scale) as f32,
+        );
+
+        for index in indices {
+            let animation = &self.animations[index];
+
+            for (frame, duration) in animation.frame_durations().into_iter().enumerate() {
+                let (x, y) = self
+                    .canvas
+                    .frame_origin(animation, frame)
+                    .expect("frames were checked against the canvas");
+                self.canvas.render_region_into(x, y, &mut buf);
+
+                let whole = Rect {
+                    x: 0,
+                    y: 0,
+                    width,
+                    height,
+                };
+                let (trimmed, pixels) = if options.trim {
+                    let trimmed = opaque_bounds(&buf).unwrap_or(Rect {
+                        width: 0,
+                        height: 0,
+                        ..whole
+                    });
+                    trimmed_buf.clear();
+                    let view = buf.view(trimmed.x, trimmed.y, trimmed.width, trimmed.height);
+                    for (_, _, pixel) in view.pixels() {
+                        trimmed_buf.extend_from_slice(&pixel.0);
+                    }
+                    (trimmed, &trimmed_buf[..])
+                } else {
+                    (whole, &*buf)
+                };
+
+                let pixels = if scale > 1 {
+                    scale_pixels(pixels, trimmed.width, scale, &mut scaled_buf);
+                    &scaled_buf[..]
+                } else {
+                    pixels
+                };
+                let trimmed = Rect {
+                    x: trimmed.x * scale,
+                    y: trimmed.y * scale,
+                    width: trimmed.width * scale,
+                    height: trimmed.height * scale,
+                };
+
+                let meta = FrameMeta {
+                    animation: index,
+                    animation_name: &animation.name,
+                    frame,
+                    duration,
+                    width: trimmed.width,
+                    height: trimmed.height,
+                    source_width: width * scale,
+                    source_height: height * scale,
+                    trimmed,
+                    pivot,
+                };
+                f(meta, pixels);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn streams_selected_animations_in_order() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = FrameOptions {
+            animations: Some(vec![2, 0]),
+            ..FrameOptions::default()
+        };
+
+        let mut seen = Vec::new();
+        doc.for_each_frame_pixels(&options, |meta, pixels| {
+            assert_eq!(32 * 16 * 4, pixels.len());
+            seen.push((meta.animation, meta.frame, meta.duration.as_millis()));
+        })
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                (2, 0, 1000),
+                (2, 1, 1000),
+                (0, 0, 150),
+                (0, 1, 300),
+                (0, 2, 450),
+                (0, 3, 600)
+            ],
+            seen
+        );
+    }
+
+    #[test]
+    fn streamed_pixels_match_rendered_frames() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+
+        doc.for_each_frame_pixels(&FrameOptions::default(), |meta, pixels| {
+            let animation = &doc.animations()[meta.animation];
+            let frame = doc.canvas().render_frame(animation, meta.frame).unwrap();
+            assert_eq!(&frame.into_raw()[..], pixels);
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn unknown_animation_is_rejected() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = FrameOptions {
+            animations: Some(vec![3]),
+            ..FrameOptions::default()
+        };
+
+        match doc.for_each_frame_pixels(&options, |_, _| panic!("no frames expected")) {
+            Err(PyxelError::AnimationOutOfBounds(3)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn trimmed_frames_keep_their_position() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = FrameOptions {
+            trim: true,
+            pivot: Pivot::BottomCenter,
+            ..FrameOptions::default()
+        };
+
+        doc.for_each_frame_pixels(&options, |meta, pixels| {
+            assert_eq!((32, 16), (meta.source_width, meta.source_height));
+            assert_eq!((16., 16.), meta.pivot);
+            assert_eq!((meta.width * meta.height * 4) as usize, pixels.len());
+
+            let animation = &doc.animations()[meta.animation];
+            let frame = doc.canvas().render_frame(animation, meta.frame).unwrap();
+            assert_eq!(
+                opaque_bounds(&frame),
+                Some(meta.trimmed).filter(|r| r.width > 0)
+            );
+
+            let r = meta.trimmed;
+            let expected = frame.view(r.x, r.y, r.width, r.height).to_image();
+            assert_eq!(&expected.into_raw()[..], pixels);
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn scaled_frames_are_scaled_after_trimming() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = FrameOptions {
+            trim: true,
+            scale: 2,
+            ..FrameOptions::default()
+        };
+
+        doc.for_each_frame_pixels(&options, |meta, pixels| {
+            assert_eq!((64, 32), (meta.source_width, meta.source_height));
+            assert_eq!((32., 16.), meta.pivot);
+
+            let animation = &doc.animations()[meta.animation];
+            let frame = doc.canvas().render_frame(animation, meta.frame).unwrap();
+            let frame = crate::render::scale_image(frame, 2);
+            let r = meta.trimmed;
+            assert_eq!(0, r.x % 2 + r.y % 2 + r.width % 2 + r.height % 2);
+
+            let expected = frame.view(r.x, r.y, r.width, r.height).to_image();
+            assert_eq!(&expected.into_raw()[..], pixels);
+        })
+        .unwrap();
+    }
+}
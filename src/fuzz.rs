@@ -0,0 +1,384 @@
+//! Arbitrary Pyxel documents for property testing and fuzzing.
+//!
+//! Documents generated by [`Pyxel`](../struct.Pyxel.html)'s `Arbitrary` implementation are
+//! structurally valid, with counts that match, tile refs within the canvas and tileset and
+//! animations within the canvas, but are otherwise as odd as the input makes them: empty palette
+//! slots, unusual names, every combination of layer flags and arbitrary pixels.
+//! [`to_archive`](fn.to_archive.html) packages a document as a `.pyxel` file, so loading that
+//! file should give back the same document.
+//!
+//! # Examples
+//!
+//! ```
+//! use arbitrary::{Arbitrary, Unstructured};
+//! # fn main() -> Result<(), pyxel::PyxelError> {
+//! let mut u = Unstructured::new(&[7; 256]);
+//! let doc = pyxel::Pyxel::arbitrary(&mut u).unwrap();
+//!
+//! let archive = pyxel::fuzz::to_archive(&doc)?;
+//! assert!(pyxel::load_from_memory(&archive)?.eq_with_pixels(&doc));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    doc_data::DOC_DATA,
+    error::PyxelError,
+    load::{layer_entry, tile_entry},
+    metadata::USER_METADATA,
+    png::encode_png,
+    pyxel::{
+        Animation, BlendMode, Canvas, Color, Layer, Palette, Pyxel, Settings, TileRef, Tileset,
+    },
+};
+
+use arbitrary::{Arbitrary, Unstructured};
+use image::{DynamicImage, RgbaImage};
+use semver::Version;
+use serde_json::{json, Map, Value};
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Write},
+    sync::Arc,
+    time::Duration,
+};
+use zip::write::FileOptions;
+
+/// The largest tile, in pixels, and canvas, in tiles, generated. Small documents keep the inputs
+/// needed to fill their pixels short.
+const MAX_TILE_SIZE: u16 = 8;
+const MAX_TILES_ACROSS: usize = 8;
+const MAX_LAYERS: usize = 4;
+const MAX_TILES: usize = 4;
+const MAX_ANIMATIONS: usize = 3;
+const MAX_COLORS: usize = 32;
+
+impl<'a> Arbitrary<'a> for Color {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Color> {
+        let [r, g, b, a] = <[u8; 4]>::arbitrary(u)?;
+        Ok(Color { r, g, b, a })
+    }
+}
+
+impl<'a> Arbitrary<'a> for BlendMode {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<BlendMode> {
+        u.choose(&BlendMode::ALL).copied()
+    }
+}
+
+/// Tile refs are rotated by quarter turns, the only rotations a document can store.
+impl<'a> Arbitrary<'a> for TileRef {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<TileRef> {
+        let index = usize::from(u16::arbitrary(u)?);
+        tile_ref(u, index)
+    }
+}
+
+fn tile_ref(u: &mut Unstructured, index: usize) -> arbitrary::Result<TileRef> {
+    let rot = f64::from(u.int_in_range(0..=3u8)?) * 90.;
+    Ok(TileRef::new(index, rot, bool::arbitrary(u)?))
+}
+
+impl<'a> Arbitrary<'a> for Palette {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Palette> {
+        let len = u.int_in_range(0..=MAX_COLORS)?;
+        let colors = (0..len)
+            .map(|_| Option::<Color>::arbitrary(u))
+            .collect::<arbitrary::Result<Vec<_>>>()?;
+
+        Ok(Palette {
+            num_colors: colors.len(),
+            colors,
+            height: u8::arbitrary(u)?,
+            width: u8::arbitrary(u)?,
+        })
+    }
+}
+
+/// Animations start at an arbitrary cell, so they may not fit the canvas of a document. Frame
+/// durations are whole milliseconds and multipliers whole percentages, as documents store them.
+impl<'a> Arbitrary<'a> for Animation {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Animation> {
+        let base_tile = usize::from(u16::arbitrary(u)?);
+        let length = u.int_in_range(1..=8)?;
+        animation(u, base_tile, length)
+    }
+}
+
+fn animation(
+    u: &mut Unstructured,
+    base_tile: usize,
+    length: usize,
+) -> arbitrary::Result<Animation> {
+    Ok(Animation {
+        base_tile,
+        frame_duration: Duration::from_millis(u64::from(u16::arbitrary(u)?)),
+        frame_duration_multipliers: (0..length)
+            .map(|_| Ok(f64::from(u16::arbitrary(u)?) / 100.))
+            .collect::<arbitrary::Result<_>>()?,
+        length,
+        name: String::arbitrary(u)?,
+    })
+}
+
+fn image(u: &mut Unstructured, width: u32, height: u32) -> arbitrary::Result<Arc<DynamicImage>> {
+    let mut pixels = vec![0; width as usize * height as usize * 4];
+    u.fill_buffer(&mut pixels)?;
+    let image = RgbaImage::from_raw(width, height, pixels).expect("buffer fits the image");
+    Ok(Arc::new(DynamicImage::ImageRgba8(image)))
+}
+
+impl<'a> Arbitrary<'a> for Pyxel {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Pyxel> {
+        let tile_width = u.int_in_range(1..=MAX_TILE_SIZE)?;
+        let tile_height = u.int_in_range(1..=MAX_TILE_SIZE)?;
+        let cols = u.int_in_range(1..=MAX_TILES_ACROSS)?;
+        let rows = u.int_in_range(1..=MAX_TILES_ACROSS)?;
+        let (width, height) = (
+            cols as u32 * u32::from(tile_width),
+            rows as u32 * u32::from(tile_height),
+        );
+
+        let num_tiles = u.int_in_range(0..=MAX_TILES)?;
+        let tile_images = (0..num_tiles)
+            .map(|_| image(u, u32::from(tile_width), u32::from(tile_height)))
+            .collect::<arbitrary::Result<Vec<_>>>()?;
+
+        let num_layers = u.int_in_range(1..=MAX_LAYERS)?;
+        let mut layers = Vec::with_capacity(num_layers);
+        for _ in 0..num_layers {
+            let mut tile_refs = BTreeMap::new();
+            if num_tiles > 0 {
+                for cell in 0..cols * rows {
+                    if u.ratio(1, 3)? {
+                        let index = u.choose_index(num_tiles)?;
+                        tile_refs.insert(cell, tile_ref(u, index)?);
+                    }
+                }
+            }
+
+            layers.push(Layer {
+                alpha: u8::arbitrary(u)?,
+                blend_mode: BlendMode::arbitrary(u)?,
+                hidden: bool::arbitrary(u)?,
+                muted: bool::arbitrary(u)?,
+                name: String::arbitrary(u)?,
+                soloed: bool::arbitrary(u)?,
+                tile_refs,
+                image: image(u, width, height)?,
+            });
+        }
+
+        let mut animations = Vec::new();
+        for _ in 0..u.int_in_range(0..=MAX_ANIMATIONS)? {
+            let base_tile = u.choose_index(cols * rows)?;
+            let length = u.int_in_range(1..=cols * rows - base_tile)?;
+            animations.push(animation(u, base_tile, length)?);
+        }
+
+        Ok(Pyxel {
+            animations,
+            canvas: Canvas {
+                num_layers: layers.len(),
+                layers,
+                height: height as i32,
+                tile_height,
+                tile_width,
+                width: width as i32,
+            },
+            extra_entries: Vec::new(),
+            load_warnings: Vec::new(),
+            name: String::arbitrary(u)?,
+            palette: Palette::arbitrary(u)?,
+            raw_doc_data: None,
+            settings: Settings::default(),
+            tileset: Tileset {
+                fixed_width: bool::arbitrary(u)?,
+                num_tiles,
+                tile_height,
+                tile_width,
+                tiles_wide: u.int_in_range(1..=u8::MAX)?,
+                images: tile_images,
+            },
+            user_metadata: None,
+            version: Version::new(0, 4, 8),
+        })
+    }
+}
+
+/// Returns `values` as a JSON object keyed by their indices, as documents store lists.
+fn indexed<T>(values: &[T], f: impl Fn(&T) -> Value) -> Value {
+    let map: Map<String, Value> = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| (i.to_string(), f(value)))
+        .collect();
+    Value::Object(map)
+}
+
+fn doc_data(doc: &Pyxel) -> Value {
+    let canvas = &doc.canvas;
+    let layers = indexed(&canvas.layers, |layer| {
+        let tile_refs: Map<String, Value> = layer
+            .tile_refs
+            .iter()
+            .map(|(cell, tile_ref)| {
+                let value = json!({
+                    "index": tile_ref.index,
+                    "rot": (tile_ref.rot / 90.).round() as u64 % 4,
+                    "flipX": tile_ref.flip_x,
+                });
+                (cell.to_string(), value)
+            })
+            .collect();
+
+        json!({
+            "alpha": layer.alpha,
+            "blendMode": layer.blend_mode.name(),
+            "hidden": layer.hidden,
+            "muted": layer.muted,
+            "name": layer.name,
+            "soloed": layer.soloed,
+            "tileRefs": tile_refs,
+        })
+    });
+
+    let animations = indexed(&doc.animations, |animation| {
+        let multipliers: Vec<u64> = animation
+            .frame_duration_multipliers
+            .iter()
+            .map(|multiplier| (multiplier * 100.).round() as u64)
+            .collect();
+
+        json!({
+            "baseTile": animation.base_tile,
+            "frameDuration": animation.frame_duration.as_millis() as u64,
+            "frameDurationMultipliers": multipliers,
+            "length": animation.length,
+            "name": animation.name,
+        })
+    });
+
+    let palette = &doc.palette;
+    let tileset = &doc.tileset;
+    json!({
+        "animations": animations,
+        "canvas": {
+            "height": canvas.height,
+            "layers": layers,
+            "numLayers": canvas.num_layers,
+            "tileHeight": canvas.tile_height,
+            "tileWidth": canvas.tile_width,
+            "width": canvas.width,
+        },
+        "name": doc.name,
+        "palette": {
+            "colors": indexed(&palette.colors, |color| json!(color)),
+            "height": palette.height,
+            "numColors": palette.num_colors,
+            "width": palette.width,
+        },
+        "settings": doc.settings,
+        "tileset": {
+            "fixedWidth": tileset.fixed_width,
+            "numTiles": tileset.num_tiles,
+            "tileHeight": tileset.tile_height,
+            "tileWidth": tileset.tile_width,
+            "tilesWide": tileset.tiles_wide,
+        },
+        "version": doc.version.to_string(),
+    })
+}
+
+/// Packages `doc` as the contents of a `.pyxel` file.
+///
+/// Along with the document data and images, the archive holds the document's
+/// [user metadata](../struct.Pyxel.html#method.user_metadata) and
+/// [extra entries](../struct.Pyxel.html#method.extra_entries). Rotations are rounded to quarter
+/// turns, frame durations to milliseconds and multipliers to percentages, the precision
+/// documents store them with, so only documents that already use that precision load back
+/// unchanged.
+pub fn to_archive(doc: &Pyxel) -> Result<Vec<u8>, PyxelError> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default();
+
+    writer.start_file(DOC_DATA, options)?;
+    serde_json::to_writer(&mut writer, &doc_data(doc))?;
+
+    for (i, layer) in doc.canvas.layers.iter().enumerate() {
+        writer.start_file(layer_entry(i), options)?;
+        writer.write_all(&encode_png(&layer.image.to_rgba())?)?;
+    }
+
+    for (i, tile) in doc.tileset.images.iter().enumerate() {
+        writer.start_file(tile_entry(i), options)?;
+        writer.write_all(&encode_png(&tile.to_rgba())?)?;
+    }
+
+    if let Some(ref metadata) = doc.user_metadata {
+        writer.start_file(USER_METADATA, options)?;
+        serde_json::to_writer(&mut writer, metadata)?;
+    }
+
+    for entry in &doc.extra_entries {
+        writer.start_file(entry.name.as_str(), options)?;
+        writer.write_all(&entry.data)?;
+    }
+
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Generates an arbitrary document along with the contents of a `.pyxel` file holding it, see
+/// [`to_archive`](fn.to_archive.html).
+///
+/// # Panics
+///
+/// Panics if the document can't be packaged, which would be a bug in this crate.
+pub fn arbitrary_archive(u: &mut Unstructured) -> arbitrary::Result<(Pyxel, Vec<u8>)> {
+    let doc = Pyxel::arbitrary(u)?;
+    let archive = to_archive(&doc).expect("arbitrary documents can be packaged");
+    Ok((doc, archive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    /// Deterministic pseudo-random input, so that failures can be reproduced.
+    fn input(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn arbitrary_documents_load_back() {
+        for seed in 0..64 {
+            let data = input(seed, 4096);
+            let (doc, archive) = arbitrary_archive(&mut Unstructured::new(&data)).unwrap();
+
+            assert!(doc.validate().is_empty(), "seed {}", seed);
+            let loaded = crate::load_from_memory(&archive).unwrap();
+            assert!(loaded.eq_with_pixels(&doc), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn loaded_documents_round_trip() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let archive = to_archive(&doc).unwrap();
+        assert!(crate::load_from_memory(&archive)
+            .unwrap()
+            .eq_with_pixels(&doc));
+    }
+}
@@ -0,0 +1,271 @@
+use crate::pyxel::{Animation, Canvas, Layer, Palette, Pyxel, Tileset};
+
+/// A 64-bit FNV-1a hasher. Unlike the standard library's hashers its output is fixed, so hashes
+/// can be stored and compared across builds.
+#[derive(Clone, Copy, Debug)]
+struct ContentHasher(u64);
+
+impl ContentHasher {
+    fn new() -> ContentHasher {
+        ContentHasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.bytes(&[value as u8]);
+    }
+
+    fn str(&mut self, value: &str) {
+        // Prefixing the length keeps neighbouring fields from running into each other.
+        self.u64(value.len() as u64);
+        self.bytes(value.as_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.u64(value.to_bits());
+    }
+
+    #[cfg(feature = "images")]
+    fn image(&mut self, image: &image::DynamicImage) {
+        use image::GenericImageView;
+
+        self.u64(u64::from(image.width()));
+        self.u64(u64::from(image.height()));
+
+        // Fully transparent pixels look the same whatever their color channels hold.
+        for (_, _, pixel) in image.pixels() {
+            if pixel[3] == 0 {
+                self.bytes(&[0; 4]);
+            } else {
+                self.bytes(&pixel.0);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "images"))]
+    fn image(&mut self, data: &[u8]) {
+        self.u64(data.len() as u64);
+        self.bytes(data);
+    }
+
+    fn layer(&mut self, layer: &Layer) {
+        self.str(&layer.name);
+        self.bytes(&[layer.alpha]);
+        self.str(&format!("{:?}", layer.blend_mode));
+        self.bool(layer.hidden);
+        self.bool(layer.muted);
+        self.bool(layer.soloed);
+
+        self.u64(layer.tile_refs.len() as u64);
+        for (&cell, tile_ref) in &layer.tile_refs {
+            self.u64(cell as u64);
+            self.u64(tile_ref.index as u64);
+            self.f64(tile_ref.rot);
+            self.bool(tile_ref.flip_x);
+        }
+
+        #[cfg(feature = "images")]
+        self.image(&layer.image);
+        #[cfg(not(feature = "images"))]
+        self.image(&layer.image_data);
+    }
+
+    fn canvas(&mut self, canvas: &Canvas) {
+        self.u64(canvas.width as u64);
+        self.u64(canvas.height as u64);
+        self.u64(u64::from(canvas.tile_width));
+        self.u64(u64::from(canvas.tile_height));
+
+        self.u64(canvas.layers.len() as u64);
+        for layer in &canvas.layers {
+            self.layer(layer);
+        }
+    }
+
+    fn palette(&mut self, palette: &Palette) {
+        self.bytes(&[palette.width, palette.height]);
+        self.u64(palette.colors.len() as u64);
+        for color in &palette.colors {
+            match *color {
+                Some(c) => self.bytes(&[1, c.r, c.g, c.b, c.a]),
+                None => self.bytes(&[0]),
+            }
+        }
+    }
+
+    fn tileset(&mut self, tileset: &Tileset) {
+        self.u64(u64::from(tileset.tile_width));
+        self.u64(u64::from(tileset.tile_height));
+        self.bytes(&[tileset.tiles_wide]);
+        self.bool(tileset.fixed_width);
+
+        let count = tileset.tile_count();
+        self.u64(count as u64);
+        for i in 0..count {
+            self.tile(tileset, i);
+        }
+    }
+
+    fn tile(&mut self, tileset: &Tileset, index: usize) {
+        #[cfg(feature = "images")]
+        self.image(&tileset.images[index]);
+        #[cfg(not(feature = "images"))]
+        self.image(&tileset.image_data[index]);
+    }
+
+    fn animation(&mut self, animation: &Animation) {
+        self.str(&animation.name);
+        self.u64(animation.base_tile as u64);
+        self.u64(animation.length as u64);
+        self.u64(animation.frame_duration.as_nanos() as u64);
+
+        self.u64(animation.frame_duration_multipliers.len() as u64);
+        for &multiplier in &animation.frame_duration_multipliers {
+            self.f64(multiplier);
+        }
+    }
+}
+
+impl Pyxel {
+    /// Returns a hash of this document's contents, for detecting changes and keying caches.
+    ///
+    /// The hash covers the metadata and decoded pixels of the document, but not the PyxelEdit
+    /// version it was saved with, so documents saved with different compression or with their
+    /// entries in a different order hash the same. Fully transparent pixels hash the same
+    /// whatever their color. Without the `images` feature, the encoded PNG data of each image is
+    /// hashed instead, so re-encoded images hash differently.
+    ///
+    /// The hash is stable across builds and platforms, but isn't cryptographically secure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let other = pyxel::open("resources/doc.pyxel")?;
+    /// assert_eq!(doc.content_hash(), other.content_hash());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        hasher.str(&self.name);
+        hasher.canvas(&self.canvas);
+        hasher.palette(&self.palette);
+        hasher.tileset(&self.tileset);
+
+        hasher.u64(self.animations.len() as u64);
+        for animation in &self.animations {
+            hasher.animation(animation);
+        }
+
+        hasher.0
+    }
+}
+
+impl Layer {
+    /// Returns a hash of this layer's metadata, tile refs and pixels. See
+    /// [`Pyxel::content_hash`](struct.Pyxel.html#method.content_hash).
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        hasher.layer(self);
+        hasher.0
+    }
+}
+
+impl Tileset {
+    /// Returns a hash of the pixels of the tile at `index`, or `None` if there is no such tile.
+    /// See [`Pyxel::content_hash`](struct.Pyxel.html#method.content_hash).
+    pub fn tile_hash(&self, index: usize) -> Option<u64> {
+        if index >= self.tile_count() {
+            return None;
+        }
+
+        let mut hasher = ContentHasher::new();
+        hasher.tile(self, index);
+        Some(hasher.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    fn load_test_file() -> Pyxel {
+        load(File::open(TEST_FILE).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn hash_ignores_version() {
+        let doc = load_test_file();
+        let mut other = load_test_file();
+        other.version = semver::Version::new(0, 4, 9);
+
+        assert_eq!(doc.content_hash(), other.content_hash());
+    }
+
+    #[test]
+    fn hash_changes_with_contents() {
+        let doc = load_test_file();
+
+        let mut renamed = load_test_file();
+        renamed.canvas.layers[3].name.push('!');
+        assert_ne!(doc.content_hash(), renamed.content_hash());
+        assert_ne!(
+            doc.canvas.layers[3].content_hash(),
+            renamed.canvas.layers[3].content_hash()
+        );
+        assert_eq!(
+            doc.canvas.layers[2].content_hash(),
+            renamed.canvas.layers[2].content_hash()
+        );
+
+        let mut retimed = load_test_file();
+        retimed.animations[0].frame_duration_multipliers[1] = 3.;
+        assert_ne!(doc.content_hash(), retimed.content_hash());
+    }
+
+    #[test]
+    fn tile_hashes_follow_pixels() {
+        let mut doc = load_test_file();
+        let other = load_test_file();
+        doc.import_tileset(&other).unwrap();
+
+        for i in 0..4 {
+            assert_eq!(doc.tileset.tile_hash(i), doc.tileset.tile_hash(i + 4));
+        }
+        assert_eq!(None, doc.tileset.tile_hash(8));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn transparent_pixels_hash_the_same() {
+        let doc = load_test_file();
+        let mut other = load_test_file();
+
+        let image = other.canvas.layers[0].image_mut();
+        let mut pixels = image.to_rgba();
+        for pixel in pixels.pixels_mut() {
+            if pixel[3] == 0 {
+                pixel.0 = [1, 2, 3, 0];
+            }
+        }
+        *image = image::DynamicImage::ImageRgba8(pixels);
+
+        assert_eq!(doc.content_hash(), other.content_hash());
+    }
+}
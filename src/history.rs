@@ -0,0 +1,422 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Pyxel, TileRef},
+    stamp::check_region,
+};
+
+use derivative::Derivative;
+#[cfg(feature = "images")]
+use std::sync::Arc;
+
+/// A change to a document, which sets one value and is undone by setting it back.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+enum Command {
+    TileRef {
+        layer: usize,
+        index: usize,
+        tile_ref: Option<TileRef>,
+    },
+    LayerName {
+        layer: usize,
+        name: String,
+    },
+    LayerHidden {
+        layer: usize,
+        hidden: bool,
+    },
+    #[cfg(feature = "images")]
+    LayerImage {
+        layer: usize,
+        #[derivative(Debug = "ignore")]
+        image: Arc<image::DynamicImage>,
+    },
+}
+
+impl Command {
+    /// Applies this command to `doc`, returning the command that undoes it.
+    ///
+    /// Commands are only created for layers and cells that were checked when they were first
+    /// applied, so this can't fail unless the document was changed outside of its history.
+    fn apply(self, doc: &mut Pyxel) -> Command {
+        let layers = &mut doc.canvas.layers;
+        match self {
+            Command::TileRef {
+                layer,
+                index,
+                tile_ref,
+            } => {
+                let tile_refs = &mut layers[layer].tile_refs;
+                let tile_ref = match tile_ref {
+                    Some(tile_ref) => tile_refs.insert(index, tile_ref),
+                    None => tile_refs.remove(&index),
+                };
+                Command::TileRef {
+                    layer,
+                    index,
+                    tile_ref,
+                }
+            }
+            Command::LayerName { layer, name } => Command::LayerName {
+                layer,
+                name: std::mem::replace(&mut layers[layer].name, name),
+            },
+            Command::LayerHidden { layer, hidden } => Command::LayerHidden {
+                layer,
+                hidden: std::mem::replace(&mut layers[layer].hidden, hidden),
+            },
+            #[cfg(feature = "images")]
+            Command::LayerImage { layer, image } => Command::LayerImage {
+                layer,
+                image: std::mem::replace(&mut layers[layer].image, image),
+            },
+        }
+    }
+}
+
+/// Applies `commands` to `doc` in reverse order, returning the commands that undo them.
+fn revert(doc: &mut Pyxel, commands: Vec<Command>) -> Vec<Command> {
+    commands
+        .into_iter()
+        .rev()
+        .map(|command| command.apply(doc))
+        .collect()
+}
+
+/// The undo and redo history of edits made to a document through
+/// [`edit`](#method.edit).
+///
+/// A history only records the edits made through it, so it should be used with a single
+/// document, and that document shouldn't be changed in other ways in between. Replaced layer
+/// images are kept by the history without being copied.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let mut doc = pyxel::open("resources/doc.pyxel")?;
+/// let mut history = pyxel::History::new();
+///
+/// history.edit(&mut doc, |tx| {
+///     tx.set_tile_ref(0, (1, 1), pyxel::TileRef::new(0, 0., false))?;
+///     tx.rename_layer(0, "Ground")
+/// })?;
+/// assert_eq!("Ground", doc.canvas().layers()[0].name());
+///
+/// assert!(history.undo(&mut doc));
+/// assert_eq!("Layer 0", doc.canvas().layers()[0].name());
+/// assert!(doc.canvas().layers()[0].tile_refs().is_empty());
+///
+/// assert!(history.redo(&mut doc));
+/// assert_eq!("Ground", doc.canvas().layers()[0].name());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    undo: Vec<Vec<Command>>,
+    redo: Vec<Vec<Command>>,
+}
+
+impl History {
+    /// Creates an empty history.
+    pub fn new() -> History {
+        History::default()
+    }
+
+    /// Makes the edits in `f` to `doc` as a single step that can be undone.
+    ///
+    /// If `f` returns an error, every edit it made is reverted and the error is returned. Making
+    /// an edit clears the steps that could be redone. Edits that don't change anything are still
+    /// recorded, but `f` making no edits at all doesn't add a step.
+    pub fn edit<F, T>(&mut self, doc: &mut Pyxel, f: F) -> Result<T, PyxelError>
+    where
+        F: FnOnce(&mut Transaction) -> Result<T, PyxelError>,
+    {
+        let mut tx = Transaction {
+            doc,
+            inverse: Vec::new(),
+        };
+
+        match f(&mut tx) {
+            Ok(value) => {
+                if !tx.inverse.is_empty() {
+                    self.undo.push(tx.inverse);
+                    self.redo.clear();
+                }
+                Ok(value)
+            }
+            Err(e) => {
+                revert(tx.doc, tx.inverse);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns `true` if there is a step to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns `true` if there is a step to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Undoes the most recent step made to `doc`, returning `false` if there was nothing to undo.
+    pub fn undo(&mut self, doc: &mut Pyxel) -> bool {
+        match self.undo.pop() {
+            Some(step) => {
+                self.redo.push(revert(doc, step));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redoes the most recently undone step, returning `false` if there was nothing to redo.
+    pub fn redo(&mut self, doc: &mut Pyxel) -> bool {
+        match self.redo.pop() {
+            Some(step) => {
+                self.undo.push(revert(doc, step));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forgets every step, so that nothing can be undone or redone.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+/// The edits being made to a document within
+/// [`History::edit`](struct.History.html#method.edit).
+///
+/// Each edit is checked in the same way as the method of [`Pyxel`](struct.Pyxel.html) it
+/// mirrors.
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    doc: &'a mut Pyxel,
+    inverse: Vec<Command>,
+}
+
+impl<'a> Transaction<'a> {
+    /// Returns the document being edited, including the edits made so far.
+    pub fn doc(&self) -> &Pyxel {
+        self.doc
+    }
+
+    fn apply(&mut self, command: Command) {
+        let inverse = command.apply(self.doc);
+        self.inverse.push(inverse);
+    }
+
+    fn check_layer(&self, layer: usize) -> Result<(), PyxelError> {
+        if layer >= self.doc.canvas.layers.len() {
+            return Err(PyxelError::LayerOutOfBounds(layer));
+        }
+        Ok(())
+    }
+
+    /// Places `tile_ref` in a cell of a layer, see
+    /// [`Pyxel::set_tile_ref`](struct.Pyxel.html#method.set_tile_ref).
+    pub fn set_tile_ref(
+        &mut self,
+        layer: usize,
+        cell: (usize, usize),
+        tile_ref: TileRef,
+    ) -> Result<Option<TileRef>, PyxelError> {
+        let previous = self.doc.set_tile_ref(layer, cell, tile_ref)?;
+        self.record_tile_ref(layer, cell, previous);
+        Ok(previous)
+    }
+
+    /// Removes the tile ref from a cell of a layer, see
+    /// [`Pyxel::clear_tile_ref`](struct.Pyxel.html#method.clear_tile_ref).
+    pub fn clear_tile_ref(
+        &mut self,
+        layer: usize,
+        cell: (usize, usize),
+    ) -> Result<Option<TileRef>, PyxelError> {
+        let previous = self.doc.clear_tile_ref(layer, cell)?;
+        self.record_tile_ref(layer, cell, previous);
+        Ok(previous)
+    }
+
+    fn record_tile_ref(
+        &mut self,
+        layer: usize,
+        (col, row): (usize, usize),
+        tile_ref: Option<TileRef>,
+    ) {
+        let index = row * self.doc.canvas.tiles_wide() + col;
+        self.inverse.push(Command::TileRef {
+            layer,
+            index,
+            tile_ref,
+        });
+    }
+
+    /// Fills or clears a region of a layer, see
+    /// [`Pyxel::fill_rect`](struct.Pyxel.html#method.fill_rect).
+    pub fn fill_rect(
+        &mut self,
+        layer: usize,
+        origin: (usize, usize),
+        width: usize,
+        height: usize,
+        tile_ref: Option<TileRef>,
+    ) -> Result<(), PyxelError> {
+        self.check_layer(layer)?;
+        check_region(&self.doc.canvas, origin, width, height)?;
+
+        for row in origin.1..origin.1 + height {
+            for col in origin.0..origin.0 + width {
+                match tile_ref {
+                    Some(tile_ref) => self.set_tile_ref(layer, (col, row), tile_ref)?,
+                    None => self.clear_tile_ref(layer, (col, row))?,
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renames a layer.
+    pub fn rename_layer(&mut self, layer: usize, name: &str) -> Result<(), PyxelError> {
+        self.check_layer(layer)?;
+        self.apply(Command::LayerName {
+            layer,
+            name: name.to_owned(),
+        });
+        Ok(())
+    }
+
+    /// Hides or shows a layer in the PyxelEdit UI.
+    pub fn set_layer_hidden(&mut self, layer: usize, hidden: bool) -> Result<(), PyxelError> {
+        self.check_layer(layer)?;
+        self.apply(Command::LayerHidden { layer, hidden });
+        Ok(())
+    }
+
+    /// Replaces the image of a layer, see
+    /// [`Layer::set_image`](struct.Layer.html#method.set_image).
+    #[cfg(feature = "images")]
+    pub fn set_layer_image(
+        &mut self,
+        layer: usize,
+        image: image::DynamicImage,
+    ) -> Result<(), PyxelError> {
+        self.check_layer(layer)?;
+
+        // Check the image against the layer without keeping it there.
+        let target = &mut self.doc.canvas.layers[layer];
+        let previous = target.image.clone();
+        target.set_image(image)?;
+        let image = std::mem::replace(&mut target.image, previous);
+
+        self.apply(Command::LayerImage { layer, image });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn undo_and_redo_steps() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let original = doc.clone();
+        let mut history = History::new();
+        let tile_ref = TileRef::new(2, 90., false);
+
+        history
+            .edit(&mut doc, |tx| {
+                tx.fill_rect(1, (4, 6), 4, 2, Some(tile_ref))?;
+                tx.set_layer_hidden(1, true)
+            })
+            .unwrap();
+        let filled = doc.clone();
+        history
+            .edit(&mut doc, |tx| {
+                tx.clear_tile_ref(1, (7, 7))?;
+                tx.rename_layer(1, "Edited")
+            })
+            .unwrap();
+        let edited = doc.clone();
+
+        assert!(history.undo(&mut doc));
+        assert_eq!(filled, doc);
+        assert!(history.undo(&mut doc));
+        assert_eq!(original, doc);
+        assert!(!history.undo(&mut doc));
+
+        assert!(history.redo(&mut doc));
+        assert_eq!(filled, doc);
+        assert!(history.redo(&mut doc));
+        assert_eq!(edited, doc);
+        assert!(!history.can_redo());
+
+        history.undo(&mut doc);
+        history
+            .edit(&mut doc, |tx| tx.rename_layer(0, "Top"))
+            .unwrap();
+        assert!(!history.can_redo());
+        history.undo(&mut doc);
+        history.undo(&mut doc);
+        assert_eq!(original, doc);
+    }
+
+    #[test]
+    fn failed_edits_are_reverted() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let original = doc.clone();
+        let mut history = History::new();
+
+        let result = history.edit(&mut doc, |tx| {
+            tx.rename_layer(2, "Renamed")?;
+            tx.set_tile_ref(2, (0, 0), TileRef::new(0, 0., false))?;
+            tx.set_tile_ref(2, (8, 0), TileRef::new(0, 0., false))
+        });
+        match result {
+            Err(PyxelError::CellOutOfBounds { col: 8, row: 0 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(original, doc);
+        assert!(!history.can_undo());
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn layer_images_are_restored() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let original = doc.clone();
+        let mut history = History::new();
+
+        history
+            .edit(&mut doc, |tx| {
+                tx.set_layer_image(3, image::DynamicImage::new_rgba8(256, 128))
+            })
+            .unwrap();
+        assert!(!doc.eq_with_pixels(&original));
+        assert!(history
+            .edit(&mut doc, |tx| {
+                tx.set_layer_image(3, image::DynamicImage::new_rgba8(1, 1))
+            })
+            .is_err());
+
+        history.undo(&mut doc);
+        assert!(Arc::ptr_eq(
+            &original.canvas.layers[3].image,
+            &doc.canvas.layers[3].image
+        ));
+    }
+}
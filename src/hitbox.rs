@@ -0,0 +1,215 @@
+use crate::{bounds::Rect, error::PyxelError, frames::FrameOptions, pyxel::Pyxel};
+
+/// What [`Pyxel::frame_hitboxes`](struct.Pyxel.html#method.frame_hitboxes) produces for each
+/// frame.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HitboxMode {
+    /// The smallest rectangle containing every pixel at least `threshold` opaque.
+    Bounds {
+        /// The alpha value from 1 to 255 at which a pixel becomes solid.
+        threshold: u8,
+    },
+    /// The runs of pixels at least `threshold` opaque, row by row.
+    Mask {
+        /// The alpha value from 1 to 255 at which a pixel becomes solid.
+        threshold: u8,
+    },
+}
+
+/// A horizontal run of solid pixels within a [`Hitbox::Mask`](enum.Hitbox.html#variant.Mask).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Run {
+    /// The row of the run.
+    pub y: u32,
+    /// The column of the first pixel of the run.
+    pub x: u32,
+    /// The number of pixels in the run.
+    pub len: u32,
+}
+
+/// The solid area of a frame, measured in pixels from the frame's top left.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Hitbox {
+    /// The smallest rectangle containing every solid pixel, or `None` if there aren't any.
+    Bounds(Option<Rect>),
+    /// The runs of solid pixels, ordered by row and then by column.
+    Mask(Vec<Run>),
+}
+
+/// Returns the runs of pixels at least `threshold` opaque in the tightly packed RGBA8 `pixels`
+/// of an image `width` pixels wide.
+fn solid_runs(pixels: &[u8], width: u32, threshold: u8) -> Vec<Run> {
+    let mut runs = Vec::new();
+    if width == 0 {
+        return runs;
+    }
+
+    for (y, row) in pixels.chunks_exact(width as usize * 4).enumerate() {
+        let mut start = None;
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            match (start, pixel[3] >= threshold) {
+                (None, true) => start = Some(x),
+                (Some(s), false) => {
+                    runs.push(Run {
+                        y: y as u32,
+                        x: s as u32,
+                        len: (x - s) as u32,
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(s) = start {
+            runs.push(Run {
+                y: y as u32,
+                x: s as u32,
+                len: width - s as u32,
+            });
+        }
+    }
+
+    runs
+}
+
+/// Returns the smallest rectangle containing every run.
+fn bounds(runs: &[Run]) -> Option<Rect> {
+    let first = runs.first()?;
+    let (mut min_x, mut max_x) = (first.x, first.x + first.len);
+    for run in runs {
+        min_x = min_x.min(run.x);
+        max_x = max_x.max(run.x + run.len);
+    }
+
+    // Runs are ordered by row.
+    let (min_y, max_y) = (first.y, runs[runs.len() - 1].y);
+    Some(Rect {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x,
+        height: max_y - min_y + 1,
+    })
+}
+
+impl Pyxel {
+    /// Returns the solid area of each frame of the animation at `animation`, found from the alpha
+    /// channel of its rendered pixels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyxel::{Hitbox, HitboxMode};
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// for hitbox in doc.frame_hitboxes(0, HitboxMode::Bounds { threshold: 128 })? {
+    ///     if let Hitbox::Bounds(Some(rect)) = hitbox {
+    ///         println!("{}x{} at ({}, {})", rect.width, rect.height, rect.x, rect.y);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn frame_hitboxes(
+        &self,
+        animation: usize,
+        mode: HitboxMode,
+    ) -> Result<Vec<Hitbox>, PyxelError> {
+        let options = FrameOptions {
+            animations: Some(vec![animation]),
+            ..FrameOptions::default()
+        };
+
+        let threshold = match mode {
+            HitboxMode::Bounds { threshold } | HitboxMode::Mask { threshold } => threshold.max(1),
+        };
+
+        let mut hitboxes = Vec::new();
+        self.for_each_frame_pixels(&options, |meta, pixels| {
+            let runs = solid_runs(pixels, meta.width, threshold);
+            hitboxes.push(match mode {
+                HitboxMode::Bounds { .. } => Hitbox::Bounds(bounds(&runs)),
+                HitboxMode::Mask { .. } => Hitbox::Mask(runs),
+            });
+        })?;
+
+        Ok(hitboxes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use image::{Rgba, RgbaImage};
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    // A document whose first animation's first frame holds a single 3x2 block of opaque pixels
+    // with a translucent pixel beside it.
+    fn document() -> Pyxel {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        for layer in &mut doc.canvas.layers {
+            *layer.image_mut() = image::DynamicImage::ImageRgba8(RgbaImage::new(256, 128));
+        }
+
+        // The second layer is soloed, so it is the only one rendered.
+        let image = doc.canvas.layers[1].image_mut().as_mut_rgba8().unwrap();
+        for y in 5..7 {
+            for x in 10..13 {
+                image.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+        image.put_pixel(20, 6, Rgba([255, 0, 0, 64]));
+        doc
+    }
+
+    #[test]
+    fn bounds_follow_threshold() {
+        let doc = document();
+
+        let hitboxes = doc
+            .frame_hitboxes(0, HitboxMode::Bounds { threshold: 128 })
+            .unwrap();
+        assert_eq!(4, hitboxes.len());
+        let rect = Rect {
+            x: 10,
+            y: 5,
+            width: 3,
+            height: 2,
+        };
+        assert_eq!(Hitbox::Bounds(Some(rect)), hitboxes[0]);
+        assert_eq!(Hitbox::Bounds(None), hitboxes[1]);
+
+        let hitboxes = doc
+            .frame_hitboxes(0, HitboxMode::Bounds { threshold: 1 })
+            .unwrap();
+        let rect = Rect { width: 11, ..rect };
+        assert_eq!(Hitbox::Bounds(Some(rect)), hitboxes[0]);
+    }
+
+    #[test]
+    fn masks_are_run_length_encoded() {
+        let doc = document();
+        let hitboxes = doc
+            .frame_hitboxes(0, HitboxMode::Mask { threshold: 1 })
+            .unwrap();
+
+        let run = |y, x, len| Run { y, x, len };
+        assert_eq!(
+            Hitbox::Mask(vec![run(5, 10, 3), run(6, 10, 3), run(6, 20, 1)]),
+            hitboxes[0]
+        );
+        assert_eq!(Hitbox::Mask(Vec::new()), hitboxes[3]);
+    }
+
+    #[test]
+    fn unknown_animation_is_rejected() {
+        let doc = document();
+        match doc.frame_hitboxes(3, HitboxMode::Mask { threshold: 1 }) {
+            Err(PyxelError::AnimationOutOfBounds(3)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
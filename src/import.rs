@@ -0,0 +1,455 @@
+use crate::{
+    deserialization::{deserialize_as_milliseconds, deserialize_multipliers},
+    error::PyxelError,
+    pyxel::{Animation, BlendMode, Canvas, Layer, Palette, Pyxel, Settings, TileRef, Tileset},
+};
+
+use image::{imageops, DynamicImage, GenericImageView, RgbaImage};
+use semver::Version;
+use serde::Deserialize;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+#[derive(Deserialize)]
+struct ExportSettings {
+    name: Option<String>,
+
+    #[serde(rename = "tileWidth")]
+    tile_width: u16,
+
+    #[serde(rename = "tileHeight")]
+    tile_height: u16,
+
+    #[serde(default)]
+    spacing: u32,
+
+    #[serde(rename = "tilesWide")]
+    tiles_wide: Option<u8>,
+
+    #[serde(rename = "numTiles")]
+    num_tiles: Option<usize>,
+
+    #[serde(default)]
+    animations: Vec<ExportAnimation>,
+}
+
+#[derive(Deserialize)]
+struct ExportAnimation {
+    name: String,
+
+    #[serde(default, rename = "baseTile")]
+    base_tile: usize,
+
+    length: usize,
+
+    #[serde(
+        deserialize_with = "deserialize_as_milliseconds",
+        rename = "frameDuration"
+    )]
+    frame_duration: Duration,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_multipliers",
+        rename = "frameDurationMultipliers"
+    )]
+    frame_duration_multipliers: Vec<f64>,
+}
+
+/// Builds a single layer document whose canvas shows `tiles` in order, `tiles_wide` across.
+pub(crate) fn document_from_tiles(
+    name: String,
+    tile_width: u16,
+    tile_height: u16,
+    tiles_wide: u8,
+    tiles: Vec<RgbaImage>,
+    animations: Vec<Animation>,
+) -> Pyxel {
+    let columns = usize::from(tiles_wide.max(1));
+    let rows = tiles.len().div_ceil(columns);
+    let (tw, th) = (u32::from(tile_width), u32::from(tile_height));
+
+    let mut image = RgbaImage::new(columns as u32 * tw, rows as u32 * th);
+    let mut tile_refs = BTreeMap::new();
+
+    for (i, tile) in tiles.iter().enumerate() {
+        let (col, row) = ((i % columns) as u32, (i / columns) as u32);
+        imageops::replace(&mut image, tile, col * tw, row * th);
+        tile_refs.insert(i, TileRef::new(i, 0., false));
+    }
+
+    let layer = Layer {
+        alpha: 255,
+        blend_mode: BlendMode::Normal,
+        hidden: false,
+        muted: false,
+        name: "Layer 0".to_owned(),
+        soloed: false,
+        tile_refs,
+        image: Arc::new(DynamicImage::ImageRgba8(image)),
+    };
+
+    Pyxel {
+        animations,
+        canvas: Canvas {
+            layers: vec![layer],
+            height: (rows as u32 * th) as i32,
+            num_layers: 1,
+            tile_height,
+            tile_width,
+            width: (columns as u32 * tw) as i32,
+        },
+        extra_entries: Vec::new(),
+        load_warnings: Vec::new(),
+        name,
+        palette: Palette {
+            colors: Vec::new(),
+            height: 0,
+            num_colors: 0,
+            width: 0,
+        },
+        raw_doc_data: None,
+        settings: Settings::default(),
+        tileset: Tileset {
+            fixed_width: false,
+            num_tiles: tiles.len(),
+            tile_height,
+            tile_width,
+            tiles_wide,
+            images: tiles
+                .into_iter()
+                .map(|tile| Arc::new(DynamicImage::ImageRgba8(tile)))
+                .collect(),
+        },
+        user_metadata: None,
+        version: Version::new(0, 4, 8),
+    }
+}
+
+/// Slices `sheet` into tiles row by row, skipping `margin` pixels around the edge of the sheet and
+/// `spacing` pixels between tiles, returning up to `max_tiles` tiles along with the number of
+/// columns and rows in the sheet.
+fn slice_sheet(
+    sheet: &DynamicImage,
+    tile_width: u32,
+    tile_height: u32,
+    margin: u32,
+    spacing: u32,
+    max_tiles: Option<usize>,
+) -> Result<(Vec<RgbaImage>, u32, u32), PyxelError> {
+    let (width, height) = (
+        sheet.width().saturating_sub(2 * margin),
+        sheet.height().saturating_sub(2 * margin),
+    );
+    if tile_width == 0 || tile_height == 0 || tile_width > width || tile_height > height {
+        return Err(PyxelError::InvalidTileSize {
+            width: tile_width,
+            height: tile_height,
+        });
+    }
+
+    let columns = (width + spacing) / (tile_width + spacing);
+    let rows = (height + spacing) / (tile_height + spacing);
+
+    let available = (columns * rows) as usize;
+    let num_tiles = max_tiles.unwrap_or(available).min(available);
+
+    let tiles = (0..num_tiles)
+        .map(|i| {
+            let (col, row) = (i as u32 % columns, i as u32 / columns);
+            sheet
+                .view(
+                    margin + col * (tile_width + spacing),
+                    margin + row * (tile_height + spacing),
+                    tile_width,
+                    tile_height,
+                )
+                .to_image()
+        })
+        .collect();
+
+    Ok((tiles, columns, rows))
+}
+
+/// Reconstructs a partial document from an image exported by PyxelEdit's "export tileset" or
+/// "export animation" commands.
+///
+/// `json_settings` describes how the image was exported, using the same key names as a
+/// document's `docData.json`:
+///
+/// ```json
+/// {
+///     "name": "hero",
+///     "tileWidth": 16,
+///     "tileHeight": 16,
+///     "spacing": 0,
+///     "tilesWide": 4,
+///     "numTiles": 6,
+///     "animations": [
+///         { "name": "walk", "baseTile": 0, "length": 4, "frameDuration": 100 }
+///     ]
+/// }
+/// ```
+///
+/// Only `tileWidth` and `tileHeight` are required. `spacing` is the number of pixels between
+/// tiles in the image, `tilesWide` sets the layout of the reconstructed canvas and tileset
+/// (defaulting to the number of columns in the image) and `numTiles` limits the number of
+/// tiles read (defaulting to every tile in the image).
+///
+/// The resulting document has a tileset containing every tile, a single layer showing the
+/// tiles in order and the animations described. Its palette is empty.
+pub fn import_pyxeledit_export(sheet_png: &[u8], json_settings: &str) -> Result<Pyxel, PyxelError> {
+    let settings: ExportSettings = serde_json::from_str(json_settings)?;
+    let sheet = image::load_from_memory_with_format(sheet_png, image::ImageFormat::PNG)?;
+
+    let (tiles, columns, _) = slice_sheet(
+        &sheet,
+        u32::from(settings.tile_width),
+        u32::from(settings.tile_height),
+        0,
+        settings.spacing,
+        settings.num_tiles,
+    )?;
+
+    let animations = settings
+        .animations
+        .into_iter()
+        .map(|animation| {
+            let mut multipliers = animation.frame_duration_multipliers;
+            multipliers.resize(animation.length, 1.);
+
+            Animation {
+                base_tile: animation.base_tile,
+                frame_duration: animation.frame_duration,
+                frame_duration_multipliers: multipliers,
+                length: animation.length,
+                name: animation.name,
+            }
+        })
+        .collect();
+
+    let tiles_wide = settings
+        .tiles_wide
+        .unwrap_or_else(|| columns.min(u32::from(u8::MAX)) as u8);
+
+    Ok(document_from_tiles(
+        settings.name.unwrap_or_default(),
+        settings.tile_width,
+        settings.tile_height,
+        tiles_wide,
+        tiles,
+        animations,
+    ))
+}
+
+/// Options for [`Pyxel::from_spritesheet`](struct.Pyxel.html#method.from_spritesheet).
+#[derive(Clone, Debug, Default)]
+pub struct SpriteSheetOptions {
+    /// The name of the document.
+    pub name: String,
+    /// The number of pixels around the edge of the sheet before the first tile.
+    pub margin: u32,
+    /// The number of pixels between tiles.
+    pub spacing: u32,
+    /// Adds an animation for each row of the sheet with every frame shown for this long, named
+    /// "Animation 1", "Animation 2" and so on. Fully transparent tiles at the end of a row are
+    /// left out of its animation, and rows that are entirely transparent don't get one.
+    pub row_animations: Option<Duration>,
+}
+
+impl Pyxel {
+    /// Builds a document from a plain sprite sheet, slicing `sheet` into `tile_width` ×
+    /// `tile_height` tiles row by row.
+    ///
+    /// The resulting document has a tileset containing every tile, a single layer laid out like
+    /// the sheet showing each tile in turn and, if requested, an animation for each row. Its
+    /// palette is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let sheet = image::DynamicImage::new_rgba8(64, 32);
+    /// let options = pyxel::SpriteSheetOptions {
+    ///     name: "hero".to_owned(),
+    ///     row_animations: Some(Duration::from_millis(100)),
+    ///     ..pyxel::SpriteSheetOptions::default()
+    /// };
+    ///
+    /// let doc = pyxel::Pyxel::from_spritesheet(&sheet, 16, 16, &options)?;
+    /// assert_eq!(8, doc.tileset().images().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_spritesheet(
+        sheet: &DynamicImage,
+        tile_width: u16,
+        tile_height: u16,
+        options: &SpriteSheetOptions,
+    ) -> Result<Pyxel, PyxelError> {
+        let (tiles, columns, rows) = slice_sheet(
+            sheet,
+            u32::from(tile_width),
+            u32::from(tile_height),
+            options.margin,
+            options.spacing,
+            None,
+        )?;
+
+        let mut animations = Vec::new();
+        if let Some(frame_duration) = options.row_animations {
+            let columns = columns as usize;
+            for row in 0..rows as usize {
+                let length = tiles[row * columns..(row + 1) * columns]
+                    .iter()
+                    .rposition(|tile| tile.pixels().any(|p| p[3] != 0))
+                    .map_or(0, |last| last + 1);
+
+                if length > 0 {
+                    animations.push(Animation {
+                        base_tile: row * columns,
+                        frame_duration,
+                        frame_duration_multipliers: vec![1.; length],
+                        length,
+                        name: format!("Animation {}", animations.len() + 1),
+                    });
+                }
+            }
+        }
+
+        let tiles_wide = columns.min(u32::from(u8::MAX)) as u8;
+        Ok(document_from_tiles(
+            options.name.clone(),
+            tile_width,
+            tile_height,
+            tiles_wide,
+            tiles,
+            animations,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::encode_png;
+    use image::Rgba;
+
+    // A 3x2 grid of 4x2 tiles separated by 1px of spacing, each filled with a color derived from
+    // its index.
+    fn sheet() -> Vec<u8> {
+        let mut sheet = RgbaImage::from_pixel(14, 5, Rgba([255, 0, 255, 255]));
+        for i in 0..6u32 {
+            let (x, y) = ((i % 3) * 5, (i / 3) * 3);
+            for dy in 0..2 {
+                for dx in 0..4 {
+                    sheet.put_pixel(x + dx, y + dy, Rgba([i as u8 * 10, 0, 0, 255]));
+                }
+            }
+        }
+        encode_png(&sheet).unwrap()
+    }
+
+    #[test]
+    fn imports_tiles_and_animations() {
+        let settings = r#"{
+            "name": "sheet",
+            "tileWidth": 4,
+            "tileHeight": 2,
+            "spacing": 1,
+            "tilesWide": 2,
+            "numTiles": 5,
+            "animations": [
+                { "name": "walk", "baseTile": 1, "length": 3, "frameDuration": 100, "frameDurationMultipliers": [100, 200] }
+            ]
+        }"#;
+        let doc = import_pyxeledit_export(&sheet(), settings).unwrap();
+
+        assert_eq!("sheet", doc.name());
+        assert_eq!(5, doc.tileset().images().len());
+        for (i, tile) in doc.tileset().images().iter().enumerate() {
+            assert_eq!((4, 2), tile.dimensions());
+            assert_eq!(Rgba([i as u8 * 10, 0, 0, 255]), tile.get_pixel(3, 1));
+        }
+
+        let canvas = doc.canvas();
+        assert_eq!((8, 6), (canvas.width(), canvas.height()));
+        assert_eq!(5, canvas.layers()[0].tile_refs().len());
+        assert_eq!(
+            Rgba([40, 0, 0, 255]),
+            canvas.layers()[0].image().get_pixel(0, 4)
+        );
+
+        let animation = &doc.animations()[0];
+        assert_eq!("walk", animation.name());
+        assert_eq!(&[1., 2., 1.], animation.frame_duration_multipliers());
+        assert!(doc.validate().is_empty());
+    }
+
+    #[test]
+    fn defaults_to_every_tile_in_the_sheet() {
+        let doc =
+            import_pyxeledit_export(&sheet(), r#"{"tileWidth": 7, "tileHeight": 2}"#).unwrap();
+
+        assert_eq!(4, doc.tileset().images().len());
+        assert_eq!(2, doc.tileset().tiles_wide());
+    }
+
+    #[test]
+    fn imports_sprite_sheet_with_row_animations() {
+        // A 3x3 grid of 4x2 tiles inside a 1px margin, leaving the third tile of the first row
+        // and the whole second row transparent.
+        let mut sheet = RgbaImage::new(14, 8);
+        for i in [0u32, 1, 6, 7, 8].iter() {
+            let (x, y) = (1 + (i % 3) * 4, 1 + (i / 3) * 2);
+            for dy in 0..2 {
+                for dx in 0..4 {
+                    sheet.put_pixel(x + dx, y + dy, Rgba([*i as u8 * 10, 0, 0, 255]));
+                }
+            }
+        }
+
+        let options = SpriteSheetOptions {
+            name: "sheet".to_owned(),
+            margin: 1,
+            row_animations: Some(Duration::from_millis(80)),
+            ..SpriteSheetOptions::default()
+        };
+        let doc =
+            Pyxel::from_spritesheet(&DynamicImage::ImageRgba8(sheet), 4, 2, &options).unwrap();
+
+        assert_eq!("sheet", doc.name());
+        assert_eq!(9, doc.tileset().images().len());
+        assert_eq!(3, doc.tileset().tiles_wide());
+        assert_eq!(
+            Rgba([70, 0, 0, 255]),
+            doc.tileset().images()[7].get_pixel(0, 0)
+        );
+
+        let animations: Vec<_> = doc
+            .animations()
+            .iter()
+            .map(|a| (a.name(), a.base_tile(), a.length()))
+            .collect();
+        assert_eq!(
+            vec![("Animation 1", 0, 2), ("Animation 2", 6, 3)],
+            animations
+        );
+        assert_eq!(
+            Duration::from_millis(80),
+            doc.animations()[1].frame_duration()
+        );
+        assert!(doc.validate().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_tile_size() {
+        match import_pyxeledit_export(&sheet(), r#"{"tileWidth": 0, "tileHeight": 3}"#) {
+            Err(PyxelError::InvalidTileSize {
+                width: 0,
+                height: 3,
+            }) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -10,17 +10,225 @@
     missing_debug_implementations
 )]
 
-use std::{fs::File, io::Cursor, path::Path};
+#[cfg(feature = "archive")]
+use std::io::Cursor;
+#[cfg(all(
+    feature = "archive",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+use std::{fs::File, path::Path};
 
+#[cfg(feature = "images")]
+mod atlas;
+mod autotile;
+mod backend;
+#[cfg(all(
+    feature = "archive",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod batch;
+#[cfg(feature = "bevy")]
+mod bevy_tilemap;
+#[cfg(feature = "images")]
+mod bounds;
+#[cfg(all(
+    feature = "archive",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod cache;
+#[cfg(feature = "capi")]
+mod capi;
+#[cfg(feature = "images")]
+mod codegen;
+mod collision;
+mod color;
+mod companion;
+#[cfg(feature = "images")]
+mod conformance;
+#[cfg(feature = "images")]
+mod convert;
+#[cfg(feature = "images")]
+mod dedupe;
 mod deserialization;
+mod diff;
+#[cfg(feature = "images")]
+mod dither;
+mod doc_data;
+mod edit;
 mod error;
+mod export;
+mod extra;
+#[cfg(feature = "images")]
+mod frames;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+mod hash;
+mod history;
+#[cfg(feature = "images")]
+mod hitbox;
+#[cfg(feature = "images")]
+mod import;
+#[cfg(feature = "archive")]
+mod load;
+#[cfg(feature = "images")]
+mod mask;
+mod merge;
+mod metadata;
+#[cfg(feature = "images")]
+mod nine_slice;
+mod normalize;
+#[cfg(feature = "images")]
+mod outline;
+#[cfg(feature = "images")]
+mod palette_derive;
+mod palette_import;
+#[cfg(feature = "images")]
+mod pixel_diff;
+#[cfg(feature = "images")]
+mod pixels;
+mod png;
+#[cfg(feature = "python")]
+mod python;
 mod pyxel;
+#[cfg(feature = "raylib")]
+mod raylib_texture;
+#[cfg(feature = "archive")]
+mod reader;
+#[cfg(feature = "images")]
+mod render;
+mod report;
+#[cfg(feature = "images")]
+mod resize;
+mod save;
+#[cfg(feature = "archive")]
+mod source;
+mod stamp;
+mod stats;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+mod text;
+#[cfg(feature = "tiled")]
+mod tiled_map;
+mod tilemap;
+mod tiles;
+mod usage;
+mod validate;
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "wgpu")]
+mod wgpu_texture;
 
+#[cfg(feature = "images")]
+pub use crate::atlas::{Atlas, AtlasBuilder, AtlasRect};
+pub use crate::autotile::Neighborhood;
+#[cfg(feature = "images")]
+pub use crate::backend::ImageCrateBackend;
+pub use crate::backend::{ImageBackend, Rgba8};
+#[cfg(all(
+    feature = "archive",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use crate::batch::{
+    open_dir, open_dir_parallel, open_dir_with_options, DirDocuments, DirOptions,
+};
+#[cfg(feature = "bevy")]
+pub use crate::bevy_tilemap::{BevyTile, BevyTilemap};
+#[cfg(feature = "images")]
+pub use crate::bounds::Rect;
+#[cfg(all(
+    feature = "archive",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use crate::cache::{CacheStats, PyxelCache};
+#[cfg(feature = "images")]
+pub use crate::codegen::CodegenOptions;
+pub use crate::collision::{CollisionGrid, CollisionRule};
+pub use crate::companion::LayerGroup;
+#[cfg(feature = "images")]
+pub use crate::companion::{LayerGroupFrame, LayerGroupSheets, TrimmedLayerGroup};
+#[cfg(feature = "images")]
+pub use crate::conformance::{OffPaletteColor, PixelPosition};
+#[cfg(feature = "images")]
+pub use crate::dedupe::DuplicateTile;
+pub use crate::diff::{diff, DocumentDiff, LayerDiff, PaletteChange, Renamed, TileRefChange};
+#[cfg(feature = "images")]
+pub use crate::dither::Dither;
+pub use crate::doc_data::{
+    from_doc_data, is_supported, CancelToken, Limit, Limits, LoadOptions, LoadWarning,
+    SUPPORTED_VERSIONS,
+};
 pub use crate::error::PyxelError;
+#[cfg(feature = "images")]
+pub use crate::export::{
+    BmFont, BmFontOptions, GameMakerOptions, GameMakerSprite, GlyphSource, GodotOptions,
+    GodotTileset, IcoOptions, IconSource, IndexedPngOptions, Ktx2Options, PhaserAtlas,
+    PhaserAtlasOptions, StripDirection, StripOptions, TexturePackerLayout, TexturePackerOptions,
+    UnityOptions,
+};
+pub use crate::export::{ExportReport, MissingColorPolicy, TmxOptions};
+pub use crate::extra::ExtraEntry;
+#[cfg(feature = "images")]
+pub use crate::frames::{FrameMeta, FrameOptions, Pivot};
+pub use crate::history::{History, Transaction};
+#[cfg(feature = "images")]
+pub use crate::hitbox::{Hitbox, HitboxMode, Run};
+#[cfg(feature = "images")]
+pub use crate::import::{import_pyxeledit_export, SpriteSheetOptions};
+#[cfg(all(
+    feature = "archive",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use crate::load::open_parallel;
+#[cfg(feature = "archive")]
+pub use crate::load::{
+    load, load_from_source, load_parallel, load_with_options, load_with_progress, LoadEvent,
+};
+#[cfg(feature = "images")]
+pub use crate::mask::AlphaMask;
+pub use crate::merge::{merge, DocumentMerge, MergeConflict};
+#[cfg(feature = "archive")]
+pub use crate::metadata::write_user_metadata;
+#[cfg(feature = "images")]
+pub use crate::nine_slice::{Margins, NineSlice};
+pub use crate::normalize::Normalization;
+#[cfg(feature = "images")]
+pub use crate::outline::{outline, silhouette, OutlineOptions};
+#[cfg(feature = "images")]
+pub use crate::pixel_diff::{DocumentPixelDiff, LayerPixelDiff, PixelDiff, RegionDiff};
+#[cfg(feature = "images")]
+pub use crate::pixels::{ByteOrder, PixelFormat};
 pub use crate::pyxel::*;
+#[cfg(feature = "archive")]
+pub use crate::reader::{DocEvent, DocReader, Entry, ImageKind, ImageReader, PyxelReader};
+#[cfg(feature = "images")]
+pub use crate::render::{Background, RenderOptions};
+#[cfg(feature = "images")]
+pub use crate::report::HtmlReportOptions;
+pub use crate::report::ReportFormat;
+#[cfg(feature = "images")]
+pub use crate::resize::Anchor;
+#[cfg(feature = "archive")]
+pub use crate::source::ArchiveSource;
+#[cfg(all(
+    feature = "archive",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use crate::source::DirectorySource;
+pub use crate::stamp::Stamp;
+pub use crate::stats::{DocumentStats, LayerStats};
+pub use crate::tilemap::{load_tilemap_export, Tilemap, TilemapLayer};
+pub use crate::usage::{TileUsage, TileUse};
+pub use crate::validate::ValidationIssue;
+#[cfg(feature = "watch")]
+pub use crate::watch::{watch, Watcher};
 
 /// Load a Pyxel document from a byte slice.
 ///
+/// This doesn't borrow from `buf`: the document owns its data, so `docData.json` and, without the
+/// `images` feature, each PNG are copied out of the archive. With the `images` feature, images
+/// are decoded straight from their decompressed entries without being buffered first, except by
+/// the `fast-png` decoder.
+///
 /// # Examples
 ///
 /// ```
@@ -31,6 +239,7 @@ pub use crate::pyxel::*;
 /// # Ok(())
 /// # }
 /// ```
+#[cfg(feature = "archive")]
 pub fn load_from_memory(buf: &[u8]) -> Result<Pyxel, PyxelError> {
     let cursor = Cursor::new(buf);
     load(cursor)
@@ -46,6 +255,10 @@ pub fn load_from_memory(buf: &[u8]) -> Result<Pyxel, PyxelError> {
 /// # Ok(())
 /// # }
 /// ```
+#[cfg(all(
+    feature = "archive",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 pub fn open<P>(path: P) -> Result<Pyxel, PyxelError>
 where
     P: AsRef<Path>,
@@ -0,0 +1,1099 @@
+use crate::{
+    doc_data::{parse_doc_data, CancelToken, Limit, Limits, LoadOptions, LoadWarning, DOC_DATA},
+    error::PyxelError,
+    extra::read_extra_entries,
+    metadata::read_user_metadata,
+    pyxel::Pyxel,
+    source::ArchiveSource,
+};
+
+use std::{
+    borrow::Cow,
+    io::{self, Read, Seek},
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::{fs::File, path::Path};
+
+/// A progress event emitted by [`load_with_progress`](fn.load_with_progress.html).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LoadEvent {
+    /// The document metadata in `docData.json` has been parsed.
+    ParsedDocData,
+    /// The image for a layer has been loaded, given as (index, total).
+    LoadedLayer(usize, usize),
+    /// The image for a tile has been loaded, given as (index, total).
+    LoadedTile(usize, usize),
+}
+
+/// Tracks the number of bytes decompressed from an archive against the limit, possibly from
+/// several threads at once, and stops reading once the load is cancelled.
+pub(crate) struct Budget {
+    max: Option<u64>,
+    used: AtomicU64,
+    cancel: Option<CancelToken>,
+}
+
+impl Budget {
+    pub(crate) fn new(options: &LoadOptions) -> Budget {
+        Budget {
+            max: options.limits.max_decompressed_bytes,
+            used: AtomicU64::new(0),
+            cancel: options.cancel.clone(),
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Charges `bytes` that are allocated without being read from the archive to this budget.
+    fn charge(&self, bytes: u64) -> Result<(), PyxelError> {
+        match self.max {
+            Some(max)
+                if self
+                    .used
+                    .fetch_add(bytes, Ordering::Relaxed)
+                    .saturating_add(bytes)
+                    > max =>
+            {
+                Err(self.exceeded())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn exceeded(&self) -> PyxelError {
+        PyxelError::LimitExceeded {
+            limit: Limit::DecompressedBytes,
+            max: self.max.unwrap_or(0),
+        }
+    }
+
+    /// Opens the entry `name` for reading, charging the bytes read from it to this budget.
+    pub(crate) fn open_entry<'a, S: ArchiveSource>(
+        &'a self,
+        source: &'a mut S,
+        name: &str,
+    ) -> Result<EntryReader<'a, S::Entry<'a>>, PyxelError> {
+        if self.is_cancelled() {
+            return Err(PyxelError::Cancelled);
+        }
+
+        let name = find_entry(source, name).ok_or(zip::result::ZipError::FileNotFound)?;
+        let size = source.entry_size(&name);
+
+        // The declared size can't be trusted, so only use it to fail early.
+        if let (Some(max), Some(size)) = (self.max, size) {
+            if size > max.saturating_sub(self.used.load(Ordering::Relaxed)) {
+                return Err(self.exceeded());
+            }
+        }
+
+        Ok(EntryReader {
+            inner: source.entry(&name)?,
+            size,
+            budget: self,
+            exceeded: false,
+            cancelled: false,
+        })
+    }
+
+    pub(crate) fn read_entry<S: ArchiveSource>(
+        &self,
+        source: &mut S,
+        name: &str,
+    ) -> Result<Vec<u8>, PyxelError> {
+        let mut entry = self.open_entry(source, name)?;
+
+        // Reserve the declared size up front so the buffer isn't regrown whilst reading, bounded
+        // so that a forged size can't force a large allocation.
+        let capacity = entry.size.unwrap_or(0).min(match self.max {
+            Some(max) => max.saturating_sub(self.used.load(Ordering::Relaxed)),
+            None => MAX_RESERVED_BYTES,
+        });
+        let mut buf = Vec::with_capacity(capacity as usize);
+
+        entry
+            .read_to_end(&mut buf)
+            .map_err(|e| entry.map_err(e.into()))?;
+        Ok(buf)
+    }
+}
+
+/// The most memory reserved for an entry before it's read when there's no decompression limit.
+const MAX_RESERVED_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Reads an archive entry, failing once the bytes read exceed the budget or the load is
+/// cancelled.
+pub(crate) struct EntryReader<'a, R> {
+    inner: R,
+    size: Option<u64>,
+    budget: &'a Budget,
+    exceeded: bool,
+    cancelled: bool,
+}
+
+impl<R> EntryReader<'_, R> {
+    /// Returns the error to report for `e`, which was returned whilst reading the entry.
+    ///
+    /// Decoders wrap the errors of the reader they read from, so this recovers the limit error
+    /// if the budget was exceeded, or the cancellation if the load was cancelled.
+    pub(crate) fn map_err(&self, e: PyxelError) -> PyxelError {
+        if self.cancelled {
+            PyxelError::Cancelled
+        } else if self.exceeded {
+            self.budget.exceeded()
+        } else {
+            e
+        }
+    }
+}
+
+impl<R: Read> Read for EntryReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.budget.is_cancelled() {
+            self.cancelled = true;
+            return Err(io::Error::other("loading was cancelled"));
+        }
+
+        let n = self.inner.read(buf)?;
+
+        if let Some(max) = self.budget.max {
+            // Other threads may be reading entries charged to the same budget.
+            let len = n as u64;
+            if self.budget.used.fetch_add(len, Ordering::Relaxed) + len > max {
+                self.exceeded = true;
+                return Err(io::Error::other("decompressed size limit exceeded"));
+            }
+        }
+
+        Ok(n)
+    }
+}
+
+/// Returns the name of the entry of `archive` that holds `name`, or `None` if it has none.
+///
+/// Tools that unzip and rezip documents may change the case of names or store every entry under
+/// a directory, so when there's no entry named exactly `name` the first entry whose file name
+/// matches it ignoring case is used, wherever it's stored. The `__MACOSX` directories macOS
+/// adds to archives are skipped.
+pub(crate) fn find_entry<'a, S: ArchiveSource>(
+    source: &mut S,
+    name: &'a str,
+) -> Option<Cow<'a, str>> {
+    if source.contains(name) {
+        return Some(Cow::Borrowed(name));
+    }
+
+    source
+        .entries()
+        .into_iter()
+        .find(|entry| {
+            !entry.starts_with("__MACOSX/")
+                && entry
+                    .rsplit(['/', '\\'])
+                    .next()
+                    .is_some_and(|file_name| file_name.eq_ignore_ascii_case(name))
+        })
+        .map(Cow::Owned)
+}
+
+/// Checks the dimensions of a PNG image against the limits before it's decoded.
+pub(crate) fn check_image(buf: &[u8], limits: &Limits) -> Result<(), PyxelError> {
+    let (width, height) = crate::png::png_dimensions(buf)?;
+    limits.check_dimensions(u64::from(width), u64::from(height))
+}
+
+/// Decodes a PNG image as it's read from `r`, checking its dimensions against the limits first.
+#[cfg(all(feature = "images", not(feature = "fast-png")))]
+fn decode_image<R: Read>(mut r: R, limits: &Limits) -> Result<image::DynamicImage, PyxelError> {
+    // The signature and IHDR chunk header are followed by the width and height.
+    let mut header = [0; 24];
+    r.read_exact(&mut header).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => PyxelError::InvalidImage,
+        _ => e.into(),
+    })?;
+    check_image(&header, limits)?;
+
+    let decoder = image::png::PNGDecoder::new(io::Cursor::new(header).chain(r))?;
+    Ok(image::DynamicImage::from_decoder(decoder)?)
+}
+
+#[cfg(not(feature = "images"))]
+type EntryImage = Vec<u8>;
+
+#[cfg(feature = "images")]
+type EntryImage = image::DynamicImage;
+
+pub(crate) fn read_doc_data<S: ArchiveSource>(
+    source: &mut S,
+    budget: &Budget,
+    options: &LoadOptions,
+) -> Result<Pyxel, PyxelError> {
+    let buf = budget
+        .read_entry(source, DOC_DATA)
+        .map_err(|e| e.in_entry(DOC_DATA))?;
+    let mut pyxel = parse_doc_data(&buf, options)?;
+    pyxel.user_metadata = read_user_metadata(source, budget)?;
+    if options.keep_extra_entries {
+        pyxel.extra_entries = read_extra_entries(source, budget, &pyxel)?;
+    }
+    Ok(pyxel)
+}
+
+pub(crate) fn layer_entry(index: usize) -> String {
+    format!("layer{}.png", index)
+}
+
+pub(crate) fn tile_entry(index: usize) -> String {
+    format!("tile{}.png", index)
+}
+
+fn read_image<S: ArchiveSource>(
+    source: &mut S,
+    budget: &Budget,
+    name: &str,
+    limits: &Limits,
+) -> Result<EntryImage, PyxelError> {
+    #[cfg(not(feature = "images"))]
+    {
+        let buf = budget
+            .read_entry(source, name)
+            .map_err(|e| e.in_entry(name))?;
+        check_image(&buf, limits)
+            .map(|_| buf)
+            .map_err(|e| e.in_entry(name))
+    }
+
+    // The fast decoder needs the whole image in memory.
+    #[cfg(feature = "fast-png")]
+    {
+        let buf = budget
+            .read_entry(source, name)
+            .map_err(|e| e.in_entry(name))?;
+        check_image(&buf, limits)
+            .and_then(|_| crate::png::decode_png(&buf))
+            .map_err(|e| e.in_entry(name))
+    }
+
+    // Decode straight from the entry rather than reading it into memory first.
+    #[cfg(all(feature = "images", not(feature = "fast-png")))]
+    {
+        let mut entry = budget
+            .open_entry(source, name)
+            .map_err(|e| e.in_entry(name))?;
+        decode_image(&mut entry, limits).map_err(|e| entry.map_err(e).in_entry(name))
+    }
+}
+
+/// Returns a transparent `width` × `height` image to use in place of the entry `name` if it's
+/// missing from the archive and the load is lenient, recording the substitution in the warnings
+/// of `pyxel`.
+///
+/// The size comes from `docData.json` rather than an image, so it's checked against the limits
+/// and the image's pixels are charged to `budget` before it's allocated.
+fn substitute_missing<S: ArchiveSource>(
+    source: &mut S,
+    name: &str,
+    (width, height): (u32, u32),
+    options: &LoadOptions,
+    budget: &Budget,
+    pyxel: &mut Pyxel,
+) -> Result<Option<EntryImage>, PyxelError> {
+    if !options.lenient || find_entry(source, name).is_some() {
+        return Ok(None);
+    }
+
+    options
+        .limits
+        .check_dimensions(u64::from(width), u64::from(height))
+        .and_then(|_| budget.charge(u64::from(width) * u64::from(height) * 4))
+        .map_err(|e| e.in_entry(name))?;
+
+    pyxel
+        .load_warnings
+        .push(LoadWarning::MissingImage(name.to_owned()));
+
+    #[cfg(not(feature = "images"))]
+    let image = crate::png::blank_png(width, height);
+
+    #[cfg(feature = "images")]
+    let image = image::DynamicImage::new_rgba8(width, height);
+
+    Ok(Some(image))
+}
+
+/// Returns whether the image for each layer of `pyxel` is skipped when loading with `options`.
+fn skipped_layers(pyxel: &Pyxel, options: &LoadOptions) -> Vec<bool> {
+    pyxel
+        .canvas
+        .effective_visibility()
+        .into_iter()
+        .map(|visible| options.skip_invisible_layers && !visible)
+        .collect()
+}
+
+/// Returns the size of the images of the layers and tiles of `pyxel`.
+fn image_sizes(pyxel: &Pyxel) -> ((u32, u32), (u32, u32)) {
+    let (canvas, tileset) = (&pyxel.canvas, &pyxel.tileset);
+    (
+        (canvas.width.max(0) as u32, canvas.height.max(0) as u32),
+        (
+            u32::from(tileset.tile_width),
+            u32::from(tileset.tile_height),
+        ),
+    )
+}
+
+fn set_layer_image(pyxel: &mut Pyxel, index: usize, image: EntryImage) {
+    #[cfg(not(feature = "images"))]
+    {
+        pyxel.canvas.layers[index].image_data = image;
+    }
+    #[cfg(feature = "images")]
+    {
+        pyxel.canvas.layers[index].image = std::sync::Arc::new(image);
+    }
+}
+
+fn push_tile_image(pyxel: &mut Pyxel, image: EntryImage) {
+    #[cfg(not(feature = "images"))]
+    pyxel.tileset.image_data.push(image);
+
+    #[cfg(feature = "images")]
+    pyxel.tileset.images.push(std::sync::Arc::new(image));
+}
+
+/// Load a Pyxel document from a reader.
+///
+/// Documents created with a new major version of PyxelEdit, 1.0 or later, are rejected with
+/// [`PyxelError::UnsupportedVersion`](enum.PyxelError.html#variant.UnsupportedVersion), see
+/// [`LoadOptions::strict_version`](struct.LoadOptions.html#structfield.strict_version) to only
+/// accept the versions this crate is tested against.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let file = File::open("resources/doc.pyxel")?;
+/// let doc = pyxel::load(file)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load<R: Read + Seek>(r: R) -> Result<Pyxel, PyxelError> {
+    load_with_options(r, &LoadOptions::default())
+}
+
+/// Load a Pyxel document from a reader using the options specified.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let file = File::open("resources/doc.pyxel")?;
+///
+/// let mut options = pyxel::LoadOptions::default();
+/// options.limits.max_decompressed_bytes = Some(64 * 1024 * 1024);
+/// options.limits.max_layers = Some(256);
+///
+/// let doc = pyxel::load_with_options(file, &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_with_options<R: Read + Seek>(r: R, options: &LoadOptions) -> Result<Pyxel, PyxelError> {
+    load_with_progress(r, options, |_| {})
+}
+
+/// Load a Pyxel document from a reader using the options specified, calling `progress` as each
+/// part of the document is loaded.
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let file = File::open("resources/doc.pyxel")?;
+///
+/// let doc = pyxel::load_with_progress(file, &pyxel::LoadOptions::default(), |event| {
+///     if let pyxel::LoadEvent::LoadedLayer(i, total) = event {
+///         println!("loaded layer {} of {}", i + 1, total);
+///     }
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_with_progress<R, F>(
+    r: R,
+    options: &LoadOptions,
+    progress: F,
+) -> Result<Pyxel, PyxelError>
+where
+    R: Read + Seek,
+    F: FnMut(LoadEvent),
+{
+    read_document(&mut zip::ZipArchive::new(r)?, options, progress)
+}
+
+/// Load a Pyxel document from the entries of `source` using the options specified.
+///
+/// This reads documents stored other than as a zip archive, see
+/// [`ArchiveSource`](trait.ArchiveSource.html). The same errors are returned as by
+/// [`load_with_options`](fn.load_with_options.html).
+///
+/// # Examples
+///
+/// ```
+/// use std::fs::File;
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let archive = zip::ZipArchive::new(File::open("resources/doc.pyxel")?)?;
+/// let doc = pyxel::load_from_source(archive, &pyxel::LoadOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_from_source<S: ArchiveSource>(
+    mut source: S,
+    options: &LoadOptions,
+) -> Result<Pyxel, PyxelError> {
+    read_document(&mut source, options, |_| {})
+}
+
+fn read_document<S, F>(
+    source: &mut S,
+    options: &LoadOptions,
+    mut progress: F,
+) -> Result<Pyxel, PyxelError>
+where
+    S: ArchiveSource,
+    F: FnMut(LoadEvent),
+{
+    let limits = &options.limits;
+    let budget = Budget::new(options);
+
+    let mut pyxel = read_doc_data(source, &budget, options)?;
+    progress(LoadEvent::ParsedDocData);
+
+    let (layer_size, tile_size) = image_sizes(&pyxel);
+    let skipped = skipped_layers(&pyxel, options);
+
+    let num_layers = pyxel.canvas.layers.len();
+    for (i, &skip) in skipped.iter().enumerate() {
+        if skip {
+            progress(LoadEvent::LoadedLayer(i, num_layers));
+            continue;
+        }
+
+        let name = layer_entry(i);
+        let image =
+            match substitute_missing(source, &name, layer_size, options, &budget, &mut pyxel)? {
+                Some(image) => image,
+                None => read_image(source, &budget, &name, limits)?,
+            };
+        set_layer_image(&mut pyxel, i, image);
+        progress(LoadEvent::LoadedLayer(i, num_layers));
+    }
+
+    let num_tiles = pyxel.tileset.num_tiles;
+    for i in 0..num_tiles {
+        let name = tile_entry(i);
+        let image =
+            match substitute_missing(source, &name, tile_size, options, &budget, &mut pyxel)? {
+                Some(image) => image,
+                None => read_image(source, &budget, &name, limits)?,
+            };
+        push_tile_image(&mut pyxel, image);
+        progress(LoadEvent::LoadedTile(i, num_tiles));
+    }
+
+    Ok(pyxel)
+}
+
+/// Reads the entries in `names` shared between workers through `next`, until every entry has been
+/// taken or one fails to read.
+fn read_images<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    names: &[String],
+    next: &AtomicUsize,
+    budget: &Budget,
+    limits: &Limits,
+) -> Vec<(usize, Result<EntryImage, PyxelError>)> {
+    let mut loaded = Vec::new();
+
+    loop {
+        let i = next.fetch_add(1, Ordering::Relaxed);
+        let name = match names.get(i) {
+            Some(name) => name,
+            None => break,
+        };
+
+        let result = read_image(archive, budget, name, limits);
+
+        // Stop every worker taking more work once an entry has failed.
+        if result.is_err() {
+            next.store(names.len(), Ordering::Relaxed);
+        }
+
+        loaded.push((i, result));
+    }
+
+    loaded
+}
+
+/// Load a Pyxel document from a reader using the options specified, reading and decoding the
+/// images within it on multiple threads.
+///
+/// The archive's central directory is only parsed once, then each thread reads entries through
+/// its own clone of `r`, so `r` must be cheap to clone and each clone must keep its own position,
+/// e.g. a `Cursor` over a byte slice or a memory map. The same errors are returned as by
+/// [`load_with_options`](fn.load_with_options.html), although which entry an error is reported
+/// for may differ when several entries are invalid.
+///
+/// # Examples
+///
+/// ```
+/// use std::{fs, io::Cursor};
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let buf = fs::read("resources/doc.pyxel")?;
+/// let doc = pyxel::load_parallel(Cursor::new(&buf[..]), &pyxel::LoadOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_parallel<R>(r: R, options: &LoadOptions) -> Result<Pyxel, PyxelError>
+where
+    R: Read + Seek + Clone + Send,
+{
+    let limits = &options.limits;
+
+    let mut archive = zip::ZipArchive::new(r)?;
+    let budget = Budget::new(options);
+
+    let mut pyxel = read_doc_data(&mut archive, &budget, options)?;
+
+    let (layer_size, tile_size) = image_sizes(&pyxel);
+    let skipped = skipped_layers(&pyxel, options);
+
+    let num_layers = pyxel.canvas.layers.len();
+    let entries: Vec<(String, (u32, u32))> = (0..num_layers)
+        .map(|i| (layer_entry(i), layer_size))
+        .chain((0..pyxel.tileset.num_tiles).map(|i| (tile_entry(i), tile_size)))
+        .collect();
+
+    let mut results: Vec<Option<Result<EntryImage, PyxelError>>> =
+        (0..entries.len()).map(|_| None).collect();
+
+    // Substitute any missing images up front, leaving the workers to read the rest. `slots` maps
+    // the index of each name to read back to its entry.
+    let mut names = Vec::new();
+    let mut slots = Vec::new();
+    for (i, (name, size)) in entries.into_iter().enumerate() {
+        if i < num_layers && skipped[i] {
+            continue;
+        }
+
+        match substitute_missing(&mut archive, &name, size, options, &budget, &mut pyxel)? {
+            Some(image) => results[i] = Some(Ok(image)),
+            None => {
+                names.push(name);
+                slots.push(i);
+            }
+        }
+    }
+
+    // Threads can't be spawned on the web, where the available parallelism is unknown.
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(names.len());
+    let next = AtomicUsize::new(0);
+
+    if threads <= 1 {
+        for (i, result) in read_images(&mut archive, &names, &next, &budget, limits) {
+            results[slots[i]] = Some(result);
+        }
+    } else {
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    let mut archive = archive.clone();
+                    let (names, next, budget) = (&names, &next, &budget);
+
+                    scope.spawn(move || read_images(&mut archive, names, next, budget, limits))
+                })
+                .collect();
+
+            for worker in workers {
+                let loaded = worker
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e));
+
+                for (i, result) in loaded {
+                    results[slots[i]] = Some(result);
+                }
+            }
+        });
+    }
+
+    // Entries after a failure may not have been read, so look for the first error before using
+    // any of the results.
+    if let Some(i) = results.iter().position(|r| matches!(r, Some(Err(_)))) {
+        if let Some(Err(e)) = results.swap_remove(i) {
+            return Err(e);
+        }
+    }
+
+    for (i, result) in results.into_iter().enumerate() {
+        let image = match result {
+            Some(result) => result.expect("errors were returned above"),
+            // Skipped layers are left without an image.
+            None => continue,
+        };
+
+        if i < num_layers {
+            set_layer_image(&mut pyxel, i, image);
+        } else {
+            push_tile_image(&mut pyxel, image);
+        }
+    }
+
+    Ok(pyxel)
+}
+
+/// A file shared between threads, each clone of which keeps its own position and reads without
+/// moving the others.
+#[cfg(any(unix, windows))]
+#[derive(Clone, Debug)]
+struct SharedFile {
+    file: Arc<File>,
+    pos: u64,
+}
+
+#[cfg(any(unix, windows))]
+impl Read for SharedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(unix)]
+        let read = std::os::unix::fs::FileExt::read_at(&*self.file, buf, self.pos)?;
+        // `seek_read` moves the file's cursor too, but no clone relies on it.
+        #[cfg(windows)]
+        let read = std::os::windows::fs::FileExt::seek_read(&*self.file, buf, self.pos)?;
+
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(any(unix, windows))]
+impl Seek for SharedFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            io::SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            io::SeekFrom::End(offset) => (self.file.metadata()?.len(), offset),
+            io::SeekFrom::Current(offset) => (self.pos, offset),
+        };
+
+        self.pos = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// Open the Pyxel document located at the path specified, reading and decoding the images within
+/// it on multiple threads.
+///
+/// Each thread streams its entries from the file with positioned reads, see
+/// [`load_parallel`](fn.load_parallel.html). On platforms without positioned reads the file is
+/// read into memory first.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let doc = pyxel::open_parallel("resources/doc.pyxel")?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub fn open_parallel<P>(path: P) -> Result<Pyxel, PyxelError>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(path)?;
+
+    #[cfg(any(unix, windows))]
+    let source = SharedFile {
+        file: Arc::new(file),
+        pos: 0,
+    };
+    #[cfg(not(any(unix, windows)))]
+    let source = {
+        let mut buf = Vec::new();
+        (&file).read_to_end(&mut buf)?;
+        io::Cursor::new(buf)
+    };
+
+    load_parallel(source, &LoadOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::File, io::Cursor};
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    fn load_with_limits(limits: Limits) -> Result<Pyxel, PyxelError> {
+        let file = File::open(TEST_FILE).unwrap();
+        load_with_options(
+            file,
+            &LoadOptions {
+                limits,
+                ..LoadOptions::default()
+            },
+        )
+    }
+
+    fn assert_limit_exceeded(result: Result<Pyxel, PyxelError>, expected: Limit) {
+        match result {
+            Err(PyxelError::LimitExceeded { limit, .. }) => assert_eq!(expected, limit),
+            Err(PyxelError::InEntry { source, .. }) => {
+                assert_limit_exceeded(Err(*source), expected)
+            }
+            other => panic!("expected {:?} to be exceeded, got {:?}", expected, other),
+        }
+    }
+
+    #[test]
+    fn generous_limits_load() {
+        let limits = Limits {
+            max_decompressed_bytes: Some(1024 * 1024),
+            max_layers: Some(11),
+            max_tiles: Some(4),
+            max_canvas_width: Some(256),
+            max_canvas_height: Some(128),
+        };
+        assert!(load_with_limits(limits).is_ok());
+    }
+
+    #[test]
+    fn progress_events() {
+        let file = File::open(TEST_FILE).unwrap();
+
+        let mut events = Vec::new();
+        load_with_progress(file, &LoadOptions::default(), |event| events.push(event)).unwrap();
+
+        let mut expected = vec![LoadEvent::ParsedDocData];
+        expected.extend((0..11).map(|i| LoadEvent::LoadedLayer(i, 11)));
+        expected.extend((0..4).map(|i| LoadEvent::LoadedTile(i, 4)));
+        assert_eq!(expected, events);
+    }
+
+    #[test]
+    fn cancel_stops_loading() {
+        let token = CancelToken::new();
+        let options = LoadOptions {
+            cancel: Some(token.clone()),
+            ..LoadOptions::default()
+        };
+
+        let mut events = Vec::new();
+        let result = load_with_progress(File::open(TEST_FILE).unwrap(), &options, |event| {
+            if event == LoadEvent::LoadedLayer(1, 11) {
+                token.cancel();
+            }
+            events.push(event);
+        });
+
+        assert!(matches!(result, Err(PyxelError::Cancelled)));
+        assert_eq!(Some(&LoadEvent::LoadedLayer(1, 11)), events.last());
+
+        let buf = std::fs::read(TEST_FILE).unwrap();
+        assert!(matches!(
+            load_parallel(Cursor::new(&buf[..]), &options),
+            Err(PyxelError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn parallel_load_matches_sequential_load() {
+        let buf = std::fs::read(TEST_FILE).unwrap();
+
+        let sequential = load_with_options(Cursor::new(&buf[..]), &LoadOptions::default()).unwrap();
+        let parallel = load_parallel(Cursor::new(&buf[..]), &LoadOptions::default()).unwrap();
+        assert!(sequential.eq_with_pixels(&parallel));
+    }
+
+    #[test]
+    fn parallel_open_streams_from_the_file() {
+        let sequential = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let parallel = open_parallel(TEST_FILE).unwrap();
+        assert!(sequential.eq_with_pixels(&parallel));
+    }
+
+    #[test]
+    fn lenient_loads_substitute_missing_images() {
+        let buf = std::fs::read(TEST_FILE).unwrap();
+        let mut out = Cursor::new(Vec::new());
+        {
+            let mut archive = zip::ZipArchive::new(Cursor::new(&buf[..])).unwrap();
+            let mut writer = zip::ZipWriter::new(&mut out);
+            for i in 0..archive.len() {
+                let entry = archive.by_index_raw(i).unwrap();
+                if entry.name() != "layer3.png" && entry.name() != "tile1.png" {
+                    writer.raw_copy_file(entry).unwrap();
+                }
+            }
+            writer.finish().unwrap();
+        }
+        let buf = out.into_inner();
+
+        match load(Cursor::new(&buf[..])) {
+            Err(PyxelError::InEntry { ref name, .. }) => assert_eq!("layer3.png", name),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let options = LoadOptions {
+            lenient: true,
+            ..LoadOptions::default()
+        };
+        let doc = load_with_options(Cursor::new(&buf[..]), &options).unwrap();
+        assert_eq!(
+            &[
+                LoadWarning::MissingImage("layer3.png".to_owned()),
+                LoadWarning::MissingImage("tile1.png".to_owned())
+            ],
+            doc.load_warnings()
+        );
+        assert_eq!(4, doc.tileset.tile_count());
+
+        #[cfg(feature = "images")]
+        {
+            use image::GenericImageView;
+
+            let image = doc.canvas.layers[3].image();
+            assert_eq!((256, 128), image.dimensions());
+            assert!(image.to_rgba().pixels().all(|p| p.0 == [0; 4]));
+            assert_eq!((32, 16), doc.tileset.images()[1].dimensions());
+        }
+
+        #[cfg(not(feature = "images"))]
+        assert_eq!(
+            (256, 128),
+            crate::png::png_dimensions(&doc.canvas.layers[3].image_data).unwrap()
+        );
+
+        let parallel = load_parallel(Cursor::new(&buf[..]), &options).unwrap();
+        assert!(doc.eq_with_pixels(&parallel));
+    }
+
+    #[test]
+    fn substituted_images_are_charged_to_the_limits() {
+        let buf = std::fs::read(TEST_FILE).unwrap();
+        let mut out = Cursor::new(Vec::new());
+        let mut archived = 0;
+        {
+            let mut archive = zip::ZipArchive::new(Cursor::new(&buf[..])).unwrap();
+            let mut writer = zip::ZipWriter::new(&mut out);
+            for i in 0..archive.len() {
+                let entry = archive.by_index_raw(i).unwrap();
+                if entry.name() != "layer3.png" {
+                    archived += entry.size();
+                    writer.raw_copy_file(entry).unwrap();
+                }
+            }
+            writer.finish().unwrap();
+        }
+        let buf = out.into_inner();
+
+        // Everything in the archive fits, but not the 256 × 128 placeholder as well.
+        let lenient = |limits| LoadOptions {
+            lenient: true,
+            limits,
+            ..LoadOptions::default()
+        };
+        let options = lenient(Limits {
+            max_decompressed_bytes: Some(archived + 256 * 128 * 4 - 1),
+            ..Limits::default()
+        });
+        assert_limit_exceeded(
+            load_with_options(Cursor::new(&buf[..]), &options),
+            Limit::DecompressedBytes,
+        );
+
+        let options = lenient(Limits {
+            max_decompressed_bytes: Some(archived + 256 * 128 * 4),
+            ..Limits::default()
+        });
+        assert!(load_with_options(Cursor::new(&buf[..]), &options).is_ok());
+    }
+
+    #[test]
+    fn invisible_layers_can_be_skipped() {
+        let options = LoadOptions {
+            skip_invisible_layers: true,
+            ..LoadOptions::default()
+        };
+        let doc = load_with_options(File::open(TEST_FILE).unwrap(), &options).unwrap();
+        let expected = load(File::open(TEST_FILE).unwrap()).unwrap();
+
+        // Only the soloed layer is shown.
+        assert_eq!(expected.canvas, doc.canvas);
+        assert!(doc.canvas.layers[1].eq_with_pixels(&expected.canvas.layers[1]));
+        for i in (0..11).filter(|&i| i != 1) {
+            assert_ne!((256, 128), doc.canvas.layers[i].dimensions());
+        }
+        assert!(expected.tileset.eq_with_pixels(&doc.tileset));
+
+        let buf = std::fs::read(TEST_FILE).unwrap();
+        let parallel = load_parallel(Cursor::new(&buf[..]), &options).unwrap();
+        assert!(doc.eq_with_pixels(&parallel));
+    }
+
+    #[test]
+    fn rezipped_entries_are_found() {
+        use std::io::Write;
+
+        let buf = std::fs::read(TEST_FILE).unwrap();
+        let mut out = Cursor::new(Vec::new());
+        {
+            let mut archive = zip::ZipArchive::new(Cursor::new(&buf[..])).unwrap();
+            let mut writer = zip::ZipWriter::new(&mut out);
+            writer
+                .start_file("__MACOSX/test/._docData.json", Default::default())
+                .unwrap();
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i).unwrap();
+                let name = match entry.name() {
+                    "layer0.png" => "test/Layer0.PNG".to_owned(),
+                    name => format!("test/{}", name),
+                };
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).unwrap();
+
+                writer.start_file(name, Default::default()).unwrap();
+                writer.write_all(&data).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        let buf = out.into_inner();
+
+        let expected = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = LoadOptions {
+            keep_extra_entries: true,
+            ..LoadOptions::default()
+        };
+        let doc = load_with_options(Cursor::new(&buf[..]), &options).unwrap();
+        let extra: Vec<&str> = doc.extra_entries().iter().map(|e| e.name()).collect();
+        assert_eq!(vec!["__MACOSX/test/._docData.json"], extra);
+
+        let parallel = load_parallel(Cursor::new(&buf[..]), &options).unwrap();
+        assert_eq!(doc.extra_entries(), parallel.extra_entries());
+        assert!(doc.eq_with_pixels(&parallel));
+        assert!(expected.eq_with_pixels(&doc));
+    }
+
+    #[test]
+    fn parallel_load_enforces_limits() {
+        let buf = std::fs::read(TEST_FILE).unwrap();
+        let options = LoadOptions {
+            limits: Limits {
+                max_decompressed_bytes: Some(6000),
+                ..Limits::default()
+            },
+            ..LoadOptions::default()
+        };
+
+        assert_limit_exceeded(
+            load_parallel(Cursor::new(&buf[..]), &options),
+            Limit::DecompressedBytes,
+        );
+    }
+
+    #[test]
+    fn layers_limit() {
+        let limits = Limits {
+            max_layers: Some(10),
+            ..Limits::default()
+        };
+        assert_limit_exceeded(load_with_limits(limits), Limit::Layers);
+    }
+
+    #[test]
+    fn layers_limit_is_checked_before_deserializing() {
+        // The layers aren't valid, so deserializing them would fail with a different error.
+        let buf = br#"{
+            "version": "0.4.8",
+            "canvas": {
+                "layers": {"0": 0, "1": 0},
+                "numLayers": 1,
+                "width": 8,
+                "height": 8
+            },
+            "tileset": {"numTiles": 0}
+        }"#;
+        let options = LoadOptions {
+            limits: Limits {
+                max_layers: Some(1),
+                ..Limits::default()
+            },
+            ..LoadOptions::default()
+        };
+        assert_limit_exceeded(parse_doc_data(buf, &options), Limit::Layers);
+    }
+
+    #[test]
+    fn tiles_limit() {
+        let limits = Limits {
+            max_tiles: Some(3),
+            ..Limits::default()
+        };
+        assert_limit_exceeded(load_with_limits(limits), Limit::Tiles);
+    }
+
+    #[test]
+    fn canvas_limits() {
+        let limits = Limits {
+            max_canvas_width: Some(255),
+            ..Limits::default()
+        };
+        assert_limit_exceeded(load_with_limits(limits), Limit::CanvasWidth);
+
+        let limits = Limits {
+            max_canvas_height: Some(127),
+            ..Limits::default()
+        };
+        assert_limit_exceeded(load_with_limits(limits), Limit::CanvasHeight);
+    }
+
+    #[test]
+    fn decompressed_bytes_limit() {
+        // docData.json alone is larger than this.
+        let limits = Limits {
+            max_decompressed_bytes: Some(4096),
+            ..Limits::default()
+        };
+        match load_with_limits(limits) {
+            Err(PyxelError::InEntry { ref name, .. }) => assert_eq!(DOC_DATA, name),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // Enough for docData.json but not every image.
+        let limits = Limits {
+            max_decompressed_bytes: Some(6000),
+            ..Limits::default()
+        };
+        assert_limit_exceeded(load_with_limits(limits), Limit::DecompressedBytes);
+    }
+}
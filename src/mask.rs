@@ -0,0 +1,135 @@
+use crate::pyxel::Layer;
+
+/// A 1-bit mask of the pixels of a layer, produced by
+/// [`Layer::alpha_mask`](struct.Layer.html#method.alpha_mask).
+///
+/// The bits are packed row by row, most significant bit first, with each row padded to a whole
+/// number of bytes as 1-bit PNG and BMP images and most stencil formats expect.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AlphaMask {
+    width: u32,
+    height: u32,
+    bits: Vec<u8>,
+}
+
+impl AlphaMask {
+    /// Returns the width of this mask in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of this mask in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the number of bytes each row of this mask is packed into.
+    pub fn stride(&self) -> usize {
+        (self.width as usize).div_ceil(8)
+    }
+
+    /// Returns `true` if the pixel at `x`, `y` is set. Pixels outside of the mask aren't set.
+    pub fn is_set(&self, x: u32, y: u32) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+
+        let byte = self.bits[y as usize * self.stride() + x as usize / 8];
+        byte & (0x80 >> (x % 8)) != 0
+    }
+
+    /// Returns the number of pixels that are set.
+    pub fn count(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the packed bits of this mask, [`stride`](#method.stride) bytes per row.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits
+    }
+
+    /// Returns whether each pixel is set, row by row.
+    pub fn to_bools(&self) -> Vec<bool> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.is_set(x, y))
+            .collect()
+    }
+}
+
+impl Layer {
+    /// Returns a mask of the pixels of this layer with an alpha greater than `threshold`, so a
+    /// `threshold` of 0 includes every pixel that isn't fully transparent.
+    ///
+    /// The alpha and visibility of the layer itself aren't taken into account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let mask = doc.canvas().layers()[0].alpha_mask(127);
+    /// println!("{} of the pixels are mostly opaque", mask.count());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn alpha_mask(&self, threshold: u8) -> AlphaMask {
+        let converted;
+        let image = match self.image.as_rgba8() {
+            Some(image) => image,
+            None => {
+                converted = self.image.to_rgba();
+                &converted
+            }
+        };
+
+        let (width, height) = image.dimensions();
+        let stride = (width as usize).div_ceil(8);
+        let mut bits = vec![0; stride * height as usize];
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if pixel[3] > threshold {
+                bits[y as usize * stride + x as usize / 8] |= 0x80 >> (x % 8);
+            }
+        }
+
+        AlphaMask {
+            width,
+            height,
+            bits,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::load::load;
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use std::{fs::File, sync::Arc};
+
+    #[test]
+    fn masks_pixels_above_threshold() {
+        let mut image = RgbaImage::new(10, 2);
+        image.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+        image.put_pixel(9, 0, Rgba([0, 0, 0, 128]));
+        image.put_pixel(3, 1, Rgba([0, 0, 0, 1]));
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let mut layer = doc.canvas().layers()[0].clone();
+        layer.image = Arc::new(DynamicImage::ImageRgba8(image));
+
+        let mask = layer.alpha_mask(0);
+        assert_eq!(3, mask.count());
+        assert_eq!(2, mask.stride());
+        assert_eq!(&[0x80, 0x40, 0x10, 0], mask.as_bytes());
+        assert!(mask.is_set(9, 0) && !mask.is_set(10, 0));
+
+        let mask = layer.alpha_mask(128);
+        assert_eq!(1, mask.count());
+        let bools = mask.to_bools();
+        assert_eq!(20, bools.len());
+        assert!(bools[0]);
+        assert_eq!(1, bools.iter().filter(|&&set| set).count());
+    }
+}
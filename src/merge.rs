@@ -0,0 +1,802 @@
+#[cfg(feature = "images")]
+use crate::pyxel::Canvas;
+use crate::{
+    diff::match_by_name,
+    error::PyxelError,
+    pyxel::{BlendMode, Color, Layer, Pyxel, TileRef},
+};
+
+/// A change made differently by each side of a [`merge`](fn.merge.html). The merged document
+/// keeps the version from `ours` wherever there's a conflict.
+///
+/// Layers are identified by their name in `ours`, or in `theirs` for layers only `theirs` kept.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum MergeConflict {
+    /// A layer was removed by one side and changed by the other.
+    LayerRemoved {
+        /// The name of the layer.
+        name: String,
+    },
+    /// Both sides added a layer with this name, with different contents.
+    LayerAdded {
+        /// The name of the layer.
+        name: String,
+    },
+    /// The name, alpha, blend mode or visibility flags of a layer were changed by both sides.
+    LayerProperties {
+        /// The name of the layer.
+        layer: String,
+    },
+    /// The tile ref in a canvas cell of a layer was changed by both sides.
+    TileRef {
+        /// The name of the layer.
+        layer: String,
+        /// The canvas cell.
+        cell: usize,
+        /// The tile ref in `ours`, or `None` if the cell is empty.
+        ours: Option<TileRef>,
+        /// The tile ref in `theirs`, or `None` if the cell is empty.
+        theirs: Option<TileRef>,
+    },
+    /// The pixels of a layer were changed by both sides.
+    LayerPixels {
+        /// The name of the layer.
+        layer: String,
+    },
+    /// An animation was removed by one side and changed by the other.
+    AnimationRemoved {
+        /// The name of the animation.
+        name: String,
+    },
+    /// Both sides added an animation with this name, with different frames or timing.
+    AnimationAdded {
+        /// The name of the animation.
+        name: String,
+    },
+    /// The frames or timing of an animation were changed by both sides.
+    Animation {
+        /// The name of the animation in `ours`.
+        name: String,
+    },
+    /// A palette slot was changed by both sides.
+    PaletteSlot {
+        /// The index of the palette slot.
+        index: usize,
+        /// The color in `ours`, or `None` if the slot is empty or missing.
+        ours: Option<Color>,
+        /// The color in `theirs`, or `None` if the slot is empty or missing.
+        theirs: Option<Color>,
+    },
+    /// The tiles of the tileset were changed by both sides.
+    Tileset,
+}
+
+/// The result of a [`merge`](fn.merge.html).
+#[derive(Clone, Debug)]
+pub struct DocumentMerge {
+    /// The merged document.
+    pub merged: Pyxel,
+    /// The changes made differently by each side, in the order they were found.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl DocumentMerge {
+    /// Returns `true` if the sides were merged without any conflicts.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Merges `value` three ways, returning `None` if each side changed it differently.
+fn merge_value<T: Clone + PartialEq>(base: &T, ours: &T, theirs: &T) -> Option<T> {
+    if ours == theirs || theirs == base {
+        Some(ours.clone())
+    } else if ours == base {
+        Some(theirs.clone())
+    } else {
+        None
+    }
+}
+
+fn layer_properties(layer: &Layer) -> (String, u8, BlendMode, bool, bool, bool) {
+    (
+        layer.name.clone(),
+        layer.alpha,
+        layer.blend_mode,
+        layer.hidden,
+        layer.muted,
+        layer.soloed,
+    )
+}
+
+fn layer_changed(base: &Layer, layer: &Layer) -> bool {
+    !base.eq_with_pixels(layer)
+}
+
+/// Merges the changes `theirs` made to the layer `base` into `merged`, which starts out as the
+/// layer in `ours`.
+fn merge_layer(
+    base: &Layer,
+    ours: &Layer,
+    theirs: &Layer,
+    merged: &mut Layer,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    match merge_value(
+        &layer_properties(base),
+        &layer_properties(ours),
+        &layer_properties(theirs),
+    ) {
+        Some((name, alpha, blend_mode, hidden, muted, soloed)) => {
+            merged.name = name;
+            merged.alpha = alpha;
+            merged.blend_mode = blend_mode;
+            merged.hidden = hidden;
+            merged.muted = muted;
+            merged.soloed = soloed;
+        }
+        None => conflicts.push(MergeConflict::LayerProperties {
+            layer: ours.name.clone(),
+        }),
+    }
+
+    let mut cells: Vec<usize> = base
+        .tile_refs
+        .keys()
+        .chain(ours.tile_refs.keys())
+        .chain(theirs.tile_refs.keys())
+        .copied()
+        .collect();
+    cells.sort_unstable();
+    cells.dedup();
+
+    let layer = &ours.name;
+    for cell in cells {
+        let (ours, theirs) = (
+            ours.tile_refs.get(&cell).copied(),
+            theirs.tile_refs.get(&cell).copied(),
+        );
+        match merge_value(&base.tile_refs.get(&cell).copied(), &ours, &theirs) {
+            Some(Some(tile_ref)) => {
+                merged.tile_refs.insert(cell, tile_ref);
+            }
+            Some(None) => {
+                merged.tile_refs.remove(&cell);
+            }
+            None => conflicts.push(MergeConflict::TileRef {
+                layer: layer.clone(),
+                cell,
+                ours,
+                theirs,
+            }),
+        }
+    }
+
+    if ours.pixels_eq(theirs) || theirs.pixels_eq(base) {
+        return;
+    }
+
+    if ours.pixels_eq(base) {
+        #[cfg(not(feature = "images"))]
+        {
+            merged.image_data = theirs.image_data.clone();
+        }
+
+        #[cfg(feature = "images")]
+        {
+            merged.image = theirs.image.clone();
+        }
+    } else {
+        conflicts.push(MergeConflict::LayerPixels {
+            layer: layer.clone(),
+        });
+    }
+}
+
+/// Returns the index of the matching layer of `doc` for each layer of `base`, and the indices of
+/// the layers `doc` added.
+fn match_layers(base: &Pyxel, doc: &Pyxel) -> (Vec<Option<usize>>, Vec<usize>) {
+    matches(
+        &base
+            .canvas
+            .layers
+            .iter()
+            .map(|l| l.name.as_str())
+            .collect::<Vec<_>>(),
+        &doc.canvas
+            .layers
+            .iter()
+            .map(|l| l.name.as_str())
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn match_animations(base: &Pyxel, doc: &Pyxel) -> (Vec<Option<usize>>, Vec<usize>) {
+    matches(
+        &base
+            .animations
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<_>>(),
+        &doc.animations
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Matches up `base_names` with `names` as [`diff`](fn.diff.html) does, returning the index in
+/// `names` of each of `base_names` and the indices of the names that were added.
+fn matches(base_names: &[&str], names: &[&str]) -> (Vec<Option<usize>>, Vec<usize>) {
+    let (pairs, _, added) = match_by_name(base_names, names);
+
+    let mut matched = vec![None; base_names.len()];
+    for (i, j) in pairs {
+        matched[i] = Some(j);
+    }
+    (matched, added)
+}
+
+/// Merges the changes made to `base` by `ours` and by `theirs`, as a three-way merge of their
+/// layers, tile refs, palette, animations and tileset.
+///
+/// Layers and animations are matched up with those of `base` as by [`diff`](fn.diff.html), then
+/// merged one part at a time: a part changed by only one side takes that side's version, and a
+/// part changed by both sides in different ways is reported as a
+/// [`MergeConflict`](enum.MergeConflict.html). Tile refs are merged cell by cell and palettes slot
+/// by slot, while the pixels of a layer, an animation and the tileset are each merged as a
+/// whole. Layers are kept in the order of `ours`, with the layers `theirs` added inserted at their
+/// index in `theirs`. Everything else, such as the document's name and settings, is taken from
+/// `ours`.
+///
+/// The documents must have the same canvas and tile size.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let base = pyxel::open("resources/doc.pyxel")?;
+/// let ours = pyxel::open("resources/doc.pyxel")?;
+/// let theirs = pyxel::open("resources/doc.pyxel")?;
+/// let merge = pyxel::merge(&base, &ours, &theirs)?;
+/// assert!(merge.is_clean());
+/// # Ok(())
+/// # }
+/// ```
+pub fn merge(base: &Pyxel, ours: &Pyxel, theirs: &Pyxel) -> Result<DocumentMerge, PyxelError> {
+    for doc in &[ours, theirs] {
+        let (canvas, tileset) = (&doc.canvas, &doc.tileset);
+        if (canvas.tile_width, canvas.tile_height)
+            != (base.canvas.tile_width, base.canvas.tile_height)
+            || (tileset.tile_width, tileset.tile_height)
+                != (base.tileset.tile_width, base.tileset.tile_height)
+        {
+            return Err(PyxelError::TileSizeMismatch {
+                width: canvas.tile_width,
+                height: canvas.tile_height,
+            });
+        }
+        if (canvas.width, canvas.height) != (base.canvas.width, base.canvas.height) {
+            return Err(PyxelError::ImageSizeMismatch {
+                width: canvas.width.max(0) as u32,
+                height: canvas.height.max(0) as u32,
+            });
+        }
+    }
+
+    let mut merged = ours.clone();
+    let mut conflicts = Vec::new();
+
+    merge_layers(base, ours, theirs, &mut merged, &mut conflicts);
+    merge_animations(base, ours, theirs, &mut merged, &mut conflicts);
+
+    let (base_colors, ours_colors, theirs_colors) = (
+        &base.palette.colors,
+        &ours.palette.colors,
+        &theirs.palette.colors,
+    );
+    let len = ours_colors.len().max(theirs_colors.len());
+    merged.palette.colors.resize(len, None);
+    merged.palette.num_colors = len;
+    for index in 0..len {
+        let color = |colors: &[Option<Color>]| colors.get(index).copied().flatten();
+        let (ours, theirs) = (color(ours_colors), color(theirs_colors));
+        match merge_value(&color(base_colors), &ours, &theirs) {
+            Some(color) => merged.palette.colors[index] = color,
+            None => conflicts.push(MergeConflict::PaletteSlot {
+                index,
+                ours,
+                theirs,
+            }),
+        }
+    }
+
+    if !ours.tileset.eq_with_pixels(&theirs.tileset)
+        && !theirs.tileset.eq_with_pixels(&base.tileset)
+    {
+        if ours.tileset.eq_with_pixels(&base.tileset) {
+            merged.tileset = theirs.tileset.clone();
+        } else {
+            conflicts.push(MergeConflict::Tileset);
+        }
+    }
+
+    Ok(DocumentMerge { merged, conflicts })
+}
+
+fn merge_layers(
+    base: &Pyxel,
+    ours: &Pyxel,
+    theirs: &Pyxel,
+    merged: &mut Pyxel,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    let (base_layers, ours_layers, theirs_layers) = (
+        &base.canvas.layers,
+        &ours.canvas.layers,
+        &theirs.canvas.layers,
+    );
+    let (ours_matched, ours_added) = match_layers(base, ours);
+    let (theirs_matched, theirs_added) = match_layers(base, theirs);
+
+    let mut removed = Vec::new();
+    for (i, base_layer) in base_layers.iter().enumerate() {
+        match (ours_matched[i], theirs_matched[i]) {
+            (Some(o), Some(t)) => merge_layer(
+                base_layer,
+                &ours_layers[o],
+                &theirs_layers[t],
+                &mut merged.canvas.layers[o],
+                conflicts,
+            ),
+            (Some(o), None) if layer_changed(base_layer, &ours_layers[o]) => {
+                conflicts.push(MergeConflict::LayerRemoved {
+                    name: ours_layers[o].name.clone(),
+                })
+            }
+            (Some(o), None) => removed.push(o),
+            (None, Some(t)) if layer_changed(base_layer, &theirs_layers[t]) => {
+                conflicts.push(MergeConflict::LayerRemoved {
+                    name: theirs_layers[t].name.clone(),
+                })
+            }
+            (None, _) => {}
+        }
+    }
+
+    removed.sort_unstable();
+    for &o in removed.iter().rev() {
+        merged.canvas.layers.remove(o);
+    }
+
+    for j in theirs_added {
+        let layer = &theirs_layers[j];
+        let existing = ours_added
+            .iter()
+            .map(|&o| &ours_layers[o])
+            .find(|ours| ours.name == layer.name);
+
+        match existing {
+            Some(ours) if !ours.eq_with_pixels(layer) => {
+                conflicts.push(MergeConflict::LayerAdded {
+                    name: layer.name.clone(),
+                })
+            }
+            Some(_) => {}
+            None => {
+                let index = j.min(merged.canvas.layers.len());
+                merged.canvas.layers.insert(index, layer.clone());
+            }
+        }
+    }
+
+    merged.canvas.num_layers = merged.canvas.layers.len();
+}
+
+fn merge_animations(
+    base: &Pyxel,
+    ours: &Pyxel,
+    theirs: &Pyxel,
+    merged: &mut Pyxel,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    let (ours_matched, ours_added) = match_animations(base, ours);
+    let (theirs_matched, theirs_added) = match_animations(base, theirs);
+
+    let mut removed = Vec::new();
+    for (i, base_animation) in base.animations.iter().enumerate() {
+        match (ours_matched[i], theirs_matched[i]) {
+            (Some(o), Some(t)) => {
+                let (ours, theirs) = (&ours.animations[o], &theirs.animations[t]);
+                match merge_value(base_animation, ours, theirs) {
+                    Some(animation) => merged.animations[o] = animation,
+                    None => conflicts.push(MergeConflict::Animation {
+                        name: ours.name.clone(),
+                    }),
+                }
+            }
+            (Some(o), None) if ours.animations[o] != *base_animation => {
+                conflicts.push(MergeConflict::AnimationRemoved {
+                    name: ours.animations[o].name.clone(),
+                })
+            }
+            (Some(o), None) => removed.push(o),
+            (None, Some(t)) if theirs.animations[t] != *base_animation => {
+                conflicts.push(MergeConflict::AnimationRemoved {
+                    name: theirs.animations[t].name.clone(),
+                })
+            }
+            (None, _) => {}
+        }
+    }
+
+    removed.sort_unstable();
+    for &o in removed.iter().rev() {
+        merged.animations.remove(o);
+    }
+
+    for j in theirs_added {
+        let animation = &theirs.animations[j];
+        let existing = ours_added
+            .iter()
+            .map(|&o| &ours.animations[o])
+            .find(|ours| ours.name == animation.name);
+
+        match existing {
+            Some(ours) if ours != animation => conflicts.push(MergeConflict::AnimationAdded {
+                name: animation.name.clone(),
+            }),
+            Some(_) => {}
+            None => merged.animations.push(animation.clone()),
+        }
+    }
+}
+
+impl Pyxel {
+    /// Appends the tiles of `other`'s tileset to this document's tileset, returning the index the
+    /// first of them was given.
+    ///
+    /// The tilesets must have the same tile size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// let other = pyxel::open("resources/doc.pyxel")?;
+    /// assert_eq!(1, doc.import_tileset(&other)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn import_tileset(&mut self, other: &Pyxel) -> Result<usize, PyxelError> {
+        let (tileset, source) = (&mut self.tileset, &other.tileset);
+        if (tileset.tile_width, tileset.tile_height) != (source.tile_width, source.tile_height) {
+            return Err(PyxelError::TileSizeMismatch {
+                width: source.tile_width,
+                height: source.tile_height,
+            });
+        }
+
+        let offset = tileset.tile_count();
+
+        #[cfg(not(feature = "images"))]
+        tileset.image_data.extend(source.image_data.iter().cloned());
+
+        #[cfg(feature = "images")]
+        tileset.images.extend(source.images.iter().cloned());
+
+        tileset.num_tiles = tileset.tile_count();
+        Ok(offset)
+    }
+
+    /// Appends the animations of `other` to this document, along with the tiles of its tileset.
+    ///
+    /// The canvas cells of each imported animation are copied after the last cell used by this
+    /// document's animations, layer by layer, with the tile indices of their tile refs remapped to
+    /// the imported tiles. With the `images` feature the pixels of the cells are copied as well.
+    /// Layers only present in one of the documents are skipped.
+    ///
+    /// The canvases must have the same tile size and this canvas must have room for every
+    /// imported frame. Nothing is modified if either isn't the case.
+    pub fn import_animations(&mut self, other: &Pyxel) -> Result<(), PyxelError> {
+        let (tile_width, tile_height) = (other.canvas.tile_width, other.canvas.tile_height);
+        if (self.canvas.tile_width, self.canvas.tile_height) != (tile_width, tile_height) {
+            return Err(PyxelError::TileSizeMismatch {
+                width: tile_width,
+                height: tile_height,
+            });
+        }
+
+        let first_cell = self
+            .animations
+            .iter()
+            .map(|animation| animation.base_tile + animation.length)
+            .max()
+            .unwrap_or(0);
+        let frames: usize = other.animations.iter().map(|a| a.length).sum();
+
+        let (tiles_wide, num_cells) = (
+            self.canvas.tiles_wide(),
+            self.canvas.tiles_wide() * self.canvas.tiles_high(),
+        );
+        if first_cell + frames > num_cells {
+            let cell = first_cell + frames - 1;
+            let tiles_wide = tiles_wide.max(1);
+            return Err(PyxelError::CellOutOfBounds {
+                col: cell % tiles_wide,
+                row: cell / tiles_wide,
+            });
+        }
+
+        let source_cells = other.canvas.tiles_wide() * other.canvas.tiles_high();
+        if let Some(animation) = other
+            .animations
+            .iter()
+            .find(|a| a.base_tile + a.length > source_cells)
+        {
+            let cell = animation.base_tile + animation.length - 1;
+            let tiles_wide = other.canvas.tiles_wide().max(1);
+            return Err(PyxelError::CellOutOfBounds {
+                col: cell % tiles_wide,
+                row: cell / tiles_wide,
+            });
+        }
+
+        let offset = self.import_tileset(other)?;
+
+        let mut cell = first_cell;
+        for animation in &other.animations {
+            let mut imported = animation.clone();
+            imported.base_tile = cell;
+
+            for frame in 0..animation.length {
+                self.copy_cell(other, animation.base_tile + frame, cell, offset);
+                cell += 1;
+            }
+
+            self.animations.push(imported);
+        }
+
+        Ok(())
+    }
+
+    /// Copies the contents of canvas cell `from` of `other` to canvas cell `to`, adding `offset`
+    /// to the tile indices of the copied tile refs.
+    fn copy_cell(&mut self, other: &Pyxel, from: usize, to: usize, offset: usize) {
+        #[cfg(feature = "images")]
+        let (width, height, (source_x, source_y), (target_x, target_y)) = (
+            u32::from(self.canvas.tile_width),
+            u32::from(self.canvas.tile_height),
+            cell_origin(&other.canvas, from),
+            cell_origin(&self.canvas, to),
+        );
+
+        for (target, source) in self.canvas.layers.iter_mut().zip(&other.canvas.layers) {
+            match source.tile_refs.get(&from) {
+                Some(tile_ref) => {
+                    let mut tile_ref = *tile_ref;
+                    tile_ref.index += offset;
+                    target.tile_refs.insert(to, tile_ref);
+                }
+                None => {
+                    target.tile_refs.remove(&to);
+                }
+            }
+
+            #[cfg(feature = "images")]
+            {
+                use image::{imageops, GenericImageView};
+
+                let fits = |image: &image::DynamicImage, x: u32, y: u32| {
+                    x + width <= image.width() && y + height <= image.height()
+                };
+
+                if fits(&source.image, source_x, source_y)
+                    && fits(&target.image, target_x, target_y)
+                {
+                    let pixels = source
+                        .image
+                        .view(source_x, source_y, width, height)
+                        .to_image();
+                    imageops::replace(target.image_mut(), &pixels, target_x, target_y);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "images")]
+fn cell_origin(canvas: &Canvas, cell: usize) -> (u32, u32) {
+    let tiles_wide = canvas.tiles_wide().max(1);
+    (
+        (cell % tiles_wide) as u32 * u32::from(canvas.tile_width),
+        (cell / tiles_wide) as u32 * u32::from(canvas.tile_height),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    fn load_test_file() -> Pyxel {
+        load(File::open(TEST_FILE).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn import_tileset_appends_tiles() {
+        let mut doc = load_test_file();
+        let other = load_test_file();
+
+        assert_eq!(4, doc.import_tileset(&other).unwrap());
+        assert_eq!(8, doc.tileset.num_tiles);
+        assert_eq!(8, doc.tileset.tile_count());
+    }
+
+    #[test]
+    fn import_tileset_requires_matching_tile_size() {
+        let mut doc = load_test_file();
+        let mut other = load_test_file();
+        other.tileset.tile_width = 16;
+
+        match doc.import_tileset(&other) {
+            Err(PyxelError::TileSizeMismatch {
+                width: 16,
+                height: 16,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn import_animations_copies_cells() {
+        let mut doc = load_test_file();
+        doc.animations.truncate(1);
+
+        let mut other = load_test_file();
+        other.animations.drain(..2);
+
+        doc.import_animations(&other).unwrap();
+
+        assert_eq!(2, doc.animations.len());
+        let imported = &doc.animations[1];
+        assert_eq!("Animation 3", imported.name);
+        assert_eq!(4, imported.base_tile);
+        assert_eq!(8, doc.tileset.num_tiles);
+
+        for layer in 0..other.canvas.layers.len() {
+            for frame in 0..2 {
+                let source = other.canvas.layers[layer].tile_refs.get(&(6 + frame));
+                let target = doc.canvas.layers[layer].tile_refs.get(&(4 + frame));
+                assert_eq!(source.map(|t| t.index + 4), target.map(|t| t.index));
+            }
+        }
+
+        #[cfg(feature = "images")]
+        {
+            let source = other.canvas.render_frame(&other.animations[0], 1).unwrap();
+            let target = doc.canvas.render_frame(imported, 1).unwrap();
+            assert_eq!(source.into_raw(), target.into_raw());
+        }
+    }
+
+    #[test]
+    fn merge_combines_changes_from_both_sides() {
+        let base = load_test_file();
+        let mut ours = load_test_file();
+        let mut theirs = load_test_file();
+
+        ours.canvas.layers[0].alpha = 100;
+        ours.canvas.layers[1]
+            .tile_refs
+            .insert(56, TileRef::new(2, 0., false));
+        ours.canvas.layers.remove(4);
+        ours.palette.colors[3] = None;
+        ours.animations[0].length = 3;
+        ours.animations[0].frame_duration_multipliers.truncate(3);
+
+        theirs.canvas.layers[0]
+            .tile_refs
+            .insert(5, TileRef::new(1, 0., false));
+        theirs.canvas.layers[1]
+            .tile_refs
+            .insert(56, TileRef::new(3, 0., false));
+        let mut added = theirs.canvas.layers[9].clone();
+        added.name = "Layer 11".to_owned();
+        theirs.canvas.layers.insert(0, added);
+        theirs.palette.colors[4] = None;
+        theirs.animations[0].length = 2;
+        theirs.animations[0].frame_duration_multipliers.truncate(2);
+        theirs.animations[1].frame_duration_multipliers = vec![2., 2.];
+
+        let merge = merge(&base, &ours, &theirs).unwrap();
+        assert_eq!(
+            vec![
+                MergeConflict::TileRef {
+                    layer: "Layer 9".to_owned(),
+                    cell: 56,
+                    ours: Some(TileRef::new(2, 0., false)),
+                    theirs: Some(TileRef::new(3, 0., false)),
+                },
+                MergeConflict::Animation {
+                    name: "Animation 1".to_owned(),
+                },
+            ],
+            merge.conflicts
+        );
+
+        let merged = &merge.merged;
+        assert!(merged.validate().is_empty());
+        let names: Vec<&str> = merged.canvas.layers.iter().map(|l| l.name()).collect();
+        assert_eq!(
+            vec![
+                "Layer 11", "Layer 10", "Layer 9", "Layer 8", "Layer 7", "Layer 5", "Layer 4",
+                "Layer 3", "Layer 2", "Layer 1", "Layer 0"
+            ],
+            names
+        );
+        assert_eq!(11, merged.canvas.num_layers);
+        assert_eq!(100, merged.canvas.layers[1].alpha);
+        assert_eq!(
+            Some(&TileRef::new(1, 0., false)),
+            merged.canvas.layers[1].tile_refs.get(&5)
+        );
+        assert_eq!(
+            Some(&TileRef::new(2, 0., false)),
+            merged.canvas.layers[2].tile_refs.get(&56)
+        );
+        assert_eq!(&[None, None], &merged.palette.colors[3..5]);
+        assert_eq!(3, merged.animations[0].length);
+        assert_eq!(
+            vec![2., 2.],
+            merged.animations[1].frame_duration_multipliers
+        );
+    }
+
+    #[test]
+    fn merge_reports_changes_to_removed_layers() {
+        let base = load_test_file();
+        let mut ours = load_test_file();
+        let mut theirs = load_test_file();
+
+        ours.canvas.layers.remove(4);
+        theirs.canvas.layers[4].hidden = true;
+
+        let merge = merge(&base, &ours, &theirs).unwrap();
+        assert_eq!(
+            vec![MergeConflict::LayerRemoved {
+                name: "Layer 6".to_owned(),
+            }],
+            merge.conflicts
+        );
+        assert_eq!(10, merge.merged.canvas.layers.len());
+        assert!(merge.merged.validate().is_empty());
+        assert!(merge.merged.tileset.eq_with_pixels(&base.tileset));
+
+        theirs.canvas.tile_width = 16;
+        match super::merge(&base, &ours, &theirs) {
+            Err(PyxelError::TileSizeMismatch {
+                width: 16,
+                height: 16,
+            }) => {}
+            other => panic!("unexpected result {:?}", other.map(|m| m.conflicts)),
+        }
+    }
+
+    #[test]
+    fn import_animations_requires_room() {
+        let mut doc = load_test_file();
+        doc.animations[0].base_tile = 60;
+        let other = load_test_file();
+
+        match doc.import_animations(&other) {
+            Err(PyxelError::CellOutOfBounds { col: 7, row: 8 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(4, doc.tileset.num_tiles);
+    }
+}
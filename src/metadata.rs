@@ -0,0 +1,178 @@
+use crate::pyxel::Pyxel;
+#[cfg(feature = "archive")]
+use crate::{
+    error::PyxelError,
+    load::{find_entry, Budget},
+    source::ArchiveSource,
+};
+
+use serde_json::Value;
+#[cfg(feature = "archive")]
+use std::io::{Read, Seek, Write};
+#[cfg(feature = "archive")]
+use zip::write::FileOptions;
+
+/// The archive entry holding user metadata. PyxelEdit ignores entries it doesn't know about, so
+/// the metadata survives the document being edited and saved again.
+#[cfg(feature = "archive")]
+pub(crate) const USER_METADATA: &str = "meta.json";
+
+/// Reads and parses the user metadata of `source`, if it has any.
+#[cfg(feature = "archive")]
+pub(crate) fn read_user_metadata<S: ArchiveSource>(
+    source: &mut S,
+    budget: &Budget,
+) -> Result<Option<Value>, PyxelError> {
+    if find_entry(source, USER_METADATA).is_none() {
+        return Ok(None);
+    }
+
+    let buf = budget
+        .read_entry(source, USER_METADATA)
+        .map_err(|e| e.in_entry(USER_METADATA))?;
+    serde_json::from_slice(&buf)
+        .map(Some)
+        .map_err(|e| PyxelError::from(e).in_entry(USER_METADATA))
+}
+
+impl Pyxel {
+    /// Returns the user metadata stored in this document's `meta.json` entry, if it has one.
+    ///
+    /// The metadata is arbitrary JSON, left for pipelines to store their own data, such as export
+    /// settings or references to source files, alongside the document. Use
+    /// [`write_user_metadata`](fn.write_user_metadata.html) to store it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// assert!(doc.user_metadata().is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn user_metadata(&self) -> Option<&Value> {
+        self.user_metadata.as_ref()
+    }
+
+    /// Replaces the user metadata of this document. This only changes the loaded document, use
+    /// [`write_user_metadata`](fn.write_user_metadata.html) to store it in a file.
+    pub fn set_user_metadata(&mut self, metadata: Option<Value>) {
+        self.user_metadata = metadata;
+    }
+}
+
+/// Copies the Pyxel document in `source` to `dest`, replacing its user metadata with `metadata`,
+/// or removing it when `metadata` is `None`.
+///
+/// Every other entry of the archive is copied without being decompressed, so the document is
+/// otherwise unchanged and can still be opened by PyxelEdit.
+///
+/// # Examples
+///
+/// ```
+/// use std::{fs, io::Cursor};
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let buf = fs::read("resources/doc.pyxel")?;
+/// let metadata = serde_json::json!({ "export": { "scale": 2 } });
+///
+/// let mut out = Cursor::new(Vec::new());
+/// pyxel::write_user_metadata(Cursor::new(&buf[..]), &mut out, Some(&metadata))?;
+///
+/// let doc = pyxel::load_from_memory(out.get_ref())?;
+/// assert_eq!(Some(&metadata), doc.user_metadata());
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "archive")]
+pub fn write_user_metadata<R, W>(
+    source: R,
+    dest: W,
+    metadata: Option<&Value>,
+) -> Result<(), PyxelError>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let mut archive = zip::ZipArchive::new(source)?;
+    let mut writer = zip::ZipWriter::new(dest);
+
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        if entry.name() != USER_METADATA {
+            writer.raw_copy_file(entry)?;
+        }
+    }
+
+    if let Some(metadata) = metadata {
+        writer.start_file(USER_METADATA, FileOptions::default())?;
+        serde_json::to_writer_pretty(&mut writer, metadata)?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use serde_json::json;
+    use std::{fs, io::Cursor};
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    fn with_metadata(buf: &[u8], metadata: Option<&Value>) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        write_user_metadata(Cursor::new(buf), &mut out, metadata).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn metadata_round_trips() {
+        let buf = fs::read(TEST_FILE).unwrap();
+        let original = load(Cursor::new(&buf[..])).unwrap();
+        assert_eq!(None, original.user_metadata());
+
+        let metadata = json!({ "source": "hero.aseprite", "scale": 2 });
+        let written = with_metadata(&buf, Some(&metadata));
+        let doc = load(Cursor::new(&written[..])).unwrap();
+        assert_eq!(Some(&metadata), doc.user_metadata());
+
+        let mut expected = original.clone();
+        expected.set_user_metadata(Some(metadata.clone()));
+        assert_eq!(expected, doc);
+
+        let replaced = with_metadata(&written, Some(&json!([1, 2, 3])));
+        let doc = load(Cursor::new(&replaced[..])).unwrap();
+        assert_eq!(Some(&json!([1, 2, 3])), doc.user_metadata());
+
+        let removed = with_metadata(&replaced, None);
+        assert_eq!(original, load(Cursor::new(&removed[..])).unwrap());
+    }
+
+    #[test]
+    fn invalid_metadata_is_reported() {
+        let buf = fs::read(TEST_FILE).unwrap();
+        let mut out = Cursor::new(Vec::new());
+        {
+            let mut archive = zip::ZipArchive::new(Cursor::new(&buf[..])).unwrap();
+            let mut writer = zip::ZipWriter::new(&mut out);
+            for i in 0..archive.len() {
+                writer
+                    .raw_copy_file(archive.by_index_raw(i).unwrap())
+                    .unwrap();
+            }
+            writer
+                .start_file(USER_METADATA, FileOptions::default())
+                .unwrap();
+            writer.write_all(b"{ not json").unwrap();
+            writer.finish().unwrap();
+        }
+
+        match load(Cursor::new(out.get_ref())) {
+            Err(PyxelError::InEntry { ref name, .. }) => assert_eq!(USER_METADATA, name),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
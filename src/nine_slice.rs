@@ -0,0 +1,228 @@
+use crate::{
+    bounds::Rect,
+    pyxel::{Canvas, Tileset},
+};
+
+use image::{GenericImageView, RgbaImage};
+
+/// The widths of the borders of a nine-slice image in pixels.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Margins {
+    /// The width of the left border.
+    pub left: u32,
+    /// The height of the top border.
+    pub top: u32,
+    /// The width of the right border.
+    pub right: u32,
+    /// The height of the bottom border.
+    pub bottom: u32,
+}
+
+impl Margins {
+    /// Creates margins with the same width on every side.
+    pub fn uniform(width: u32) -> Margins {
+        Margins {
+            left: width,
+            top: width,
+            right: width,
+            bottom: width,
+        }
+    }
+}
+
+/// An image cut into nine slices by its margins, produced by
+/// [`Tileset::nine_slice`](struct.Tileset.html#method.nine_slice) and
+/// [`Canvas::nine_slice`](struct.Canvas.html#method.nine_slice).
+#[derive(Clone, Debug)]
+pub struct NineSlice {
+    /// The width of the whole image in pixels.
+    pub width: u32,
+    /// The height of the whole image in pixels.
+    pub height: u32,
+    /// The margins the image was cut by.
+    pub margins: Margins,
+    /// The area of each slice within the whole image, in the same order as `slices`.
+    pub rects: [Rect; 9],
+    /// The slices row by row: top-left, top, top-right, left, center, right, bottom-left, bottom
+    /// and bottom-right. Slices along a zero margin are empty.
+    pub slices: [RgbaImage; 9],
+}
+
+impl NineSlice {
+    /// Cuts `image` by `margins`, or returns `None` if the margins don't fit within it.
+    fn new(image: &RgbaImage, margins: Margins) -> Option<NineSlice> {
+        let (width, height) = image.dimensions();
+        let center_width = width.checked_sub(margins.left.checked_add(margins.right)?)?;
+        let center_height = height.checked_sub(margins.top.checked_add(margins.bottom)?)?;
+
+        let columns = [
+            (0, margins.left),
+            (margins.left, center_width),
+            (width - margins.right, margins.right),
+        ];
+        let rows = [
+            (0, margins.top),
+            (margins.top, center_height),
+            (height - margins.bottom, margins.bottom),
+        ];
+
+        let rect = |i: usize| {
+            let ((x, width), (y, height)) = (columns[i % 3], rows[i / 3]);
+            Rect {
+                x,
+                y,
+                width,
+                height,
+            }
+        };
+        let rects = [
+            rect(0),
+            rect(1),
+            rect(2),
+            rect(3),
+            rect(4),
+            rect(5),
+            rect(6),
+            rect(7),
+            rect(8),
+        ];
+
+        let slice = |r: &Rect| image.view(r.x, r.y, r.width, r.height).to_image();
+        let slices = [
+            slice(&rects[0]),
+            slice(&rects[1]),
+            slice(&rects[2]),
+            slice(&rects[3]),
+            slice(&rects[4]),
+            slice(&rects[5]),
+            slice(&rects[6]),
+            slice(&rects[7]),
+            slice(&rects[8]),
+        ];
+
+        Some(NineSlice {
+            width,
+            height,
+            margins,
+            rects,
+            slices,
+        })
+    }
+}
+
+impl Tileset {
+    /// Cuts the tile at `index` into nine slices by `margins`, or returns `None` if there is no
+    /// such tile or the margins don't fit within it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyxel::Margins;
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let panel = doc.tileset().nine_slice(0, Margins::uniform(4)).unwrap();
+    /// assert_eq!((8, 8), panel.slices[4].dimensions());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn nine_slice(&self, index: usize, margins: Margins) -> Option<NineSlice> {
+        let image = self.images.get(index)?.to_rgba();
+        NineSlice::new(&image, margins)
+    }
+}
+
+impl Canvas {
+    /// Cuts the `rect` region of the visible layers of this canvas into nine slices by `margins`,
+    /// or returns `None` if the region lies outside of the canvas or the margins don't fit within
+    /// it.
+    pub fn nine_slice(&self, rect: Rect, margins: Margins) -> Option<NineSlice> {
+        let (width, height) = (self.width.max(0) as u32, self.height.max(0) as u32);
+        if rect.x.checked_add(rect.width)? > width || rect.y.checked_add(rect.height)? > height {
+            return None;
+        }
+
+        let image = self.render_region(rect.x, rect.y, rect.width, rect.height);
+        NineSlice::new(&image, margins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use image::imageops;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    /// Reassembles the slices of `nine_slice` into an image of its original size.
+    fn reassemble(nine_slice: &NineSlice) -> RgbaImage {
+        let mut image = RgbaImage::new(nine_slice.width, nine_slice.height);
+        for (rect, slice) in nine_slice.rects.iter().zip(&nine_slice.slices) {
+            imageops::replace(&mut image, slice, rect.x, rect.y);
+        }
+        image
+    }
+
+    #[test]
+    fn tile_slices_cover_the_tile() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let margins = Margins {
+            left: 3,
+            top: 2,
+            right: 5,
+            bottom: 4,
+        };
+        let nine_slice = doc.tileset.nine_slice(1, margins).unwrap();
+
+        let sizes: Vec<_> = nine_slice.slices.iter().map(|s| s.dimensions()).collect();
+        assert_eq!(
+            vec![
+                (3, 2),
+                (24, 2),
+                (5, 2),
+                (3, 10),
+                (24, 10),
+                (5, 10),
+                (3, 4),
+                (24, 4),
+                (5, 4)
+            ],
+            sizes
+        );
+        assert_eq!(
+            doc.tileset.images[1].to_rgba().into_raw(),
+            reassemble(&nine_slice).into_raw()
+        );
+    }
+
+    #[test]
+    fn margins_must_fit() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        assert!(doc.tileset.nine_slice(0, Margins::uniform(9)).is_none());
+        assert!(doc.tileset.nine_slice(4, Margins::uniform(2)).is_none());
+
+        let rect = Rect {
+            x: 240,
+            y: 0,
+            width: 32,
+            height: 16,
+        };
+        assert!(doc.canvas.nine_slice(rect, Margins::uniform(2)).is_none());
+    }
+
+    #[test]
+    fn canvas_slices_match_flattened_canvas() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let rect = Rect {
+            x: 32,
+            y: 16,
+            width: 64,
+            height: 32,
+        };
+        let nine_slice = doc.canvas.nine_slice(rect, Margins::uniform(6)).unwrap();
+
+        let expected = doc.canvas.flatten().view(32, 16, 64, 32).to_image();
+        assert_eq!(expected.into_raw(), reassemble(&nine_slice).into_raw());
+    }
+}
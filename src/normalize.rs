@@ -0,0 +1,394 @@
+use crate::pyxel::Pyxel;
+
+use std::fmt;
+
+/// A change made by [`Pyxel::normalize`](struct.Pyxel.html#method.normalize).
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Normalization {
+    /// The palette's declared number of colors was corrected.
+    CorrectedColorCount {
+        /// The number of colors previously declared.
+        declared: usize,
+        /// The number of colors now declared.
+        actual: usize,
+    },
+
+    /// The canvas' declared number of layers was corrected.
+    CorrectedLayerCount {
+        /// The number of layers previously declared.
+        declared: usize,
+        /// The number of layers now declared.
+        actual: usize,
+    },
+
+    /// The tileset's declared number of tiles was corrected.
+    CorrectedTileCount {
+        /// The number of tiles previously declared.
+        declared: usize,
+        /// The number of tiles now declared.
+        actual: usize,
+    },
+
+    /// A tile ref keyed by a cell outside of the canvas, or referring to a tile that isn't in the
+    /// tileset, was removed.
+    RemovedTileRef {
+        /// The index of the layer that contained the tile ref.
+        layer: usize,
+        /// The cell the tile ref was keyed by.
+        cell: usize,
+        /// The tile index it referred to.
+        index: usize,
+    },
+
+    /// A tile ref's rotation was wrapped into `0..360` degrees.
+    WrappedRotation {
+        /// The index of the layer containing the tile ref.
+        layer: usize,
+        /// The cell the tile ref is keyed by.
+        cell: usize,
+        /// The previous rotation in degrees.
+        rot: f64,
+    },
+
+    /// An animation was shortened so that its frames lie within the canvas.
+    TruncatedAnimation {
+        /// The index of the animation.
+        animation: usize,
+        /// The previous length of the animation.
+        length: usize,
+        /// The length of the animation now.
+        actual: usize,
+    },
+
+    /// An animation's frame duration multipliers were padded or truncated to its length.
+    ResizedMultipliers {
+        /// The index of the animation.
+        animation: usize,
+        /// The number of multipliers previously present.
+        count: usize,
+        /// The number of multipliers now present.
+        actual: usize,
+    },
+
+    /// A negative or non-finite frame duration multiplier was replaced with `1`.
+    ReplacedMultiplier {
+        /// The index of the animation.
+        animation: usize,
+        /// The frame the multiplier applies to.
+        frame: usize,
+        /// The previous multiplier.
+        multiplier: f64,
+    },
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Normalization::CorrectedColorCount { declared, actual } => write!(
+                f,
+                "corrected the palette's color count from {} to {}",
+                declared, actual
+            ),
+            Normalization::CorrectedLayerCount { declared, actual } => write!(
+                f,
+                "corrected the canvas' layer count from {} to {}",
+                declared, actual
+            ),
+            Normalization::CorrectedTileCount { declared, actual } => write!(
+                f,
+                "corrected the tileset's tile count from {} to {}",
+                declared, actual
+            ),
+            Normalization::RemovedTileRef { layer, cell, index } => write!(
+                f,
+                "removed layer {} cell {}'s tile ref to tile {}",
+                layer, cell, index
+            ),
+            Normalization::WrappedRotation { layer, cell, rot } => write!(
+                f,
+                "wrapped layer {} cell {}'s rotation of {} degrees",
+                layer, cell, rot
+            ),
+            Normalization::TruncatedAnimation {
+                animation,
+                length,
+                actual,
+            } => write!(
+                f,
+                "shortened animation {} from {} to {} frames",
+                animation, length, actual
+            ),
+            Normalization::ResizedMultipliers {
+                animation,
+                count,
+                actual,
+            } => write!(
+                f,
+                "resized animation {}'s duration multipliers from {} to {}",
+                animation, count, actual
+            ),
+            Normalization::ReplacedMultiplier {
+                animation,
+                frame,
+                multiplier,
+            } => write!(
+                f,
+                "replaced animation {} frame {}'s duration multiplier of {} with 1",
+                animation, frame, multiplier
+            ),
+        }
+    }
+}
+
+impl Pyxel {
+    /// Restores the invariants checked by [`validate`](#method.validate) in place, returning every
+    /// change made.
+    ///
+    /// The normalized document:
+    ///
+    /// - declares the number of palette slots, layers and tiles actually present
+    /// - has no tile refs outside of the canvas or referring to tiles missing from the tileset
+    /// - has tile ref rotations within `0..360` degrees
+    /// - has no animation frames outside of the canvas
+    /// - has a finite, non-negative frame duration multiplier for every animation frame
+    ///
+    /// An empty list means the document was already normalized. The pixels of the document's
+    /// images are never changed, and empty palette slots are kept so that every color keeps its
+    /// index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// for change in doc.normalize() {
+    ///     println!("{}", change);
+    /// }
+    /// assert!(doc.validate().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn normalize(&mut self) -> Vec<Normalization> {
+        let mut changes = Vec::new();
+
+        let palette = &mut self.palette;
+        if palette.num_colors != palette.colors.len() {
+            changes.push(Normalization::CorrectedColorCount {
+                declared: palette.num_colors,
+                actual: palette.colors.len(),
+            });
+            palette.num_colors = palette.colors.len();
+        }
+
+        let num_tiles = self.tileset.tile_count();
+        if self.tileset.num_tiles != num_tiles {
+            changes.push(Normalization::CorrectedTileCount {
+                declared: self.tileset.num_tiles,
+                actual: num_tiles,
+            });
+            self.tileset.num_tiles = num_tiles;
+        }
+
+        let canvas = &mut self.canvas;
+        let num_cells = canvas.tiles_wide() * canvas.tiles_high();
+        if canvas.num_layers != canvas.layers.len() {
+            changes.push(Normalization::CorrectedLayerCount {
+                declared: canvas.num_layers,
+                actual: canvas.layers.len(),
+            });
+            canvas.num_layers = canvas.layers.len();
+        }
+
+        for (i, layer) in canvas.layers.iter_mut().enumerate() {
+            layer.tile_refs.retain(|&cell, tile_ref| {
+                let keep = cell < num_cells && tile_ref.index < num_tiles;
+                if !keep {
+                    changes.push(Normalization::RemovedTileRef {
+                        layer: i,
+                        cell,
+                        index: tile_ref.index,
+                    });
+                }
+                keep
+            });
+
+            for (&cell, tile_ref) in layer.tile_refs.iter_mut() {
+                let rot = tile_ref.rot.rem_euclid(360.);
+                // NaN rotations can't be wrapped, and never compare equal.
+                if rot != tile_ref.rot && !tile_ref.rot.is_nan() {
+                    changes.push(Normalization::WrappedRotation {
+                        layer: i,
+                        cell,
+                        rot: tile_ref.rot,
+                    });
+                    tile_ref.rot = rot;
+                }
+            }
+        }
+
+        for (i, animation) in self.animations.iter_mut().enumerate() {
+            let length = animation
+                .length
+                .min(num_cells.saturating_sub(animation.base_tile));
+            if length != animation.length {
+                changes.push(Normalization::TruncatedAnimation {
+                    animation: i,
+                    length: animation.length,
+                    actual: length,
+                });
+                animation.length = length;
+            }
+
+            let multipliers = &mut animation.frame_duration_multipliers;
+            if multipliers.len() != length {
+                changes.push(Normalization::ResizedMultipliers {
+                    animation: i,
+                    count: multipliers.len(),
+                    actual: length,
+                });
+                multipliers.resize(length, 1.);
+            }
+            for (frame, multiplier) in multipliers.iter_mut().enumerate() {
+                if !multiplier.is_finite() || *multiplier < 0. {
+                    changes.push(Normalization::ReplacedMultiplier {
+                        animation: i,
+                        frame,
+                        multiplier: *multiplier,
+                    });
+                    *multiplier = 1.;
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// Returns a normalized copy of this document, see [`normalize`](#method.normalize).
+    ///
+    /// This is a separate pass for callers that want a consistent model to work on. Rendering,
+    /// exporting and diffing don't go through it: they operate on documents as they were loaded
+    /// and handle their quirks themselves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?.normalized();
+    /// assert!(doc.validate().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn normalized(&self) -> Pyxel {
+        let mut doc = self.clone();
+        doc.normalize();
+        doc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load::load, pyxel::TileRef};
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn normalized_document_is_valid() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+
+        doc.palette.colors.insert(3, None);
+        doc.canvas.num_layers = 12;
+        doc.tileset.num_tiles = 5;
+        doc.canvas.layers[0]
+            .tile_refs
+            .insert(64, TileRef::new(1, 0., false));
+        doc.canvas.layers[1]
+            .tile_refs
+            .insert(10, TileRef::new(4, 0., false));
+        doc.canvas.layers[1]
+            .tile_refs
+            .insert(11, TileRef::new(2, 450., true));
+        doc.animations[2].base_tile = 63;
+        doc.animations[0].frame_duration_multipliers = vec![1., -2., f64::NAN];
+
+        assert!(!doc.validate().is_empty());
+
+        let normalized = doc.normalized();
+        assert!(normalized.validate().is_empty());
+
+        assert_eq!(16, normalized.palette.num_colors);
+        assert_eq!(None, normalized.palette.colors[3]);
+        assert_eq!(doc.palette.colors, normalized.palette.colors);
+        assert_eq!(4, normalized.tileset.num_tiles);
+        assert!(!normalized.canvas.layers[0].tile_refs.contains_key(&64));
+        assert!(!normalized.canvas.layers[1].tile_refs.contains_key(&10));
+        assert_eq!(90., normalized.canvas.layers[1].tile_refs[&11].rot);
+        assert_eq!(1, normalized.animations[2].length);
+        assert_eq!(
+            vec![1., 1., 1., 1.],
+            normalized.animations[0].frame_duration_multipliers
+        );
+    }
+
+    #[test]
+    fn normalize_reports_changes() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+
+        doc.palette.colors.insert(3, None);
+        doc.canvas.layers[0]
+            .tile_refs
+            .insert(64, TileRef::new(1, 0., false));
+        doc.canvas.layers[1]
+            .tile_refs
+            .insert(10, TileRef::new(4, 0., false));
+        doc.canvas.layers[1]
+            .tile_refs
+            .insert(11, TileRef::new(2, -90., true));
+        doc.animations[2].base_tile = 63;
+
+        assert_eq!(
+            vec![
+                Normalization::CorrectedColorCount {
+                    declared: 15,
+                    actual: 16
+                },
+                Normalization::RemovedTileRef {
+                    layer: 0,
+                    cell: 64,
+                    index: 1
+                },
+                Normalization::RemovedTileRef {
+                    layer: 1,
+                    cell: 10,
+                    index: 4
+                },
+                Normalization::WrappedRotation {
+                    layer: 1,
+                    cell: 11,
+                    rot: -90.
+                },
+                Normalization::TruncatedAnimation {
+                    animation: 2,
+                    length: 2,
+                    actual: 1
+                },
+                Normalization::ResizedMultipliers {
+                    animation: 2,
+                    count: 2,
+                    actual: 1
+                },
+            ],
+            doc.normalize()
+        );
+        assert!(doc.normalize().is_empty());
+    }
+
+    #[test]
+    fn normalizing_valid_document_changes_nothing() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        assert_eq!(doc, doc.normalized());
+        assert!(doc.normalize().is_empty());
+    }
+}
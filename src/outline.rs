@@ -0,0 +1,206 @@
+use crate::pyxel::{Color, Layer};
+
+use image::{Rgba, RgbaImage};
+
+/// Options for [`outline`](fn.outline.html) and [`Layer::outline`](struct.Layer.html#method.outline).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct OutlineOptions {
+    /// The thickness of the outline in pixels.
+    pub width: u32,
+    /// The color of the outline.
+    pub color: Color,
+    /// Whether pixels touching the sprite only at a corner are part of the outline, which gives
+    /// square rather than rounded corners.
+    pub diagonals: bool,
+    /// Whether the sprite is drawn over the outline, rather than leaving its pixels transparent.
+    pub include_sprite: bool,
+}
+
+impl Default for OutlineOptions {
+    fn default() -> Self {
+        OutlineOptions {
+            width: 1,
+            color: Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+            diagonals: false,
+            include_sprite: false,
+        }
+    }
+}
+
+/// Returns the outline around the non-transparent pixels of `image`, as an image of the same
+/// size. Parts of the outline falling outside of the image are cut off.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+/// let frame = doc.canvas().render_frame(&doc.animations()[0], 0).unwrap();
+/// let outlined = pyxel::outline(&frame, &pyxel::OutlineOptions {
+///     include_sprite: true,
+///     ..pyxel::OutlineOptions::default()
+/// });
+/// assert_eq!(frame.dimensions(), outlined.dimensions());
+/// # Ok(())
+/// # }
+/// ```
+pub fn outline(image: &RgbaImage, options: &OutlineOptions) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let solid: Vec<bool> = image.pixels().map(|p| p[3] != 0).collect();
+
+    // Grow the sprite one pixel at a time, so the outline is `options.width` pixels thick.
+    let mut grown = solid.clone();
+    for _ in 0..options.width {
+        let previous = grown.clone();
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let i = (y * i64::from(width) + x) as usize;
+                if previous[i] {
+                    continue;
+                }
+
+                grown[i] = neighbors(options.diagonals).iter().any(|&(dx, dy)| {
+                    let (nx, ny) = (x + dx, y + dy);
+                    nx >= 0
+                        && ny >= 0
+                        && nx < i64::from(width)
+                        && ny < i64::from(height)
+                        && previous[(ny * i64::from(width) + nx) as usize]
+                });
+            }
+        }
+    }
+
+    let Color { r, g, b, a } = options.color;
+    RgbaImage::from_fn(width, height, |x, y| {
+        let i = (y * width + x) as usize;
+        if solid[i] {
+            if options.include_sprite {
+                *image.get_pixel(x, y)
+            } else {
+                Rgba([0; 4])
+            }
+        } else if grown[i] {
+            Rgba([r, g, b, a])
+        } else {
+            Rgba([0; 4])
+        }
+    })
+}
+
+fn neighbors(diagonals: bool) -> &'static [(i64, i64)] {
+    if diagonals {
+        &[
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ]
+    } else {
+        &[(0, -1), (-1, 0), (1, 0), (0, 1)]
+    }
+}
+
+/// Returns `image` with every non-transparent pixel replaced by `color`, keeping the pixel's
+/// coverage by scaling the color's alpha by the pixel's.
+pub fn silhouette(image: &RgbaImage, color: Color) -> RgbaImage {
+    let Color { r, g, b, a } = color;
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let alpha = image.get_pixel(x, y)[3];
+        if alpha == 0 {
+            return Rgba([0; 4]);
+        }
+
+        let alpha = (u32::from(a) * u32::from(alpha) + 127) / 255;
+        Rgba([r, g, b, alpha as u8])
+    })
+}
+
+impl Layer {
+    /// Returns the outline around the non-transparent pixels of this layer. See
+    /// [`outline`](fn.outline.html).
+    pub fn outline(&self, options: &OutlineOptions) -> RgbaImage {
+        outline(&self.image.to_rgba(), options)
+    }
+
+    /// Returns the pixels of this layer filled with `color`. See
+    /// [`silhouette`](fn.silhouette.html).
+    pub fn silhouette(&self, color: Color) -> RgbaImage {
+        silhouette(&self.image.to_rgba(), color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color {
+        r: 255,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+
+    fn dot() -> RgbaImage {
+        let mut image = RgbaImage::new(7, 7);
+        image.put_pixel(3, 3, Rgba([0, 0, 255, 255]));
+        image
+    }
+
+    fn mask(image: &RgbaImage) -> Vec<String> {
+        (0..image.height())
+            .map(|y| {
+                (0..image.width())
+                    .map(|x| match image.get_pixel(x, y)[3] {
+                        0 => '.',
+                        _ if image.get_pixel(x, y)[0] == 255 => 'o',
+                        _ => '#',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn outlines_grow_by_width() {
+        let options = OutlineOptions {
+            width: 2,
+            color: RED,
+            ..OutlineOptions::default()
+        };
+        assert_eq!(
+            vec![".......", "...o...", "..ooo..", ".oo.oo.", "..ooo..", "...o...", "......."],
+            mask(&outline(&dot(), &options))
+        );
+
+        let options = OutlineOptions {
+            diagonals: true,
+            include_sprite: true,
+            ..options
+        };
+        assert_eq!(
+            vec![".......", ".ooooo.", ".ooooo.", ".oo#oo.", ".ooooo.", ".ooooo.", "......."],
+            mask(&outline(&dot(), &options))
+        );
+    }
+
+    #[test]
+    fn silhouettes_keep_coverage() {
+        let mut image = dot();
+        image.put_pixel(0, 0, Rgba([10, 20, 30, 128]));
+
+        let filled = silhouette(&image, RED);
+        assert_eq!(Rgba([255, 0, 0, 255]), *filled.get_pixel(3, 3));
+        assert_eq!(Rgba([255, 0, 0, 128]), *filled.get_pixel(0, 0));
+        assert_eq!(Rgba([0; 4]), *filled.get_pixel(1, 0));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    palette_import::DEFAULT_COLUMNS,
+    pyxel::{Color, Palette, Pyxel},
+};
+
+use std::collections::HashMap;
+
+impl Pyxel {
+    /// Builds a palette from the colors used by the layer images of this document.
+    ///
+    /// Fully transparent pixels are ignored. If the document uses at most `max_colors` colors
+    /// they are returned in the order they first appear, starting with the top-most layer.
+    /// Otherwise the colors are quantized down to `max_colors` using median cut.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let palette = doc.derive_palette(16);
+    /// assert!(palette.colors().len() <= 16);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn derive_palette(&self, max_colors: usize) -> Palette {
+        let mut counts: HashMap<[u8; 4], u64> = HashMap::new();
+        let mut colors = Vec::new();
+
+        for layer in &self.canvas.layers {
+            for pixel in layer.image.to_rgba().pixels() {
+                if pixel.0[3] == 0 {
+                    continue;
+                }
+
+                let count = counts.entry(pixel.0).or_insert(0);
+                if *count == 0 {
+                    colors.push(pixel.0);
+                }
+                *count += 1;
+            }
+        }
+
+        let colors = if colors.len() <= max_colors {
+            colors
+        } else {
+            let weighted = colors.iter().map(|color| (*color, counts[color])).collect();
+            median_cut(weighted, max_colors)
+        };
+
+        let colors = colors
+            .into_iter()
+            .map(|[r, g, b, a]| Color { r, g, b, a })
+            .collect();
+        Palette::from_colors(colors, DEFAULT_COLUMNS)
+    }
+}
+
+type Bucket = Vec<([u8; 4], u64)>;
+
+/// Returns the channel with the widest range of values in `bucket` and the size of the range.
+fn widest_channel(bucket: &Bucket) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let values = bucket.iter().map(|(color, _)| color[channel]);
+            let range = values.clone().max().unwrap_or(0) - values.min().unwrap_or(0);
+            (channel, range)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+/// Reduces `colors` to at most `max_colors` colors by repeatedly splitting the bucket with the
+/// widest channel at its weighted median, then averaging each bucket.
+fn median_cut(colors: Bucket, max_colors: usize) -> Vec<[u8; 4]> {
+    if max_colors == 0 {
+        return Vec::new();
+    }
+
+    let mut buckets = vec![colors];
+
+    while buckets.len() < max_colors {
+        let (index, (channel, range)) = match buckets
+            .iter()
+            .map(widest_channel)
+            .enumerate()
+            .max_by_key(|&(_, (_, range))| range)
+        {
+            Some(widest) => widest,
+            None => break,
+        };
+        if range == 0 {
+            break;
+        }
+
+        let mut bucket = buckets.swap_remove(index);
+        bucket.sort_by_key(|(color, _)| color[channel]);
+
+        let total: u64 = bucket.iter().map(|(_, count)| count).sum();
+        let mut seen = 0;
+        let mut split = bucket.len() - 1;
+        for (i, (_, count)) in bucket.iter().enumerate() {
+            seen += count;
+            if seen * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+        // Both halves must keep at least one color.
+        let split = split.clamp(1, bucket.len() - 1);
+
+        let upper = bucket.split_off(split);
+        buckets.push(bucket);
+        buckets.push(upper);
+    }
+
+    buckets
+        .iter()
+        .map(|bucket| {
+            let total: u64 = bucket.iter().map(|(_, count)| count).sum();
+            let mut color = [0; 4];
+            for (channel, value) in color.iter_mut().enumerate() {
+                let sum: u64 = bucket
+                    .iter()
+                    .map(|(color, count)| u64::from(color[channel]) * count)
+                    .sum();
+                *value = ((sum + total / 2) / total) as u8;
+            }
+            color
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn collects_unique_colors() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let palette = doc.derive_palette(usize::MAX);
+
+        let colors = palette.colors();
+        assert!(!colors.is_empty());
+        assert_eq!(colors.len(), palette.num_colors);
+        for (i, color) in colors.iter().enumerate() {
+            assert!(color.unwrap().a != 0);
+            assert!(!colors[..i].contains(color));
+        }
+    }
+
+    #[test]
+    fn quantizes_to_max_colors() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let all = doc.derive_palette(usize::MAX).colors().len();
+        assert!(all > 4);
+
+        assert_eq!(4, doc.derive_palette(4).colors().len());
+        assert!(doc.derive_palette(0).colors().is_empty());
+    }
+
+    #[test]
+    fn median_cut_averages_buckets() {
+        let colors = vec![
+            ([0, 0, 0, 255], 1),
+            ([10, 0, 0, 255], 3),
+            ([200, 0, 0, 255], 1),
+        ];
+        assert_eq!(
+            vec![[8, 0, 0, 255], [200, 0, 0, 255]],
+            median_cut(colors, 2)
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Color, Palette},
+};
+
+use std::io::{BufRead, BufReader, Read};
+
+/// The number of palette columns used when a palette file doesn't specify one.
+pub(crate) const DEFAULT_COLUMNS: u8 = 8;
+
+impl Palette {
+    pub(crate) fn from_colors(colors: Vec<Color>, columns: u8) -> Palette {
+        let columns = columns.max(1);
+        let rows = colors.len().div_ceil(usize::from(columns));
+
+        Palette {
+            num_colors: colors.len(),
+            colors: colors.into_iter().map(Some).collect(),
+            height: rows.min(usize::from(u8::MAX)) as u8,
+            width: columns,
+        }
+    }
+
+    /// Reads a GIMP palette (`.gpl`).
+    ///
+    /// The palette takes its width from the file's `Columns` header if present. Every color is
+    /// fully opaque.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let gpl = "GIMP Palette\n#\n255 0 0 Red\n0 0 255 Blue\n";
+    /// let palette = pyxel::Palette::from_gpl(gpl.as_bytes())?;
+    /// assert_eq!(2, palette.colors().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_gpl<R: Read>(reader: R) -> Result<Palette, PyxelError> {
+        let mut lines = BufReader::new(reader).lines();
+        match lines.next().transpose()? {
+            Some(ref line) if line.trim() == "GIMP Palette" => {}
+            _ => return Err(PyxelError::InvalidPalette),
+        }
+
+        let mut colors = Vec::new();
+        let mut columns = 0;
+
+        for line in lines {
+            let line = line?;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with("Name:") {
+                continue;
+            }
+
+            if let Some(value) = line.strip_prefix("Columns:") {
+                columns = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| PyxelError::InvalidPalette)?;
+                continue;
+            }
+
+            let mut channels = line.split_whitespace().map(str::parse::<u8>);
+            match (channels.next(), channels.next(), channels.next()) {
+                (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => colors.push(Color { r, g, b, a: 255 }),
+                _ => return Err(PyxelError::InvalidPalette),
+            }
+        }
+
+        // GIMP uses zero columns to mean the palette's width is unspecified.
+        if columns == 0 {
+            columns = DEFAULT_COLUMNS;
+        }
+        Ok(Palette::from_colors(colors, columns))
+    }
+
+    /// Reads an Adobe Swatch Exchange file (`.ase`).
+    ///
+    /// RGB, CMYK and grayscale swatches are read in file order, ignoring any groups. Every color
+    /// is fully opaque.
+    pub fn from_ase<R: Read>(mut reader: R) -> Result<Palette, PyxelError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let mut input = AseInput { data: &data };
+        if input.take(4)? != b"ASEF" {
+            return Err(PyxelError::InvalidPalette);
+        }
+        input.take(4)?;
+        let num_blocks = input.u32()?;
+
+        let mut colors = Vec::new();
+        for _ in 0..num_blocks {
+            let block_type = input.u16()?;
+            let len = input.u32()? as usize;
+            let mut block = AseInput {
+                data: input.take(len)?,
+            };
+
+            // Group start and end blocks have no colors.
+            if block_type != 0x0001 {
+                continue;
+            }
+
+            let name_len = usize::from(block.u16()?);
+            block.take(name_len * 2)?;
+
+            let channel = |value: f32| (value.clamp(0., 1.) * 255.).round() as u8;
+            let color = match block.take(4)? {
+                b"RGB " => {
+                    let (r, g, b) = (block.f32()?, block.f32()?, block.f32()?);
+                    (channel(r), channel(g), channel(b))
+                }
+                b"CMYK" => {
+                    let (c, m, y, k) = (block.f32()?, block.f32()?, block.f32()?, block.f32()?);
+                    (
+                        channel((1. - c) * (1. - k)),
+                        channel((1. - m) * (1. - k)),
+                        channel((1. - y) * (1. - k)),
+                    )
+                }
+                b"Gray" => {
+                    let gray = channel(block.f32()?);
+                    (gray, gray, gray)
+                }
+                _ => return Err(PyxelError::InvalidPalette),
+            };
+
+            colors.push(Color {
+                r: color.0,
+                g: color.1,
+                b: color.2,
+                a: 255,
+            });
+        }
+
+        Ok(Palette::from_colors(colors, DEFAULT_COLUMNS))
+    }
+
+    /// Reads a plain list of `rrggbb` hex codes (`.hex`), one per line.
+    ///
+    /// Leading `#`s and blank lines are ignored. Every color is fully opaque.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let palette = pyxel::Palette::from_hex("ff0000\n#0000ff\n".as_bytes())?;
+    /// assert_eq!(2, palette.colors().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_hex<R: Read>(reader: R) -> Result<Palette, PyxelError> {
+        use hex::FromHex;
+
+        let mut colors = Vec::new();
+
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let line = line.strip_prefix('#').unwrap_or(line);
+            let [r, g, b] = <[u8; 3]>::from_hex(line).map_err(|_| PyxelError::InvalidPalette)?;
+            colors.push(Color { r, g, b, a: 255 });
+        }
+
+        Ok(Palette::from_colors(colors, DEFAULT_COLUMNS))
+    }
+}
+
+/// The unread part of an Adobe Swatch Exchange file, which is big-endian throughout.
+struct AseInput<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> AseInput<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PyxelError> {
+        if len > self.data.len() {
+            return Err(PyxelError::InvalidPalette);
+        }
+
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, PyxelError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, PyxelError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, PyxelError> {
+        self.u32().map(f32::from_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load::load, MissingColorPolicy};
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    fn opaque_palette() -> Palette {
+        let mut palette = load(File::open(TEST_FILE).unwrap()).unwrap().palette;
+        for color in palette.colors.iter_mut().flatten() {
+            color.a = 255;
+        }
+        palette
+    }
+
+    #[test]
+    fn round_trip_through_palette_files() {
+        let palette = opaque_palette();
+        let (colors, _) = palette.export_colors(MissingColorPolicy::Skip).unwrap();
+        let colors: Vec<_> = colors.into_iter().map(Some).collect();
+
+        let mut gpl = Vec::new();
+        palette
+            .export_gpl(&mut gpl, MissingColorPolicy::Skip)
+            .unwrap();
+        let gpl = Palette::from_gpl(gpl.as_slice()).unwrap();
+        assert_eq!(colors, gpl.colors);
+        assert_eq!(palette.width, gpl.width);
+
+        let mut ase = Vec::new();
+        palette
+            .export_ase(&mut ase, MissingColorPolicy::Skip)
+            .unwrap();
+        assert_eq!(colors, Palette::from_ase(ase.as_slice()).unwrap().colors);
+
+        let mut hex = Vec::new();
+        palette
+            .export_hex(&mut hex, MissingColorPolicy::Skip)
+            .unwrap();
+        let hex = Palette::from_hex(hex.as_slice()).unwrap();
+        assert_eq!(colors, hex.colors);
+        assert_eq!(colors.len(), hex.num_colors);
+    }
+
+    #[test]
+    fn reject_invalid_palette_files() {
+        for gpl in &[
+            "JASC-PAL\n",
+            "GIMP Palette\n255 0\n",
+            "GIMP Palette\n256 0 0\n",
+        ] {
+            match Palette::from_gpl(gpl.as_bytes()) {
+                Err(PyxelError::InvalidPalette) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+
+        match Palette::from_ase(&b"ASEF\0\x01\0\0\0\0\0\x01\0\x01"[..]) {
+            Err(PyxelError::InvalidPalette) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        match Palette::from_hex("ff00\n".as_bytes()) {
+            Err(PyxelError::InvalidPalette) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{
+    bounds::Rect,
+    diff::match_by_name,
+    pyxel::{Layer, Pyxel},
+};
+
+use image::{GenericImageView, Rgba, RgbaImage};
+
+/// The pixels that differ between two images, produced by
+/// [`Layer::pixel_diff`](struct.Layer.html#method.pixel_diff).
+///
+/// Images of different sizes are compared over the larger of their widths and heights, with any
+/// pixel outside of an image taken to be transparent. Fully transparent pixels are equal
+/// whatever their color channels hold, since they look the same.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PixelDiff {
+    width: u32,
+    height: u32,
+    changed: Vec<bool>,
+    changed_pixels: u64,
+}
+
+/// The changed pixels within one region of a [`PixelDiff`](struct.PixelDiff.html), see
+/// [`PixelDiff::regions`](struct.PixelDiff.html#method.regions).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RegionDiff {
+    /// The region.
+    pub rect: Rect,
+    /// The number of pixels within the region that changed.
+    pub changed_pixels: u64,
+}
+
+fn pixel<I: GenericImageView<Pixel = Rgba<u8>>>(image: &I, x: u32, y: u32) -> Rgba<u8> {
+    if x < image.width() && y < image.height() {
+        let p = image.get_pixel(x, y);
+        if p[3] != 0 {
+            return p;
+        }
+    }
+    Rgba([0; 4])
+}
+
+impl PixelDiff {
+    pub(crate) fn new<A, B>(old: &A, new: &B) -> PixelDiff
+    where
+        A: GenericImageView<Pixel = Rgba<u8>>,
+        B: GenericImageView<Pixel = Rgba<u8>>,
+    {
+        let width = old.width().max(new.width());
+        let height = old.height().max(new.height());
+
+        let mut changed = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                changed.push(pixel(old, x, y) != pixel(new, x, y));
+            }
+        }
+
+        PixelDiff {
+            width,
+            height,
+            changed_pixels: changed.iter().filter(|&&c| c).count() as u64,
+            changed,
+        }
+    }
+
+    /// Returns the width of the compared area in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the compared area in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the number of pixels that changed.
+    pub fn changed_pixels(&self) -> u64 {
+        self.changed_pixels
+    }
+
+    /// Returns `true` if no pixels changed.
+    pub fn is_empty(&self) -> bool {
+        self.changed_pixels == 0
+    }
+
+    /// Returns `true` if the pixel at `x`, `y` changed.
+    pub fn is_changed(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height && self.changed[(y * self.width + x) as usize]
+    }
+
+    /// Returns the smallest rectangle containing every changed pixel, or `None` if no pixels
+    /// changed.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.bounds_within(0, 0, self.width, self.height)
+    }
+
+    fn bounds_within(&self, x: u32, y: u32, width: u32, height: u32) -> Option<Rect> {
+        let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+        for row in y..y + height {
+            for col in x..x + width {
+                if self.changed[(row * self.width + col) as usize] {
+                    left = left.min(col);
+                    top = top.min(row);
+                    right = right.max(col + 1);
+                    bottom = bottom.max(row + 1);
+                }
+            }
+        }
+
+        if left == u32::MAX {
+            return None;
+        }
+
+        Some(Rect {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        })
+    }
+
+    /// Splits the compared area into a grid of `width` × `height` pixel regions, row by row,
+    /// and returns those containing changed pixels. Regions along the right and bottom edges
+    /// are cut short if the area isn't a multiple of the region size.
+    ///
+    /// Passing the canvas tile size gives a summary per canvas cell.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` is zero.
+    pub fn regions(&self, width: u32, height: u32) -> Vec<RegionDiff> {
+        assert!(width > 0 && height > 0, "region size must not be zero");
+
+        let mut regions = Vec::new();
+        for y in (0..self.height).step_by(height as usize) {
+            for x in (0..self.width).step_by(width as usize) {
+                let rect = Rect {
+                    x,
+                    y,
+                    width: width.min(self.width - x),
+                    height: height.min(self.height - y),
+                };
+
+                let changed_pixels = (rect.y..rect.y + rect.height)
+                    .flat_map(|row| (rect.x..rect.x + rect.width).map(move |col| (col, row)))
+                    .filter(|&(col, row)| self.changed[(row * self.width + col) as usize])
+                    .count() as u64;
+
+                if changed_pixels > 0 {
+                    regions.push(RegionDiff {
+                        rect,
+                        changed_pixels,
+                    });
+                }
+            }
+        }
+
+        regions
+    }
+
+    /// Draws a visual diff over `image`, usually the new version of what was compared. Unchanged
+    /// pixels are faded to a third of their opacity and changed pixels are drawn in
+    /// `highlight`, so the changes stand out.
+    ///
+    /// The result covers the compared area, with `image` drawn at its top-left corner.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let old = pyxel::open("resources/doc.pyxel")?;
+    /// let new = pyxel::open("resources/doc.pyxel")?;
+    /// let diff = old.pixel_diff(&new);
+    /// let magenta = image::Rgba([255, 0, 255, 255]);
+    /// let visual = diff.flattened.visualize(&new.canvas().flatten(), magenta);
+    /// assert_eq!((128, 128), visual.dimensions());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn visualize(&self, image: &RgbaImage, highlight: Rgba<u8>) -> RgbaImage {
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            if self.changed[(y * self.width + x) as usize] {
+                highlight
+            } else {
+                let Rgba([r, g, b, a]) = pixel(image, x, y);
+                Rgba([r, g, b, a / 3])
+            }
+        })
+    }
+}
+
+/// The pixel changes to a layer present in both documents, part of a
+/// [`DocumentPixelDiff`](struct.DocumentPixelDiff.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LayerPixelDiff {
+    /// The index of the layer in the old document.
+    pub old_index: usize,
+    /// The index of the layer in the new document.
+    pub new_index: usize,
+    /// The pixels of the layer that changed.
+    pub diff: PixelDiff,
+}
+
+/// The pixel differences between two documents, produced by
+/// [`Pyxel::pixel_diff`](struct.Pyxel.html#method.pixel_diff).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DocumentPixelDiff {
+    /// The layers present in both documents whose pixels changed. Layers without changes are
+    /// left out.
+    pub layers: Vec<LayerPixelDiff>,
+    /// The pixels of the flattened canvases that changed, i.e. the changes that can be seen.
+    pub flattened: PixelDiff,
+}
+
+impl Layer {
+    /// Compares the pixels of this layer's image, the old version, with those of `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let layer = &doc.canvas().layers()[0];
+    /// assert_eq!(0, layer.pixel_diff(layer).changed_pixels());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pixel_diff(&self, other: &Layer) -> PixelDiff {
+        PixelDiff::new(&self.image.to_rgba(), &other.image.to_rgba())
+    }
+}
+
+impl Pyxel {
+    /// Compares the pixels of this document, the old version, with those of `other`, layer by
+    /// layer and as flattened canvases.
+    ///
+    /// Layers are matched up as they are by [`diff`](fn.diff.html), which also reports the layers
+    /// that were added or removed.
+    pub fn pixel_diff(&self, other: &Pyxel) -> DocumentPixelDiff {
+        let names = |doc: &Pyxel| -> Vec<String> {
+            doc.canvas
+                .layers
+                .iter()
+                .map(|layer| layer.name.clone())
+                .collect()
+        };
+        let (old_names, new_names) = (names(self), names(other));
+        let (pairs, _, _) = match_by_name(
+            &old_names.iter().map(String::as_str).collect::<Vec<_>>(),
+            &new_names.iter().map(String::as_str).collect::<Vec<_>>(),
+        );
+
+        let layers = pairs
+            .into_iter()
+            .filter_map(|(old_index, new_index)| {
+                let diff =
+                    self.canvas.layers[old_index].pixel_diff(&other.canvas.layers[new_index]);
+                if diff.is_empty() {
+                    None
+                } else {
+                    Some(LayerPixelDiff {
+                        old_index,
+                        new_index,
+                        diff,
+                    })
+                }
+            })
+            .collect();
+
+        DocumentPixelDiff {
+            layers,
+            flattened: PixelDiff::new(&self.canvas.flatten(), &other.canvas.flatten()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use image::GenericImage;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn counts_and_summarizes_changed_pixels() {
+        let old = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let mut new = old.clone();
+        {
+            let image = new.canvas.layers[1].image_mut();
+            image.put_pixel(0, 0, Rgba([1, 2, 3, 255]));
+            image.put_pixel(33, 17, Rgba([1, 2, 3, 255]));
+            image.put_pixel(40, 20, Rgba([1, 2, 3, 255]));
+        }
+        // Hidden, so only changes the layer diff.
+        new.canvas.layers[3]
+            .image_mut()
+            .put_pixel(5, 5, Rgba([9, 9, 9, 255]));
+
+        let diff = old.pixel_diff(&new);
+        let layers: Vec<_> = diff
+            .layers
+            .iter()
+            .map(|l| (l.old_index, l.new_index, l.diff.changed_pixels()))
+            .collect();
+        assert_eq!(vec![(1, 1, 3), (3, 3, 1)], layers);
+
+        let flattened = &diff.flattened;
+        assert_eq!(
+            Some(Rect {
+                x: 0,
+                y: 0,
+                width: 41,
+                height: 21
+            }),
+            flattened.bounds()
+        );
+        let regions: Vec<_> = flattened
+            .regions(32, 16)
+            .iter()
+            .map(|r| (r.rect.x, r.rect.y, r.changed_pixels))
+            .collect();
+        assert_eq!(vec![(0, 0, 1), (32, 16, 2)], regions);
+
+        let visual = flattened.visualize(&new.canvas.flatten(), Rgba([255, 0, 255, 255]));
+        assert_eq!(Rgba([255, 0, 255, 255]), *visual.get_pixel(33, 17));
+        assert!(old.pixel_diff(&old).layers.is_empty());
+    }
+
+    #[test]
+    fn images_of_different_sizes_are_compared() {
+        let mut a = RgbaImage::new(2, 2);
+        a.put_pixel(1, 1, Rgba([1, 1, 1, 255]));
+        // Transparent pixels match regardless of their color.
+        a.put_pixel(0, 0, Rgba([1, 1, 1, 0]));
+        let b = RgbaImage::new(3, 1);
+
+        let diff = PixelDiff::new(&a, &b);
+        assert_eq!(
+            (3, 2, 1),
+            (diff.width(), diff.height(), diff.changed_pixels())
+        );
+        assert!(diff.is_changed(1, 1));
+        assert!(!diff.is_changed(0, 0));
+        assert!(!diff.is_changed(5, 5));
+    }
+}
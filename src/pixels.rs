@@ -0,0 +1,156 @@
+use crate::pyxel::Layer;
+
+use image::RgbaImage;
+
+/// The order the bytes of multi-byte pixels are written in.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ByteOrder {
+    /// Most significant byte first, as most SPI displays such as the ST7789 expect.
+    BigEndian,
+    /// Least significant byte first.
+    LittleEndian,
+}
+
+/// A pixel format produced by [`Layer::to_pixels`](struct.Layer.html#method.to_pixels).
+///
+/// Rows are tightly packed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PixelFormat {
+    /// 8 bits per channel, in red, green, blue, alpha order.
+    Rgba8888,
+    /// 8 bits per channel, in red, green, blue order, with alpha dropped.
+    Rgb888,
+    /// 16 bits per pixel: 5 bits of red, 6 of green and 5 of blue, with alpha dropped.
+    Rgb565(ByteOrder),
+    /// 16 bits per pixel: 4 bits each of red, green, blue and alpha.
+    Rgba4444(ByteOrder),
+    /// 1 bit per pixel, packed most significant bit first with each row padded to a whole byte.
+    /// A bit is set for white pixels: opaque pixels at least `threshold` bright. Pixels less than
+    /// half opaque are black.
+    Mono {
+        /// The brightness from 0 to 255 at which a pixel becomes white.
+        threshold: u8,
+    },
+}
+
+fn push_u16(buf: &mut Vec<u8>, value: u16, order: ByteOrder) {
+    match order {
+        ByteOrder::BigEndian => buf.extend_from_slice(&value.to_be_bytes()),
+        ByteOrder::LittleEndian => buf.extend_from_slice(&value.to_le_bytes()),
+    }
+}
+
+/// Returns the Rec. 601 luma of `pixel`.
+fn brightness(pixel: [u8; 4]) -> u8 {
+    let [r, g, b, _] = pixel;
+    ((299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b) + 500) / 1000) as u8
+}
+
+/// Converts `image` to `format`.
+pub(crate) fn convert(image: &RgbaImage, format: PixelFormat) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let pixels = || image.pixels().map(|p| p.0);
+
+    match format {
+        PixelFormat::Rgba8888 => image.to_vec(),
+        PixelFormat::Rgb888 => pixels().flat_map(|[r, g, b, _]| vec![r, g, b]).collect(),
+        PixelFormat::Rgb565(order) => {
+            let mut buf = Vec::with_capacity((width * height * 2) as usize);
+            for [r, g, b, _] in pixels() {
+                let value = u16::from(r >> 3) << 11 | u16::from(g >> 2) << 5 | u16::from(b >> 3);
+                push_u16(&mut buf, value, order);
+            }
+            buf
+        }
+        PixelFormat::Rgba4444(order) => {
+            let mut buf = Vec::with_capacity((width * height * 2) as usize);
+            for [r, g, b, a] in pixels() {
+                let value = u16::from(r >> 4) << 12
+                    | u16::from(g >> 4) << 8
+                    | u16::from(b >> 4) << 4
+                    | u16::from(a >> 4);
+                push_u16(&mut buf, value, order);
+            }
+            buf
+        }
+        PixelFormat::Mono { threshold } => {
+            let stride = width.div_ceil(8) as usize;
+            let mut buf = vec![0; stride * height as usize];
+            for (x, y, pixel) in image.enumerate_pixels() {
+                if pixel.0[3] >= 128 && brightness(pixel.0) >= threshold {
+                    buf[y as usize * stride + x as usize / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            buf
+        }
+    }
+}
+
+impl Layer {
+    /// Returns the pixels of this layer converted to `format`, for embedding in firmware or
+    /// uploading straight to a display.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyxel::{ByteOrder, PixelFormat};
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let pixels = doc.canvas().layers()[0].to_pixels(PixelFormat::Rgb565(ByteOrder::BigEndian));
+    /// assert_eq!(128 * 128 * 2, pixels.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_pixels(&self, format: PixelFormat) -> Vec<u8> {
+        match self.image.as_rgba8() {
+            Some(image) => convert(image, format),
+            None => convert(&self.image.to_rgba(), format),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn image() -> RgbaImage {
+        let mut image = RgbaImage::new(9, 2);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 0, Rgba([0, 255, 0, 128]));
+        image.put_pixel(8, 0, Rgba([255, 255, 255, 255]));
+        image.put_pixel(0, 1, Rgba([255, 255, 255, 100]));
+        image
+    }
+
+    #[test]
+    fn packs_16_bit_formats() {
+        let image = image();
+
+        let rgb565 = convert(&image, PixelFormat::Rgb565(ByteOrder::BigEndian));
+        assert_eq!(9 * 2 * 2, rgb565.len());
+        assert_eq!([0xf8, 0x00, 0x07, 0xe0], rgb565[..4]);
+
+        let rgb565 = convert(&image, PixelFormat::Rgb565(ByteOrder::LittleEndian));
+        assert_eq!([0x00, 0xf8, 0xe0, 0x07], rgb565[..4]);
+
+        let rgba4444 = convert(&image, PixelFormat::Rgba4444(ByteOrder::BigEndian));
+        assert_eq!([0xf0, 0x0f, 0x0f, 0x08], rgba4444[..4]);
+    }
+
+    #[test]
+    fn packs_mono_rows_to_whole_bytes() {
+        let mono = convert(&image(), PixelFormat::Mono { threshold: 128 });
+
+        // Red is too dark, half-transparent green is bright enough and the translucent white
+        // pixel is too transparent.
+        assert_eq!(vec![0b0100_0000, 0b1000_0000, 0, 0], mono);
+    }
+
+    #[test]
+    fn drops_alpha_from_rgb888() {
+        let rgb = convert(&image(), PixelFormat::Rgb888);
+        assert_eq!(9 * 2 * 3, rgb.len());
+        assert_eq!([255, 0, 0, 0, 255, 0], rgb[..6]);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::error::PyxelError;
+
+#[cfg(feature = "images")]
+use image::{png::PNGEncoder, ColorType, RgbaImage};
+
+#[cfg(any(test, feature = "archive", not(feature = "images")))]
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Reads the dimensions of a PNG image from its IHDR chunk without decoding it.
+#[cfg(any(test, feature = "archive", not(feature = "images")))]
+pub(crate) fn png_dimensions(data: &[u8]) -> Result<(u32, u32), PyxelError> {
+    // The signature is followed by the IHDR chunk's length and type, then the width and height.
+    if data.len() < 24 || &data[..8] != SIGNATURE || &data[12..16] != b"IHDR" {
+        return Err(PyxelError::InvalidImage);
+    }
+
+    let be_u32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+    Ok((be_u32(&data[16..20]), be_u32(&data[20..24])))
+}
+
+/// Encodes a fully transparent RGBA image as PNG without compressing it, for when `image` isn't
+/// available to encode one.
+#[cfg(any(test, all(feature = "archive", not(feature = "images"))))]
+pub(crate) fn blank_png(width: u32, height: u32) -> Vec<u8> {
+    // Each row is a filter type followed by its pixels, all of which are zero.
+    let raw_len = height as usize * (1 + 4 * width as usize);
+
+    // A zlib stream of stored deflate blocks, which can each hold up to 65535 bytes.
+    let mut idat = vec![0x78, 0x01];
+    let mut remaining = raw_len;
+    loop {
+        let len = remaining.min(0xffff);
+        remaining -= len;
+        idat.push(u8::from(remaining == 0));
+        idat.extend_from_slice(&(len as u16).to_le_bytes());
+        idat.extend_from_slice(&(!(len as u16)).to_le_bytes());
+        idat.resize(idat.len() + len, 0);
+        if remaining == 0 {
+            break;
+        }
+    }
+    // The Adler-32 checksum of zeros only counts them.
+    idat.extend_from_slice(&((((raw_len % 65521) as u32) << 16) | 1).to_be_bytes());
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel RGBA, with the only compression, filter and interlace methods.
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = SIGNATURE.to_vec();
+    for (kind, data) in [(b"IHDR", &ihdr), (b"IDAT", &idat), (b"IEND", &Vec::new())] {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        png.extend_from_slice(&crc32(kind.iter().chain(data.iter())).to_be_bytes());
+    }
+    png
+}
+
+/// Computes the CRC-32 checksum PNG chunks end with.
+#[cfg(any(test, all(feature = "archive", not(feature = "images"))))]
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Encodes an RGBA image as PNG.
+#[cfg(feature = "images")]
+pub(crate) fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, PyxelError> {
+    let mut buf = Vec::new();
+    PNGEncoder::new(&mut buf).encode(image, image.width(), image.height(), ColorType::RGBA(8))?;
+    Ok(buf)
+}
+
+/// Decodes a PNG image using `zune-png`, which is considerably faster than `image`'s decoder.
+///
+/// Images with 16 bits per channel are reduced to 8 bits, and palette images are expanded, to
+/// match the images produced by `image`.
+#[cfg(all(feature = "archive", feature = "fast-png"))]
+pub(crate) fn decode_png(data: &[u8]) -> Result<image::DynamicImage, PyxelError> {
+    use image::{DynamicImage, ImageBuffer, ImageError};
+    use zune_png::zune_core::{colorspace::ColorSpace, options::DecoderOptions};
+
+    let format_error = |e: String| PyxelError::Image(ImageError::FormatError(e));
+
+    let options = DecoderOptions::default().png_set_strip_to_8bit(true);
+    let mut decoder = zune_png::PngDecoder::new_with_options(data, options);
+    let pixels = decoder
+        .decode_raw()
+        .map_err(|e| format_error(e.to_string()))?;
+
+    let (width, height) = decoder.get_dimensions().ok_or(PyxelError::InvalidImage)?;
+    let (width, height) = (width as u32, height as u32);
+    let invalid = || format_error("decoded image has the wrong size".to_owned());
+
+    Ok(match decoder.get_colorspace() {
+        Some(ColorSpace::RGBA) => DynamicImage::ImageRgba8(
+            ImageBuffer::from_raw(width, height, pixels).ok_or_else(invalid)?,
+        ),
+        Some(ColorSpace::RGB) => DynamicImage::ImageRgb8(
+            ImageBuffer::from_raw(width, height, pixels).ok_or_else(invalid)?,
+        ),
+        Some(ColorSpace::LumaA) => DynamicImage::ImageLumaA8(
+            ImageBuffer::from_raw(width, height, pixels).ok_or_else(invalid)?,
+        ),
+        Some(ColorSpace::Luma) => DynamicImage::ImageLuma8(
+            ImageBuffer::from_raw(width, height, pixels).ok_or_else(invalid)?,
+        ),
+        other => return Err(format_error(format!("unsupported color space {:?}", other))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_dimensions_from_header() {
+        let mut data = SIGNATURE.to_vec();
+        data.extend_from_slice(&[0, 0, 0, 13]);
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&[0, 0, 1, 0, 0, 0, 0, 128]);
+
+        assert_eq!((256, 128), png_dimensions(&data).unwrap());
+    }
+
+    #[test]
+    fn blank_png_is_transparent() {
+        let data = blank_png(200, 400);
+        assert_eq!((200, 400), png_dimensions(&data).unwrap());
+
+        #[cfg(feature = "images")]
+        {
+            let image = image::load_from_memory(&data).unwrap().to_rgba();
+            assert_eq!((200, 400), image.dimensions());
+            assert!(image.pixels().all(|p| p.0 == [0; 4]));
+        }
+    }
+
+    #[cfg(feature = "fast-png")]
+    #[test]
+    fn fast_decoder_matches_image() {
+        use std::{fs::File, io::Read};
+
+        let file = File::open("resources/test_v0.4.8.pyxel").unwrap();
+        let mut archive = zip::ZipArchive::new(file).unwrap();
+
+        for name in &["layer0.png", "layer5.png", "tile0.png"] {
+            let mut data = Vec::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+
+            let expected = image::load_from_memory(&data).unwrap();
+            let decoded = decode_png(&data).unwrap();
+            assert_eq!(expected.color(), decoded.color(), "{}", name);
+            assert_eq!(expected.raw_pixels(), decoded.raw_pixels(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        assert!(png_dimensions(b"").is_err());
+        assert!(png_dimensions(b"GIF89a not a png, but long enough").is_err());
+    }
+}
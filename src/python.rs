@@ -0,0 +1,292 @@
+//! The Python module exposed by the `python` feature.
+//!
+//! Pixels are returned as `bytes` of 8-bit RGBA rows alongside a `(height, width, 4)` shape, so
+//! they can be viewed with `numpy.frombuffer(layer.pixels(), numpy.uint8).reshape(layer.shape)`
+//! without this crate depending on numpy.
+
+// The code generated by PyO3's macros converts errors to `PyErr` even when they already are one.
+#![allow(clippy::useless_conversion)]
+
+use crate::{
+    error::PyxelError,
+    pyxel::{Animation, Layer, Pyxel},
+};
+
+use image::{DynamicImage, GenericImageView};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+use std::collections::BTreeMap;
+
+fn to_py_err(e: PyxelError) -> PyErr {
+    match e {
+        PyxelError::Io(e) => e.into(),
+        e => PyValueError::new_err(e.to_string()),
+    }
+}
+
+fn shape(image: &DynamicImage) -> (u32, u32, u32) {
+    (image.height(), image.width(), 4)
+}
+
+fn pixels<'py>(py: Python<'py>, image: &DynamicImage) -> Bound<'py, PyBytes> {
+    PyBytes::new_bound(py, &image.to_rgba())
+}
+
+/// A PyxelEdit document.
+#[pyclass(name = "Document", module = "pyxel", frozen)]
+struct PyDocument {
+    doc: Pyxel,
+}
+
+#[pymethods]
+impl PyDocument {
+    /// The name of the document.
+    #[getter]
+    fn name(&self) -> &str {
+        &self.doc.name
+    }
+
+    /// The width of the canvas in pixels.
+    #[getter]
+    fn width(&self) -> i32 {
+        self.doc.canvas.width
+    }
+
+    /// The height of the canvas in pixels.
+    #[getter]
+    fn height(&self) -> i32 {
+        self.doc.canvas.height
+    }
+
+    /// The width of the tiles in pixels.
+    #[getter]
+    fn tile_width(&self) -> u16 {
+        self.doc.canvas.tile_width
+    }
+
+    /// The height of the tiles in pixels.
+    #[getter]
+    fn tile_height(&self) -> u16 {
+        self.doc.canvas.tile_height
+    }
+
+    /// The layers of the canvas, top-most first.
+    #[getter]
+    fn layers(&self) -> Vec<PyLayer> {
+        let visibility = self.doc.canvas.effective_visibility();
+        self.doc
+            .canvas
+            .layers
+            .iter()
+            .zip(visibility)
+            .map(|(layer, visible)| PyLayer {
+                layer: layer.clone(),
+                visible,
+            })
+            .collect()
+    }
+
+    /// The animations of the document.
+    #[getter]
+    fn animations(&self) -> Vec<PyAnimation> {
+        self.doc
+            .animations
+            .iter()
+            .map(|animation| PyAnimation {
+                animation: animation.clone(),
+            })
+            .collect()
+    }
+
+    /// The number of tiles in the tileset.
+    #[getter]
+    fn tile_count(&self) -> usize {
+        self.doc.tileset.images.len()
+    }
+
+    /// Returns the pixels of a tile as RGBA bytes.
+    fn tile_pixels<'py>(&self, py: Python<'py>, tile: usize) -> PyResult<Bound<'py, PyBytes>> {
+        self.doc
+            .tileset
+            .images
+            .get(tile)
+            .map(|image| pixels(py, image))
+            .ok_or_else(|| {
+                pyo3::exceptions::PyIndexError::new_err(format!("tile {} does not exist", tile))
+            })
+    }
+
+    /// Composites the visible layers into RGBA bytes with the shape `(height, width, 4)`.
+    fn flatten<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.doc.canvas.flatten())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<pyxel.Document {:?}>", self.doc.name)
+    }
+}
+
+/// A layer of a PyxelEdit canvas.
+#[pyclass(name = "Layer", module = "pyxel", frozen)]
+struct PyLayer {
+    layer: Layer,
+    visible: bool,
+}
+
+#[pymethods]
+impl PyLayer {
+    /// The name of the layer.
+    #[getter]
+    fn name(&self) -> &str {
+        &self.layer.name
+    }
+
+    /// The alpha of the layer, from 0 to 255.
+    #[getter]
+    fn alpha(&self) -> u8 {
+        self.layer.alpha
+    }
+
+    /// The blend mode of the layer, e.g. `"normal"`.
+    #[getter]
+    fn blend_mode(&self) -> String {
+        self.layer.blend_mode.to_string()
+    }
+
+    /// Whether the layer is hidden in the editor.
+    #[getter]
+    fn hidden(&self) -> bool {
+        self.layer.hidden
+    }
+
+    /// Whether the layer is shown in the editor, taking hidden, muted and soloed layers into
+    /// account.
+    #[getter]
+    fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// The shape of the layer's pixels, `(height, width, 4)`.
+    #[getter]
+    fn shape(&self) -> (u32, u32, u32) {
+        shape(&self.layer.image)
+    }
+
+    /// The tile refs of the layer as a dict of canvas cell to `(tile, rotation, flip_x)`.
+    #[getter]
+    fn tile_refs(&self) -> BTreeMap<usize, (usize, f64, bool)> {
+        self.layer
+            .tile_refs
+            .iter()
+            .map(|(&cell, tile_ref)| (cell, (tile_ref.index, tile_ref.rot, tile_ref.flip_x)))
+            .collect()
+    }
+
+    /// Returns the pixels of the layer as RGBA bytes.
+    fn pixels<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        pixels(py, &self.layer.image)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<pyxel.Layer {:?}>", self.layer.name)
+    }
+}
+
+/// An animation of a PyxelEdit document.
+#[pyclass(name = "Animation", module = "pyxel", frozen)]
+struct PyAnimation {
+    animation: Animation,
+}
+
+#[pymethods]
+impl PyAnimation {
+    /// The name of the animation.
+    #[getter]
+    fn name(&self) -> &str {
+        &self.animation.name
+    }
+
+    /// The canvas cell of the first frame.
+    #[getter]
+    fn base_tile(&self) -> usize {
+        self.animation.base_tile
+    }
+
+    /// The number of frames.
+    #[getter]
+    fn length(&self) -> usize {
+        self.animation.length
+    }
+
+    /// The duration of each frame in milliseconds.
+    #[getter]
+    fn frame_durations_ms(&self) -> Vec<u64> {
+        self.animation
+            .frame_durations()
+            .iter()
+            .map(|duration| duration.as_millis() as u64)
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<pyxel.Animation {:?}>", self.animation.name)
+    }
+}
+
+/// Opens the document at `path`.
+#[pyfunction]
+fn open(py: Python, path: std::path::PathBuf) -> PyResult<PyDocument> {
+    let doc = py.allow_threads(|| crate::open(path)).map_err(to_py_err)?;
+    Ok(PyDocument { doc })
+}
+
+/// Loads a document from `bytes`.
+#[pyfunction]
+fn load(py: Python, data: &[u8]) -> PyResult<PyDocument> {
+    let doc = py
+        .allow_threads(|| crate::load_from_memory(data))
+        .map_err(to_py_err)?;
+    Ok(PyDocument { doc })
+}
+
+/// Loads PyxelEdit documents.
+#[pymodule]
+fn pyxel(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(open, module)?)?;
+    module.add_function(wrap_pyfunction!(load, module)?)?;
+    module.add_class::<PyDocument>()?;
+    module.add_class::<PyLayer>()?;
+    module.add_class::<PyAnimation>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn python_module_reads_documents() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "pyxel").unwrap();
+            pyxel(&module).unwrap();
+
+            let locals = pyo3::types::PyDict::new_bound(py);
+            locals.set_item("pyxel", module).unwrap();
+            py.run_bound(
+                r#"
+doc = pyxel.open("resources/test_v0.4.8.pyxel")
+assert (doc.width, doc.height) == (256, 128)
+layer = doc.layers[0]
+assert layer.name == "Layer 10" and layer.blend_mode == "subtract"
+assert len(layer.pixels()) == 128 * 256 * 4 and layer.shape == (128, 256, 4)
+assert len(layer.tile_refs) == 4
+assert doc.animations[0].frame_durations_ms == [150, 300, 450, 600]
+"#,
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+        });
+    }
+}
@@ -3,16 +3,18 @@ use crate::{
         deserialize_as_degrees, deserialize_as_milliseconds, deserialize_map_as_vec,
         deserialize_multipliers,
     },
+    doc_data::LoadWarning,
     error::PyxelError,
+    extra::ExtraEntry,
 };
 
 use derivative::Derivative;
 use semver::Version;
-use serde::Deserialize;
-use std::{collections::BTreeMap, time::Duration};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 /// An RGBA color
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Color {
     /// The red component of this color.
     pub r: u8,
@@ -24,20 +26,76 @@ pub struct Color {
     pub a: u8,
 }
 
+/// The hex formats a [`Color`](struct.Color.html) can be parsed from.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ColorFormat {
+    /// `AARRGGBB`, as used by PyxelEdit.
+    #[default]
+    Argb,
+    /// `RRGGBBAA`, as used by CSS.
+    Rgba,
+    /// `RRGGBB`, which is always fully opaque.
+    Rgb,
+}
+
+impl Color {
+    /// Parses a hex color in the given format, ignoring a leading `#`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyxel::{Color, ColorFormat};
+    ///
+    /// let color = Color::parse("#ff8000", ColorFormat::Rgb).unwrap();
+    /// assert_eq!(Color::parse("ffff8000", ColorFormat::Argb), Ok(color));
+    /// assert_eq!(Color::parse("#ff8000ff", ColorFormat::Rgba), Ok(color));
+    /// ```
+    pub fn parse(s: &str, format: ColorFormat) -> Result<Color, hex::FromHexError> {
+        use hex::FromHex;
+
+        let s = s.strip_prefix('#').unwrap_or(s);
+
+        Ok(match format {
+            ColorFormat::Argb => {
+                let [a, r, g, b] = <[u8; 4]>::from_hex(s)?;
+                Color { r, g, b, a }
+            }
+            ColorFormat::Rgba => {
+                let [r, g, b, a] = <[u8; 4]>::from_hex(s)?;
+                Color { r, g, b, a }
+            }
+            ColorFormat::Rgb => {
+                let [r, g, b] = <[u8; 3]>::from_hex(s)?;
+                Color { r, g, b, a: 255 }
+            }
+        })
+    }
+}
+
+/// Formats the color as `AARRGGBB`, the format used by PyxelEdit.
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02x}{:02x}{:02x}{:02x}",
+            self.a, self.r, self.g, self.b
+        )
+    }
+}
+
+/// Parses a color in the `AARRGGBB` format used by PyxelEdit, ignoring a leading `#`. Use
+/// [`Color::parse`](struct.Color.html#method.parse) for other formats.
 impl std::str::FromStr for Color {
     type Err = hex::FromHexError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        use hex::FromHex;
-
-        let decoded = <[u8; 4]>::from_hex(s)?;
+        Color::parse(s, ColorFormat::Argb)
+    }
+}
 
-        Ok(Color {
-            r: decoded[1],
-            g: decoded[2],
-            b: decoded[3],
-            a: decoded[0],
-        })
+impl serde::ser::Serialize for Color {
+    fn serialize<S: serde::ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
 }
 
@@ -63,25 +121,35 @@ impl<'de> serde::de::Visitor<'de> for ColorVisitor {
 }
 
 /// A Pyxel palette.
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct Palette {
     #[serde(deserialize_with = "deserialize_map_as_vec")]
-    colors: Vec<Option<Color>>,
+    pub(crate) colors: Vec<Option<Color>>,
 
-    height: u8,
+    pub(crate) height: u8,
 
     #[serde(rename = "numColors")]
-    num_colors: usize,
+    pub(crate) num_colors: usize,
 
-    width: u8,
+    pub(crate) width: u8,
 }
 
 impl Palette {
     /// Returns the colors that make up this palette.
-    pub fn colors(&self) -> &Vec<Option<Color>> {
+    pub fn colors(&self) -> &[Option<Color>] {
         &self.colors
     }
 
+    /// Returns the number of colors this palette declares.
+    ///
+    /// This is the count stored in the document, which isn't checked against the colors
+    /// themselves, so it may differ from the length of [`colors`](#method.colors) for a document
+    /// that was edited by hand or written by another tool. Use
+    /// [`Pyxel::validate`](struct.Pyxel.html#method.validate) to detect this.
+    pub fn num_colors(&self) -> usize {
+        self.num_colors
+    }
+
     /// Returns the height of this palette when displayed in the PyxelEdit UI.
     pub fn height(&self) -> u8 {
         self.height
@@ -93,18 +161,70 @@ impl Palette {
     }
 }
 
+/// The editor settings of a Pyxel document, such as the color of its background and grid.
+///
+/// PyxelEdit only stores the settings that have been changed from its defaults, so each of them
+/// is optional. Settings this crate doesn't know about are kept in [`other`](#method.other).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Settings {
+    #[serde(
+        default,
+        rename = "backgroundColor",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) background_color: Option<Color>,
+
+    #[serde(default, rename = "gridColor", skip_serializing_if = "Option::is_none")]
+    pub(crate) grid_color: Option<Color>,
+
+    #[serde(default, rename = "showGrid", skip_serializing_if = "Option::is_none")]
+    pub(crate) show_grid: Option<bool>,
+
+    #[serde(flatten)]
+    pub(crate) other: BTreeMap<String, serde_json::Value>,
+}
+
+impl Settings {
+    /// Returns the color drawn behind the canvas, if one has been set.
+    pub fn background_color(&self) -> Option<Color> {
+        self.background_color
+    }
+
+    /// Returns the color of the grid drawn over the canvas, if one has been set.
+    pub fn grid_color(&self) -> Option<Color> {
+        self.grid_color
+    }
+
+    /// Returns whether the grid is shown over the canvas, if this has been set.
+    pub fn show_grid(&self) -> Option<bool> {
+        self.show_grid
+    }
+
+    /// Returns the settings that aren't exposed by another method, keyed by their name in the
+    /// document.
+    pub fn other(&self) -> &BTreeMap<String, serde_json::Value> {
+        &self.other
+    }
+}
+
 /// A reference to a tile in a Pyxel tileset.
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
 pub struct TileRef {
-    index: usize,
+    pub(crate) index: usize,
     #[serde(deserialize_with = "deserialize_as_degrees")]
-    rot: f64,
+    pub(crate) rot: f64,
 
     #[serde(rename = "flipX")]
-    flip_x: bool,
+    pub(crate) flip_x: bool,
 }
 
 impl TileRef {
+    /// Creates a reference to the tile at `index` in the tileset, rotated clockwise by `rot`
+    /// degrees and optionally flipped horizontally.
+    pub fn new(index: usize, rot: f64, flip_x: bool) -> TileRef {
+        TileRef { index, rot, flip_x }
+    }
+
     /// Returns the index of the tile in the tileset.
     pub fn index(&self) -> usize {
         self.index
@@ -169,36 +289,125 @@ pub enum BlendMode {
     Subtract,
 }
 
+impl BlendMode {
+    /// Every blend mode, in the order PyxelEdit lists them.
+    pub const ALL: [BlendMode; 11] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Add,
+        BlendMode::Difference,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::Hardlight,
+        BlendMode::Invert,
+        BlendMode::Overlay,
+        BlendMode::Screen,
+        BlendMode::Subtract,
+    ];
+
+    /// Returns the name PyxelEdit uses for this blend mode, e.g. `hardlight`.
+    pub fn name(self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Add => "add",
+            BlendMode::Difference => "difference",
+            BlendMode::Darken => "darken",
+            BlendMode::Lighten => "lighten",
+            BlendMode::Hardlight => "hardlight",
+            BlendMode::Invert => "invert",
+            BlendMode::Overlay => "overlay",
+            BlendMode::Screen => "screen",
+            BlendMode::Subtract => "subtract",
+        }
+    }
+}
+
+/// Formats the blend mode as the name PyxelEdit uses for it, see
+/// [`BlendMode::name`](enum.BlendMode.html#method.name).
+impl std::fmt::Display for BlendMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The error returned when parsing a [`BlendMode`](enum.BlendMode.html) fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseBlendModeError;
+
+impl std::fmt::Display for ParseBlendModeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("unknown blend mode")
+    }
+}
+
+impl std::error::Error for ParseBlendModeError {}
+
+/// Parses the name PyxelEdit uses for a blend mode, e.g. `hardlight`.
+///
+/// # Examples
+///
+/// ```
+/// use pyxel::BlendMode;
+///
+/// assert_eq!(Ok(BlendMode::Hardlight), "hardlight".parse());
+/// for blend_mode in BlendMode::ALL.iter() {
+///     assert_eq!(Ok(*blend_mode), blend_mode.to_string().parse());
+/// }
+/// ```
+impl std::str::FromStr for BlendMode {
+    type Err = ParseBlendModeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BlendMode::ALL
+            .iter()
+            .copied()
+            .find(|blend_mode| blend_mode.name() == s)
+            .ok_or(ParseBlendModeError)
+    }
+}
+
+#[cfg(feature = "images")]
+fn default_image() -> Arc<image::DynamicImage> {
+    Arc::new(image::DynamicImage::new_rgba8(1, 1))
+}
+
 #[cfg(feature = "images")]
-fn default_image() -> image::DynamicImage {
-    image::DynamicImage::new_rgba8(1, 1)
+fn same_pixels(a: &image::DynamicImage, b: &image::DynamicImage) -> bool {
+    use image::GenericImageView;
+
+    a.dimensions() == b.dimensions() && a.to_rgba().into_raw() == b.to_rgba().into_raw()
 }
 
 /// A Pyxel canvas layer.
-#[derive(Derivative, Deserialize)]
-#[derivative(Debug)]
+///
+/// Comparing layers with `==` only compares their metadata and tile refs, use
+/// [`eq_with_pixels`](#method.eq_with_pixels) to compare their images as well.
+#[derive(Clone, Derivative, Deserialize)]
+#[derivative(Debug, PartialEq)]
 pub struct Layer {
-    alpha: u8,
+    pub(crate) alpha: u8,
 
     #[serde(rename = "blendMode")]
-    blend_mode: BlendMode,
+    pub(crate) blend_mode: BlendMode,
 
-    hidden: bool,
-    muted: bool,
-    name: String,
-    soloed: bool,
+    pub(crate) hidden: bool,
+    pub(crate) muted: bool,
+    pub(crate) name: String,
+    pub(crate) soloed: bool,
 
     #[serde(rename = "tileRefs")]
-    tile_refs: BTreeMap<usize, TileRef>,
+    pub(crate) tile_refs: BTreeMap<usize, TileRef>,
 
     #[cfg(not(feature = "images"))]
+    #[derivative(PartialEq = "ignore")]
     #[serde(skip)]
-    image_data: Vec<u8>,
+    pub(crate) image_data: Vec<u8>,
 
     #[cfg(feature = "images")]
-    #[derivative(Debug = "ignore")]
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
     #[serde(default = "default_image", skip)]
-    image: image::DynamicImage,
+    pub(crate) image: Arc<image::DynamicImage>,
 }
 
 impl Layer {
@@ -223,7 +432,7 @@ impl Layer {
     }
 
     /// Returns the name of this layer.
-    pub fn name(&self) -> &String {
+    pub fn name(&self) -> &str {
         &self.name
     }
 
@@ -237,9 +446,76 @@ impl Layer {
         &self.tile_refs
     }
 
+    /// Returns the tile refs for this layer along with the position of their top left corner in
+    /// pixels, for a layer of `canvas`. The tile refs are in cell order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let canvas = doc.canvas();
+    /// for (x, y, tile_ref) in canvas.layers()[0].placed_tiles(canvas) {
+    ///     println!("tile {} at {}, {}", tile_ref.index(), x, y);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn placed_tiles<'a>(
+        &'a self,
+        canvas: &Canvas,
+    ) -> impl Iterator<Item = (u32, u32, &'a TileRef)> + 'a {
+        let tiles_wide = canvas.tiles_wide().max(1);
+        let (tile_width, tile_height) = (canvas.tile_width as usize, canvas.tile_height as usize);
+
+        self.tile_refs.iter().map(move |(&cell, tile_ref)| {
+            let x = cell % tiles_wide * tile_width;
+            let y = cell / tiles_wide * tile_height;
+            (x as u32, y as u32, tile_ref)
+        })
+    }
+
+    /// Returns the width of the image for this layer in pixels.
+    ///
+    /// This is read from the PNG header, so is available without the `images` feature.
+    pub fn width(&self) -> u32 {
+        self.dimensions().0
+    }
+
+    /// Returns the height of the image for this layer in pixels.
+    ///
+    /// This is read from the PNG header, so is available without the `images` feature.
+    pub fn height(&self) -> u32 {
+        self.dimensions().1
+    }
+
+    pub(crate) fn dimensions(&self) -> (u32, u32) {
+        #[cfg(not(feature = "images"))]
+        return crate::png::png_dimensions(&self.image_data).unwrap_or((0, 0));
+
+        #[cfg(feature = "images")]
+        return image::GenericImageView::dimensions(self.image.as_ref());
+    }
+
+    /// Returns `true` if this layer is equal to `other`, including the pixels of their images.
+    ///
+    /// Without the `images` feature the raw PNG bytes are compared instead.
+    pub fn eq_with_pixels(&self, other: &Layer) -> bool {
+        self == other && self.pixels_eq(other)
+    }
+
+    /// Returns `true` if the image for this layer has the same pixels as the image for `other`.
+    pub(crate) fn pixels_eq(&self, other: &Layer) -> bool {
+        #[cfg(not(feature = "images"))]
+        return self.image_data == other.image_data;
+
+        #[cfg(feature = "images")]
+        return same_pixels(&self.image, &other.image);
+    }
+
     /// Returns the raw bytes of the image for this layer.
     #[cfg(not(feature = "images"))]
-    pub fn image_data(&self) -> &Vec<u8> {
+    pub fn image_data(&self) -> &[u8] {
         &self.image_data
     }
 
@@ -248,33 +524,163 @@ impl Layer {
     pub fn image(&self) -> &image::DynamicImage {
         &self.image
     }
+
+    /// Returns the image for this layer for modification.
+    ///
+    /// Images are shared between clones of a document, so the image is copied first if any other
+    /// clone still refers to it.
+    #[cfg(feature = "images")]
+    pub fn image_mut(&mut self) -> &mut image::DynamicImage {
+        Arc::make_mut(&mut self.image)
+    }
+
+    /// Replaces the image for this layer with the PNG image `png`.
+    ///
+    /// Every layer's image covers the whole canvas, so `png` must have the same dimensions as
+    /// the image it replaces. Tile refs are left unchanged.
+    #[cfg(not(feature = "images"))]
+    pub fn set_image_data(&mut self, png: Vec<u8>) -> Result<(), PyxelError> {
+        self.check_image_size(crate::png::png_dimensions(&png)?)?;
+        self.image_data = png;
+        Ok(())
+    }
+
+    /// Replaces the image for this layer with `image`.
+    ///
+    /// Every layer's image covers the whole canvas, so `image` must have the same dimensions as
+    /// the image it replaces. Tile refs are left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// let layer = &mut doc.canvas_mut().layers_mut()[0];
+    /// layer.set_image(image::DynamicImage::new_rgba8(128, 128))?;
+    /// assert!(layer.set_image(image::DynamicImage::new_rgba8(64, 64)).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "images")]
+    pub fn set_image(&mut self, image: image::DynamicImage) -> Result<(), PyxelError> {
+        self.check_image_size(image::GenericImageView::dimensions(&image))?;
+        self.image = Arc::new(image);
+        Ok(())
+    }
+
+    /// Replaces the image for this layer with the `width` × `height` image in `rgba`, a buffer
+    /// of 8-bit RGBA pixels row by row.
+    ///
+    /// The dimensions must match the image being replaced, as for
+    /// [`set_image`](#method.set_image), and `rgba` must hold at least `width` × `height`
+    /// pixels.
+    #[cfg(feature = "images")]
+    pub fn set_rgba(&mut self, width: u32, height: u32, rgba: Vec<u8>) -> Result<(), PyxelError> {
+        let image =
+            image::RgbaImage::from_raw(width, height, rgba).ok_or(PyxelError::InvalidImage)?;
+        self.set_image(image::DynamicImage::ImageRgba8(image))
+    }
+
+    fn check_image_size(&self, (width, height): (u32, u32)) -> Result<(), PyxelError> {
+        if (width, height) != self.dimensions() {
+            return Err(PyxelError::ImageSizeMismatch { width, height });
+        }
+        Ok(())
+    }
+
+    /// Consumes this layer, returning the raw bytes of its image.
+    #[cfg(not(feature = "images"))]
+    pub fn into_image_data(self) -> Vec<u8> {
+        self.image_data
+    }
+
+    /// Consumes this layer, returning its image.
+    ///
+    /// The image is only copied if a clone of this layer still refers to it.
+    #[cfg(feature = "images")]
+    pub fn into_image(self) -> image::DynamicImage {
+        unshare(self.image)
+    }
+}
+
+/// Takes the image out of `image`, copying it only if it is still shared.
+#[cfg(feature = "images")]
+fn unshare(image: Arc<image::DynamicImage>) -> image::DynamicImage {
+    Arc::try_unwrap(image).unwrap_or_else(|shared| (*shared).clone())
 }
 
 /// A Pyxel canvas.
-#[derive(Debug, Deserialize)]
+///
+/// Comparing canvases with `==` ignores the images of their layers.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct Canvas {
     #[serde(deserialize_with = "deserialize_map_as_vec")]
-    layers: Vec<Layer>,
-    height: i32,
+    pub(crate) layers: Vec<Layer>,
+    pub(crate) height: i32,
 
     #[serde(rename = "numLayers")]
-    num_layers: usize,
+    pub(crate) num_layers: usize,
 
     #[serde(rename = "tileHeight")]
-    tile_height: u16,
+    pub(crate) tile_height: u16,
 
     #[serde(rename = "tileWidth")]
-    tile_width: u16,
+    pub(crate) tile_width: u16,
 
-    width: i32,
+    pub(crate) width: i32,
 }
 
 impl Canvas {
-    /// Returns the layers of this canvas.
-    pub fn layers(&self) -> &Vec<Layer> {
+    /// Returns the layers of this canvas, from the top layer to the bottom one as they're listed in
+    /// the PyxelEdit UI. The layer at index 0 is drawn over every other layer.
+    pub fn layers(&self) -> &[Layer] {
         &self.layers
     }
 
+    /// Returns an iterator over the layers of this canvas in the order they're drawn in, starting
+    /// with the bottom layer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let canvas = doc.canvas();
+    /// let bottom = canvas.layers_bottom_to_top().next().unwrap();
+    /// assert_eq!(canvas.layers().last().unwrap().name(), bottom.name());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn layers_bottom_to_top(&self) -> std::iter::Rev<std::slice::Iter<'_, Layer>> {
+        self.layers.iter().rev()
+    }
+
+    /// Returns an iterator over the layers of this canvas starting with the top layer, the order
+    /// of [`layers`](#method.layers).
+    pub fn layers_top_to_bottom(&self) -> std::slice::Iter<'_, Layer> {
+        self.layers.iter()
+    }
+
+    /// Returns the layers of this canvas for modification.
+    pub fn layers_mut(&mut self) -> &mut [Layer] {
+        &mut self.layers
+    }
+
+    /// Consumes this canvas, returning its layers.
+    pub fn into_layers(self) -> Vec<Layer> {
+        self.layers
+    }
+
+    /// Returns the number of layers this canvas declares.
+    ///
+    /// This is the count stored in the document. A layer image is loaded for each entry of
+    /// [`layers`](#method.layers) rather than for each declared layer, so the two may differ for a
+    /// document that was edited by hand or written by another tool. Use
+    /// [`Pyxel::validate`](struct.Pyxel.html#method.validate) to detect this.
+    pub fn num_layers(&self) -> usize {
+        self.num_layers
+    }
+
     /// Returns the height of this canvas in pixels.
     pub fn height(&self) -> i32 {
         self.height
@@ -294,35 +700,90 @@ impl Canvas {
     pub fn width(&self) -> i32 {
         self.width
     }
+
+    /// Returns the number of tile columns in this canvas.
+    pub fn tiles_wide(&self) -> usize {
+        if self.tile_width == 0 {
+            return 0;
+        }
+
+        (self.width.max(0) as usize) / (self.tile_width as usize)
+    }
+
+    /// Returns the number of tile rows in this canvas.
+    pub fn tiles_high(&self) -> usize {
+        if self.tile_height == 0 {
+            return 0;
+        }
+
+        (self.height.max(0) as usize) / (self.tile_height as usize)
+    }
+
+    /// Returns the index used to key tile refs for the cell at `col`, `row`, or `None` if the
+    /// cell lies outside of this canvas.
+    pub fn cell_index(&self, col: usize, row: usize) -> Option<usize> {
+        if col < self.tiles_wide() && row < self.tiles_high() {
+            Some(row * self.tiles_wide() + col)
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether each layer of this canvas is shown in the PyxelEdit editor, in layer
+    /// order.
+    ///
+    /// Hidden and muted layers are never shown. If any layer is soloed, only the soloed layers
+    /// are shown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// assert_eq!(vec![true], doc.canvas().effective_visibility());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn effective_visibility(&self) -> Vec<bool> {
+        let any_soloed = self.layers.iter().any(|layer| layer.soloed);
+        self.layers
+            .iter()
+            .map(|layer| !layer.hidden && !layer.muted && (!any_soloed || layer.soloed))
+            .collect()
+    }
 }
 
 /// A Pyxel tileset.
-#[derive(Derivative, Deserialize)]
-#[derivative(Debug)]
+///
+/// Comparing tilesets with `==` only compares their metadata, use
+/// [`eq_with_pixels`](#method.eq_with_pixels) to compare their images as well.
+#[derive(Clone, Derivative, Deserialize)]
+#[derivative(Debug, PartialEq)]
 pub struct Tileset {
     #[serde(rename = "fixedWidth")]
-    fixed_width: bool,
+    pub(crate) fixed_width: bool,
 
     #[serde(rename = "numTiles")]
-    num_tiles: usize,
+    pub(crate) num_tiles: usize,
 
     #[serde(rename = "tileHeight")]
-    tile_height: u16,
+    pub(crate) tile_height: u16,
 
     #[serde(rename = "tileWidth")]
-    tile_width: u16,
+    pub(crate) tile_width: u16,
 
     #[serde(rename = "tilesWide")]
-    tiles_wide: u8,
+    pub(crate) tiles_wide: u8,
 
     #[cfg(not(feature = "images"))]
+    #[derivative(PartialEq = "ignore")]
     #[serde(skip)]
-    image_data: Vec<Vec<u8>>,
+    pub(crate) image_data: Vec<Vec<u8>>,
 
     #[cfg(feature = "images")]
-    #[derivative(Debug = "ignore")]
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
     #[serde(skip)]
-    images: Vec<image::DynamicImage>,
+    pub(crate) images: Vec<Arc<image::DynamicImage>>,
 }
 
 impl Tileset {
@@ -331,6 +792,17 @@ impl Tileset {
         self.fixed_width
     }
 
+    /// Returns the number of tiles this tileset declares.
+    ///
+    /// A tile image is loaded for each declared tile, so this matches the number of tile images
+    /// when a document is loaded. Changing the tileset afterwards, e.g. with
+    /// [`Pyxel::import_tileset`](struct.Pyxel.html#method.import_tileset), keeps the two in step,
+    /// but they may differ if the images are edited directly. Use
+    /// [`Pyxel::validate`](struct.Pyxel.html#method.validate) to detect this.
+    pub fn num_tiles(&self) -> usize {
+        self.num_tiles
+    }
+
     /// Returns the tile height in pixels of the tiles in this tileset.
     pub fn tile_height(&self) -> u16 {
         self.tile_height
@@ -346,39 +818,110 @@ impl Tileset {
         self.tiles_wide
     }
 
+    /// Returns the number of tile images loaded for this tileset.
+    pub(crate) fn tile_count(&self) -> usize {
+        #[cfg(not(feature = "images"))]
+        return self.image_data.len();
+
+        #[cfg(feature = "images")]
+        return self.images.len();
+    }
+
+    /// Returns the dimensions in pixels of the image for the tile at `index`, or `None` if there is
+    /// no such tile.
+    ///
+    /// This is read from the PNG header, so is available without the `images` feature.
+    pub fn tile_image_size(&self, index: usize) -> Option<(u32, u32)> {
+        #[cfg(not(feature = "images"))]
+        return self
+            .image_data
+            .get(index)
+            .map(|data| crate::png::png_dimensions(data).unwrap_or((0, 0)));
+
+        #[cfg(feature = "images")]
+        return self
+            .images
+            .get(index)
+            .map(|image| image::GenericImageView::dimensions(image.as_ref()));
+    }
+
+    /// Returns `true` if this tileset is equal to `other`, including the pixels of their tile
+    /// images.
+    ///
+    /// Without the `images` feature the raw PNG bytes are compared instead.
+    pub fn eq_with_pixels(&self, other: &Tileset) -> bool {
+        #[cfg(not(feature = "images"))]
+        return self == other && self.image_data == other.image_data;
+
+        #[cfg(feature = "images")]
+        return self == other
+            && self.images.len() == other.images.len()
+            && self
+                .images
+                .iter()
+                .zip(&other.images)
+                .all(|(a, b)| same_pixels(a, b));
+    }
+
     /// Returns raw bytes of the images for the tiles in this tileset.
     #[cfg(not(feature = "images"))]
-    pub fn image_data(&self) -> &Vec<Vec<u8>> {
+    pub fn image_data(&self) -> &[Vec<u8>] {
         &self.image_data
     }
 
     /// Returns the images for the tiles in this tileset.
+    ///
+    /// Images are shared between clones of a document, so cloning a tileset or document doesn't
+    /// copy any pixels.
     #[cfg(feature = "images")]
-    pub fn images(&self) -> &Vec<image::DynamicImage> {
+    pub fn images(&self) -> &[Arc<image::DynamicImage>] {
         &self.images
     }
+
+    /// Returns the image for the tile at `index` for modification, or `None` if there is no such
+    /// tile.
+    ///
+    /// The image is copied first if any clone of this tileset still refers to it.
+    #[cfg(feature = "images")]
+    pub fn image_mut(&mut self, index: usize) -> Option<&mut image::DynamicImage> {
+        self.images.get_mut(index).map(Arc::make_mut)
+    }
+
+    /// Consumes this tileset, returning the raw bytes of the images for its tiles.
+    #[cfg(not(feature = "images"))]
+    pub fn into_image_data(self) -> Vec<Vec<u8>> {
+        self.image_data
+    }
+
+    /// Consumes this tileset, returning the images for its tiles.
+    ///
+    /// Each image is only copied if a clone of this tileset still refers to it.
+    #[cfg(feature = "images")]
+    pub fn into_images(self) -> Vec<image::DynamicImage> {
+        self.images.into_iter().map(unshare).collect()
+    }
 }
 
 /// A Pyxel animation.
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct Animation {
     #[serde(rename = "baseTile")]
-    base_tile: usize,
+    pub(crate) base_tile: usize,
 
     #[serde(
         deserialize_with = "deserialize_as_milliseconds",
         rename = "frameDuration"
     )]
-    frame_duration: Duration,
+    pub(crate) frame_duration: Duration,
 
     #[serde(
         deserialize_with = "deserialize_multipliers",
         rename = "frameDurationMultipliers"
     )]
-    frame_duration_multipliers: Vec<f64>,
+    pub(crate) frame_duration_multipliers: Vec<f64>,
 
-    length: usize,
-    name: String,
+    pub(crate) length: usize,
+    pub(crate) name: String,
 }
 
 impl Animation {
@@ -393,7 +936,7 @@ impl Animation {
     }
 
     /// Returns the frame duration multipliers for this animation.
-    pub fn frame_duration_multipliers(&self) -> &Vec<f64> {
+    pub fn frame_duration_multipliers(&self) -> &[f64] {
         &self.frame_duration_multipliers
     }
 
@@ -402,27 +945,59 @@ impl Animation {
         self.length
     }
 
+    /// Returns the duration of each frame of this animation, i.e. the base frame duration scaled
+    /// by the frame's multiplier.
+    pub fn frame_durations(&self) -> Vec<Duration> {
+        (0..self.length)
+            .map(|i| {
+                let multiplier = self.frame_duration_multipliers.get(i).unwrap_or(&1.);
+                self.frame_duration.mul_f64(*multiplier)
+            })
+            .collect()
+    }
+
     /// Returns the name of this animation.
-    pub fn name(&self) -> &String {
+    pub fn name(&self) -> &str {
         &self.name
     }
 }
 
 /// A Pyxel document.
-#[derive(Debug, Deserialize)]
+///
+/// Comparing documents with `==` ignores the images of their layers and tiles, use
+/// [`eq_with_pixels`](#method.eq_with_pixels) to compare them as well. It also ignores what was
+/// read from the archive alongside the document: its
+/// [extra entries](#method.extra_entries), [load warnings](#method.load_warnings),
+/// [user metadata](#method.user_metadata) and [raw document data](#method.raw_doc_data).
+#[derive(Clone, Derivative, Deserialize)]
+#[derivative(Debug, PartialEq)]
 pub struct Pyxel {
     #[serde(deserialize_with = "deserialize_map_as_vec")]
-    animations: Vec<Animation>,
-    canvas: Canvas,
-    name: String,
-    palette: Palette,
-    tileset: Tileset,
-    version: Version,
+    pub(crate) animations: Vec<Animation>,
+    pub(crate) canvas: Canvas,
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    pub(crate) extra_entries: Vec<ExtraEntry>,
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    pub(crate) load_warnings: Vec<LoadWarning>,
+    pub(crate) name: String,
+    pub(crate) palette: Palette,
+    #[serde(skip)]
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    pub(crate) raw_doc_data: Option<Arc<str>>,
+    #[serde(default)]
+    pub(crate) settings: Settings,
+    pub(crate) tileset: Tileset,
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    pub(crate) user_metadata: Option<serde_json::Value>,
+    pub(crate) version: Version,
 }
 
 impl Pyxel {
     /// Returns the animations for this document.
-    pub fn animations(&self) -> &Vec<Animation> {
+    pub fn animations(&self) -> &[Animation] {
         &self.animations
     }
 
@@ -431,8 +1006,13 @@ impl Pyxel {
         &self.canvas
     }
 
+    /// Returns a mutable reference to the canvas for this document.
+    pub fn canvas_mut(&mut self) -> &mut Canvas {
+        &mut self.canvas
+    }
+
     /// Returns the name of this document.
-    pub fn name(&self) -> &String {
+    pub fn name(&self) -> &str {
         &self.name
     }
 
@@ -441,100 +1021,164 @@ impl Pyxel {
         &self.palette
     }
 
+    /// Returns the `docData.json` entry this document was loaded from, as it was read.
+    ///
+    /// This is an escape hatch for fields this crate doesn't model yet. It holds the exact text at
+    /// load time, so doesn't reflect any changes made since and isn't written when saving. The
+    /// text is only kept when loading with
+    /// [`LoadOptions::keep_raw_doc_data`](struct.LoadOptions.html#structfield.keep_raw_doc_data)
+    /// set, this returns `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let options = pyxel::LoadOptions {
+    ///     keep_raw_doc_data: true,
+    ///     ..pyxel::LoadOptions::default()
+    /// };
+    /// let doc = pyxel::load_with_options(File::open("resources/doc.pyxel")?, &options)?;
+    /// let doc_data = doc.raw_doc_data_value().unwrap();
+    /// assert_eq!(doc.name(), doc_data["name"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn raw_doc_data(&self) -> Option<&str> {
+        self.raw_doc_data.as_deref()
+    }
+
+    /// Returns the [`raw_doc_data`](#method.raw_doc_data) of this document parsed as JSON.
+    pub fn raw_doc_data_value(&self) -> Option<serde_json::Value> {
+        serde_json::from_str(self.raw_doc_data()?).ok()
+    }
+
+    /// Returns the editor settings for this document.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// assert_eq!(None, doc.settings().background_color());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
     /// Returns the tileset for this document.
     pub fn tileset(&self) -> &Tileset {
         &self.tileset
     }
 
+    /// Returns a mutable reference to the tileset for this document.
+    pub fn tileset_mut(&mut self) -> &mut Tileset {
+        &mut self.tileset
+    }
+
     /// Returns the version of PyxelEdit this document was created with.
     pub fn version(&self) -> &Version {
         &self.version
     }
-}
 
-#[cfg(not(feature = "images"))]
-fn load_image_data_from_zip<R: std::io::Read + std::io::Seek>(
-    zip: &mut zip::ZipArchive<R>,
-    path: &str,
-) -> Result<Vec<u8>, PyxelError> {
-    use std::io::Read;
-
-    let mut file = zip.by_name(path)?;
-
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf)?;
-
-    Ok(buf)
-}
-
-#[cfg(feature = "images")]
-fn load_image_from_zip<R: std::io::Read + std::io::Seek>(
-    zip: &mut zip::ZipArchive<R>,
-    path: &str,
-) -> Result<image::DynamicImage, PyxelError> {
-    use std::io::Read;
-
-    let mut file = zip.by_name(path)?;
-
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf)?;
-
-    let image = image::load_from_memory_with_format(&buf, image::ImageFormat::PNG)?;
-    Ok(image)
-}
-
-/// Load a Pyxel document from a reader.
-///
-/// # Examples
-///
-/// ```
-/// use std::fs::File;
-/// # fn main() -> Result<(), pyxel::PyxelError> {
-/// let file = File::open("resources/doc.pyxel")?;
-/// let doc = pyxel::load(file)?;
-/// # Ok(())
-/// # }
-/// ```
-pub fn load<R: std::io::Read + std::io::Seek>(r: R) -> Result<Pyxel, PyxelError> {
-    let mut archive = zip::ZipArchive::new(r)?;
-    let data = archive.by_name("docData.json")?;
-
-    let mut pyxel: Pyxel = serde_json::from_reader(data)?;
-
-    for i in 0..pyxel.canvas().num_layers {
-        #[cfg(not(feature = "images"))]
-        {
-            let image_data = load_image_data_from_zip(&mut archive, &format!("layer{}.png", i))?;
-            pyxel.canvas.layers[i].image_data = image_data;
-        }
-        #[cfg(feature = "images")]
-        {
-            let image = load_image_from_zip(&mut archive, &format!("layer{}.png", i))?;
-            pyxel.canvas.layers[i].image = image;
+    /// Consumes this document, returning the parts it is made up of so they can be moved out
+    /// without being cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let parts = doc.into_parts();
+    /// let layers = parts.canvas.into_layers();
+    /// assert_eq!("Layer 0", layers[0].name());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_parts(self) -> PyxelParts {
+        PyxelParts {
+            animations: self.animations,
+            canvas: self.canvas,
+            extra_entries: self.extra_entries,
+            name: self.name,
+            palette: self.palette,
+            settings: self.settings,
+            tileset: self.tileset,
+            user_metadata: self.user_metadata,
+            version: self.version,
         }
     }
 
-    for i in 0..pyxel.tileset().num_tiles {
-        #[cfg(not(feature = "images"))]
-        {
-            let image_data = load_image_data_from_zip(&mut archive, &format!("layer{}.png", i))?;
-            pyxel.tileset.image_data.insert(i, image_data);
-        }
-        #[cfg(feature = "images")]
-        {
-            let image = load_image_from_zip(&mut archive, &format!("tile{}.png", i))?;
-            pyxel.tileset.images.insert(i, image);
-        }
+    /// Returns `true` if this document is equal to `other`, including the pixels of the images of
+    /// their layers and tiles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let a = pyxel::open("resources/doc.pyxel")?;
+    /// let b = pyxel::open("resources/doc.pyxel")?;
+    /// assert!(a.eq_with_pixels(&b));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn eq_with_pixels(&self, other: &Pyxel) -> bool {
+        self == other
+            && self.tileset.eq_with_pixels(&other.tileset)
+            && self.canvas.layers.len() == other.canvas.layers.len()
+            && self
+                .canvas
+                .layers
+                .iter()
+                .zip(&other.canvas.layers)
+                .all(|(a, b)| a.eq_with_pixels(b))
     }
+}
 
-    Ok(pyxel)
+/// The parts of a Pyxel document, returned by
+/// [`Pyxel::into_parts`](struct.Pyxel.html#method.into_parts).
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct PyxelParts {
+    /// The animations of the document.
+    pub animations: Vec<Animation>,
+    /// The canvas of the document.
+    pub canvas: Canvas,
+    /// The entries of the document's archive that aren't part of the document.
+    pub extra_entries: Vec<ExtraEntry>,
+    /// The name of the document.
+    pub name: String,
+    /// The palette of the document.
+    pub palette: Palette,
+    /// The editor settings of the document.
+    pub settings: Settings,
+    /// The tileset of the document.
+    pub tileset: Tileset,
+    /// The user metadata of the document.
+    pub user_metadata: Option<serde_json::Value>,
+    /// The version of PyxelEdit the document was created with.
+    pub version: Version,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::load::load;
     use std::{collections::BTreeMap, fs::File, str::FromStr};
 
+    #[test]
+    fn color_round_trips_through_display() {
+        let c = Color::from_str("#80aabbcc").unwrap();
+        assert_eq!(128, c.a);
+        assert_eq!("80aabbcc", c.to_string());
+        assert_eq!(Ok(c), c.to_string().parse());
+        assert_eq!("\"80aabbcc\"", serde_json::to_string(&c).unwrap());
+        assert!(Color::parse("aabbcc", ColorFormat::Argb).is_err());
+        assert!(Color::parse("ffaabbcc", ColorFormat::Rgb).is_err());
+    }
+
     #[test]
     fn convert_color_from_aarrggbb() {
         let c = Color::from_str("ffaabbcc").unwrap();
@@ -546,6 +1190,30 @@ mod tests {
 
     const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
 
+    #[test]
+    fn effective_visibility_applies_solo() {
+        let file = File::open(TEST_FILE).unwrap();
+        let mut doc = load(file).unwrap();
+
+        // Layer 9 is soloed, so it's the only layer shown.
+        assert_eq!(vec![1], visible_layers(&doc.canvas));
+
+        doc.canvas.layers[1].soloed = false;
+        assert_eq!(
+            vec![0, 1, 4, 5, 6, 7, 8, 9, 10],
+            visible_layers(&doc.canvas)
+        );
+
+        doc.canvas.layers[3].soloed = true;
+        doc.canvas.layers[5].soloed = true;
+        assert_eq!(vec![5], visible_layers(&doc.canvas));
+    }
+
+    fn visible_layers(canvas: &Canvas) -> Vec<usize> {
+        let visibility = canvas.effective_visibility();
+        (0..visibility.len()).filter(|&i| visibility[i]).collect()
+    }
+
     #[test]
     fn load_palette_colors() {
         let file = File::open(TEST_FILE).unwrap();
@@ -648,6 +1316,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn settings_keep_unknown_keys() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        assert_eq!(&Settings::default(), doc.settings());
+
+        let json = r#"{ "backgroundColor": "ff202020", "showGrid": true, "zoom": 4 }"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            Color::from_str("ff202020").ok(),
+            settings.background_color()
+        );
+        assert_eq!(None, settings.grid_color());
+        assert_eq!(Some(true), settings.show_grid());
+        assert_eq!(Some(&serde_json::json!(4)), settings.other().get("zoom"));
+        assert_eq!(1, settings.other().len());
+
+        let written = serde_json::to_value(&settings).unwrap();
+        assert_eq!(settings, serde_json::from_value(written).unwrap());
+    }
+
     #[test]
     fn load_canvas_layer_tilerefs() {
         let file = File::open(TEST_FILE).unwrap();
@@ -721,4 +1409,206 @@ mod tests {
 
         assert_eq!(&tile_refs, doc.canvas().layers()[1].tile_refs());
     }
+
+    #[test]
+    fn blend_mode_names_match_documents() {
+        for &blend_mode in BlendMode::ALL.iter() {
+            let json = serde_json::Value::from(blend_mode.name());
+            assert_eq!(
+                blend_mode,
+                serde_json::from_value::<BlendMode>(json).unwrap()
+            );
+            assert_eq!(Ok(blend_mode), blend_mode.to_string().parse());
+        }
+        assert_eq!(Err(ParseBlendModeError), "Hardlight".parse::<BlendMode>());
+    }
+
+    #[test]
+    fn layers_iterate_in_draw_order() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let canvas = doc.canvas();
+
+        let names: Vec<&str> = canvas.layers_bottom_to_top().map(Layer::name).collect();
+        assert_eq!("Layer 0", names[0]);
+        assert_eq!("Layer 10", names[10]);
+        let names: Vec<&str> = canvas.layers_top_to_bottom().map(Layer::name).collect();
+        assert_eq!("Layer 10", names[0]);
+        assert_eq!(11, canvas.layers_top_to_bottom().len());
+    }
+
+    #[test]
+    fn raw_doc_data_is_kept_from_loading() {
+        assert_eq!(
+            None,
+            load(File::open(TEST_FILE).unwrap()).unwrap().raw_doc_data()
+        );
+
+        let options = crate::doc_data::LoadOptions {
+            keep_raw_doc_data: true,
+            ..crate::doc_data::LoadOptions::default()
+        };
+        let doc = crate::load::load_with_options(File::open(TEST_FILE).unwrap(), &options).unwrap();
+        let buf = {
+            let mut archive = zip::ZipArchive::new(File::open(TEST_FILE).unwrap()).unwrap();
+            let mut buf = String::new();
+            std::io::Read::read_to_string(&mut archive.by_name("docData.json").unwrap(), &mut buf)
+                .unwrap();
+            buf
+        };
+        assert_eq!(Some(&buf[..]), doc.raw_doc_data());
+        assert!(doc.raw_doc_data().unwrap().contains("\"tileWidth\""));
+        let doc_data = doc.raw_doc_data_value().unwrap();
+        assert_eq!(32, doc_data["canvas"]["tileWidth"]);
+
+        let mut edited = doc.clone();
+        edited.raw_doc_data = None;
+        assert_eq!(doc, edited);
+        assert_eq!(None, edited.raw_doc_data_value());
+    }
+
+    #[test]
+    fn placed_tiles_are_in_pixels() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let canvas = doc.canvas();
+
+        let placed: Vec<_> = canvas.layers()[1].placed_tiles(canvas).collect();
+        assert_eq!(8, placed.len());
+        assert_eq!((0, 112), (placed[0].0, placed[0].1));
+        assert_eq!(
+            (224, 112, 270.0),
+            (placed[7].0, placed[7].1, placed[7].2.rot)
+        );
+        assert!(placed[7].2.flip_x);
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        let file = File::open(TEST_FILE).unwrap();
+        let doc = load(file).unwrap();
+
+        let mut copy = doc.clone();
+        copy.canvas_mut().layers[0].tile_refs.clear();
+
+        assert_eq!(4, doc.canvas().layers()[0].tile_refs().len());
+        assert!(copy.canvas().layers()[0].tile_refs().is_empty());
+        assert_eq!(
+            doc.tileset().tile_image_size(0),
+            copy.tileset().tile_image_size(0)
+        );
+    }
+
+    #[test]
+    fn compare_metadata_and_pixels() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+
+        let mut renamed = doc.clone();
+        renamed.canvas_mut().layers[3].name = "Renamed".to_owned();
+        assert_ne!(doc, renamed);
+
+        let mut repainted = doc.clone();
+        #[cfg(not(feature = "images"))]
+        repainted.canvas.layers[3].image_data.push(0);
+        #[cfg(feature = "images")]
+        {
+            *repainted.canvas.layers[3].image_mut() = image::DynamicImage::new_rgba8(256, 128);
+        }
+
+        assert_eq!(doc, repainted);
+        assert!(doc.eq_with_pixels(&doc.clone()));
+        assert!(!doc.eq_with_pixels(&repainted));
+
+        let mut annotated = doc.clone();
+        annotated.set_user_metadata(Some(serde_json::json!({ "scale": 2 })));
+        annotated.extra_entries.push(ExtraEntry {
+            name: "notes.txt".to_owned(),
+            data: b"notes".to_vec(),
+        });
+        annotated
+            .load_warnings
+            .push(LoadWarning::MissingImage("tile0.png".to_owned()));
+        assert_eq!(doc, annotated);
+        assert!(doc.eq_with_pixels(&annotated));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn clones_share_images_until_modified() {
+        use image::GenericImage;
+
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let mut copy = doc.clone();
+        assert!(Arc::ptr_eq(
+            &doc.canvas.layers[0].image,
+            &copy.canvas.layers[0].image
+        ));
+        assert!(Arc::ptr_eq(&doc.tileset.images[0], &copy.tileset.images[0]));
+
+        copy.canvas.layers[0]
+            .image_mut()
+            .put_pixel(0, 0, image::Rgba([1, 2, 3, 4]));
+        copy.tileset
+            .image_mut(0)
+            .unwrap()
+            .put_pixel(0, 0, image::Rgba([1, 2, 3, 4]));
+
+        assert!(!doc.canvas.layers[0].pixels_eq(&copy.canvas.layers[0]));
+        assert!(!doc.tileset.eq_with_pixels(&copy.tileset));
+        assert!(Arc::ptr_eq(&doc.tileset.images[1], &copy.tileset.images[1]));
+        assert!(copy.tileset.image_mut(4).is_none());
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn into_parts_moves_images_out() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let copy = doc.clone();
+
+        let parts = copy.into_parts();
+        assert_eq!(doc.name, parts.name);
+
+        let tiles = parts.tileset.into_images();
+        assert_eq!(4, tiles.len());
+        assert!(same_pixels(&doc.tileset.images[2], &tiles[2]));
+
+        // Still shared with `doc`, so the image has to be copied.
+        let pixels = |image: &image::DynamicImage| image.as_rgba8().map(|b| b.as_ptr());
+        let layer = parts.canvas.into_layers().remove(0);
+        let image = layer.into_image();
+        assert_ne!(pixels(&doc.canvas.layers[0].image), pixels(&image));
+        assert!(same_pixels(&doc.canvas.layers[0].image, &image));
+
+        // Nothing else refers to this one, so it's moved.
+        let layer = doc.into_parts().canvas.into_layers().remove(0);
+        let before = pixels(&layer.image);
+        assert!(before.is_some());
+        assert_eq!(before, pixels(&layer.into_image()));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn set_image_checks_dimensions() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let layer = &mut doc.canvas_mut().layers_mut()[2];
+
+        let mut rgba = vec![0; 256 * 128 * 4];
+        rgba[..4].copy_from_slice(&[1, 2, 3, 4]);
+        layer.set_rgba(256, 128, rgba).unwrap();
+        assert_eq!(
+            image::Rgba([1, 2, 3, 4]),
+            image::GenericImageView::get_pixel(layer.image(), 0, 0)
+        );
+
+        match layer.set_image(image::DynamicImage::new_rgba8(128, 256)) {
+            Err(PyxelError::ImageSizeMismatch {
+                width: 128,
+                height: 256,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match layer.set_rgba(256, 128, vec![0; 16]) {
+            Err(PyxelError::InvalidImage) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!((256, 128), layer.dimensions());
+    }
 }
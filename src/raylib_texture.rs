@@ -0,0 +1,139 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Layer, TileRef, Tileset},
+};
+
+use raylib::{
+    core::{
+        math::Rectangle,
+        texture::{Image, Texture2D},
+    },
+    RaylibHandle, RaylibThread,
+};
+
+fn raylib_error(e: raylib::core::error::Error) -> PyxelError {
+    PyxelError::Backend(Box::new(e))
+}
+
+/// Decodes the PNG image `png` with raylib.
+fn load_png(png: &[u8]) -> Result<Image, PyxelError> {
+    Image::load_image_from_mem(".png", png).map_err(raylib_error)
+}
+
+/// Decodes `image` with raylib, by way of PNG.
+#[cfg(feature = "images")]
+fn load_image(image: &image::DynamicImage) -> Result<Image, PyxelError> {
+    load_png(&crate::png::encode_png(&image.to_rgba())?)
+}
+
+fn load_texture(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    image: &Image,
+) -> Result<Texture2D, PyxelError> {
+    rl.load_texture_from_image(thread, image)
+        .map_err(raylib_error)
+}
+
+impl Layer {
+    /// Returns the image for this layer as a raylib `Image`, which can be used without a window.
+    pub fn to_raylib_image(&self) -> Result<Image, PyxelError> {
+        #[cfg(not(feature = "images"))]
+        return load_png(&self.image_data);
+
+        #[cfg(feature = "images")]
+        return load_image(&self.image);
+    }
+
+    /// Uploads the image for this layer to a raylib `Texture2D`. The window must be open.
+    pub fn load_raylib_texture(
+        &self,
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+    ) -> Result<Texture2D, PyxelError> {
+        load_texture(rl, thread, &self.to_raylib_image()?)
+    }
+}
+
+impl Tileset {
+    /// Returns the image for the tile at `index` as a raylib `Image`.
+    pub fn tile_to_raylib_image(&self, index: usize) -> Result<Image, PyxelError> {
+        #[cfg(not(feature = "images"))]
+        return load_png(
+            self.image_data
+                .get(index)
+                .ok_or(PyxelError::TileOutOfBounds(index))?,
+        );
+
+        #[cfg(feature = "images")]
+        return load_image(
+            self.images
+                .get(index)
+                .ok_or(PyxelError::TileOutOfBounds(index))?,
+        );
+    }
+
+    /// Uploads the [`atlas`](#method.atlas) of this tileset to a raylib `Texture2D`, for drawing
+    /// tiles with the rectangles from
+    /// [`TileRef::raylib_source_rect`](struct.TileRef.html#method.raylib_source_rect). The window
+    /// must be open.
+    #[cfg(feature = "images")]
+    pub fn load_raylib_atlas(
+        &self,
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+    ) -> Result<Texture2D, PyxelError> {
+        let atlas = load_png(&crate::png::encode_png(&self.atlas())?)?;
+        load_texture(rl, thread, &atlas)
+    }
+}
+
+impl TileRef {
+    /// Returns the rectangle of the referenced tile within the atlas of `tileset`, laid out
+    /// `tiles_wide` across as by [`Tileset::atlas`](struct.Tileset.html#method.atlas), to pass as
+    /// the source of raylib's `draw_texture_pro`.
+    ///
+    /// Flipped tile refs have a negative width, which raylib draws mirrored. Pass
+    /// [`rot`](#method.rot) as the rotation, with the origin at the center of the destination
+    /// rectangle, to rotate the tile as PyxelEdit does.
+    pub fn raylib_source_rect(&self, tileset: &Tileset) -> Rectangle {
+        let tiles_wide = usize::from(tileset.tiles_wide).max(1);
+        let (width, height) = (
+            f32::from(tileset.tile_width),
+            f32::from(tileset.tile_height),
+        );
+        let (col, row) = (self.index % tiles_wide, self.index / tiles_wide);
+
+        Rectangle::new(
+            col as f32 * width,
+            row as f32 * height,
+            if self.flip_x { -width } else { width },
+            height,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    #[test]
+    fn source_rects_follow_the_atlas_layout() {
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let tileset = doc.tileset();
+
+        let rect = TileRef::new(3, 90., false).raylib_source_rect(tileset);
+        assert_eq!(
+            (96., 0., 32., 16.),
+            (rect.x, rect.y, rect.width, rect.height)
+        );
+
+        let flipped = TileRef::new(9, 0., true).raylib_source_rect(tileset);
+        assert_eq!(
+            (32., 16., -32., 16.),
+            (flipped.x, flipped.y, flipped.width, flipped.height)
+        );
+    }
+}
@@ -0,0 +1,661 @@
+use crate::{
+    doc_data::{parse_doc_data, LoadOptions, DOC_DATA},
+    error::PyxelError,
+    extra::read_extra_entries,
+    load::{check_image, layer_entry, tile_entry, Budget, EntryReader},
+    metadata::read_user_metadata,
+    pyxel::{Animation, BlendMode, Palette, Pyxel, TileRef},
+};
+
+use semver::Version;
+use std::{
+    fmt,
+    io::{self, Read, Seek},
+};
+
+/// The maximum number of tile refs in a single
+/// [`DocEvent::TileRefBatch`](enum.DocEvent.html#variant.TileRefBatch).
+const TILE_REF_BATCH_SIZE: usize = 256;
+
+/// Identifies the image held by an archive entry.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageKind {
+    /// The image for the layer at this index.
+    Layer(usize),
+    /// The image for the tile at this index.
+    Tile(usize),
+}
+
+/// An event produced by a [`DocReader`](struct.DocReader.html).
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum DocEvent {
+    /// The start of the document. This is always the first event.
+    DocumentStart {
+        /// The name of the document.
+        name: String,
+        /// The version of PyxelEdit the document was created with.
+        version: Version,
+    },
+
+    /// The document's palette.
+    Palette(Palette),
+
+    /// The start of the canvas, followed by the metadata and tile refs of each of its layers.
+    CanvasStart {
+        /// The width of the canvas in pixels.
+        width: i32,
+        /// The height of the canvas in pixels.
+        height: i32,
+        /// The width of the canvas' tiles in pixels.
+        tile_width: u16,
+        /// The height of the canvas' tiles in pixels.
+        tile_height: u16,
+        /// The number of layers in the canvas.
+        num_layers: usize,
+    },
+
+    /// The metadata of a layer, followed by its tile refs.
+    LayerMeta {
+        /// The index of the layer.
+        index: usize,
+        /// The name of the layer.
+        name: String,
+        /// The alpha value of the layer.
+        alpha: u8,
+        /// The blend mode of the layer.
+        blend_mode: BlendMode,
+        /// Whether the layer is hidden in the PyxelEdit UI.
+        hidden: bool,
+        /// Whether the layer is muted in the PyxelEdit UI.
+        muted: bool,
+        /// Whether the layer is soloed in the PyxelEdit UI.
+        soloed: bool,
+    },
+
+    /// Some of the tile refs of a layer, keyed by canvas cell in ascending order.
+    TileRefBatch {
+        /// The index of the layer.
+        layer: usize,
+        /// The tile refs and the cells they're placed in.
+        tile_refs: Vec<(usize, TileRef)>,
+    },
+
+    /// The metadata of the tileset.
+    TilesetMeta {
+        /// Whether the tileset is fixed width in the PyxelEdit UI.
+        fixed_width: bool,
+        /// The number of tiles in the tileset.
+        num_tiles: usize,
+        /// The width of the tiles in pixels.
+        tile_width: u16,
+        /// The height of the tiles in pixels.
+        tile_height: u16,
+        /// The width of the tileset in tiles in the PyxelEdit UI.
+        tiles_wide: u8,
+    },
+
+    /// An animation.
+    AnimationMeta {
+        /// The index of the animation.
+        index: usize,
+        /// The animation.
+        animation: Animation,
+    },
+
+    /// The undecoded PNG data of a layer or tile image.
+    ImageEntry {
+        /// The image held by the entry.
+        kind: ImageKind,
+        /// The PNG data.
+        data: Vec<u8>,
+    },
+}
+
+/// The next event a [`DocReader`](struct.DocReader.html) produces.
+#[derive(Clone, Copy, Debug)]
+enum Stage {
+    DocumentStart,
+    Palette,
+    CanvasStart,
+    LayerMeta(usize),
+    /// The batch of the layer's tile refs starting at this cell.
+    TileRefs(usize, usize),
+    TilesetMeta,
+    AnimationMeta(usize),
+    Images,
+    Done,
+}
+
+/// A pull-based reader producing the contents of a Pyxel document as a sequence of
+/// [`DocEvent`s](enum.DocEvent.html), for building custom models without loading a
+/// [`Pyxel`](struct.Pyxel.html).
+///
+/// This is built on a [`PyxelReader`](struct.PyxelReader.html). The document metadata is parsed
+/// up front and each event is made from it as it's reached. Each image is only read from the
+/// archive when its event is reached and is handed over undecoded, so at most one image is held
+/// in memory at a time. Iteration stops after the first error.
+///
+/// # Examples
+///
+/// ```
+/// use pyxel::{DocEvent, DocReader};
+/// use std::fs::File;
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let file = File::open("resources/doc.pyxel")?;
+///
+/// for event in DocReader::new(file)? {
+///     if let DocEvent::ImageEntry { kind, data } = event? {
+///         println!("{:?} is {} bytes", kind, data.len());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct DocReader<R> {
+    reader: PyxelReader<R>,
+    doc: Pyxel,
+    stage: Stage,
+}
+
+impl<R: Read + Seek> DocReader<R> {
+    /// Creates a reader for the Pyxel document in `r`, parsing its metadata.
+    pub fn new(r: R) -> Result<DocReader<R>, PyxelError> {
+        DocReader::with_options(r, &LoadOptions::default())
+    }
+
+    /// Creates a reader for the Pyxel document in `r` using the options specified, parsing its
+    /// metadata.
+    pub fn with_options(r: R, options: &LoadOptions) -> Result<DocReader<R>, PyxelError> {
+        let mut reader = PyxelReader::with_options(r, options)?;
+
+        let doc = match reader.next_entry() {
+            Some(Ok(Entry::DocData(doc))) => *doc,
+            Some(Err(e)) => return Err(e),
+            Some(Ok(_)) | None => unreachable!("the document data is always the first entry"),
+        };
+
+        Ok(DocReader {
+            reader,
+            doc,
+            stage: Stage::DocumentStart,
+        })
+    }
+
+    /// Returns the event for the current stage and moves on to the next one, or `None` if the
+    /// stage has nothing left to produce.
+    fn advance(&mut self) -> Option<Result<DocEvent, PyxelError>> {
+        let doc = &mut self.doc;
+        let num_layers = doc.canvas.layers.len();
+        let after_layer = |index: usize| {
+            if index + 1 < num_layers {
+                Stage::LayerMeta(index + 1)
+            } else {
+                Stage::TilesetMeta
+            }
+        };
+
+        let (event, next) = match self.stage {
+            Stage::DocumentStart => (
+                DocEvent::DocumentStart {
+                    name: std::mem::take(&mut doc.name),
+                    version: doc.version.clone(),
+                },
+                Stage::Palette,
+            ),
+            Stage::Palette => (DocEvent::Palette(doc.palette.clone()), Stage::CanvasStart),
+            Stage::CanvasStart => (
+                DocEvent::CanvasStart {
+                    width: doc.canvas.width,
+                    height: doc.canvas.height,
+                    tile_width: doc.canvas.tile_width,
+                    tile_height: doc.canvas.tile_height,
+                    num_layers,
+                },
+                if num_layers > 0 {
+                    Stage::LayerMeta(0)
+                } else {
+                    Stage::TilesetMeta
+                },
+            ),
+            Stage::LayerMeta(index) => {
+                let layer = &mut doc.canvas.layers[index];
+                (
+                    DocEvent::LayerMeta {
+                        index,
+                        name: std::mem::take(&mut layer.name),
+                        alpha: layer.alpha,
+                        blend_mode: layer.blend_mode,
+                        hidden: layer.hidden,
+                        muted: layer.muted,
+                        soloed: layer.soloed,
+                    },
+                    Stage::TileRefs(index, 0),
+                )
+            }
+            Stage::TileRefs(layer, start) => {
+                let tile_refs: Vec<(usize, TileRef)> = doc.canvas.layers[layer]
+                    .tile_refs
+                    .range(start..)
+                    .take(TILE_REF_BATCH_SIZE)
+                    .map(|(&cell, &tile_ref)| (cell, tile_ref))
+                    .collect();
+
+                let next = match tile_refs.last() {
+                    Some(&(cell, _)) if tile_refs.len() == TILE_REF_BATCH_SIZE => {
+                        Stage::TileRefs(layer, cell + 1)
+                    }
+                    _ => after_layer(layer),
+                };
+                if tile_refs.is_empty() {
+                    self.stage = next;
+                    return None;
+                }
+
+                (DocEvent::TileRefBatch { layer, tile_refs }, next)
+            }
+            Stage::TilesetMeta => {
+                let tileset = &doc.tileset;
+                (
+                    DocEvent::TilesetMeta {
+                        fixed_width: tileset.fixed_width,
+                        num_tiles: tileset.num_tiles,
+                        tile_width: tileset.tile_width,
+                        tile_height: tileset.tile_height,
+                        tiles_wide: tileset.tiles_wide,
+                    },
+                    Stage::AnimationMeta(0),
+                )
+            }
+            Stage::AnimationMeta(index) => match doc.animations.get(index) {
+                Some(animation) => (
+                    DocEvent::AnimationMeta {
+                        index,
+                        animation: animation.clone(),
+                    },
+                    Stage::AnimationMeta(index + 1),
+                ),
+                None => {
+                    self.stage = Stage::Images;
+                    return None;
+                }
+            },
+            Stage::Images => return self.read_image(),
+            Stage::Done => return None,
+        };
+
+        self.stage = next;
+        Some(Ok(event))
+    }
+
+    fn read_image(&mut self) -> Option<Result<DocEvent, PyxelError>> {
+        let (kind, name, image) = match self.reader.next_entry() {
+            Some(Ok(Entry::LayerImage(i, image))) => (ImageKind::Layer(i), layer_entry(i), image),
+            Some(Ok(Entry::TileImage(i, image))) => (ImageKind::Tile(i), tile_entry(i), image),
+            Some(Ok(Entry::DocData(_))) => unreachable!("the document data was already read"),
+            Some(Err(e)) => return Some(Err(e)),
+            None => {
+                self.stage = Stage::Done;
+                return None;
+            }
+        };
+
+        Some(
+            image
+                .into_vec()
+                .map(|data| DocEvent::ImageEntry { kind, data })
+                .map_err(|e| e.in_entry(&name)),
+        )
+    }
+}
+
+impl<R: Read + Seek> Iterator for DocReader<R> {
+    type Item = Result<DocEvent, PyxelError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Stage::Done = self.stage {
+                return None;
+            }
+
+            if let Some(result) = self.advance() {
+                if result.is_err() {
+                    self.stage = Stage::Done;
+                }
+                return Some(result);
+            }
+        }
+    }
+}
+
+impl<R> fmt::Debug for DocReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DocReader")
+            .field("stage", &self.stage)
+            .finish()
+    }
+}
+
+/// An archive entry produced by [`PyxelReader::next_entry`](struct.PyxelReader.html#method.next_entry).
+#[non_exhaustive]
+pub enum Entry<'a> {
+    /// The document metadata, without any images. This is always the first entry.
+    DocData(Box<Pyxel>),
+    /// The undecoded PNG image for the layer at this index.
+    LayerImage(usize, ImageReader<'a>),
+    /// The undecoded PNG image for the tile at this index.
+    TileImage(usize, ImageReader<'a>),
+}
+
+impl fmt::Debug for Entry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Entry::DocData(ref doc) => f.debug_tuple("DocData").field(doc).finish(),
+            Entry::LayerImage(i, _) => f.debug_tuple("LayerImage").field(&i).finish(),
+            Entry::TileImage(i, _) => f.debug_tuple("TileImage").field(&i).finish(),
+        }
+    }
+}
+
+/// Reads the PNG data of an image entry straight from the archive.
+///
+/// The image's dimensions have already been checked against the limits. Reading fails with an
+/// error of kind [`io::ErrorKind::Other`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html)
+/// once the limit on decompressed bytes is exceeded.
+pub struct ImageReader<'a> {
+    header: [u8; 24],
+    pos: usize,
+    entry: EntryReader<'a, zip::read::ZipFile<'a>>,
+}
+
+impl Read for ImageReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos < self.header.len() {
+            let n = (&self.header[self.pos..]).read(buf)?;
+            self.pos += n;
+            return Ok(n);
+        }
+
+        self.entry.read(buf)
+    }
+}
+
+impl ImageReader<'_> {
+    /// Reads the rest of the image into memory.
+    fn into_vec(mut self) -> Result<Vec<u8>, PyxelError> {
+        let mut data = Vec::new();
+        match self.read_to_end(&mut data) {
+            Ok(_) => Ok(data),
+            Err(e) => Err(self.entry.map_err(e.into())),
+        }
+    }
+}
+
+impl fmt::Debug for ImageReader<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ImageReader").finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Next {
+    DocData,
+    Layer(usize),
+    Tile(usize),
+    Done,
+}
+
+/// Reads a Pyxel document entry by entry, handing each image over as a stream rather than
+/// holding it in memory, so documents of any size can be processed, e.g. by piping each layer
+/// straight to disk.
+///
+/// Unlike [`DocReader`](struct.DocReader.html), each entry borrows the reader, so this isn't an
+/// [`Iterator`](https://doc.rust-lang.org/std/iter/trait.Iterator.html). Reading stops after the
+/// first error.
+///
+/// # Examples
+///
+/// ```
+/// use pyxel::{Entry, PyxelReader};
+/// use std::{fs::File, io};
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let mut reader = PyxelReader::new(File::open("resources/doc.pyxel")?)?;
+///
+/// while let Some(entry) = reader.next_entry() {
+///     if let Entry::LayerImage(i, mut image) = entry? {
+///         let len = io::copy(&mut image, &mut io::sink())?;
+///         println!("layer {} is {} bytes", i, len);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct PyxelReader<R> {
+    archive: zip::ZipArchive<R>,
+    budget: Budget,
+    options: LoadOptions,
+    next: Next,
+    num_layers: usize,
+    num_tiles: usize,
+}
+
+impl<R: Read + Seek> PyxelReader<R> {
+    /// Creates a reader for the Pyxel document in `r`.
+    pub fn new(r: R) -> Result<PyxelReader<R>, PyxelError> {
+        PyxelReader::with_options(r, &LoadOptions::default())
+    }
+
+    /// Creates a reader for the Pyxel document in `r` using the options specified.
+    pub fn with_options(r: R, options: &LoadOptions) -> Result<PyxelReader<R>, PyxelError> {
+        Ok(PyxelReader {
+            archive: zip::ZipArchive::new(r)?,
+            budget: Budget::new(options),
+            options: options.clone(),
+            next: Next::DocData,
+            num_layers: 0,
+            num_tiles: 0,
+        })
+    }
+
+    /// Returns the next entry of the document, or `None` once every entry has been read.
+    pub fn next_entry(&mut self) -> Option<Result<Entry<'_>, PyxelError>> {
+        // Skip past the end of the layers and tiles, which also covers empty canvases and
+        // tilesets.
+        let current = match self.next {
+            Next::Layer(i) if i >= self.num_layers => Next::Tile(0),
+            next => next,
+        };
+        let current = match current {
+            Next::Tile(i) if i >= self.num_tiles => Next::Done,
+            current => current,
+        };
+
+        self.next = match current {
+            Next::DocData => {
+                self.next = Next::Layer(0);
+                return Some(self.read_doc_data());
+            }
+            Next::Layer(i) => Next::Layer(i + 1),
+            Next::Tile(i) => Next::Tile(i + 1),
+            Next::Done => return None,
+        };
+
+        let limits = &self.options.limits;
+        let result = match current {
+            Next::Layer(i) => read_image(&mut self.archive, &self.budget, &layer_entry(i), limits)
+                .map(|image| Entry::LayerImage(i, image)),
+            Next::Tile(i) => read_image(&mut self.archive, &self.budget, &tile_entry(i), limits)
+                .map(|image| Entry::TileImage(i, image)),
+            Next::DocData | Next::Done => unreachable!(),
+        };
+
+        if result.is_err() {
+            self.next = Next::Done;
+        }
+        Some(result)
+    }
+
+    fn read_doc_data(&mut self) -> Result<Entry<'_>, PyxelError> {
+        let result = self
+            .budget
+            .read_entry(&mut self.archive, DOC_DATA)
+            .map_err(|e| e.in_entry(DOC_DATA))
+            .and_then(|buf| parse_doc_data(&buf, &self.options))
+            .and_then(|mut doc| {
+                doc.user_metadata = read_user_metadata(&mut self.archive, &self.budget)?;
+                if self.options.keep_extra_entries {
+                    doc.extra_entries = read_extra_entries(&mut self.archive, &self.budget, &doc)?;
+                }
+                Ok(doc)
+            });
+
+        match result {
+            Ok(doc) => {
+                self.num_layers = doc.canvas.layers.len();
+                self.num_tiles = doc.tileset.num_tiles;
+                Ok(Entry::DocData(Box::new(doc)))
+            }
+            Err(e) => {
+                self.next = Next::Done;
+                Err(e)
+            }
+        }
+    }
+}
+
+fn read_image<'a, R: Read + Seek>(
+    archive: &'a mut zip::ZipArchive<R>,
+    budget: &'a Budget,
+    name: &str,
+    limits: &crate::doc_data::Limits,
+) -> Result<ImageReader<'a>, PyxelError> {
+    let mut entry = budget
+        .open_entry(archive, name)
+        .map_err(|e| e.in_entry(name))?;
+
+    // Check the dimensions of the image from its header before handing it over.
+    let mut header = [0; 24];
+    entry
+        .read_exact(&mut header)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => PyxelError::InvalidImage,
+            _ => entry.map_err(e.into()),
+        })
+        .and_then(|_| check_image(&header, limits))
+        .map_err(|e| e.in_entry(name))?;
+
+    Ok(ImageReader {
+        header,
+        pos: 0,
+        entry,
+    })
+}
+
+impl<R> fmt::Debug for PyxelReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PyxelReader")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn events_follow_document_order() {
+        let file = File::open(TEST_FILE).unwrap();
+        let events: Vec<DocEvent> = DocReader::new(file)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        match events[0] {
+            DocEvent::DocumentStart { ref name, .. } => assert_eq!("test_v0.4.8", name),
+            ref other => panic!("unexpected event {:?}", other),
+        }
+        match events[2] {
+            DocEvent::CanvasStart {
+                width: 256,
+                height: 128,
+                num_layers: 11,
+                ..
+            } => {}
+            ref other => panic!("unexpected event {:?}", other),
+        }
+
+        let layers = events
+            .iter()
+            .filter(|e| matches!(e, DocEvent::LayerMeta { .. }))
+            .count();
+        let tile_refs: usize = events
+            .iter()
+            .map(|e| match e {
+                DocEvent::TileRefBatch { tile_refs, .. } => tile_refs.len(),
+                _ => 0,
+            })
+            .sum();
+        let animations = events
+            .iter()
+            .filter(|e| matches!(e, DocEvent::AnimationMeta { .. }))
+            .count();
+        assert_eq!((11, 12, 3), (layers, tile_refs, animations));
+
+        let images: Vec<ImageKind> = events
+            .iter()
+            .filter_map(|e| match e {
+                DocEvent::ImageEntry { kind, .. } => Some(*kind),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(15, images.len());
+        assert_eq!(ImageKind::Layer(0), images[0]);
+        assert_eq!(ImageKind::Tile(3), images[14]);
+    }
+
+    #[test]
+    fn stops_after_an_error() {
+        let file = File::open(TEST_FILE).unwrap();
+        let mut options = LoadOptions::default();
+        options.limits.max_decompressed_bytes = Some(6000);
+
+        let mut reader = DocReader::with_options(file, &options).unwrap();
+        assert!(reader.any(|event| event.is_err()));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn entries_stream_each_image() {
+        let file = File::open(TEST_FILE).unwrap();
+        let mut reader = PyxelReader::new(file).unwrap();
+
+        match reader.next_entry() {
+            Some(Ok(Entry::DocData(ref doc))) => assert_eq!("test_v0.4.8", doc.name()),
+            other => panic!("unexpected entry {:?}", other),
+        }
+
+        let mut entries = Vec::new();
+        while let Some(entry) = reader.next_entry() {
+            let (index, mut image, layer) = match entry.unwrap() {
+                Entry::LayerImage(i, image) => (i, image, true),
+                Entry::TileImage(i, image) => (i, image, false),
+                other => panic!("unexpected entry {:?}", other),
+            };
+
+            let mut buf = Vec::new();
+            image.read_to_end(&mut buf).unwrap();
+            assert_eq!(b"\x89PNG", &buf[..4]);
+            entries.push((layer, index));
+        }
+
+        assert_eq!(15, entries.len());
+        assert_eq!((true, 0), entries[0]);
+        assert_eq!((true, 10), entries[10]);
+        assert_eq!((false, 3), entries[14]);
+        assert!(reader.next_entry().is_none());
+    }
+}
@@ -0,0 +1,721 @@
+use crate::pyxel::{Animation, BlendMode, Canvas, Color, Layer, Pyxel};
+
+use image::{imageops, GenericImageView, RgbaImage};
+
+/// What [`Canvas::flatten_with`](struct.Canvas.html#method.flatten_with) and
+/// [`Canvas::render_frame_with`](struct.Canvas.html#method.render_frame_with) composite the
+/// canvas onto.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Background {
+    /// Keep transparent pixels transparent.
+    #[default]
+    Transparent,
+    /// A single color.
+    Solid(Color),
+    /// A checkerboard of two colors, as editors show behind transparent pixels.
+    Checkerboard {
+        /// The width and height of each square in pixels of the rendered, scaled image.
+        size: u32,
+        /// The color of the top left square and every other square.
+        light: Color,
+        /// The color of the remaining squares.
+        dark: Color,
+    },
+}
+
+impl Background {
+    /// Returns a checkerboard of white and light gray 8 pixel squares, as PyxelEdit shows.
+    pub fn checkerboard() -> Background {
+        let gray = |v| Color {
+            r: v,
+            g: v,
+            b: v,
+            a: 255,
+        };
+        Background::Checkerboard {
+            size: 8,
+            light: gray(255),
+            dark: gray(204),
+        }
+    }
+
+    /// Returns the color of this background at `x`, `y`.
+    fn color_at(self, x: u32, y: u32) -> [u8; 4] {
+        let color = match self {
+            Background::Transparent => return [0; 4],
+            Background::Solid(color) => color,
+            Background::Checkerboard { size, light, dark } => {
+                let size = size.max(1);
+                if (x / size + y / size).is_multiple_of(2) {
+                    light
+                } else {
+                    dark
+                }
+            }
+        };
+        [color.r, color.g, color.b, color.a]
+    }
+}
+
+/// Options for rendering previews with
+/// [`Canvas::flatten_with`](struct.Canvas.html#method.flatten_with) and
+/// [`Canvas::render_frame_with`](struct.Canvas.html#method.render_frame_with).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderOptions {
+    /// What the canvas is composited onto.
+    pub background: Background,
+    /// The factor the image is scaled up by with nearest-neighbor filtering, before the
+    /// background is added. Values of 0 and 1 leave the image at its original size.
+    pub scale: u32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            background: Background::Transparent,
+            scale: 1,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Scales `image` and composites it onto the background.
+    fn apply(&self, image: RgbaImage) -> RgbaImage {
+        let mut image = scale_image(image, self.scale);
+        if self.background != Background::Transparent {
+            for (x, y, pixel) in image.enumerate_pixels_mut() {
+                let background = self.background.color_at(x, y);
+                pixel.0 = blend_pixel_with(|_, s| s, background, pixel.0, 1.);
+            }
+        }
+        image
+    }
+}
+
+impl Canvas {
+    /// Composites the visible layers of this canvas into a single image, applying each layer's
+    /// blend mode and alpha. See [`effective_visibility`](#method.effective_visibility) for which
+    /// layers are visible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let image = doc.canvas().flatten();
+    /// assert_eq!(128, image.width());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn flatten(&self) -> RgbaImage {
+        self.render_region(0, 0, self.width.max(0) as u32, self.height.max(0) as u32)
+    }
+
+    /// Composites the visible layers of this canvas as [`flatten`](#method.flatten) does, then
+    /// scales the image up `scale` times with nearest-neighbor filtering, so every pixel becomes a
+    /// `scale` × `scale` block. A `scale` of 0 or 1 leaves the image as it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let image = doc.canvas().flatten_scaled(3);
+    /// assert_eq!((384, 384), image.dimensions());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn flatten_scaled(&self, scale: u32) -> RgbaImage {
+        scale_image(self.flatten(), scale)
+    }
+
+    /// Composites the canvas tile shown for `frame` of `animation`, or returns `None` if the frame
+    /// is not part of the animation or lies outside of this canvas.
+    pub fn render_frame(&self, animation: &Animation, frame: usize) -> Option<RgbaImage> {
+        let (x, y) = self.frame_origin(animation, frame)?;
+        Some(self.render_tile_at(x, y))
+    }
+
+    /// Composites the visible layers of this canvas as [`flatten`](#method.flatten) does, then
+    /// scales the image and composites it onto a background as set by `options`, for previews
+    /// meant for people rather than engines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let options = pyxel::RenderOptions {
+    ///     background: pyxel::Background::checkerboard(),
+    ///     scale: 4,
+    /// };
+    /// let preview = doc.canvas().flatten_with(&options);
+    /// assert!(preview.pixels().all(|pixel| pixel[3] == 255));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn flatten_with(&self, options: &RenderOptions) -> RgbaImage {
+        options.apply(self.flatten())
+    }
+
+    /// Composites the canvas tile shown for `frame` of `animation` as
+    /// [`render_frame`](#method.render_frame) does, then scales the image and composites it onto
+    /// a background as set by `options`.
+    pub fn render_frame_with(
+        &self,
+        animation: &Animation,
+        frame: usize,
+        options: &RenderOptions,
+    ) -> Option<RgbaImage> {
+        self.render_frame(animation, frame)
+            .map(|image| options.apply(image))
+    }
+
+    /// Composites the visible layers of the canvas tile at `col`, `row`, or returns `None` if the
+    /// cell lies outside of this canvas.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let image = doc.canvas().render_tile(0, 0).unwrap();
+    /// assert_eq!((16, 16), image.dimensions());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn render_tile(&self, col: usize, row: usize) -> Option<RgbaImage> {
+        self.cell_index(col, row)?;
+        Some(self.render_tile_at(
+            col as u32 * u32::from(self.tile_width),
+            row as u32 * u32::from(self.tile_height),
+        ))
+    }
+
+    fn render_tile_at(&self, x: u32, y: u32) -> RgbaImage {
+        self.render_region(
+            x,
+            y,
+            u32::from(self.tile_width),
+            u32::from(self.tile_height),
+        )
+    }
+
+    /// Returns the pixel position of the canvas tile shown for `frame` of `animation`.
+    pub(crate) fn frame_origin(&self, animation: &Animation, frame: usize) -> Option<(u32, u32)> {
+        if frame >= animation.length {
+            return None;
+        }
+
+        let tile = animation.base_tile + frame;
+        let tiles_wide = self.tiles_wide();
+        if tiles_wide == 0 || tile / tiles_wide >= self.tiles_high() {
+            return None;
+        }
+
+        Some((
+            (tile % tiles_wide) as u32 * u32::from(self.tile_width),
+            (tile / tiles_wide) as u32 * u32::from(self.tile_height),
+        ))
+    }
+
+    /// Composites the `width` × `height` pixel region of the visible layers starting at `x`, `y`.
+    pub(crate) fn render_region(&self, x: u32, y: u32, width: u32, height: u32) -> RgbaImage {
+        let mut out = RgbaImage::new(width, height);
+        self.render_region_into(x, y, &mut out);
+        out
+    }
+
+    /// Composites the region of the visible layers starting at `x`, `y` into `out`, which is
+    /// cleared first.
+    pub(crate) fn render_region_into(&self, x: u32, y: u32, out: &mut RgbaImage) {
+        for p in out.iter_mut() {
+            *p = 0;
+        }
+
+        // Layers are stored top-most first, so composite them in reverse.
+        let visibility = self.effective_visibility();
+        for (layer, _) in self
+            .layers
+            .iter()
+            .zip(visibility)
+            .rev()
+            .filter(|&(_, visible)| visible)
+        {
+            composite_layer(out, layer, x, y);
+        }
+    }
+}
+
+impl Pyxel {
+    /// Returns the image of each layer for the canvas tile shown for `frame` of `animation`
+    /// without compositing them, in the order of
+    /// [`Canvas::layers`](struct.Canvas.html#method.layers), or `None` if the frame is not part of
+    /// the animation or lies outside of the canvas.
+    ///
+    /// Every layer is included whether it's visible or not, and the blend modes and alpha of the
+    /// layers aren't applied, so they can be composited on the GPU or used separately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let layers = doc.render_frame_layers(&doc.animations()[0], 0).unwrap();
+    /// assert_eq!(doc.canvas().layers().len(), layers.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn render_frame_layers(
+        &self,
+        animation: &Animation,
+        frame: usize,
+    ) -> Option<Vec<RgbaImage>> {
+        let canvas = &self.canvas;
+        let (x, y) = canvas.frame_origin(animation, frame)?;
+        let (width, height) = (u32::from(canvas.tile_width), u32::from(canvas.tile_height));
+
+        Some(
+            canvas
+                .layers
+                .iter()
+                .map(|layer| crop_layer(layer, x, y, width, height))
+                .collect(),
+        )
+    }
+}
+
+/// Copies the `width` × `height` pixel region of the image for `layer` starting at `x`, `y`.
+/// Pixels outside of the image are left transparent.
+pub(crate) fn crop_layer(layer: &Layer, x: u32, y: u32, width: u32, height: u32) -> RgbaImage {
+    let mut out = RgbaImage::new(width, height);
+    let (layer_width, layer_height) = layer.image.dimensions();
+    let (width, height) = (
+        width.min(layer_width.saturating_sub(x)),
+        height.min(layer_height.saturating_sub(y)),
+    );
+    if width > 0 && height > 0 {
+        let region = layer.image.view(x, y, width, height).to_image();
+        imageops::replace(&mut out, &region, 0, 0);
+    }
+    out
+}
+
+fn composite_layer(out: &mut RgbaImage, layer: &Layer, x: u32, y: u32) {
+    let converted;
+    let image = match layer.image.as_rgba8() {
+        Some(image) => image,
+        None => {
+            converted = layer.image.to_rgba();
+            &converted
+        }
+    };
+    let opacity = f32::from(layer.alpha) / 255.;
+
+    // Dispatch on the blend mode once per layer so each row is blended by a loop specialised for
+    // it. Normal layers, the most common by far, are blended with integer arithmetic.
+    macro_rules! composite_with {
+        ($($mode:ident),*) => {
+            match layer.blend_mode {
+                BlendMode::Normal => for_each_row(out, image, x, y, |dst, src| {
+                    composite_normal_row(dst, src, u32::from(layer.alpha))
+                }),
+                $(BlendMode::$mode => for_each_row(out, image, x, y, |dst, src| {
+                    composite_row(dst, src, opacity, |b, s| blend_channel(BlendMode::$mode, b, s))
+                }),)*
+            }
+        };
+    }
+
+    composite_with!(
+        Multiply, Add, Difference, Darken, Lighten, Hardlight, Invert, Overlay, Screen, Subtract
+    );
+}
+
+/// Calls `f` with each row of `out` and the row of `image` it overlaps, with `image` offset by
+/// `x`, `y`. Rows are RGBA8 pixels, clipped to the part of `out` that `image` covers.
+fn for_each_row<F: FnMut(&mut [u8], &[u8])>(
+    out: &mut RgbaImage,
+    image: &RgbaImage,
+    x: u32,
+    y: u32,
+    mut f: F,
+) {
+    let width = out.width().min(image.width().saturating_sub(x));
+    let height = out.height().min(image.height().saturating_sub(y));
+
+    let (out_stride, src_stride) = (out.width() as usize * 4, image.width() as usize * 4);
+    let row_len = width as usize * 4;
+    let (out, src) = (&mut **out, &**image);
+
+    for dy in 0..height as usize {
+        let src_start = (y as usize + dy) * src_stride + x as usize * 4;
+        let src_row = &src[src_start..src_start + row_len];
+        let out_row = &mut out[dy * out_stride..dy * out_stride + row_len];
+        f(out_row, src_row);
+    }
+}
+
+fn composite_row<F: Fn(f32, f32) -> f32 + Copy>(
+    dst: &mut [u8],
+    src: &[u8],
+    opacity: f32,
+    blend: F,
+) {
+    for (dst, src) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+        let blended = blend_pixel_with(
+            blend,
+            [dst[0], dst[1], dst[2], dst[3]],
+            [src[0], src[1], src[2], src[3]],
+            opacity,
+        );
+        dst.copy_from_slice(&blended);
+    }
+}
+
+/// Composites a row of `src` pixels over `dst` with the normal blend mode and an `opacity` from
+/// 0 to 255.
+fn composite_normal_row(dst: &mut [u8], src: &[u8], opacity: u32) {
+    for (dst, src) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+        blend_normal(dst, src, opacity);
+    }
+}
+
+/// Composites the RGBA8 pixel `src` over `dst` in place with the normal blend mode and an
+/// `opacity` from 0 to 255, using only integer arithmetic.
+///
+/// The alphas are kept as weights out of 255³ rather than being rounded to 8 bits, so each
+/// channel is the W3C model's result rounded to the nearest level, never more than one level
+/// from the floating point model. A fully transparent result is stored as transparent black.
+#[inline(always)]
+fn blend_normal(dst: &mut [u8], src: &[u8], opacity: u32) {
+    const ONE: u32 = 255 * 255;
+
+    // The source alpha out of 255², then both weights out of 255³. The output alpha is at most
+    // 255³, so the weighted sum of a channel, at most 255⁴, fits a `u32` with room to round.
+    let sa = u32::from(src[3]) * opacity;
+    let (src_weight, dst_weight) = (sa * 255, u32::from(dst[3]) * (ONE - sa));
+    let oa = src_weight + dst_weight;
+
+    let divisor = oa.max(1);
+    for i in 0..3 {
+        let mixed = src_weight * u32::from(src[i]) + dst_weight * u32::from(dst[i]);
+        dst[i] = ((mixed + divisor / 2) / divisor) as u8;
+    }
+    dst[3] = ((oa + ONE / 2) / ONE) as u8;
+}
+
+#[inline(always)]
+fn blend_channel(mode: BlendMode, b: f32, s: f32) -> f32 {
+    match mode {
+        BlendMode::Normal => s,
+        BlendMode::Multiply => b * s,
+        BlendMode::Add => (b + s).min(1.),
+        BlendMode::Difference => (b - s).abs(),
+        BlendMode::Darken => b.min(s),
+        BlendMode::Lighten => b.max(s),
+        BlendMode::Hardlight => hard_light(b, s),
+        BlendMode::Invert => 1. - b,
+        BlendMode::Overlay => hard_light(s, b),
+        BlendMode::Screen => b + s - b * s,
+        BlendMode::Subtract => (b - s).max(0.),
+    }
+}
+
+fn hard_light(b: f32, s: f32) -> f32 {
+    if s <= 0.5 {
+        b * 2. * s
+    } else {
+        let s = 2. * s - 1.;
+        b + s - b * s
+    }
+}
+
+/// Scales `image` up `scale` times with nearest-neighbor filtering. A `scale` of 0 or 1 returns
+/// `image` unchanged.
+pub(crate) fn scale_image(image: RgbaImage, scale: u32) -> RgbaImage {
+    if scale <= 1 {
+        return image;
+    }
+
+    let (width, height) = image.dimensions();
+    let mut pixels = Vec::new();
+    scale_pixels(&image, width, scale, &mut pixels);
+    RgbaImage::from_raw(width * scale, height * scale, pixels)
+        .expect("scaled pixels match their dimensions")
+}
+
+/// Writes the RGBA8 pixels of a `width` pixel wide image, scaled up `scale` times with
+/// nearest-neighbor filtering, to `out`, which is cleared first.
+pub(crate) fn scale_pixels(pixels: &[u8], width: u32, scale: u32, out: &mut Vec<u8>) {
+    out.clear();
+
+    let (stride, scale) = (width as usize * 4, scale.max(1) as usize);
+    if stride == 0 {
+        return;
+    }
+
+    out.reserve(pixels.len() * scale * scale);
+    for row in pixels.chunks(stride) {
+        let start = out.len();
+        for pixel in row.chunks(4) {
+            for _ in 0..scale {
+                out.extend_from_slice(pixel);
+            }
+        }
+        for _ in 1..scale {
+            out.extend_from_within(start..start + stride * scale);
+        }
+    }
+}
+
+/// Composites `src` over `dst` using the W3C compositing model for separable blend modes.
+#[cfg(test)]
+fn blend_pixel(mode: BlendMode, dst: [u8; 4], src: [u8; 4], opacity: f32) -> [u8; 4] {
+    blend_pixel_with(|b, s| blend_channel(mode, b, s), dst, src, opacity)
+}
+
+/// Composites `src` over `dst` using the W3C compositing model for separable blend modes, with
+/// `blend` mixing each channel.
+#[inline(always)]
+fn blend_pixel_with<F: Fn(f32, f32) -> f32>(
+    blend: F,
+    dst: [u8; 4],
+    src: [u8; 4],
+    opacity: f32,
+) -> [u8; 4] {
+    let sa = f32::from(src[3]) / 255. * opacity;
+    let ba = f32::from(dst[3]) / 255.;
+    // A fully transparent result is stored as transparent black rather than dividing by zero.
+    let oa = (sa + ba * (1. - sa)).max(f32::MIN_POSITIVE);
+
+    // Every channel lies within 0..=1, so adding a half and truncating rounds it.
+    let mut out = [0; 4];
+    for i in 0..3 {
+        let s = f32::from(src[i]) / 255.;
+        let b = f32::from(dst[i]) / 255.;
+
+        let mixed = (1. - ba) * s + ba * blend(b, s);
+        let c = (sa * mixed + ba * b * (1. - sa)) / oa;
+
+        out[i] = (c * 255. + 0.5) as u8;
+    }
+    out[3] = (oa * 255. + 0.5) as u8;
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use image::GenericImageView;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn normal_blend_over_transparent_is_source() {
+        let src = [10, 20, 30, 200];
+        assert_eq!(src, blend_pixel(BlendMode::Normal, [0; 4], src, 1.));
+    }
+
+    #[test]
+    fn blend_respects_layer_opacity() {
+        let dst = [0, 0, 0, 255];
+        let src = [255, 255, 255, 255];
+        assert_eq!(
+            [128, 128, 128, 255],
+            blend_pixel(BlendMode::Normal, dst, src, 128. / 255.)
+        );
+    }
+
+    #[test]
+    fn separable_blend_modes() {
+        let dst = [200, 100, 0, 255];
+        let src = [100, 100, 100, 255];
+
+        assert_eq!(
+            [78, 39, 0, 255],
+            blend_pixel(BlendMode::Multiply, dst, src, 1.)
+        );
+        assert_eq!(
+            [255, 200, 100, 255],
+            blend_pixel(BlendMode::Add, dst, src, 1.)
+        );
+        assert_eq!(
+            [100, 0, 0, 255],
+            blend_pixel(BlendMode::Subtract, dst, src, 1.)
+        );
+        assert_eq!(
+            [55, 155, 255, 255],
+            blend_pixel(BlendMode::Invert, dst, src, 1.)
+        );
+    }
+
+    #[test]
+    fn flatten_matches_per_pixel_blending() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        for layer in &mut doc.canvas.layers {
+            layer.hidden = false;
+            layer.muted = false;
+            layer.soloed = false;
+            layer.alpha = 200;
+        }
+        let canvas = doc.canvas();
+
+        let mut expected = RgbaImage::new(256, 128);
+        for layer in canvas.layers_bottom_to_top() {
+            let image = layer.image.to_rgba();
+            for (x, y, dst) in expected.enumerate_pixels_mut() {
+                let src = image.get_pixel(x, y).0;
+                dst.0 = blend_pixel(layer.blend_mode, dst.0, src, 200. / 255.);
+            }
+        }
+
+        assert_within_a_level(&expected.into_raw(), &canvas.flatten().into_raw());
+    }
+
+    /// Asserts that each channel of `actual` is within one level of `expected`.
+    fn assert_within_a_level(expected: &[u8], actual: &[u8]) {
+        let close = expected
+            .iter()
+            .zip(actual)
+            .all(|(&e, &a)| (i16::from(e) - i16::from(a)).abs() <= 1);
+        assert!(close, "expected {:?}, got {:?}", expected, actual);
+    }
+
+    #[test]
+    fn integer_normal_blending_matches_the_float_model() {
+        let check = |dst: [u8; 4], src: [u8; 4], opacity: u8| {
+            let expected = blend_pixel(BlendMode::Normal, dst, src, f32::from(opacity) / 255.);
+            let mut actual = dst;
+            blend_normal(&mut actual, &src, u32::from(opacity));
+            assert_within_a_level(&expected, &actual);
+        };
+
+        // Low alphas, where rounding the alphas to 8 bits would be off by several levels.
+        check([216, 207, 63, 8], [5, 209, 178, 89], 30);
+        check([0; 4], [10, 20, 30, 200], 255);
+        check([0, 0, 0, 255], [255; 4], 128);
+
+        let values = [0, 1, 2, 7, 30, 64, 127, 128, 200, 254, 255];
+        for &src_a in &values {
+            for &dst_a in &values {
+                for &opacity in &values {
+                    for &(s, b) in &[(0, 255), (255, 0), (17, 230), (128, 129)] {
+                        check([b, s, b, dst_a], [s, b, s, src_a], opacity);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flatten_matches_canvas_size() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let image = doc.canvas().flatten();
+        assert_eq!((256, 128), image.dimensions());
+    }
+
+    #[test]
+    fn flatten_scaled_repeats_pixels() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let flat = doc.canvas().flatten();
+        let scaled = doc.canvas().flatten_scaled(3);
+
+        assert_eq!((768, 384), scaled.dimensions());
+        for (x, y, pixel) in scaled.enumerate_pixels() {
+            assert_eq!(flat.get_pixel(x / 3, y / 3), pixel);
+        }
+        assert_eq!(flat.into_raw(), doc.canvas().flatten_scaled(0).into_raw());
+    }
+
+    #[test]
+    fn render_frame_is_tile_sized() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let animation = &doc.animations()[0];
+
+        let frame = doc.canvas().render_frame(animation, 3).unwrap();
+        assert_eq!((32, 16), frame.dimensions());
+        assert!(doc.canvas().render_frame(animation, 4).is_none());
+    }
+
+    #[test]
+    fn render_tile_matches_flattened_canvas() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let canvas = doc.canvas();
+
+        let flat = canvas.flatten();
+        let tile = canvas.render_tile(3, 2).unwrap();
+        let expected = flat.view(96, 32, 32, 16).to_image();
+        assert_eq!(expected.into_raw(), tile.into_raw());
+
+        assert!(canvas.render_tile(8, 0).is_none());
+        assert!(canvas.render_tile(0, 8).is_none());
+    }
+
+    #[test]
+    fn previews_are_composited_onto_their_background() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let canvas = doc.canvas();
+        let flat = canvas.flatten();
+        let (x, y) = flat
+            .enumerate_pixels()
+            .find(|(_, _, pixel)| pixel[3] == 0)
+            .map(|(x, y, _)| (x, y))
+            .unwrap();
+
+        let transparent = canvas.flatten_with(&RenderOptions::default());
+        assert_eq!(flat.clone().into_raw(), transparent.into_raw());
+
+        let red = Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+        let solid = canvas.flatten_with(&RenderOptions {
+            background: Background::Solid(red),
+            scale: 2,
+        });
+        assert_eq!((512, 256), solid.dimensions());
+        assert_eq!([255, 0, 0, 255], solid.get_pixel(x * 2 + 1, y * 2).0);
+        assert!(solid.pixels().all(|pixel| pixel[3] == 255));
+
+        let options = RenderOptions {
+            background: Background::checkerboard(),
+            scale: 1,
+        };
+        let frame = canvas
+            .render_frame_with(&doc.animations()[0], 0, &options)
+            .unwrap();
+        let checker = |x, y| Background::checkerboard().color_at(x, y);
+        assert_eq!([255; 4], checker(7, 7));
+        assert_eq!([204, 204, 204, 255], checker(8, 7));
+        for (x, y, pixel) in frame.enumerate_pixels() {
+            let source = flat.get_pixel(x, y);
+            if source[3] == 0 {
+                assert_eq!(checker(x, y), pixel.0);
+            } else if source[3] == 255 {
+                assert_eq!(source, pixel);
+            }
+        }
+    }
+
+    #[test]
+    fn frame_layers_are_uncomposited_crops() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let animation = &doc.animations()[1];
+
+        // Frame 1 of the second animation is tile 5, at 160, 0.
+        let layers = doc.render_frame_layers(animation, 1).unwrap();
+        assert_eq!(11, layers.len());
+        for (layer, image) in doc.canvas().layers().iter().zip(&layers) {
+            let expected = layer.image().view(160, 0, 32, 16).to_image();
+            assert_eq!(expected.into_raw(), image.clone().into_raw());
+        }
+
+        assert!(doc.render_frame_layers(animation, 2).is_none());
+    }
+}
@@ -0,0 +1,662 @@
+use crate::{error::PyxelError, pyxel::Pyxel, text::writeln_str};
+#[cfg(feature = "images")]
+use crate::{png::encode_png, text::write_str};
+
+#[cfg(feature = "images")]
+use image::RgbaImage;
+use serde_json::json;
+
+/// The format of the report produced by [`Pyxel::report`](struct.Pyxel.html#method.report).
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(not(feature = "images"), derive(Copy))]
+pub enum ReportFormat {
+    /// GitHub flavored Markdown, with a table for the layers and animations, for pasting into
+    /// pull requests and issues.
+    #[default]
+    Markdown,
+    /// A pretty printed JSON object, for tools collecting audits of many documents.
+    Json,
+    /// A standalone HTML page with previews, as produced by
+    /// [`Pyxel::report_html`](struct.Pyxel.html#method.report_html).
+    #[cfg(feature = "images")]
+    Html(HtmlReportOptions),
+}
+
+#[cfg(feature = "images")]
+/// Options controlling the contents of an HTML report.
+#[derive(Clone, Debug)]
+pub struct HtmlReportOptions {
+    /// The title of the report. Defaults to the document name when `None`.
+    pub title: Option<String>,
+
+    /// The factor previews are scaled up by when displayed.
+    pub scale: u32,
+
+    /// Include a preview of the flattened canvas.
+    pub canvas: bool,
+
+    /// Include a preview of each layer.
+    pub layers: bool,
+
+    /// Include a preview of each tile in the tileset.
+    pub tiles: bool,
+
+    /// Include a playable preview of each animation.
+    pub animations: bool,
+}
+
+#[cfg(feature = "images")]
+impl Default for HtmlReportOptions {
+    fn default() -> Self {
+        HtmlReportOptions {
+            title: None,
+            scale: 2,
+            canvas: true,
+            layers: true,
+            tiles: true,
+            animations: true,
+        }
+    }
+}
+
+#[cfg(feature = "images")]
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:4px 8px;text-align:left}\
+img{image-rendering:pixelated;background:repeating-conic-gradient(#ddd 0 25%,#fff 0 50%) 0 0/16px 16px}\
+.swatch{display:inline-block;width:24px;height:24px;border:1px solid #888;vertical-align:middle}\
+.frames img{margin-right:4px}";
+
+#[cfg(feature = "images")]
+// Cycles each animation preview through its frames using the per-frame durations.
+const SCRIPT: &str = "document.querySelectorAll('img[data-frames]').forEach(function(img){\
+var f=JSON.parse(img.dataset.frames),d=JSON.parse(img.dataset.durations),i=0;\
+(function next(){img.src=f[i];setTimeout(next,d[i]);i=(i+1)%f.length;})();});";
+
+#[cfg(feature = "images")]
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(feature = "images")]
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(feature = "images")]
+fn data_uri(image: &RgbaImage) -> Result<String, PyxelError> {
+    Ok(format!(
+        "data:image/png;base64,{}",
+        base64(&encode_png(image)?)
+    ))
+}
+
+#[cfg(feature = "images")]
+fn img_tag(image: &RgbaImage, scale: u32, alt: &str) -> Result<String, PyxelError> {
+    Ok(format!(
+        "<img src=\"{}\" width=\"{}\" height=\"{}\" alt=\"{}\">",
+        data_uri(image)?,
+        image.width() * scale,
+        image.height() * scale,
+        escape(alt)
+    ))
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+#[cfg(feature = "images")]
+impl Pyxel {
+    /// Generates a standalone HTML page summarizing this document, with previews embedded as
+    /// base64 encoded PNGs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let html = doc.report_html(&pyxel::HtmlReportOptions::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn report_html(&self, options: &HtmlReportOptions) -> Result<String, PyxelError> {
+        let scale = options.scale.max(1);
+        let title = escape(options.title.as_ref().unwrap_or(&self.name));
+        let canvas = &self.canvas;
+
+        let mut html = String::new();
+
+        write_str!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n"
+        );
+        write_str!(
+            html,
+            "<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+            title,
+            STYLE
+        );
+        write_str!(html, "<h1>{}</h1>\n", title);
+
+        write_str!(html, "<h2>Document</h2>\n<table>\n");
+        write_str!(
+            html,
+            "<tr><th>Name</th><td>{}</td></tr>\n",
+            escape(&self.name)
+        );
+        write_str!(html, "<tr><th>Version</th><td>{}</td></tr>\n", self.version);
+        write_str!(
+            html,
+            "<tr><th>Canvas</th><td>{}×{} px</td></tr>\n",
+            canvas.width,
+            canvas.height
+        );
+        write_str!(
+            html,
+            "<tr><th>Tile size</th><td>{}×{} px ({}×{} tiles)</td></tr>\n",
+            canvas.tile_width,
+            canvas.tile_height,
+            canvas.tiles_wide(),
+            canvas.tiles_high()
+        );
+        write_str!(html, "</table>\n");
+
+        if options.canvas {
+            write_str!(
+                html,
+                "<h2>Canvas</h2>\n<p>{}</p>\n",
+                img_tag(&canvas.flatten(), scale, "canvas")?
+            );
+        }
+
+        write_str!(html, "<h2>Layers</h2>\n<table>\n<tr><th>#</th><th>Name</th><th>Blend mode</th><th>Alpha</th>");
+        write_str!(
+            html,
+            "<th>Hidden</th><th>Muted</th><th>Soloed</th><th>Tile refs</th>"
+        );
+        if options.layers {
+            write_str!(html, "<th>Preview</th>");
+        }
+        write_str!(html, "</tr>\n");
+        for (i, layer) in canvas.layers.iter().enumerate() {
+            write_str!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{:?}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>",
+                i,
+                escape(&layer.name),
+                layer.blend_mode,
+                layer.alpha,
+                yes_no(layer.hidden),
+                yes_no(layer.muted),
+                yes_no(layer.soloed),
+                layer.tile_refs.len()
+            );
+            if options.layers {
+                write_str!(
+                    html,
+                    "<td>{}</td>",
+                    img_tag(&layer.image.to_rgba(), scale, &layer.name)?
+                );
+            }
+            write_str!(html, "</tr>\n");
+        }
+        write_str!(html, "</table>\n");
+
+        write_str!(
+            html,
+            "<h2>Tileset</h2>\n<p>{} tiles of {}×{} px</p>\n",
+            self.tileset.images.len(),
+            self.tileset.tile_width,
+            self.tileset.tile_height
+        );
+        if options.tiles {
+            write_str!(html, "<p class=\"frames\">");
+            for (i, image) in self.tileset.images.iter().enumerate() {
+                write_str!(
+                    html,
+                    "{}",
+                    img_tag(&image.to_rgba(), scale, &format!("tile {}", i))?
+                );
+            }
+            write_str!(html, "</p>\n");
+        }
+
+        write_str!(html, "<h2>Palette</h2>\n<p>");
+        for color in self.palette.colors.iter().flatten() {
+            write_str!(
+                html,
+                "<span class=\"swatch\" style=\"background:rgba({},{},{},{:.3})\" title=\"{}\"></span> ",
+                color.r,
+                color.g,
+                color.b,
+                f32::from(color.a) / 255.,
+                color
+            );
+        }
+        write_str!(html, "</p>\n");
+
+        write_str!(html, "<h2>Animations</h2>\n<table>\n<tr><th>Name</th><th>Base tile</th><th>Frames</th><th>Durations (ms)</th>");
+        if options.animations {
+            write_str!(html, "<th>Preview</th>");
+        }
+        write_str!(html, "</tr>\n");
+        for animation in self.animations.iter() {
+            let durations: Vec<u128> = animation
+                .frame_durations()
+                .iter()
+                .map(|d| d.as_millis())
+                .collect();
+            let durations = durations
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            write_str!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td>",
+                escape(&animation.name),
+                animation.base_tile,
+                animation.length,
+                durations
+            );
+
+            if options.animations {
+                let frames = (0..animation.length)
+                    .filter_map(|frame| canvas.render_frame(animation, frame))
+                    .map(|image| data_uri(&image).map(|uri| format!("\"{}\"", uri)))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if let Some(first) = frames.first() {
+                    write_str!(
+                        html,
+                        "<td><img src={} width=\"{}\" height=\"{}\" alt=\"{}\" data-frames='[{}]' data-durations='[{}]'></td>",
+                        first,
+                        u32::from(canvas.tile_width) * scale,
+                        u32::from(canvas.tile_height) * scale,
+                        escape(&animation.name),
+                        frames.join(","),
+                        durations
+                    );
+                } else {
+                    write_str!(html, "<td></td>");
+                }
+            }
+            write_str!(html, "</tr>\n");
+        }
+        write_str!(html, "</table>\n");
+
+        if options.animations {
+            write_str!(html, "<script>{}</script>\n", SCRIPT);
+        }
+        write_str!(html, "</body>\n</html>\n");
+
+        Ok(html)
+    }
+}
+
+/// Escapes the characters of `s` that would break out of a Markdown table cell or be read as
+/// formatting.
+fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '|' | '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' | '\r' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl Pyxel {
+    /// Summarizes this document for people reviewing it: the canvas size, a table of the layers
+    /// with their blend modes and visibility, the animations with their frame durations, the
+    /// colors of the palette, how often each tile is used and any problems found by
+    /// [`validate`](#method.validate).
+    ///
+    /// The Markdown and JSON formats have no previews, so the report is small enough to attach to
+    /// a pull request or check into an audit log. The HTML format is the page produced by
+    /// [`report_html`](#method.report_html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let markdown = doc.report(&pyxel::ReportFormat::Markdown)?;
+    /// assert!(markdown.starts_with("# "));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn report(&self, format: &ReportFormat) -> Result<String, PyxelError> {
+        Ok(match format {
+            ReportFormat::Markdown => self.report_markdown(),
+            ReportFormat::Json => self.report_json(),
+            #[cfg(feature = "images")]
+            ReportFormat::Html(options) => self.report_html(options)?,
+        })
+    }
+
+    fn report_markdown(&self) -> String {
+        let canvas = &self.canvas;
+        let stats = self.stats();
+        let visible = canvas.effective_visibility();
+
+        let mut md = String::new();
+
+        writeln_str!(md, "# {}\n", escape_markdown(&self.name));
+        writeln_str!(md, "- PyxelEdit version: {}", self.version);
+        writeln_str!(md, "- Canvas: {}×{} px", canvas.width, canvas.height);
+        writeln_str!(
+            md,
+            "- Tiles: {}×{} px, {}×{} cells, {} of {} cells used",
+            canvas.tile_width,
+            canvas.tile_height,
+            canvas.tiles_wide(),
+            canvas.tiles_high(),
+            stats.used_cells,
+            stats.cells
+        );
+        writeln_str!(md, "- Decoded size: {} bytes\n", stats.decoded_bytes);
+
+        writeln_str!(md, "## Layers\n");
+        writeln_str!(
+            md,
+            "| # | Name | Blend mode | Alpha | Visible | Tile refs |"
+        );
+        writeln_str!(
+            md,
+            "|---|------|------------|-------|---------|-----------|"
+        );
+        for (i, layer) in canvas.layers.iter().enumerate() {
+            writeln_str!(
+                md,
+                "| {} | {} | {} | {} | {} | {} |",
+                i,
+                escape_markdown(&layer.name),
+                layer.blend_mode.name(),
+                layer.alpha,
+                yes_no(visible[i]),
+                layer.tile_refs.len()
+            );
+        }
+        writeln_str!(md);
+
+        writeln_str!(md, "## Animations\n");
+        if self.animations.is_empty() {
+            writeln_str!(md, "None.\n");
+        } else {
+            writeln_str!(md, "| Name | Base tile | Frames | Durations (ms) |");
+            writeln_str!(md, "|------|-----------|--------|----------------|");
+            for animation in &self.animations {
+                writeln_str!(
+                    md,
+                    "| {} | {} | {} | {} |",
+                    escape_markdown(&animation.name),
+                    animation.base_tile,
+                    animation.length,
+                    animation
+                        .frame_durations()
+                        .iter()
+                        .map(|d| d.as_millis().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            writeln_str!(md);
+        }
+
+        writeln_str!(md, "## Palette\n");
+        let colors: Vec<String> = self
+            .palette
+            .colors
+            .iter()
+            .flatten()
+            .map(|color| format!("`{}`", color))
+            .collect();
+        if colors.is_empty() {
+            writeln_str!(md, "None.\n");
+        } else {
+            writeln_str!(
+                md,
+                "{} of {} slots hold a color: {}\n",
+                colors.len(),
+                self.palette.colors.len(),
+                colors.join(" ")
+            );
+        }
+
+        writeln_str!(md, "## Tiles\n");
+        let usage = self.tile_usage();
+        if usage.is_empty() {
+            writeln_str!(md, "None.\n");
+        } else {
+            writeln_str!(md, "| Tile | Uses |");
+            writeln_str!(md, "|------|------|");
+            for tile in &usage {
+                writeln_str!(md, "| {} | {} |", tile.tile, tile.count());
+            }
+            writeln_str!(md);
+        }
+
+        writeln_str!(md, "## Issues\n");
+        let issues = self.validate();
+        if issues.is_empty() {
+            writeln_str!(md, "None.");
+        } else {
+            for issue in issues {
+                writeln_str!(md, "- {}", issue);
+            }
+        }
+
+        md
+    }
+
+    fn report_json(&self) -> String {
+        let canvas = &self.canvas;
+        let stats = self.stats();
+        let visible = canvas.effective_visibility();
+
+        let layers: Vec<_> = canvas
+            .layers
+            .iter()
+            .zip(&visible)
+            .map(|(layer, &visible)| {
+                json!({
+                    "name": layer.name,
+                    "blendMode": layer.blend_mode.name(),
+                    "alpha": layer.alpha,
+                    "hidden": layer.hidden,
+                    "muted": layer.muted,
+                    "soloed": layer.soloed,
+                    "visible": visible,
+                    "tileRefs": layer.tile_refs.len(),
+                })
+            })
+            .collect();
+        let animations: Vec<_> = self
+            .animations
+            .iter()
+            .map(|animation| {
+                let durations: Vec<u64> = animation
+                    .frame_durations()
+                    .iter()
+                    .map(|d| d.as_millis() as u64)
+                    .collect();
+                json!({
+                    "name": animation.name,
+                    "baseTile": animation.base_tile,
+                    "length": animation.length,
+                    "durations": durations,
+                })
+            })
+            .collect();
+        let palette: Vec<_> = self
+            .palette
+            .colors
+            .iter()
+            .map(|color| color.map(|color| color.to_string()))
+            .collect();
+        let tiles: Vec<_> = self.tile_usage().iter().map(|tile| tile.count()).collect();
+        let issues: Vec<_> = self
+            .validate()
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect();
+
+        let report = json!({
+            "name": self.name,
+            "version": self.version.to_string(),
+            "canvas": {
+                "width": canvas.width,
+                "height": canvas.height,
+                "tileWidth": canvas.tile_width,
+                "tileHeight": canvas.tile_height,
+                "cells": stats.cells,
+                "usedCells": stats.used_cells,
+            },
+            "decodedBytes": stats.decoded_bytes,
+            "layers": layers,
+            "animations": animations,
+            "palette": palette,
+            "tileUses": tiles,
+            "issues": issues,
+        });
+        serde_json::to_string_pretty(&report).expect("reports serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn base64_encodes_with_padding() {
+        assert_eq!("", base64(b""));
+        assert_eq!("Zg==", base64(b"f"));
+        assert_eq!("Zm8=", base64(b"fo"));
+        assert_eq!("Zm9v", base64(b"foo"));
+        assert_eq!("Zm9vYmFy", base64(b"foobar"));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn escape_html() {
+        assert_eq!("a &lt;b&gt; &amp; &quot;c&quot;", escape("a <b> & \"c\""));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn report_summarizes_document() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let html = doc.report_html(&HtmlReportOptions::default()).unwrap();
+
+        assert!(html.contains("<title>test_v0.4.8</title>"));
+        assert!(html.contains("Layer 10"));
+        assert!(html.contains("Animation 3"));
+        assert!(html.contains("150,300,450,600"));
+        assert!(html.contains("title=\"ffbe3535\""));
+        assert!(html.contains("data:image/png;base64,"));
+
+        let options = HtmlReportOptions::default();
+        assert_eq!(html, doc.report(&ReportFormat::Html(options)).unwrap());
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn report_without_previews() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let options = HtmlReportOptions {
+            title: Some("<Review>".to_owned()),
+            canvas: false,
+            layers: false,
+            tiles: false,
+            animations: false,
+            ..HtmlReportOptions::default()
+        };
+        let html = doc.report_html(&options).unwrap();
+
+        assert!(html.contains("<h1>&lt;Review&gt;</h1>"));
+        assert!(!html.contains("data:image/png"));
+    }
+
+    #[test]
+    fn markdown_report_summarizes_document() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        doc.canvas.layers[0].name = "a|b".to_owned();
+        let md = doc.report(&ReportFormat::Markdown).unwrap();
+
+        assert!(md.starts_with("# test\\_v0.4.8\n"));
+        assert!(md.contains("- Canvas: 256×128 px\n"));
+        assert!(md.contains("| 0 | a\\|b | subtract | 255 | no | 4 |\n"));
+        // Layer 9 is soloed, hiding every other layer.
+        assert!(md.contains("| 1 | Layer 9 | screen | 255 | yes | 8 |\n"));
+        assert!(md.contains("| 150, 300, 450, 600 |\n"));
+        assert!(md.contains("`ffbe3535`"));
+        assert!(md.contains("| Tile | Uses |"));
+        assert!(md.ends_with("## Issues\n\nNone.\n"));
+    }
+
+    #[test]
+    fn json_report_summarizes_document() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&doc.report(&ReportFormat::Json).unwrap()).unwrap();
+
+        assert_eq!(256, report["canvas"]["width"]);
+        assert_eq!(11, report["layers"].as_array().unwrap().len());
+        assert_eq!(false, report["layers"][3]["visible"]);
+        assert!(report["animations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|animation| animation["durations"] == json!([150, 300, 450, 600])));
+        assert_eq!(4, report["tileUses"].as_array().unwrap().len());
+        assert_eq!(0, report["issues"].as_array().unwrap().len());
+        let palette = report["palette"].as_array().unwrap();
+        assert!(palette.contains(&json!("ffbe3535")));
+    }
+}
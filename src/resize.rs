@@ -0,0 +1,280 @@
+use crate::{error::PyxelError, pyxel::Pyxel};
+
+use image::{imageops, DynamicImage, GenericImageView, RgbaImage};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// The edge or corner of a canvas that stays in place when it is resized with
+/// [`Pyxel::resize_canvas`](struct.Pyxel.html#method.resize_canvas).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Anchor {
+    /// The top-left corner.
+    #[default]
+    TopLeft,
+    /// The middle of the top edge.
+    Top,
+    /// The top-right corner.
+    TopRight,
+    /// The middle of the left edge.
+    Left,
+    /// The center.
+    Center,
+    /// The middle of the right edge.
+    Right,
+    /// The bottom-left corner.
+    BottomLeft,
+    /// The middle of the bottom edge.
+    Bottom,
+    /// The bottom-right corner.
+    BottomRight,
+}
+
+impl Anchor {
+    /// Returns the fraction of the change in size added before the existing content, as
+    /// (horizontal, vertical) halves.
+    fn halves(self) -> (isize, isize) {
+        match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        }
+    }
+}
+
+impl Pyxel {
+    /// Crops the canvas to the `cols` × `rows` tiles starting at the tile at `col`, `row`.
+    ///
+    /// Layer images are cropped to match, tile refs are re-keyed to their new cells and those
+    /// outside of the crop are removed. Animations are moved along with their frames and
+    /// shortened to the frames that remain consecutive cells of the cropped canvas. Animations
+    /// whose first frame is cropped away are removed.
+    ///
+    /// The region must lie within the canvas.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// doc.crop_canvas(2, 2, 4, 4)?;
+    /// assert_eq!((64, 64), (doc.canvas().width(), doc.canvas().height()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn crop_canvas(
+        &mut self,
+        col: usize,
+        row: usize,
+        cols: usize,
+        rows: usize,
+    ) -> Result<(), PyxelError> {
+        let canvas = &self.canvas;
+        if col + cols > canvas.tiles_wide() || row + rows > canvas.tiles_high() {
+            return Err(PyxelError::CellOutOfBounds {
+                col: col + cols.max(1) - 1,
+                row: row + rows.max(1) - 1,
+            });
+        }
+
+        self.reframe(-(col as isize), -(row as isize), cols, rows);
+        Ok(())
+    }
+
+    /// Resizes the canvas to `cols` × `rows` tiles, keeping the content at `anchor` in place.
+    ///
+    /// Growing the canvas adds transparent tiles, shrinking it crops the content on the sides
+    /// away from `anchor`, with the same effect on tile refs and animations as
+    /// [`crop_canvas`](#method.crop_canvas). When the change in size is odd, centered content
+    /// moves towards the top-left.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// doc.resize_canvas(10, 4, pyxel::Anchor::Bottom);
+    /// assert_eq!((160, 64), (doc.canvas().width(), doc.canvas().height()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resize_canvas(&mut self, cols: usize, rows: usize, anchor: Anchor) {
+        let (dx, dy) = (
+            cols as isize - self.canvas.tiles_wide() as isize,
+            rows as isize - self.canvas.tiles_high() as isize,
+        );
+        let (h, v) = anchor.halves();
+
+        // Round towards negative infinity so that odd changes favour the top-left.
+        self.reframe((dx * h).div_euclid(2), (dy * v).div_euclid(2), cols, rows);
+    }
+
+    /// Moves the content of the canvas `offset_col`, `offset_row` tiles and resizes it to
+    /// `cols` × `rows` tiles, dropping anything that no longer fits.
+    fn reframe(&mut self, offset_col: isize, offset_row: isize, cols: usize, rows: usize) {
+        let canvas = &mut self.canvas;
+        let (old_cols, old_rows) = (canvas.tiles_wide(), canvas.tiles_high());
+        let (tile_width, tile_height) = (
+            u32::from(canvas.tile_width) as isize,
+            u32::from(canvas.tile_height) as isize,
+        );
+
+        let map_cell = |cell: usize| -> Option<usize> {
+            if old_cols == 0 || cell >= old_cols * old_rows {
+                return None;
+            }
+
+            let col = (cell % old_cols) as isize + offset_col;
+            let row = (cell / old_cols) as isize + offset_row;
+            if col < 0 || row < 0 || col as usize >= cols || row as usize >= rows {
+                return None;
+            }
+
+            Some(row as usize * cols + col as usize)
+        };
+
+        let (width, height) = (cols as isize * tile_width, rows as isize * tile_height);
+
+        for layer in &mut canvas.layers {
+            let tile_refs = std::mem::take(&mut layer.tile_refs);
+            layer.tile_refs = tile_refs
+                .into_iter()
+                .filter_map(|(cell, tile_ref)| map_cell(cell).map(|cell| (cell, tile_ref)))
+                .collect::<BTreeMap<_, _>>();
+
+            let (x, y) = (offset_col * tile_width, offset_row * tile_height);
+            let mut image = RgbaImage::new(width as u32, height as u32);
+
+            // Copy the part of the old image that overlaps the new one.
+            let (source_width, source_height) =
+                (layer.image.width() as isize, layer.image.height() as isize);
+            let (left, top) = (x.max(0), y.max(0));
+            let (right, bottom) = (
+                (x + source_width).min(width),
+                (y + source_height).min(height),
+            );
+            if left < right && top < bottom {
+                let overlap = layer
+                    .image
+                    .view(
+                        (left - x) as u32,
+                        (top - y) as u32,
+                        (right - left) as u32,
+                        (bottom - top) as u32,
+                    )
+                    .to_image();
+                imageops::replace(&mut image, &overlap, left as u32, top as u32);
+            }
+
+            layer.image = Arc::new(DynamicImage::ImageRgba8(image));
+        }
+
+        canvas.width = width as i32;
+        canvas.height = height as i32;
+
+        self.animations.retain_mut(|animation| {
+            let base_tile = match map_cell(animation.base_tile) {
+                Some(base_tile) => base_tile,
+                None => return false,
+            };
+
+            let length = (0..animation.length)
+                .take_while(|&frame| {
+                    map_cell(animation.base_tile + frame) == Some(base_tile + frame)
+                })
+                .count();
+
+            animation.base_tile = base_tile;
+            animation.length = length;
+            animation.frame_duration_multipliers.truncate(length);
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load::load, pyxel::TileRef};
+    use image::Rgba;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    fn load_test_file() -> Pyxel {
+        load(File::open(TEST_FILE).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn crop_moves_pixels_tile_refs_and_animations() {
+        let mut doc = load_test_file();
+        let original = doc.clone();
+
+        doc.crop_canvas(1, 0, 4, 2).unwrap();
+
+        let canvas = doc.canvas();
+        assert_eq!((128, 32), (canvas.width(), canvas.height()));
+        assert!(doc.validate().is_empty());
+
+        for (layer, before) in canvas.layers.iter().zip(&original.canvas.layers) {
+            assert_eq!(
+                before.image.view(32, 0, 128, 32).to_image().into_raw(),
+                layer.image.to_rgba().into_raw()
+            );
+
+            for (&cell, tile_ref) in &layer.tile_refs {
+                let (col, row) = (cell % 4, cell / 4);
+                let old = original.canvas.cell_index(col + 1, row).unwrap();
+                assert_eq!(Some(tile_ref), before.tile_refs.get(&old));
+            }
+        }
+
+        // Animation 1 starts in the first column and animation 3 in the seventh, so both are
+        // cropped away. Animation 2's second frame moves outside of the canvas.
+        let animations: Vec<_> = doc
+            .animations()
+            .iter()
+            .map(|a| (a.name(), a.base_tile(), a.length()))
+            .collect();
+        assert_eq!(vec![("Animation 2", 3, 1)], animations);
+
+        match doc.crop_canvas(2, 0, 3, 1) {
+            Err(PyxelError::CellOutOfBounds { col: 4, row: 0 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resize_keeps_content_at_anchor() {
+        let mut doc = load_test_file();
+        doc.canvas.layers[0]
+            .tile_refs
+            .insert(63, TileRef::new(1, 0., false));
+        image::GenericImage::put_pixel(
+            doc.canvas.layers[0].image_mut(),
+            255,
+            127,
+            Rgba([1, 2, 3, 4]),
+        );
+
+        doc.resize_canvas(11, 9, Anchor::BottomRight);
+
+        let layer = &doc.canvas.layers[0];
+        assert_eq!((352, 144), layer.image.dimensions());
+        assert_eq!(Rgba([1, 2, 3, 4]), layer.image.get_pixel(351, 143));
+        assert!(layer.tile_refs.contains_key(&(9 * 11 - 1)));
+        assert_eq!(3 + 11, doc.animations[0].base_tile);
+
+        doc.resize_canvas(5, 9, Anchor::Right);
+        assert_eq!((160, 144), doc.canvas.layers[0].image.dimensions());
+        assert_eq!(
+            Rgba([1, 2, 3, 4]),
+            doc.canvas.layers[0].image.get_pixel(159, 143)
+        );
+        assert!(doc.validate().is_empty());
+    }
+}
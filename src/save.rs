@@ -0,0 +1,123 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Layer, Tileset},
+};
+
+use std::io::Write;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::{fs::File, io::BufWriter, path::Path};
+
+/// Writes the PNG encoding of an image. Without the `images` feature images are already PNGs, so
+/// they're written unchanged.
+#[cfg(not(feature = "images"))]
+fn write_png<W: Write>(png: &[u8], mut w: W) -> Result<(), PyxelError> {
+    w.write_all(png)?;
+    Ok(())
+}
+
+#[cfg(feature = "images")]
+fn write_png<W: Write>(image: &image::DynamicImage, mut w: W) -> Result<(), PyxelError> {
+    w.write_all(&crate::png::encode_png(&image.to_rgba())?)?;
+    Ok(())
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn create<P: AsRef<Path>>(path: P) -> Result<BufWriter<File>, PyxelError> {
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+impl Layer {
+    /// Writes the image for this layer to `w` as a PNG.
+    ///
+    /// Without the `images` feature the PNG stored in the document is written as it is.
+    pub fn write_png<W: Write>(&self, w: W) -> Result<(), PyxelError> {
+        #[cfg(not(feature = "images"))]
+        return write_png(&self.image_data, w);
+
+        #[cfg(feature = "images")]
+        return write_png(&self.image, w);
+    }
+
+    /// Saves the image for this layer as a PNG file at `path`, see
+    /// [`write_png`](#method.write_png).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// doc.canvas().layers()[0].save_png("layer0.png")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), PyxelError> {
+        let mut w = create(path)?;
+        self.write_png(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+impl Tileset {
+    /// Writes the image for the tile at `index` to `w` as a PNG.
+    ///
+    /// Without the `images` feature the PNG stored in the document is written as it is.
+    pub fn write_tile_png<W: Write>(&self, index: usize, w: W) -> Result<(), PyxelError> {
+        #[cfg(not(feature = "images"))]
+        let tile = self.image_data.get(index);
+
+        #[cfg(feature = "images")]
+        let tile = self.images.get(index);
+
+        write_png(tile.ok_or(PyxelError::TileOutOfBounds(index))?, w)
+    }
+
+    /// Saves the image for the tile at `index` as a PNG file at `path`, see
+    /// [`write_tile_png`](#method.write_tile_png).
+    ///
+    /// Nothing is created if there is no such tile.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn save_tile_png<P: AsRef<Path>>(&self, index: usize, path: P) -> Result<(), PyxelError> {
+        if index >= self.tile_count() {
+            return Err(PyxelError::TileOutOfBounds(index));
+        }
+
+        let mut w = create(path)?;
+        self.write_tile_png(index, &mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::PyxelError, load::load, png::png_dimensions};
+    use std::fs::{self, File};
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn writes_layers_and_tiles_as_png() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+
+        let mut layer = Vec::new();
+        doc.canvas().layers()[2].write_png(&mut layer).unwrap();
+        assert_eq!((256, 128), png_dimensions(&layer).unwrap());
+
+        let mut tile = Vec::new();
+        doc.tileset().write_tile_png(3, &mut tile).unwrap();
+        assert_eq!((32, 16), png_dimensions(&tile).unwrap());
+
+        let path = std::env::temp_dir().join(format!("pyxel-save-{}.png", std::process::id()));
+        match doc.tileset().save_tile_png(4, &path) {
+            Err(PyxelError::TileOutOfBounds(4)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(!path.exists());
+
+        doc.tileset().save_tile_png(3, &path).unwrap();
+        assert_eq!(tile, fs::read(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+    }
+}
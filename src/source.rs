@@ -0,0 +1,195 @@
+use crate::error::PyxelError;
+
+use std::io::{Read, Seek};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A source of the entries that make up a Pyxel document.
+///
+/// Documents are zip archives, which `zip::ZipArchive` reads, but implementing this for another
+/// source lets [`load_from_source`](fn.load_from_source.html) load documents from custom pack
+/// files or, with [`DirectorySource`](struct.DirectorySource.html), from a document that has been
+/// extracted to a directory.
+///
+/// Entries are named as they would be within the archive, such as `docData.json` and
+/// `layer0.png`, using `/` to separate directories.
+pub trait ArchiveSource {
+    /// The reader for an entry, which may borrow the source.
+    type Entry<'a>: Read
+    where
+        Self: 'a;
+
+    /// Returns the names of the entries of the source, in the order they're stored.
+    fn entries(&mut self) -> Vec<String>;
+
+    /// Opens the entry `name` for reading.
+    fn entry(&mut self, name: &str) -> Result<Self::Entry<'_>, PyxelError>;
+
+    /// Returns the size of the entry `name` once read, if it's known before reading it, so that
+    /// entries that would exceed
+    /// [`Limits::max_decompressed_bytes`](struct.Limits.html#structfield.max_decompressed_bytes)
+    /// can be rejected early. The size is never trusted otherwise.
+    ///
+    /// The default implementation returns `None`.
+    fn entry_size(&mut self, name: &str) -> Option<u64> {
+        let _ = name;
+        None
+    }
+
+    /// Returns `true` if the source has an entry named exactly `name`.
+    ///
+    /// The default implementation searches the [`entries`](#tymethod.entries).
+    fn contains(&mut self, name: &str) -> bool {
+        self.entries().iter().any(|entry| entry == name)
+    }
+}
+
+impl<R: Read + Seek> ArchiveSource for zip::ZipArchive<R> {
+    type Entry<'a>
+        = zip::read::ZipFile<'a>
+    where
+        Self: 'a;
+
+    fn entries(&mut self) -> Vec<String> {
+        // `file_names` isn't in the order the entries are stored.
+        (0..self.len())
+            .filter_map(|i| self.by_index_raw(i).ok().map(|e| e.name().to_owned()))
+            .collect()
+    }
+
+    fn entry(&mut self, name: &str) -> Result<Self::Entry<'_>, PyxelError> {
+        Ok(self.by_name(name)?)
+    }
+
+    fn entry_size(&mut self, name: &str) -> Option<u64> {
+        self.by_name(name).ok().map(|entry| entry.size())
+    }
+
+    fn contains(&mut self, name: &str) -> bool {
+        self.by_name(name).is_ok()
+    }
+}
+
+/// A Pyxel document that has been extracted to a directory, read with
+/// [`load_from_source`](fn.load_from_source.html).
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let source = pyxel::DirectorySource::new("extracted/hero")?;
+/// let doc = pyxel::load_from_source(source, &pyxel::LoadOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Clone, Debug)]
+pub struct DirectorySource {
+    root: PathBuf,
+    names: Vec<String>,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl DirectorySource {
+    /// Lists the files within `root` and its subdirectories, which are the entries of the source,
+    /// sorted by name. Files added afterwards aren't seen.
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<DirectorySource, PyxelError> {
+        fn visit(dir: &Path, prefix: &str, names: &mut Vec<String>) -> io::Result<()> {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+                if entry.file_type()?.is_dir() {
+                    visit(&entry.path(), &format!("{}/", name), names)?;
+                } else {
+                    names.push(name);
+                }
+            }
+            Ok(())
+        }
+
+        let root = root.as_ref().to_owned();
+        let mut names = Vec::new();
+        visit(&root, "", &mut names)?;
+        names.sort();
+
+        Ok(DirectorySource { root, names })
+    }
+
+    fn path(&self, name: &str) -> io::Result<PathBuf> {
+        // Only open the files that were listed, so names can't escape the directory.
+        if self.names.iter().any(|entry| entry == name) {
+            Ok(self.root.join(name))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no file named {}", name),
+            ))
+        }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl ArchiveSource for DirectorySource {
+    type Entry<'a> = File;
+
+    fn entries(&mut self) -> Vec<String> {
+        self.names.clone()
+    }
+
+    fn entry(&mut self, name: &str) -> Result<File, PyxelError> {
+        Ok(File::open(self.path(name)?)?)
+    }
+
+    fn entry_size(&mut self, name: &str) -> Option<u64> {
+        let path = self.path(name).ok()?;
+        fs::metadata(path).ok().map(|metadata| metadata.len())
+    }
+
+    fn contains(&mut self, name: &str) -> bool {
+        self.names.iter().any(|entry| entry == name)
+    }
+}
+
+#[cfg(all(test, not(all(target_arch = "wasm32", target_os = "unknown"))))]
+mod tests {
+    use super::*;
+    use crate::{
+        doc_data::LoadOptions,
+        load::{load, load_from_source},
+    };
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn extracted_documents_load() {
+        let dir = std::env::temp_dir().join(format!("pyxel-source-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        zip::ZipArchive::new(File::open(TEST_FILE).unwrap())
+            .unwrap()
+            .extract(dir.join("test"))
+            .unwrap();
+        fs::write(dir.join("test/notes.txt"), b"shade the hero").unwrap();
+
+        let mut source = DirectorySource::new(&dir).unwrap();
+        assert!(source.contains("test/docData.json"));
+        assert!(!source.contains("docData.json"));
+        assert!(source.entry("../test/docData.json").is_err());
+
+        let options = LoadOptions {
+            keep_extra_entries: true,
+            ..LoadOptions::default()
+        };
+        let doc = load_from_source(source, &options).unwrap();
+        let expected = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let extra: Vec<&str> = doc.extra_entries().iter().map(|e| e.name()).collect();
+        assert_eq!(vec!["test/notes.txt"], extra);
+        assert!(expected.tileset().eq_with_pixels(doc.tileset()));
+        assert_eq!(expected.canvas(), doc.canvas());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
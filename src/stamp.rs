@@ -0,0 +1,331 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Canvas, Pyxel, TileRef, Tileset},
+};
+
+/// A small grid of tile refs that can be stamped onto a canvas layer.
+///
+/// Cells without a tile ref are transparent; stamping leaves the corresponding canvas cells untouched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stamp {
+    width: usize,
+    height: usize,
+    tile_refs: Vec<Option<TileRef>>,
+}
+
+impl Stamp {
+    /// Creates an empty stamp `width` cells wide and `height` cells high.
+    ///
+    /// Fails with [`CellOutOfBounds`](enum.PyxelError.html#variant.CellOutOfBounds) for the far
+    /// corner if the stamp has more cells than can be addressed.
+    pub fn new(width: usize, height: usize) -> Result<Stamp, PyxelError> {
+        let cells = width
+            .checked_mul(height)
+            .ok_or(PyxelError::CellOutOfBounds {
+                col: width,
+                row: height,
+            })?;
+
+        Ok(Stamp {
+            width,
+            height,
+            tile_refs: vec![None; cells],
+        })
+    }
+
+    /// Captures the tile refs of a `width` × `height` cell region of a canvas layer, starting at
+    /// `origin` (column, row).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let stamp = pyxel::Stamp::capture(doc.canvas(), 1, (0, 7), 8, 1)?;
+    /// assert_eq!(8, stamp.width());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn capture(
+        canvas: &Canvas,
+        layer: usize,
+        origin: (usize, usize),
+        width: usize,
+        height: usize,
+    ) -> Result<Stamp, PyxelError> {
+        let source = canvas
+            .layers
+            .get(layer)
+            .ok_or(PyxelError::LayerOutOfBounds(layer))?;
+
+        check_region(canvas, origin, width, height)?;
+
+        let mut stamp = Stamp::new(width, height)?;
+
+        for row in 0..height {
+            for col in 0..width {
+                let index = canvas
+                    .cell_index(origin.0 + col, origin.1 + row)
+                    .expect("region was checked against the canvas");
+
+                stamp.set(col, row, source.tile_refs.get(&index).copied());
+            }
+        }
+
+        Ok(stamp)
+    }
+
+    /// Returns the width of this stamp in cells.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of this stamp in cells.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the tile ref at `col`, `row`, or `None` if the cell is transparent or outside of
+    /// this stamp.
+    pub fn get(&self, col: usize, row: usize) -> Option<&TileRef> {
+        if col < self.width && row < self.height {
+            self.tile_refs[row * self.width + col].as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Sets the tile ref at `col`, `row`. `None` makes the cell transparent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is outside of this stamp.
+    pub fn set(&mut self, col: usize, row: usize, tile_ref: Option<TileRef>) {
+        assert!(
+            col < self.width && row < self.height,
+            "cell ({}, {}) is outside of the {}x{} stamp",
+            col,
+            row,
+            self.width,
+            self.height
+        );
+
+        self.tile_refs[row * self.width + col] = tile_ref;
+    }
+}
+
+pub(crate) fn check_region(
+    canvas: &Canvas,
+    origin: (usize, usize),
+    width: usize,
+    height: usize,
+) -> Result<(), PyxelError> {
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+
+    // A far corner past `usize::MAX` is reported as that corner, saturated.
+    let col = origin.0.checked_add(width - 1);
+    let row = origin.1.checked_add(height - 1);
+    match (col, row) {
+        (Some(col), Some(row)) if canvas.cell_index(col, row).is_some() => Ok(()),
+        _ => Err(PyxelError::CellOutOfBounds {
+            col: col.unwrap_or(usize::MAX),
+            row: row.unwrap_or(usize::MAX),
+        }),
+    }
+}
+
+impl Canvas {
+    /// Stamps the tile refs of `stamp` onto `layer` with its top left cell at `origin` (column,
+    /// row).
+    ///
+    /// The whole stamp must fit within the canvas and every tile ref must refer to a tile of
+    /// `tileset`; nothing is modified if either doesn't hold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let mut canvas = doc.canvas().clone();
+    /// let stamp = pyxel::Stamp::capture(&canvas, 1, (0, 7), 8, 1)?;
+    /// canvas.stamp(doc.tileset(), 2, (0, 3), &stamp)?;
+    /// assert_eq!(8, canvas.layers()[2].tile_refs().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stamp(
+        &mut self,
+        tileset: &Tileset,
+        layer: usize,
+        origin: (usize, usize),
+        stamp: &Stamp,
+    ) -> Result<(), PyxelError> {
+        check_region(self, origin, stamp.width, stamp.height)?;
+
+        let tile_count = tileset.tile_count();
+        if let Some(tile_ref) = stamp
+            .tile_refs
+            .iter()
+            .flatten()
+            .find(|tile_ref| tile_ref.index >= tile_count)
+        {
+            return Err(PyxelError::TileOutOfBounds(tile_ref.index));
+        }
+
+        let tiles_wide = self.tiles_wide();
+        let target = self
+            .layers
+            .get_mut(layer)
+            .ok_or(PyxelError::LayerOutOfBounds(layer))?;
+
+        for row in 0..stamp.height {
+            for col in 0..stamp.width {
+                if let Some(tile_ref) = stamp.get(col, row) {
+                    let index = (origin.1 + row) * tiles_wide + origin.0 + col;
+                    target.tile_refs.insert(index, *tile_ref);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Pyxel {
+    /// Stamps the tile refs of `stamp` onto `layer` of the canvas, checking them against the
+    /// tileset of this document. See [`Canvas::stamp`](struct.Canvas.html#method.stamp).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/test_v0.4.8.pyxel")?;
+    /// let stamp = pyxel::Stamp::capture(doc.canvas(), 1, (0, 7), 8, 1)?;
+    /// doc.stamp(2, (0, 3), &stamp)?;
+    /// assert_eq!(8, doc.canvas().layers()[2].tile_refs().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stamp(
+        &mut self,
+        layer: usize,
+        origin: (usize, usize),
+        stamp: &Stamp,
+    ) -> Result<(), PyxelError> {
+        self.canvas.stamp(&self.tileset, layer, origin, stamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn stamp_captured_region() {
+        let file = File::open(TEST_FILE).unwrap();
+        let mut doc = load(file).unwrap();
+
+        let stamp = Stamp::capture(doc.canvas(), 1, (0, 7), 8, 1).unwrap();
+        doc.stamp(2, (0, 3), &stamp).unwrap();
+
+        let source = &doc.canvas().layers()[1];
+        let target = &doc.canvas().layers()[2];
+        assert_eq!(8, target.tile_refs().len());
+
+        for col in 0..8 {
+            assert_eq!(
+                source.tile_refs().get(&(56 + col)),
+                target.tile_refs().get(&(24 + col))
+            );
+        }
+    }
+
+    #[test]
+    fn transparent_cells_are_left_untouched() {
+        let file = File::open(TEST_FILE).unwrap();
+        let mut doc = load(file).unwrap();
+
+        let mut stamp = Stamp::new(2, 1).unwrap();
+        stamp.set(1, 0, Some(TileRef::new(3, 90., true)));
+        doc.stamp(0, (0, 0), &stamp).unwrap();
+
+        let tile_refs = doc.canvas().layers()[0].tile_refs();
+        assert_eq!(0, tile_refs[&0].index());
+        assert_eq!(TileRef::new(3, 90., true), tile_refs[&1]);
+    }
+
+    #[test]
+    fn stamp_outside_canvas_is_rejected() {
+        let file = File::open(TEST_FILE).unwrap();
+        let mut doc = load(file).unwrap();
+
+        let mut stamp = Stamp::new(2, 2).unwrap();
+        stamp.set(0, 0, Some(TileRef::new(1, 0., false)));
+
+        match doc.stamp(1, (7, 0), &stamp) {
+            Err(PyxelError::CellOutOfBounds { col: 8, row: 1 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(8, doc.canvas().layers()[1].tile_refs().len());
+
+        match doc.stamp(11, (0, 0), &stamp) {
+            Err(PyxelError::LayerOutOfBounds(11)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stamp_with_unknown_tile_is_rejected() {
+        let file = File::open(TEST_FILE).unwrap();
+        let mut doc = load(file).unwrap();
+
+        let mut stamp = Stamp::new(2, 1).unwrap();
+        stamp.set(0, 0, Some(TileRef::new(1, 0., false)));
+        stamp.set(1, 0, Some(TileRef::new(4, 0., false)));
+
+        match doc.stamp(2, (0, 0), &stamp) {
+            Err(PyxelError::TileOutOfBounds(4)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(doc.canvas().layers()[2].tile_refs().is_empty());
+
+        let mut canvas = doc.canvas().clone();
+        match canvas.stamp(doc.tileset(), 2, (0, 0), &stamp) {
+            Err(PyxelError::TileOutOfBounds(4)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        stamp.set(1, 0, Some(TileRef::new(3, 0., false)));
+        canvas.stamp(doc.tileset(), 2, (0, 0), &stamp).unwrap();
+        assert_eq!(2, canvas.layers()[2].tile_refs().len());
+    }
+
+    #[test]
+    fn overflowing_regions_are_rejected() {
+        let file = File::open(TEST_FILE).unwrap();
+        let mut doc = load(file).unwrap();
+
+        match Stamp::new(usize::MAX, 2) {
+            Err(PyxelError::CellOutOfBounds { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match Stamp::capture(doc.canvas(), 1, (usize::MAX, 0), 2, 1) {
+            Err(PyxelError::CellOutOfBounds {
+                col: usize::MAX,
+                row: 0,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let stamp = Stamp::new(2, 2).unwrap();
+        match doc.stamp(1, (0, usize::MAX), &stamp) {
+            Err(PyxelError::CellOutOfBounds { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
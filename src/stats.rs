@@ -0,0 +1,180 @@
+use crate::pyxel::{Layer, Pyxel};
+
+#[cfg(feature = "images")]
+use std::collections::HashSet;
+
+/// Statistics about a layer, part of [`DocumentStats`](struct.DocumentStats.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LayerStats {
+    /// The name of the layer.
+    pub name: String,
+    /// The number of canvas cells the layer places a tile in.
+    pub tile_refs: usize,
+    /// The number of pixels of the layer that aren't fully transparent. Only counted with the
+    /// `images` feature.
+    pub opaque_pixels: Option<u64>,
+}
+
+/// Statistics about a document, produced by [`Pyxel::stats`](struct.Pyxel.html#method.stats).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DocumentStats {
+    /// The number of layers of the canvas.
+    pub layers: usize,
+    /// The number of tiles in the tileset.
+    pub tiles: usize,
+    /// The number of animations.
+    pub animations: usize,
+    /// The number of cells of the canvas tile grid.
+    pub cells: usize,
+    /// The number of cells that some layer places a tile in.
+    pub used_cells: usize,
+    /// The number of cells that no layer places a tile in.
+    pub empty_cells: usize,
+    /// The number of bytes the layer and tile images take up once decoded to RGBA8.
+    pub decoded_bytes: u64,
+    /// Statistics about each layer, in layer order.
+    pub layer_stats: Vec<LayerStats>,
+    /// The number of slots in the palette.
+    pub palette_slots: usize,
+    /// The number of palette slots holding a color.
+    pub palette_colors: usize,
+    /// The number of palette colors used by some non-transparent pixel of a layer. Only counted
+    /// with the `images` feature.
+    pub palette_colors_used: Option<usize>,
+}
+
+#[cfg(feature = "images")]
+fn opaque_pixels(layer: &Layer) -> Option<u64> {
+    use image::GenericImageView;
+
+    Some(layer.image.pixels().filter(|(_, _, p)| p[3] != 0).count() as u64)
+}
+
+#[cfg(not(feature = "images"))]
+fn opaque_pixels(_: &Layer) -> Option<u64> {
+    None
+}
+
+impl Pyxel {
+    /// Returns statistics about this document.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let stats = doc.stats();
+    /// println!(
+    ///     "{} layers, {} of {} cells used, {} bytes decoded",
+    ///     stats.layers, stats.used_cells, stats.cells, stats.decoded_bytes
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> DocumentStats {
+        let canvas = &self.canvas;
+        let cells = canvas.tiles_wide() * canvas.tiles_high();
+
+        let mut used = vec![false; cells];
+        for layer in &canvas.layers {
+            for &cell in layer.tile_refs.keys().filter(|&&cell| cell < cells) {
+                used[cell] = true;
+            }
+        }
+        let used_cells = used.iter().filter(|&&used| used).count();
+
+        // Images are decoded to the size of the canvas and of a tile respectively.
+        let layer_bytes = canvas.width.max(0) as u64 * canvas.height.max(0) as u64 * 4;
+        let tile_bytes =
+            u64::from(self.tileset.tile_width) * u64::from(self.tileset.tile_height) * 4;
+        let tiles = self.tileset.tile_count();
+
+        let layer_stats = canvas
+            .layers
+            .iter()
+            .map(|layer| LayerStats {
+                name: layer.name.clone(),
+                tile_refs: layer.tile_refs.len(),
+                opaque_pixels: opaque_pixels(layer),
+            })
+            .collect();
+
+        DocumentStats {
+            layers: canvas.layers.len(),
+            tiles,
+            animations: self.animations.len(),
+            cells,
+            used_cells,
+            empty_cells: cells - used_cells,
+            decoded_bytes: layer_bytes * canvas.layers.len() as u64 + tile_bytes * tiles as u64,
+            layer_stats,
+            palette_slots: self.palette.colors.len(),
+            palette_colors: self.palette.colors.iter().filter(|c| c.is_some()).count(),
+            palette_colors_used: self.palette_colors_used(),
+        }
+    }
+
+    #[cfg(feature = "images")]
+    fn palette_colors_used(&self) -> Option<usize> {
+        use image::GenericImageView;
+
+        let mut seen = HashSet::new();
+        for layer in &self.canvas.layers {
+            for (_, _, pixel) in layer.image.pixels() {
+                if pixel[3] != 0 {
+                    seen.insert(pixel.0);
+                }
+            }
+        }
+
+        let colors: HashSet<[u8; 4]> = self
+            .palette
+            .colors
+            .iter()
+            .flatten()
+            .map(|c| [c.r, c.g, c.b, c.a])
+            .collect();
+        Some(colors.intersection(&seen).count())
+    }
+
+    #[cfg(not(feature = "images"))]
+    fn palette_colors_used(&self) -> Option<usize> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn counts_document_contents() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let stats = doc.stats();
+
+        assert_eq!((11, 4, 3), (stats.layers, stats.tiles, stats.animations));
+        assert_eq!(64, stats.cells);
+        assert_eq!(stats.cells, stats.used_cells + stats.empty_cells);
+        assert!(stats.used_cells >= 8);
+        assert_eq!(11 * 256 * 128 * 4 + 4 * 32 * 16 * 4, stats.decoded_bytes);
+
+        assert_eq!(11, stats.layer_stats.len());
+        assert_eq!("Layer 9", stats.layer_stats[1].name);
+        assert_eq!(8, stats.layer_stats[1].tile_refs);
+        assert_eq!(0, stats.layer_stats[5].tile_refs);
+
+        assert_eq!(15, stats.palette_colors);
+        assert!(stats.palette_colors <= stats.palette_slots);
+
+        #[cfg(feature = "images")]
+        {
+            assert!(stats.layer_stats[0].opaque_pixels.unwrap() > 0);
+            assert!(stats.palette_colors_used.unwrap() <= stats.palette_colors);
+        }
+        #[cfg(not(feature = "images"))]
+        assert_eq!(None, stats.palette_colors_used);
+    }
+}
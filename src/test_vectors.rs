@@ -0,0 +1,211 @@
+//! Canonical Pyxel documents along with the values expected from loading them.
+//!
+//! These are the documents this crate is tested against, so crates building on top of it, such
+//! as engine importers or exporters, can check their integration against the same ground truth.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), pyxel::PyxelError> {
+//! for vector in pyxel::test_vectors::ALL {
+//!     let doc = vector.load()?;
+//!     assert_eq!(vector.expected.layers.len(), doc.canvas().layers().len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    error::PyxelError,
+    pyxel::{BlendMode, Pyxel},
+};
+
+/// A canonical document and the values expected from loading it.
+#[derive(Clone, Copy, Debug)]
+pub struct TestVector {
+    /// A short description of what the document covers.
+    pub description: &'static str,
+    /// The contents of the `.pyxel` file.
+    pub data: &'static [u8],
+    /// The values expected from loading the document.
+    pub expected: Expected,
+}
+
+impl TestVector {
+    /// Loads the document.
+    pub fn load(&self) -> Result<Pyxel, PyxelError> {
+        crate::load_from_memory(self.data)
+    }
+}
+
+/// The values expected from loading a [`TestVector`](struct.TestVector.html).
+#[derive(Clone, Copy, Debug)]
+pub struct Expected {
+    /// The name of the document.
+    pub name: &'static str,
+    /// The version of PyxelEdit the document was created with.
+    pub version: &'static str,
+    /// The width and height of the canvas in pixels.
+    pub canvas_size: (i32, i32),
+    /// The width and height of the canvas' tiles in pixels.
+    pub tile_size: (u16, u16),
+    /// The layers of the canvas, top-most first.
+    pub layers: &'static [ExpectedLayer],
+    /// The number of colors in the palette.
+    pub num_colors: usize,
+    /// The number of tiles in the tileset.
+    pub num_tiles: usize,
+    /// The animations of the document.
+    pub animations: &'static [ExpectedAnimation],
+}
+
+/// A layer expected in a [`TestVector`](struct.TestVector.html).
+#[derive(Clone, Copy, Debug)]
+pub struct ExpectedLayer {
+    /// The name of the layer.
+    pub name: &'static str,
+    /// The blend mode of the layer.
+    pub blend_mode: BlendMode,
+    /// The alpha value of the layer.
+    pub alpha: u8,
+    /// Whether the layer is hidden.
+    pub hidden: bool,
+    /// The number of tile refs placed on the layer.
+    pub tile_refs: usize,
+}
+
+/// An animation expected in a [`TestVector`](struct.TestVector.html).
+#[derive(Clone, Copy, Debug)]
+pub struct ExpectedAnimation {
+    /// The name of the animation.
+    pub name: &'static str,
+    /// The canvas tile the animation starts at.
+    pub base_tile: usize,
+    /// The duration of each frame in milliseconds.
+    pub frame_durations_ms: &'static [u64],
+}
+
+const fn layer(name: &'static str, blend_mode: BlendMode, tile_refs: usize) -> ExpectedLayer {
+    ExpectedLayer {
+        name,
+        blend_mode,
+        alpha: 255,
+        hidden: false,
+        tile_refs,
+    }
+}
+
+/// A single layer document with one tile and no animations.
+pub const MINIMAL: TestVector = TestVector {
+    description: "a single layer document with one tile and no animations",
+    data: include_bytes!("../resources/doc.pyxel"),
+    expected: Expected {
+        name: "doc",
+        version: "0.4.8",
+        canvas_size: (128, 128),
+        tile_size: (16, 16),
+        layers: &[layer("Layer 0", BlendMode::Normal, 0)],
+        num_colors: 20,
+        num_tiles: 1,
+        animations: &[],
+    },
+};
+
+/// A document covering every blend mode, hidden layers, tile refs and animations with frame
+/// duration multipliers.
+pub const FEATURES_V0_4_8: TestVector = TestVector {
+    description: "every blend mode, a hidden layer, tile refs and animations",
+    data: include_bytes!("../resources/test_v0.4.8.pyxel"),
+    expected: Expected {
+        name: "test_v0.4.8",
+        version: "0.4.8",
+        canvas_size: (256, 128),
+        tile_size: (32, 16),
+        layers: &[
+            layer("Layer 10", BlendMode::Subtract, 4),
+            layer("Layer 9", BlendMode::Screen, 8),
+            layer("Layer 8", BlendMode::Overlay, 0),
+            ExpectedLayer {
+                hidden: true,
+                ..layer("Layer 7", BlendMode::Invert, 0)
+            },
+            layer("Layer 6", BlendMode::Hardlight, 0),
+            layer("Layer 5", BlendMode::Lighten, 0),
+            layer("Layer 4", BlendMode::Darken, 0),
+            layer("Layer 3", BlendMode::Difference, 0),
+            layer("Layer 2", BlendMode::Add, 0),
+            layer("Layer 1", BlendMode::Multiply, 0),
+            layer("Layer 0", BlendMode::Normal, 0),
+        ],
+        num_colors: 15,
+        num_tiles: 4,
+        animations: &[
+            ExpectedAnimation {
+                name: "Animation 1",
+                base_tile: 0,
+                frame_durations_ms: &[150, 300, 450, 600],
+            },
+            ExpectedAnimation {
+                name: "Animation 2",
+                base_tile: 4,
+                frame_durations_ms: &[100, 100],
+            },
+            ExpectedAnimation {
+                name: "Animation 3",
+                base_tile: 6,
+                frame_durations_ms: &[1000, 1000],
+            },
+        ],
+    },
+};
+
+/// Every test vector.
+pub const ALL: &[TestVector] = &[MINIMAL, FEATURES_V0_4_8];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors_match_loaded_documents() {
+        for vector in ALL {
+            let doc = vector.load().unwrap();
+            let expected = &vector.expected;
+            let canvas = doc.canvas();
+
+            assert_eq!(expected.name, doc.name());
+            assert_eq!(expected.version, doc.version().to_string());
+            assert_eq!(expected.canvas_size, (canvas.width(), canvas.height()));
+            assert_eq!(
+                expected.tile_size,
+                (canvas.tile_width(), canvas.tile_height())
+            );
+            assert_eq!(expected.num_colors, doc.palette().colors().len());
+            let tileset = doc.tileset();
+            assert!(tileset.tile_image_size(expected.num_tiles - 1).is_some());
+            assert!(tileset.tile_image_size(expected.num_tiles).is_none());
+
+            assert_eq!(expected.layers.len(), canvas.layers().len());
+            for (layer, expected) in canvas.layers().iter().zip(expected.layers) {
+                assert_eq!(expected.name, layer.name());
+                assert_eq!(expected.blend_mode, layer.blend_mode());
+                assert_eq!(expected.alpha, layer.alpha());
+                assert_eq!(expected.hidden, layer.hidden());
+                assert_eq!(expected.tile_refs, layer.tile_refs().len());
+            }
+
+            assert_eq!(expected.animations.len(), doc.animations().len());
+            for (animation, expected) in doc.animations().iter().zip(expected.animations) {
+                let durations: Vec<u64> = animation
+                    .frame_durations()
+                    .iter()
+                    .map(|d| d.as_millis() as u64)
+                    .collect();
+
+                assert_eq!(expected.name, animation.name());
+                assert_eq!(expected.base_tile, animation.base_tile());
+                assert_eq!(expected.frame_durations_ms, &durations[..]);
+            }
+        }
+    }
+}
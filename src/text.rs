@@ -0,0 +1,24 @@
+/// Appends formatted text to a `String`, as `write!` does without returning a `Result`.
+///
+/// Writing to a `String` can't fail, so exporters building text don't need to handle errors
+/// for every line they write.
+macro_rules! write_str {
+    ($dst:expr, $($arg:tt)*) => {{
+        use std::fmt::Write as _;
+        write!($dst, $($arg)*).expect("writing to a String can't fail")
+    }};
+}
+
+/// Appends formatted text and a newline to a `String`, as `writeln!` does without returning a
+/// `Result`.
+macro_rules! writeln_str {
+    ($dst:expr) => {
+        $dst.push('\n')
+    };
+    ($dst:expr, $($arg:tt)*) => {{
+        use std::fmt::Write as _;
+        writeln!($dst, $($arg)*).expect("writing to a String can't fail")
+    }};
+}
+
+pub(crate) use {write_str, writeln_str};
@@ -0,0 +1,90 @@
+use crate::{error::PyxelError, export::TmxOptions, pyxel::Pyxel};
+
+use std::{
+    io::{self, Cursor},
+    path::Path,
+};
+
+/// The path the map is loaded from, which tile image paths are relative to.
+const MAP_PATH: &str = "map.tmx";
+
+impl Pyxel {
+    /// Converts this document to a `tiled` crate `Map`, as [`to_tmx`](#method.to_tmx) would
+    /// convert it to a TMX map, without writing anything to disk.
+    ///
+    /// The map holds the tileset as its only tileset. The tile images aren't loaded, each tile
+    /// refers to its image by a path relative to the directory the map is in, as set by
+    /// `options`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// let map = doc.to_tiled_map(&pyxel::TmxOptions::default())?;
+    /// assert_eq!(doc.canvas().layers().len(), map.layers().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_tiled_map(&self, options: &TmxOptions) -> Result<tiled::Map, PyxelError> {
+        let tmx = self.to_tmx(options);
+        let reader = move |path: &Path| {
+            if path == Path::new(MAP_PATH) {
+                Ok(Cursor::new(tmx.clone().into_bytes()))
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "only the map exists",
+                ))
+            }
+        };
+
+        Ok(tiled::Loader::with_reader(reader).load_tmx_map(MAP_PATH)?)
+    }
+
+    /// Converts the tileset of this document to a `tiled` crate `Tileset`, the same tileset held
+    /// by [`to_tiled_map`](#method.to_tiled_map).
+    pub fn to_tiled_tileset(&self, options: &TmxOptions) -> Result<tiled::Tileset, PyxelError> {
+        let map = self.to_tiled_map(options)?;
+        Ok(map.tilesets()[0].as_ref().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    #[test]
+    fn documents_convert_to_tiled_maps() {
+        let doc = load(File::open("resources/test_v0.4.8.pyxel").unwrap()).unwrap();
+        let map = doc.to_tiled_map(&TmxOptions::default()).unwrap();
+
+        assert_eq!(
+            (8, 8, 32, 16),
+            (map.width, map.height, map.tile_width, map.tile_height)
+        );
+        assert_eq!(11, map.layers().len());
+        let layer = map.get_layer(9).unwrap();
+        assert_eq!(doc.canvas().layers()[1].name(), layer.name);
+
+        let tiles = layer.as_tile_layer().unwrap();
+        for (&cell, tile_ref) in doc.canvas().layers()[1].tile_refs() {
+            let tile = tiles.get_tile(cell as i32 % 8, cell as i32 / 8).unwrap();
+            assert_eq!(tile_ref.index() as u32, tile.id());
+        }
+        // Rotated by 270 degrees and flipped horizontally.
+        let tile = tiles.get_tile(7, 7).unwrap();
+        assert_eq!(
+            (false, false, true),
+            (tile.flip_h, tile.flip_v, tile.flip_d)
+        );
+        assert!(tiles.get_tile(0, 0).is_none());
+
+        let tileset = doc.to_tiled_tileset(&TmxOptions::default()).unwrap();
+        assert_eq!(4, tileset.tilecount);
+        let image = tileset.get_tile(3).unwrap().image.clone().unwrap();
+        assert_eq!(Path::new("tile3.png"), image.source);
+    }
+}
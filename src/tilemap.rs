@@ -0,0 +1,238 @@
+use crate::{deserialization::deserialize_as_degrees, error::PyxelError, pyxel::TileRef};
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, io::Read};
+
+#[derive(Deserialize)]
+struct RawTilemap {
+    tileswide: usize,
+    tileshigh: usize,
+    tilewidth: u16,
+    tileheight: u16,
+    layers: Vec<RawLayer>,
+}
+
+#[derive(Deserialize)]
+struct RawLayer {
+    name: String,
+    number: usize,
+    tiles: Vec<RawTile>,
+}
+
+#[derive(Deserialize)]
+struct RawTile {
+    x: usize,
+    y: usize,
+    tile: i64,
+    #[serde(deserialize_with = "deserialize_as_degrees")]
+    rot: f64,
+    #[serde(rename = "flipX")]
+    flip_x: bool,
+}
+
+/// A tilemap exported by PyxelEdit's "export tilemap" command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tilemap {
+    tiles_wide: usize,
+    tiles_high: usize,
+    tile_width: u16,
+    tile_height: u16,
+    layers: Vec<TilemapLayer>,
+}
+
+impl Tilemap {
+    /// Returns the number of tile columns in this tilemap.
+    pub fn tiles_wide(&self) -> usize {
+        self.tiles_wide
+    }
+
+    /// Returns the number of tile rows in this tilemap.
+    pub fn tiles_high(&self) -> usize {
+        self.tiles_high
+    }
+
+    /// Returns the width of the tiles in this tilemap in pixels.
+    pub fn tile_width(&self) -> u16 {
+        self.tile_width
+    }
+
+    /// Returns the height of the tiles in this tilemap in pixels.
+    pub fn tile_height(&self) -> u16 {
+        self.tile_height
+    }
+
+    /// Returns the layers of this tilemap, top-most first as in
+    /// [`Canvas::layers`](struct.Canvas.html#method.layers).
+    pub fn layers(&self) -> &[TilemapLayer] {
+        &self.layers
+    }
+
+    /// Returns the index used to key tile refs for the cell at `col`, `row`, or `None` if the
+    /// cell lies outside of this tilemap. Cells are numbered row by row, as they are for a
+    /// [`Canvas`](struct.Canvas.html#method.cell_index).
+    pub fn cell_index(&self, col: usize, row: usize) -> Option<usize> {
+        if col < self.tiles_wide && row < self.tiles_high {
+            Some(row * self.tiles_wide + col)
+        } else {
+            None
+        }
+    }
+}
+
+/// A layer of a [`Tilemap`](struct.Tilemap.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TilemapLayer {
+    name: String,
+    number: usize,
+    tile_refs: BTreeMap<usize, TileRef>,
+}
+
+impl TilemapLayer {
+    /// Returns the name of this layer.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the number PyxelEdit gave this layer, where 0 is the top-most layer.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Returns the tile refs of this layer keyed by cell index, see
+    /// [`Tilemap::cell_index`](struct.Tilemap.html#method.cell_index). Empty cells have no tile
+    /// ref.
+    pub fn tile_refs(&self) -> &BTreeMap<usize, TileRef> {
+        &self.tile_refs
+    }
+}
+
+/// Load a tilemap exported by PyxelEdit's "export tilemap" command from a reader.
+///
+/// Tile refs are read into the same [`TileRef`](struct.TileRef.html)s as a document's layers,
+/// with rotations converted to degrees. Tiles placed outside of the tilemap are rejected with
+/// [`PyxelError::CellOutOfBounds`](enum.PyxelError.html#variant.CellOutOfBounds).
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let json = r#"{
+///     "tileswide": 2, "tileshigh": 1, "tilewidth": 16, "tileheight": 16,
+///     "layers": [{
+///         "name": "Layer 0", "number": 0,
+///         "tiles": [
+///             { "x": 0, "y": 0, "index": 0, "tile": 3, "rot": 1, "flipX": false },
+///             { "x": 1, "y": 0, "index": 1, "tile": -1, "rot": 0, "flipX": false }
+///         ]
+///     }]
+/// }"#;
+///
+/// let tilemap = pyxel::load_tilemap_export(json.as_bytes())?;
+/// let tile_ref = tilemap.layers()[0].tile_refs()[&0];
+/// assert_eq!((3, 90.), (tile_ref.index(), tile_ref.rot()));
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_tilemap_export<R: Read>(r: R) -> Result<Tilemap, PyxelError> {
+    let raw: RawTilemap = serde_json::from_reader(r)?;
+    let (tiles_wide, tiles_high) = (raw.tileswide, raw.tileshigh);
+
+    let mut layers = raw
+        .layers
+        .into_iter()
+        .map(|layer| {
+            let mut tile_refs = BTreeMap::new();
+
+            for tile in layer.tiles {
+                if tile.x >= tiles_wide || tile.y >= tiles_high {
+                    return Err(PyxelError::CellOutOfBounds {
+                        col: tile.x,
+                        row: tile.y,
+                    });
+                }
+
+                // Empty cells are exported with a tile of -1.
+                if tile.tile >= 0 {
+                    tile_refs.insert(
+                        tile.y * tiles_wide + tile.x,
+                        TileRef::new(tile.tile as usize, tile.rot, tile.flip_x),
+                    );
+                }
+            }
+
+            Ok(TilemapLayer {
+                name: layer.name,
+                number: layer.number,
+                tile_refs,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    layers.sort_by_key(|layer| layer.number);
+
+    Ok(Tilemap {
+        tiles_wide,
+        tiles_high,
+        tile_width: raw.tilewidth,
+        tile_height: raw.tileheight,
+        layers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"{
+        "tileshigh": 2,
+        "tilewidth": 32,
+        "tileswide": 3,
+        "tileheight": 16,
+        "layers": [
+            {
+                "number": 1,
+                "name": "Background",
+                "tiles": [
+                    { "x": 0, "index": 0, "y": 0, "tile": 0, "flipX": false, "rot": 0 },
+                    { "x": 1, "index": 1, "y": 0, "tile": 0, "flipX": false, "rot": 0 }
+                ]
+            },
+            {
+                "number": 0,
+                "name": "Foreground",
+                "tiles": [
+                    { "x": 2, "index": 5, "y": 1, "tile": 2, "flipX": true, "rot": 3 },
+                    { "x": 0, "index": 3, "y": 1, "tile": -1, "flipX": false, "rot": 0 }
+                ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn loads_layers_and_tile_refs() {
+        let tilemap = load_tilemap_export(EXPORT.as_bytes()).unwrap();
+
+        assert_eq!((3, 2), (tilemap.tiles_wide(), tilemap.tiles_high()));
+        assert_eq!((32, 16), (tilemap.tile_width(), tilemap.tile_height()));
+
+        let names: Vec<&str> = tilemap.layers().iter().map(|l| l.name()).collect();
+        assert_eq!(vec!["Foreground", "Background"], names);
+
+        let foreground = &tilemap.layers()[0];
+        assert_eq!(1, foreground.tile_refs().len());
+        assert_eq!(
+            TileRef::new(2, 270., true),
+            foreground.tile_refs()[&tilemap.cell_index(2, 1).unwrap()]
+        );
+        assert_eq!(2, tilemap.layers()[1].tile_refs().len());
+        assert_eq!(None, tilemap.cell_index(3, 0));
+    }
+
+    #[test]
+    fn tiles_outside_the_tilemap_are_rejected() {
+        let json = EXPORT.replace(r#""x": 2, "index": 5"#, r#""x": 3, "index": 5"#);
+        match load_tilemap_export(json.as_bytes()) {
+            Err(PyxelError::CellOutOfBounds { col: 3, row: 1 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
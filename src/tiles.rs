@@ -0,0 +1,303 @@
+use crate::{
+    error::PyxelError,
+    pyxel::{Pyxel, Tileset},
+};
+
+impl Tileset {
+    fn check_tile_size(&self, width: u32, height: u32) -> Result<(), PyxelError> {
+        if (width, height) != (u32::from(self.tile_width), u32::from(self.tile_height)) {
+            return Err(PyxelError::TileSizeMismatch {
+                width: width.min(u32::from(u16::MAX)) as u16,
+                height: height.min(u32::from(u16::MAX)) as u16,
+            });
+        }
+        Ok(())
+    }
+
+    /// Appends `image` to this tileset, returning the index of the new tile.
+    ///
+    /// The image must have the dimensions of the tileset's tiles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// let tileset = doc.tileset_mut();
+    /// assert_eq!(1, tileset.add_tile(image::DynamicImage::new_rgba8(16, 16))?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "images")]
+    pub fn add_tile(&mut self, image: image::DynamicImage) -> Result<usize, PyxelError> {
+        use image::GenericImageView;
+
+        self.check_tile_size(image.width(), image.height())?;
+        self.images.push(std::sync::Arc::new(image));
+        self.num_tiles = self.images.len();
+        Ok(self.images.len() - 1)
+    }
+
+    /// Appends the PNG image `png` to this tileset, returning the index of the new tile.
+    ///
+    /// The image must have the dimensions of the tileset's tiles.
+    #[cfg(not(feature = "images"))]
+    pub fn add_tile_data(&mut self, png: Vec<u8>) -> Result<usize, PyxelError> {
+        let (width, height) = crate::png::png_dimensions(&png)?;
+        self.check_tile_size(width, height)?;
+        self.image_data.push(png);
+        self.num_tiles = self.image_data.len();
+        Ok(self.image_data.len() - 1)
+    }
+}
+
+impl Pyxel {
+    /// Removes the tile at `index` from the tileset, returning the number of tile refs removed.
+    ///
+    /// Tile refs to the removed tile are removed from every layer, and tile refs to later tiles
+    /// are remapped to their new indices. The pixels of the layers are left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// doc.remove_tile(0)?;
+    /// assert_eq!(0, doc.tileset().num_tiles());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_tile(&mut self, index: usize) -> Result<usize, PyxelError> {
+        let tileset = &mut self.tileset;
+        if index >= tileset.tile_count() {
+            return Err(PyxelError::TileOutOfBounds(index));
+        }
+
+        #[cfg(not(feature = "images"))]
+        tileset.image_data.remove(index);
+
+        #[cfg(feature = "images")]
+        tileset.images.remove(index);
+
+        tileset.num_tiles = tileset.tile_count();
+
+        let mut removed = 0;
+        for layer in &mut self.canvas.layers {
+            let before = layer.tile_refs.len();
+            layer
+                .tile_refs
+                .retain(|_, tile_ref| tile_ref.index != index);
+            removed += before - layer.tile_refs.len();
+
+            for tile_ref in layer.tile_refs.values_mut() {
+                if tile_ref.index > index {
+                    tile_ref.index -= 1;
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Replaces the tileset with the cells of the layers, cut on the tile grid, and returns the
+    /// number of tiles created.
+    ///
+    /// This turns a document drawn freehand into a tilemap. Each layer is cut up on its own, and
+    /// its tile refs are replaced with refs to the tiles cut from its cells, so every tile ref
+    /// matches the pixels of its layer whether the layer is visible or not. Fully transparent
+    /// cells don't get a tile. When `dedupe` is `true`, cells with the same pixels as an earlier
+    /// cell of any layer, in layer then row order, share its tile. The pixels of the layers are
+    /// left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let mut doc = pyxel::open("resources/doc.pyxel")?;
+    /// let tiles = doc.build_tileset_from_canvas(true);
+    /// assert_eq!(tiles, doc.tileset().num_tiles());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "images")]
+    pub fn build_tileset_from_canvas(&mut self, dedupe: bool) -> usize {
+        use crate::{pyxel::TileRef, render::crop_layer};
+        use image::DynamicImage;
+        use std::{
+            collections::{BTreeMap, HashMap},
+            sync::Arc,
+        };
+
+        let canvas = &mut self.canvas;
+        let (tile_width, tile_height) =
+            (u32::from(canvas.tile_width), u32::from(canvas.tile_height));
+        let (tiles_wide, cells) = (
+            canvas.tiles_wide(),
+            canvas.tiles_wide() * canvas.tiles_high(),
+        );
+
+        let mut images = Vec::new();
+        let mut seen: HashMap<Vec<u8>, usize> = HashMap::new();
+        for layer in &mut canvas.layers {
+            let mut tile_refs = BTreeMap::new();
+            for cell in 0..cells {
+                let (x, y) = (
+                    (cell % tiles_wide) as u32 * tile_width,
+                    (cell / tiles_wide) as u32 * tile_height,
+                );
+                let tile = crop_layer(layer, x, y, tile_width, tile_height);
+                if tile.pixels().all(|pixel| pixel[3] == 0) {
+                    continue;
+                }
+
+                let index = match seen.get(&*tile as &[u8]) {
+                    Some(&index) if dedupe => index,
+                    _ => {
+                        let index = images.len();
+                        if dedupe {
+                            seen.insert(tile.to_vec(), index);
+                        }
+                        images.push(Arc::new(DynamicImage::ImageRgba8(tile)));
+                        index
+                    }
+                };
+                tile_refs.insert(cell, TileRef::new(index, 0., false));
+            }
+            layer.tile_refs = tile_refs;
+        }
+
+        let tileset = &mut self.tileset;
+        tileset.tile_width = canvas.tile_width;
+        tileset.tile_height = canvas.tile_height;
+        tileset.images = images;
+        tileset.num_tiles = tileset.images.len();
+        tileset.num_tiles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::load;
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    fn load_test_file() -> Pyxel {
+        load(File::open(TEST_FILE).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn remove_tile_fixes_up_tile_refs() {
+        let mut doc = load_test_file();
+        let original = doc.clone();
+        let uses = |doc: &Pyxel, index| {
+            doc.canvas
+                .layers
+                .iter()
+                .flat_map(|layer| layer.tile_refs.values())
+                .filter(|tile_ref| tile_ref.index == index)
+                .count()
+        };
+
+        let removed = doc.remove_tile(1).unwrap();
+        assert_eq!(uses(&original, 1), removed);
+        assert_eq!(3, doc.tileset.num_tiles);
+        assert_eq!(uses(&original, 2), uses(&doc, 1));
+        assert_eq!(uses(&original, 3), uses(&doc, 2));
+        assert_eq!(0, uses(&doc, 3));
+        assert!(doc.validate().is_empty());
+
+        #[cfg(feature = "images")]
+        assert!(std::sync::Arc::ptr_eq(
+            &original.tileset.images[3],
+            &doc.tileset.images[2]
+        ));
+
+        match doc.remove_tile(3) {
+            Err(PyxelError::TileOutOfBounds(3)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn add_tile_checks_dimensions() {
+        let mut doc = load_test_file();
+        let tileset = doc.tileset_mut();
+
+        assert_eq!(
+            4,
+            tileset
+                .add_tile(image::DynamicImage::new_rgba8(32, 16))
+                .unwrap()
+        );
+        assert_eq!(5, tileset.num_tiles());
+
+        match tileset.add_tile(image::DynamicImage::new_rgba8(16, 16)) {
+            Err(PyxelError::TileSizeMismatch {
+                width: 16,
+                height: 16,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(5, tileset.images().len());
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn tileset_is_built_from_each_layer() {
+        use image::{imageops, GenericImageView, RgbaImage};
+
+        // Redraws each layer from nothing but its tile refs.
+        fn from_tile_refs(doc: &Pyxel) -> Vec<Vec<u8>> {
+            let canvas = &doc.canvas;
+            canvas
+                .layers
+                .iter()
+                .map(|layer| {
+                    let mut image = RgbaImage::new(canvas.width as u32, canvas.height as u32);
+                    for (x, y, tile_ref) in layer.placed_tiles(canvas) {
+                        let tile = doc.tileset.images[tile_ref.index].to_rgba();
+                        imageops::replace(&mut image, &tile, x, y);
+                    }
+                    image.into_raw()
+                })
+                .collect()
+        }
+
+        let mut doc = load_test_file();
+        let pixels: Vec<Vec<u8>> = doc
+            .canvas
+            .layers
+            .iter()
+            .map(|layer| layer.image.to_rgba().into_raw())
+            .collect();
+        let mut deduped = doc.clone();
+
+        let count = doc.build_tileset_from_canvas(false);
+        assert_eq!(count, doc.tileset.images.len());
+        let refs: usize = doc.canvas.layers.iter().map(|l| l.tile_refs.len()).sum();
+        assert_eq!(count, refs);
+        assert!(doc.validate().is_empty());
+        assert_eq!(pixels, from_tile_refs(&doc));
+        // The hidden layer gets tiles of its own pixels too.
+        let hidden = &doc.canvas.layers[3];
+        assert_eq!(
+            hidden.image.pixels().any(|(_, _, p)| p[3] != 0),
+            !hidden.tile_refs.is_empty()
+        );
+
+        let unique = deduped.build_tileset_from_canvas(true);
+        assert!(unique < count);
+        assert!(deduped.validate().is_empty());
+        assert_eq!(pixels, from_tile_refs(&deduped));
+        let images: Vec<_> = deduped
+            .tileset
+            .images
+            .iter()
+            .map(|i| i.to_rgba().into_raw())
+            .collect();
+        assert!((1..images.len()).all(|i| !images[..i].contains(&images[i])));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::pyxel::Pyxel;
+
+/// A canvas cell of a layer that refers to a tile.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TileUse {
+    /// The index of the layer.
+    pub layer: usize,
+    /// The column of the canvas cell.
+    pub col: usize,
+    /// The row of the canvas cell.
+    pub row: usize,
+}
+
+/// The places a tile is used, produced by
+/// [`Pyxel::tile_usage`](struct.Pyxel.html#method.tile_usage).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TileUsage {
+    /// The index of the tile.
+    pub tile: usize,
+    /// The canvas cells referring to the tile, ordered by layer and then by cell.
+    pub uses: Vec<TileUse>,
+}
+
+impl TileUsage {
+    /// Returns the number of times the tile is used.
+    pub fn count(&self) -> usize {
+        self.uses.len()
+    }
+
+    /// Returns `true` if the tile isn't used by any layer.
+    pub fn is_unused(&self) -> bool {
+        self.uses.is_empty()
+    }
+}
+
+impl Pyxel {
+    /// Returns where each tile of the tileset is used, in tile order.
+    ///
+    /// Tile refs keyed by cells outside of the canvas or referring to tiles that aren't in the
+    /// tileset are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// for usage in doc.tile_usage() {
+    ///     println!("tile {} is used {} times", usage.tile, usage.count());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tile_usage(&self) -> Vec<TileUsage> {
+        let mut usage: Vec<TileUsage> = (0..self.tileset.tile_count())
+            .map(|tile| TileUsage {
+                tile,
+                uses: Vec::new(),
+            })
+            .collect();
+
+        let canvas = &self.canvas;
+        let (tiles_wide, num_cells) = (
+            canvas.tiles_wide(),
+            canvas.tiles_wide() * canvas.tiles_high(),
+        );
+
+        for (layer, contents) in canvas.layers.iter().enumerate() {
+            for (&cell, tile_ref) in &contents.tile_refs {
+                if cell >= num_cells {
+                    continue;
+                }
+
+                if let Some(usage) = usage.get_mut(tile_ref.index) {
+                    usage.uses.push(TileUse {
+                        layer,
+                        col: cell % tiles_wide,
+                        row: cell / tiles_wide,
+                    });
+                }
+            }
+        }
+
+        usage
+    }
+
+    /// Returns the indices of the tiles that no tile ref of any layer refers to, in tile order.
+    ///
+    /// Unlike [`tile_usage`](#method.tile_usage), tile refs keyed by cells outside of the canvas
+    /// count as uses, so that pruning never leaves a tile ref dangling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// assert_eq!(vec![0], doc.unused_tiles());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unused_tiles(&self) -> Vec<usize> {
+        let mut used = vec![false; self.tileset.tile_count()];
+        for layer in &self.canvas.layers {
+            for tile_ref in layer.tile_refs.values() {
+                if let Some(used) = used.get_mut(tile_ref.index) {
+                    *used = true;
+                }
+            }
+        }
+
+        (0..used.len()).filter(|&tile| !used[tile]).collect()
+    }
+
+    /// Removes the tiles returned by [`unused_tiles`](#method.unused_tiles) from the tileset,
+    /// remapping the tile refs of later tiles to their new indices as
+    /// [`remove_tile`](#method.remove_tile) does. Returns the indices the removed tiles had.
+    pub fn prune_unused(&mut self) -> Vec<usize> {
+        let unused = self.unused_tiles();
+        for &tile in unused.iter().rev() {
+            self.remove_tile(tile)
+                .expect("unused tiles are in the tileset");
+        }
+        unused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load::load, pyxel::TileRef};
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn counts_tile_refs() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        let usage = doc.tile_usage();
+
+        assert_eq!(4, usage.len());
+        assert_eq!(12, usage.iter().map(TileUsage::count).sum::<usize>());
+
+        doc.canvas.layers[5]
+            .tile_refs
+            .insert(63, TileRef::new(3, 0., false));
+        doc.canvas.layers[5]
+            .tile_refs
+            .insert(64, TileRef::new(3, 0., false));
+        doc.canvas.layers[5]
+            .tile_refs
+            .insert(62, TileRef::new(4, 0., false));
+
+        let after = doc.tile_usage();
+        assert_eq!(usage[3].count() + 1, after[3].count());
+        assert_eq!(
+            Some(&TileUse {
+                layer: 5,
+                col: 7,
+                row: 7
+            }),
+            after[3].uses.last()
+        );
+    }
+
+    #[test]
+    fn prunes_unused_tiles() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        assert!(doc.unused_tiles().is_empty());
+
+        doc.canvas.layers[0].tile_refs.remove(&1);
+        doc.canvas.layers[0].tile_refs.remove(&3);
+        assert_eq!(vec![1, 3], doc.unused_tiles());
+
+        assert_eq!(vec![1, 3], doc.prune_unused());
+        assert_eq!(2, doc.tileset.num_tiles);
+        assert_eq!(1, doc.canvas.layers[0].tile_refs[&2].index);
+        assert!(doc.unused_tiles().is_empty());
+        assert!(doc.validate().is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+use crate::pyxel::Pyxel;
+
+use std::fmt;
+
+/// A problem found by [`Pyxel::validate`](struct.Pyxel.html#method.validate).
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ValidationIssue {
+    /// The palette's declared number of colors doesn't match the number of palette slots.
+    ColorCountMismatch {
+        /// The number of colors declared by the document.
+        declared: usize,
+        /// The number of palette slots present.
+        actual: usize,
+    },
+
+    /// The canvas' declared number of layers doesn't match the number of layers present.
+    LayerCountMismatch {
+        /// The number of layers declared by the document.
+        declared: usize,
+        /// The number of layers present.
+        actual: usize,
+    },
+
+    /// The tileset's declared number of tiles doesn't match the number of tile images present.
+    TileCountMismatch {
+        /// The number of tiles declared by the document.
+        declared: usize,
+        /// The number of tile images present.
+        actual: usize,
+    },
+
+    /// The canvas dimensions aren't a multiple of the tile dimensions.
+    CanvasNotTileAligned,
+
+    /// A tile ref is keyed by a cell outside of the canvas.
+    TileRefOutsideCanvas {
+        /// The index of the layer containing the tile ref.
+        layer: usize,
+        /// The cell the tile ref is keyed by.
+        cell: usize,
+    },
+
+    /// A tile ref refers to a tile that isn't in the tileset.
+    TileRefOutOfBounds {
+        /// The index of the layer containing the tile ref.
+        layer: usize,
+        /// The cell the tile ref is keyed by.
+        cell: usize,
+        /// The tile index referred to.
+        index: usize,
+    },
+
+    /// An animation contains frames beyond the last canvas tile.
+    AnimationOutsideCanvas {
+        /// The index of the animation.
+        animation: usize,
+    },
+
+    /// An animation's number of frame duration multipliers doesn't match its length.
+    MultiplierCountMismatch {
+        /// The index of the animation.
+        animation: usize,
+        /// The length of the animation.
+        length: usize,
+        /// The number of multipliers present.
+        actual: usize,
+    },
+
+    /// A layer image doesn't have the same dimensions as the canvas.
+    LayerImageSizeMismatch {
+        /// The index of the layer.
+        layer: usize,
+        /// The width of the layer image in pixels.
+        width: u32,
+        /// The height of the layer image in pixels.
+        height: u32,
+    },
+
+    /// A tile image doesn't have the dimensions of the tileset's tiles.
+    TileImageSizeMismatch {
+        /// The index of the tile.
+        tile: usize,
+        /// The width of the tile image in pixels.
+        width: u32,
+        /// The height of the tile image in pixels.
+        height: u32,
+    },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationIssue::ColorCountMismatch { declared, actual } => write!(
+                f,
+                "palette declares {} colors but has {} slots",
+                declared, actual
+            ),
+            ValidationIssue::LayerCountMismatch { declared, actual } => {
+                write!(f, "canvas declares {} layers but has {}", declared, actual)
+            }
+            ValidationIssue::TileCountMismatch { declared, actual } => write!(
+                f,
+                "tileset declares {} tiles but has {} images",
+                declared, actual
+            ),
+            ValidationIssue::CanvasNotTileAligned => {
+                write!(f, "canvas size is not a multiple of the tile size")
+            }
+            ValidationIssue::TileRefOutsideCanvas { layer, cell } => write!(
+                f,
+                "layer {} has a tile ref for cell {} outside of the canvas",
+                layer, cell
+            ),
+            ValidationIssue::TileRefOutOfBounds { layer, cell, index } => write!(
+                f,
+                "layer {} cell {} refers to tile {} which is not in the tileset",
+                layer, cell, index
+            ),
+            ValidationIssue::AnimationOutsideCanvas { animation } => write!(
+                f,
+                "animation {} has frames outside of the canvas",
+                animation
+            ),
+            ValidationIssue::MultiplierCountMismatch {
+                animation,
+                length,
+                actual,
+            } => write!(
+                f,
+                "animation {} has {} frames but {} duration multipliers",
+                animation, length, actual
+            ),
+            ValidationIssue::LayerImageSizeMismatch {
+                layer,
+                width,
+                height,
+            } => write!(
+                f,
+                "layer {} image is {}x{} which doesn't match the canvas",
+                layer, width, height
+            ),
+            ValidationIssue::TileImageSizeMismatch {
+                tile,
+                width,
+                height,
+            } => write!(
+                f,
+                "tile {} image is {}x{} which doesn't match the tile size",
+                tile, width, height
+            ),
+        }
+    }
+}
+
+impl Pyxel {
+    /// Cross-checks the contents of this document, returning every inconsistency found.
+    ///
+    /// An empty list means the document is consistent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), pyxel::PyxelError> {
+    /// let doc = pyxel::open("resources/doc.pyxel")?;
+    /// for issue in doc.validate() {
+    ///     println!("{}", issue);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        let palette = &self.palette;
+        if palette.num_colors != palette.colors.len() {
+            issues.push(ValidationIssue::ColorCountMismatch {
+                declared: palette.num_colors,
+                actual: palette.colors.len(),
+            });
+        }
+
+        let canvas = &self.canvas;
+        if canvas.num_layers != canvas.layers.len() {
+            issues.push(ValidationIssue::LayerCountMismatch {
+                declared: canvas.num_layers,
+                actual: canvas.layers.len(),
+            });
+        }
+
+        let tileset = &self.tileset;
+        let num_tiles = tileset.tile_count();
+        if tileset.num_tiles != num_tiles {
+            issues.push(ValidationIssue::TileCountMismatch {
+                declared: tileset.num_tiles,
+                actual: num_tiles,
+            });
+        }
+
+        let tile_width = i32::from(canvas.tile_width);
+        let tile_height = i32::from(canvas.tile_height);
+        if tile_width == 0
+            || tile_height == 0
+            || canvas.width % tile_width != 0
+            || canvas.height % tile_height != 0
+        {
+            issues.push(ValidationIssue::CanvasNotTileAligned);
+        }
+
+        let num_cells = canvas.tiles_wide() * canvas.tiles_high();
+
+        for (i, layer) in canvas.layers.iter().enumerate() {
+            for (&cell, tile_ref) in &layer.tile_refs {
+                if cell >= num_cells {
+                    issues.push(ValidationIssue::TileRefOutsideCanvas { layer: i, cell });
+                }
+
+                if tile_ref.index >= num_tiles {
+                    issues.push(ValidationIssue::TileRefOutOfBounds {
+                        layer: i,
+                        cell,
+                        index: tile_ref.index,
+                    });
+                }
+            }
+
+            let (width, height) = layer.dimensions();
+            if i64::from(width) != i64::from(canvas.width)
+                || i64::from(height) != i64::from(canvas.height)
+            {
+                issues.push(ValidationIssue::LayerImageSizeMismatch {
+                    layer: i,
+                    width,
+                    height,
+                });
+            }
+        }
+
+        for (i, animation) in self.animations.iter().enumerate() {
+            if animation.base_tile + animation.length > num_cells {
+                issues.push(ValidationIssue::AnimationOutsideCanvas { animation: i });
+            }
+
+            if animation.frame_duration_multipliers.len() != animation.length {
+                issues.push(ValidationIssue::MultiplierCountMismatch {
+                    animation: i,
+                    length: animation.length,
+                    actual: animation.frame_duration_multipliers.len(),
+                });
+            }
+        }
+
+        #[cfg(feature = "images")]
+        {
+            use image::GenericImageView;
+
+            for (i, image) in tileset.images.iter().enumerate() {
+                let (width, height) = image.dimensions();
+                if width != u32::from(tileset.tile_width)
+                    || height != u32::from(tileset.tile_height)
+                {
+                    issues.push(ValidationIssue::TileImageSizeMismatch {
+                        tile: i,
+                        width,
+                        height,
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load::load, pyxel::TileRef};
+    use std::fs::File;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn valid_document_has_no_issues() {
+        let doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+        assert!(doc.validate().is_empty());
+    }
+
+    #[test]
+    fn reports_inconsistencies() {
+        let mut doc = load(File::open(TEST_FILE).unwrap()).unwrap();
+
+        doc.palette.num_colors = 16;
+        doc.canvas.num_layers = 12;
+        doc.tileset.num_tiles = 5;
+        doc.canvas.layers[0]
+            .tile_refs
+            .insert(64, TileRef::new(4, 0., false));
+        doc.animations[2].base_tile = 63;
+
+        assert_eq!(
+            vec![
+                ValidationIssue::ColorCountMismatch {
+                    declared: 16,
+                    actual: 15
+                },
+                ValidationIssue::LayerCountMismatch {
+                    declared: 12,
+                    actual: 11
+                },
+                ValidationIssue::TileCountMismatch {
+                    declared: 5,
+                    actual: 4
+                },
+                ValidationIssue::TileRefOutsideCanvas { layer: 0, cell: 64 },
+                ValidationIssue::TileRefOutOfBounds {
+                    layer: 0,
+                    cell: 64,
+                    index: 4
+                },
+                ValidationIssue::AnimationOutsideCanvas { animation: 2 },
+            ],
+            doc.validate()
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{error::PyxelError, pyxel::Pyxel};
+
+use notify::{EventKind, RecursiveMode, Watcher as _};
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+/// How long to wait after the last change to a document before reloading it. Saving a document
+/// takes several writes, which shouldn't each trigger a reload.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watches a Pyxel document for changes, created by [`watch`](fn.watch.html).
+///
+/// The document is no longer watched once this is dropped.
+pub struct Watcher {
+    _watcher: notify::RecommendedWatcher,
+    path: PathBuf,
+}
+
+impl fmt::Debug for Watcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Watcher").field("path", &self.path).finish()
+    }
+}
+
+/// Watch the Pyxel document located at the path specified, reloading it and passing the result
+/// to `callback` whenever it changes.
+///
+/// Errors, including those from loading a partially written or corrupt document, are passed to
+/// `callback` rather than stopping the watch, so an artist can fix a mistake and save again.
+/// `callback` is called on a background thread, and isn't called for the document as it is when
+/// watching starts.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), pyxel::PyxelError> {
+/// let _watcher = pyxel::watch("resources/doc.pyxel", |result| match result {
+///     Ok(doc) => println!("reloaded {}", doc.name()),
+///     Err(e) => eprintln!("failed to reload: {}", e),
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn watch<P, F>(path: P, mut callback: F) -> Result<Watcher, PyxelError>
+where
+    P: AsRef<Path>,
+    F: FnMut(Result<Pyxel, PyxelError>) + Send + 'static,
+{
+    let path = path.as_ref().to_path_buf();
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a path to a file"))?
+        .to_owned();
+
+    // Documents are often saved by replacing the file, which would stop a watch on the file
+    // itself, so watch the directory containing it instead.
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    let reload_path = path.clone();
+    thread::spawn(move || {
+        // The sender is dropped along with the watcher, which ends this loop.
+        while let Ok(event) = rx.recv() {
+            match event {
+                Ok(event) => {
+                    let changed = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                        && event
+                            .paths
+                            .iter()
+                            .any(|p| p.file_name() == Some(&file_name));
+
+                    if !changed {
+                        continue;
+                    }
+                }
+                Err(e) => {
+                    callback(Err(e.into()));
+                    continue;
+                }
+            }
+
+            loop {
+                match rx.recv_timeout(DEBOUNCE) {
+                    Ok(_) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            callback(crate::open(&reload_path));
+        }
+    });
+
+    Ok(Watcher {
+        _watcher: watcher,
+        path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const TEST_FILE: &str = "resources/test_v0.4.8.pyxel";
+
+    #[test]
+    fn reloads_on_change() {
+        let dir = std::env::temp_dir().join(format!("pyxel-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("doc.pyxel");
+        fs::copy(TEST_FILE, &path).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let watcher = watch(&path, move |result| tx.send(result).unwrap()).unwrap();
+
+        fs::write(&path, b"not a pyxel document").unwrap();
+        let result = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(result.is_err());
+
+        fs::copy(TEST_FILE, &path).unwrap();
+        let doc = rx.recv_timeout(Duration::from_secs(10)).unwrap().unwrap();
+        assert_eq!("test_v0.4.8", doc.name());
+
+        drop(watcher);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
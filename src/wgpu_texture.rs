@@ -0,0 +1,124 @@
+use crate::{
+    atlas::Atlas,
+    pyxel::{Layer, Tileset},
+};
+
+use image::RgbaImage;
+
+/// Copies the rows of `image` into a buffer with each row padded to
+/// `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT` bytes, as copies from buffers to textures require,
+/// returning the buffer and its bytes per row.
+fn padded_rows(image: &RgbaImage) -> (Vec<u8>, u32) {
+    let (width, height) = image.dimensions();
+    let row_len = width as usize * 4;
+    let bytes_per_row = (width * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let mut buf = vec![0; bytes_per_row as usize * height as usize];
+    for (row, pixels) in buf
+        .chunks_exact_mut(bytes_per_row as usize)
+        .zip(image.chunks_exact(row_len.max(1)))
+    {
+        row[..row_len].copy_from_slice(pixels);
+    }
+
+    (buf, bytes_per_row)
+}
+
+/// Creates an sRGB texture holding `image`, sampled in shaders and able to be copied to.
+fn create_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &RgbaImage,
+    label: Option<&str>,
+) -> wgpu::Texture {
+    let size = wgpu::Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        // PyxelEdit's colors are sRGB, so let the GPU linearize them when they're sampled.
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    let (data, bytes_per_row) = padded_rows(image);
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &data,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(bytes_per_row),
+            rows_per_image: Some(image.height()),
+        },
+        size,
+    );
+
+    texture
+}
+
+impl Layer {
+    /// Creates a `Rgba8UnormSrgb` texture holding the image for this layer, labelled with its
+    /// name. The texture can be bound for sampling and copied to.
+    ///
+    /// The pixels are written through `queue`, so are available once it's next submitted.
+    pub fn create_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+        create_texture(device, queue, &self.image.to_rgba(), Some(&self.name))
+    }
+}
+
+impl Tileset {
+    /// Creates a texture holding the [`atlas`](#method.atlas) of this tileset, in the same
+    /// format as [`Layer::create_texture`](struct.Layer.html#method.create_texture).
+    pub fn create_atlas_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> wgpu::Texture {
+        create_texture(device, queue, &self.atlas(), Some("tileset"))
+    }
+}
+
+impl Atlas {
+    /// Creates a texture holding the image of this atlas, in the same format as
+    /// [`Layer::create_texture`](struct.Layer.html#method.create_texture). Use the
+    /// [`uv_min`](struct.AtlasRect.html#structfield.uv_min) and
+    /// [`uv_max`](struct.AtlasRect.html#structfield.uv_max) of each tile to sample it.
+    pub fn create_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+        create_texture(device, queue, &self.image, Some("atlas"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn rows_are_padded_to_the_copy_alignment() {
+        let mut image = RgbaImage::new(65, 2);
+        image.put_pixel(64, 0, Rgba([1, 2, 3, 4]));
+        image.put_pixel(0, 1, Rgba([5, 6, 7, 8]));
+
+        let (buf, bytes_per_row) = padded_rows(&image);
+        assert_eq!(512, bytes_per_row);
+        assert_eq!(1024, buf.len());
+        assert_eq!(&[1, 2, 3, 4], &buf[256..260]);
+        assert!(buf[260..512].iter().all(|&b| b == 0));
+        assert_eq!(&[5, 6, 7, 8], &buf[512..516]);
+
+        assert_eq!(256, padded_rows(&RgbaImage::new(64, 1)).1);
+    }
+}
@@ -1,6 +1,7 @@
 use semver::Version;
 use std::{
     fs::{read, File},
+    io::{Cursor, Read, Write},
     time::Duration,
 };
 
@@ -21,12 +22,13 @@ fn check_v0_4_8(doc: Pyxel) {
         assert_eq!(frame_duration, animation.frame_duration());
         assert_eq!(
             frame_duration_multipliers,
-            animation.frame_duration_multipliers().as_slice()
+            animation.frame_duration_multipliers()
         );
         assert_eq!(length, animation.length());
         assert_eq!(name, animation.name());
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn check_layer(
         layer: &Layer,
         alpha: u8,
@@ -58,6 +60,15 @@ fn check_v0_4_8(doc: Pyxel) {
         4,
         "Animation 1",
     );
+    assert_eq!(
+        vec![
+            Duration::from_millis(150),
+            Duration::from_millis(300),
+            Duration::from_millis(450),
+            Duration::from_millis(600)
+        ],
+        doc.animations()[0].frame_durations()
+    );
 
     // 1
     check_animation(
@@ -83,6 +94,7 @@ fn check_v0_4_8(doc: Pyxel) {
 
     // canvas.layers
     assert_eq!(11, doc.canvas().layers().len());
+    assert_eq!(11, doc.canvas().num_layers());
 
     // 0
     check_layer(
@@ -216,6 +228,10 @@ fn check_v0_4_8(doc: Pyxel) {
         0,
     );
 
+    for layer in doc.canvas().layers() {
+        assert_eq!((256, 128), (layer.width(), layer.height()));
+    }
+
     assert_eq!(128, doc.canvas().height());
     assert_eq!(16, doc.canvas().tile_height());
     assert_eq!(32, doc.canvas().tile_width());
@@ -226,14 +242,21 @@ fn check_v0_4_8(doc: Pyxel) {
 
     // palette
     assert_eq!(15, doc.palette().colors().len());
+    assert_eq!(15, doc.palette().num_colors());
     assert_eq!(4, doc.palette().height());
     assert_eq!(8, doc.palette().width());
 
     // tileset
-    assert_eq!(false, doc.tileset().fixed_width());
+    assert!(!doc.tileset().fixed_width());
     assert_eq!(16, doc.tileset().tile_height());
     assert_eq!(32, doc.tileset().tile_width());
     assert_eq!(8, doc.tileset().tiles_wide());
+    assert_eq!(4, doc.tileset().num_tiles());
+
+    for i in 0..4 {
+        assert_eq!(Some((32, 16)), doc.tileset().tile_image_size(i));
+    }
+    assert_eq!(None, doc.tileset().tile_image_size(4));
 
     #[cfg(not(feature = "images"))]
     assert_eq!(4, doc.tileset().image_data().len());
@@ -264,3 +287,171 @@ fn load_from_memory_v0_4_8() {
     let doc = pyxel::load_from_memory(&buf).unwrap();
     check_v0_4_8(doc);
 }
+
+/// Rebuilds the archive at `path`, passing each entry through `f` which can drop it (by returning
+/// `None`) or replace its contents.
+fn rezip<F>(path: &str, f: F) -> Vec<u8>
+where
+    F: Fn(&str, Vec<u8>) -> Option<Vec<u8>>,
+{
+    let mut src = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+    let mut dst = zip::ZipWriter::new(Cursor::new(Vec::new()));
+
+    for i in 0..src.len() {
+        let mut entry = src.by_index(i).unwrap();
+        let name = entry.name().to_owned();
+
+        let mut buf = Vec::new();
+        entry.read_to_end(&mut buf).unwrap();
+
+        if let Some(buf) = f(&name, buf) {
+            dst.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            dst.write_all(&buf).unwrap();
+        }
+    }
+
+    dst.finish().unwrap().into_inner()
+}
+
+fn entry_name(err: &PyxelError) -> &str {
+    match err {
+        PyxelError::InEntry { name, .. } => name,
+        _ => panic!("expected an error with entry context, got {:?}", err),
+    }
+}
+
+#[test]
+fn error_names_missing_layer_entry() {
+    let buf = rezip(TEST_FILE_V0_4_8, |name, buf| {
+        if name == "layer7.png" {
+            None
+        } else {
+            Some(buf)
+        }
+    });
+
+    let err = pyxel::load_from_memory(&buf).unwrap_err();
+    assert_eq!("layer7.png", entry_name(&err));
+    assert!(err.to_string().starts_with("layer7.png: "));
+}
+
+#[test]
+fn error_names_missing_tile_entry() {
+    let buf = rezip(TEST_FILE_V0_4_8, |name, buf| {
+        if name == "tile3.png" {
+            None
+        } else {
+            Some(buf)
+        }
+    });
+
+    let err = pyxel::load_from_memory(&buf).unwrap_err();
+    assert_eq!("tile3.png", entry_name(&err));
+}
+
+#[test]
+fn error_names_malformed_doc_data() {
+    let buf = rezip(TEST_FILE_V0_4_8, |name, buf| {
+        if name == "docData.json" {
+            Some(b"{".to_vec())
+        } else {
+            Some(buf)
+        }
+    });
+
+    let err = pyxel::load_from_memory(&buf).unwrap_err();
+    assert_eq!("docData.json", entry_name(&err));
+
+    match err {
+        PyxelError::InEntry { source, .. } => match *source {
+            #[cfg(not(feature = "simd-json"))]
+            PyxelError::Serde(_) => {}
+            #[cfg(feature = "simd-json")]
+            PyxelError::SimdJson(_) => {}
+            _ => panic!("expected a deserialization error, got {:?}", source),
+        },
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn error_reports_unsupported_version() {
+    let buf = rezip(TEST_FILE_V0_4_8, |name, buf| {
+        if name == "docData.json" {
+            let json = String::from_utf8(buf).unwrap();
+            Some(
+                json.replace("\"version\": \"0.4.8\"", "\"version\": \"1.0.0\"")
+                    .into_bytes(),
+            )
+        } else {
+            Some(buf)
+        }
+    });
+
+    match pyxel::load_from_memory(&buf) {
+        Err(PyxelError::UnsupportedVersion(version)) => {
+            assert_eq!(Version::parse("1.0.0").unwrap(), version)
+        }
+        other => panic!("expected an unsupported version error, got {:?}", other),
+    }
+}
+
+#[test]
+fn strict_version_rejects_untested_versions() {
+    let buf = rezip(TEST_FILE_V0_4_8, |name, buf| {
+        if name == "docData.json" {
+            let json = String::from_utf8(buf).unwrap();
+            Some(
+                json.replace("\"version\": \"0.4.8\"", "\"version\": \"0.5.1\"")
+                    .into_bytes(),
+            )
+        } else {
+            Some(buf)
+        }
+    });
+    assert!(pyxel::load_from_memory(&buf).is_ok());
+
+    let options = pyxel::LoadOptions {
+        strict_version: true,
+        ..pyxel::LoadOptions::default()
+    };
+    match pyxel::load_with_options(Cursor::new(&buf[..]), &options) {
+        Err(PyxelError::UnsupportedVersion(version)) => {
+            assert_eq!(Version::parse("0.5.1").unwrap(), version)
+        }
+        other => panic!("expected an unsupported version error, got {:?}", other),
+    }
+
+    let file = File::open(TEST_FILE_V0_4_8).unwrap();
+    assert!(pyxel::load_with_options(file, &options).is_ok());
+}
+
+#[test]
+fn doc_reader_batches_tile_refs() {
+    let buf = rezip(TEST_FILE_V0_4_8, |name, buf| {
+        if name == "docData.json" {
+            let mut json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+            let tile_refs = &mut json["canvas"]["layers"]["1"]["tileRefs"];
+            for cell in 0..512 {
+                tile_refs[cell.to_string()] =
+                    serde_json::json!({ "index": 0, "rot": 0, "flipX": false });
+            }
+            Some(serde_json::to_vec(&json).unwrap())
+        } else {
+            Some(buf)
+        }
+    });
+
+    let batches: Vec<(usize, usize)> = DocReader::new(Cursor::new(buf))
+        .unwrap()
+        .filter_map(|event| match event.unwrap() {
+            DocEvent::TileRefBatch {
+                layer: 1,
+                tile_refs,
+            } => Some((tile_refs[0].0, tile_refs.len())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(vec![(0, 256), (256, 256)], batches);
+}